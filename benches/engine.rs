@@ -0,0 +1,134 @@
+// -- Engine benchmarks
+//
+// Baselines for the hot paths at snake lengths of 10, 1,000 and 10,000, so
+// changes to how the body and the board are stored can be measured.
+// `cargo bench --bench engine`, add `-- tick` for just one group.
+
+use criterion::{BatchSize, BenchmarkId, Criterion, criterion_group, criterion_main};
+use rand::SeedableRng;
+use snake2::backend::AnsiBackend;
+use snake2::game::{Game, GameOptions, generate_food};
+use snake2::render::draw_game;
+use snake2::rng::GameRng;
+use snake2::snake::{SnakeBodyPoint, SnakeDirection};
+use snake2::theme::Theme;
+use std::hint::black_box;
+
+const LENGTHS: [usize; 3] = [10, 1_000, 10_000];
+// Room for the longest snake with plenty of free cells left
+const COLS: u16 = 200;
+const ROWS: u16 = 100;
+
+/// A snake of `length` going back and forth from the top row down, the head
+/// at the end heading right into free cells.
+fn long_game(length: usize) -> Game {
+    let options = GameOptions {
+        seed: Some(1),
+        ..Default::default()
+    };
+    let mut game = Game::with_options(COLS, ROWS, SnakeDirection::Right, &options);
+
+    // One cell short of each row, so the last row always has room ahead
+    let width = COLS as usize - 1;
+    let mut body: Vec<SnakeBodyPoint> = (0..length)
+        .map(|i| {
+            let (row, col) = (i / width, i % width);
+            let x = if row.is_multiple_of(2) {
+                col
+            } else {
+                width - 1 - col
+            };
+            SnakeBodyPoint {
+                x: x as u16,
+                y: row as u16,
+                letter: None,
+            }
+        })
+        .collect();
+    body.reverse();
+    let row = (length - 1) / width;
+    let direction = if row.is_multiple_of(2) {
+        SnakeDirection::Right
+    } else {
+        SnakeDirection::Left
+    };
+
+    game.snake.body = body;
+    game.snake.direction = direction;
+    game.snake.last_moved = direction;
+    // The food was placed for the short snake, it may be under the long one now
+    game.entities.clear();
+    game
+}
+
+fn tick(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tick");
+    for length in LENGTHS {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(length),
+            &length,
+            |b, &length| {
+                b.iter_batched(
+                    || long_game(length),
+                    |mut game| black_box(game.tick()),
+                    BatchSize::LargeInput,
+                );
+            },
+        );
+    }
+    group.finish();
+}
+
+fn collision(c: &mut Criterion) {
+    let mut group = c.benchmark_group("collision");
+    for length in LENGTHS {
+        let game = long_game(length);
+        // The worst case, a free cell means looking at every segment
+        let free = (COLS - 1, ROWS - 1);
+        group.bench_with_input(BenchmarkId::from_parameter(length), &game, |b, game| {
+            b.iter(|| black_box(game.is_free(black_box(free.0), black_box(free.1))));
+        });
+    }
+    group.finish();
+}
+
+fn food(c: &mut Criterion) {
+    let mut group = c.benchmark_group("food");
+    for length in LENGTHS {
+        let game = long_game(length);
+        let mut rng = GameRng::seed_from_u64(1);
+        group.bench_with_input(BenchmarkId::from_parameter(length), &game, |b, game| {
+            b.iter(|| {
+                generate_food(
+                    &game.cols,
+                    &game.rows,
+                    &game.snake,
+                    &game.entities,
+                    &mut rng,
+                )
+            });
+        });
+    }
+    group.finish();
+}
+
+// The renderer draws every frame from scratch, so this is the number a
+// diffing renderer has to beat
+fn frame(c: &mut Criterion) {
+    let mut group = c.benchmark_group("frame");
+    let theme = Theme::default();
+    for length in LENGTHS {
+        let game = long_game(length);
+        let mut out = AnsiBackend::new();
+        group.bench_with_input(BenchmarkId::from_parameter(length), &game, |b, game| {
+            b.iter(|| {
+                draw_game(&mut out, game, &theme).unwrap();
+                black_box(out.take_frame())
+            });
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, tick, collision, food, frame);
+criterion_main!(benches);
@@ -0,0 +1,17 @@
+// Level files, anything that parses writes back the same and plays, and
+// level indexes.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use snake2::catalog::parse_index;
+use snake2::level::Level;
+use snake2::snake::SnakeDirection;
+
+fuzz_target!(|text: &str| {
+    if let Ok(level) = Level::parse(text) {
+        assert_eq!(Level::parse(&level.to_text()), Ok(level.clone()));
+        let mut game = level.start(SnakeDirection::Right, &Default::default());
+        game.tick();
+    }
+    let _ = parse_index(text);
+});
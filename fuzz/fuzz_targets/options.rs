@@ -0,0 +1,21 @@
+// Option values that end up parsed from the command line, profile configs
+// and challenge codes someone pasted.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use snake2::challenge::Challenge;
+use snake2::rules::{Mode, Modifiers};
+use snake2::snake::{SnakeDirection, Spawn};
+
+fuzz_target!(|text: &str| {
+    let _ = text.parse::<Mode>();
+    if let Ok(modifiers) = text.parse::<Modifiers>() {
+        assert_eq!(modifiers.to_string().parse::<Modifiers>(), Ok(modifiers));
+    }
+    let _ = text.parse::<Spawn>();
+    let _ = SnakeDirection::from_name(text);
+    if let Ok(challenge) = text.parse::<Challenge>() {
+        let code = challenge.code();
+        assert_eq!(code.parse::<Challenge>(), Ok(challenge));
+    }
+});
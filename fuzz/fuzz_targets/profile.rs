@@ -0,0 +1,16 @@
+// The files in a profile directory: config, high scores, stats, level scores
+// and achievements.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use snake2::profile::{
+    parse_achievements, parse_config, parse_high_scores, parse_level_scores, parse_stats,
+};
+
+fuzz_target!(|text: &str| {
+    parse_config(text);
+    let _ = parse_high_scores(text);
+    let _ = parse_stats(text);
+    let _ = parse_level_scores(text);
+    let _ = parse_achievements(text);
+});
@@ -0,0 +1,18 @@
+// Replays get passed around as files and pasted as text. Whatever the
+// bytes, reading one (in either format) either fails with a message or
+// gives a replay that plays to its end.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use snake2::replay::Replay;
+
+fuzz_target!(|bytes: &[u8]| {
+    if let Ok(replay) = Replay::load(bytes) {
+        assert_eq!(Replay::from_bytes(&replay.to_bytes()), Ok(replay.clone()));
+        assert_eq!(Replay::from_notation(&replay.to_notation()), Ok(replay.clone()));
+        let mut replay = replay;
+        // A long end is fine, it's just more ticks than a fuzzer has time for
+        replay.end = replay.end.min(replay.start_game().unwrap().ticks + 100);
+        replay.play().expect("a replay that reads plays");
+    }
+});
@@ -0,0 +1,18 @@
+// Saved games come from disk and can be edited by hand. Whatever is in
+// there, loading either fails with a message or gives a game that plays.
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use snake2::save::{load_game, save_game};
+
+fuzz_target!(|text: &str| {
+    if let Ok(mut game) = load_game(text) {
+        // What loads saves again to something that loads the same
+        let saved = save_game(&game);
+        let again = load_game(&saved).expect("a saved game loads");
+        assert_eq!(save_game(&again), saved);
+        for _ in 0..10 {
+            game.tick();
+        }
+    }
+});
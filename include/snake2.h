@@ -0,0 +1,91 @@
+#ifndef SNAKE2_H
+#define SNAKE2_H
+
+/* Generated by cbindgen from src/ffi.rs, don't edit by hand */
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+typedef enum Snake2Direction {
+  SNAKE2_DIRECTION_UP,
+  SNAKE2_DIRECTION_DOWN,
+  SNAKE2_DIRECTION_LEFT,
+  SNAKE2_DIRECTION_RIGHT,
+} Snake2Direction;
+
+typedef enum Snake2Outcome {
+  SNAKE2_OUTCOME_MOVED,
+  SNAKE2_OUTCOME_ATE,
+  SNAKE2_OUTCOME_DIED,
+} Snake2Outcome;
+
+// What's on a cell, to draw it.
+typedef enum Snake2Cell {
+  SNAKE2_CELL_EMPTY,
+  SNAKE2_CELL_HEAD,
+  SNAKE2_CELL_BODY,
+  SNAKE2_CELL_FOOD,
+  SNAKE2_CELL_WALL,
+  SNAKE2_CELL_OTHER,
+} Snake2Cell;
+
+// A game and what its last tick did.
+typedef struct Snake2Game Snake2Game;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// A new game on a `cols` by `rows` board. `mode` is a mode name like
+// `--mode` takes (NULL for classic) and `seed` 0 picks one at random. NULL
+// for an unknown mode or a board smaller than 2x2.
+struct Snake2Game *snake2_new(uint16_t cols, uint16_t rows, const char *mode, uint64_t seed);
+
+void snake2_free(struct Snake2Game *game);
+
+// Turn on the next tick, turning back on itself is ignored.
+void snake2_turn(struct Snake2Game *game, enum Snake2Direction direction);
+
+// Move the snake one cell. A dead game stays dead, ticking it again gives
+// `Died` with nothing changed.
+enum Snake2Outcome snake2_tick(struct Snake2Game *game);
+
+uint16_t snake2_cols(const struct Snake2Game *game);
+
+uint16_t snake2_rows(const struct Snake2Game *game);
+
+uint32_t snake2_score(const struct Snake2Game *game);
+
+uint32_t snake2_level(const struct Snake2Game *game);
+
+uint64_t snake2_ticks(const struct Snake2Game *game);
+
+// The seed the game plays, to play it again.
+uint64_t snake2_seed(const struct Snake2Game *game);
+
+// Milliseconds until the next tick should happen.
+uint64_t snake2_timer(const struct Snake2Game *game);
+
+size_t snake2_length(const struct Snake2Game *game);
+
+// Where segment `index` is, 0 being the head. False past the tail.
+bool snake2_segment(const struct Snake2Game *game, size_t index, uint16_t *x, uint16_t *y);
+
+// Where the food is, false when there's none.
+bool snake2_food(const struct Snake2Game *game, uint16_t *x, uint16_t *y);
+
+// What's on a cell, `Empty` off the board.
+enum Snake2Cell snake2_cell(const struct Snake2Game *game, uint16_t x, uint16_t y);
+
+// The whole board as JSON, the same line `--io-protocol` writes. Free it
+// with `snake2_string_free`.
+char *snake2_state_json(const struct Snake2Game *game);
+
+void snake2_string_free(char *string);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* SNAKE2_H */
@@ -0,0 +1,75 @@
+// -- Achievements
+//
+// Milestones a profile reaches once and keeps: a score, a length or a time in
+// one run, or enough games and hours over all of them. They're worked out
+// when a run is recorded, from the run and the lifetime stats with it added,
+// so nothing is kept track of while playing. Each profile has its own in
+// `achievements.txt` (see `profile.rs`), signed like the scores.
+
+use crate::profile::{RunRecord, Stats};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Achievement {
+    FirstRun,
+    Score50,
+    Score100,
+    Length30,
+    FiveMinutes,
+    Regular,
+    Devoted,
+}
+
+impl Achievement {
+    pub const ALL: [Achievement; 7] = [
+        Achievement::FirstRun,
+        Achievement::Score50,
+        Achievement::Score100,
+        Achievement::Length30,
+        Achievement::FiveMinutes,
+        Achievement::Regular,
+        Achievement::Devoted,
+    ];
+
+    /// How it's written in `achievements.txt`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Achievement::FirstRun => "first-run",
+            Achievement::Score50 => "score-50",
+            Achievement::Score100 => "score-100",
+            Achievement::Length30 => "length-30",
+            Achievement::FiveMinutes => "five-minutes",
+            Achievement::Regular => "regular",
+            Achievement::Devoted => "devoted",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Achievement> {
+        Achievement::ALL.into_iter().find(|a| a.name() == name)
+    }
+
+    /// What it takes, for the game over screen and `snake2 profiles`.
+    pub fn describe(self) -> &'static str {
+        match self {
+            Achievement::FirstRun => "finish a run",
+            Achievement::Score50 => "score 50 in one run",
+            Achievement::Score100 => "score 100 in one run",
+            Achievement::Length30 => "grow the snake to 30",
+            Achievement::FiveMinutes => "stay alive for five minutes",
+            Achievement::Regular => "play 50 games",
+            Achievement::Devoted => "play for ten hours in all",
+        }
+    }
+
+    /// Whether `run` earns it, with `stats` the lifetime stats it's in.
+    pub fn earned(self, run: &RunRecord, stats: &Stats) -> bool {
+        match self {
+            Achievement::FirstRun => stats.games >= 1,
+            Achievement::Score50 => run.score >= 50,
+            Achievement::Score100 => run.score >= 100,
+            Achievement::Length30 => run.length >= 30,
+            Achievement::FiveMinutes => run.seconds >= 5 * 60,
+            Achievement::Regular => stats.games >= 50,
+            Achievement::Devoted => stats.seconds_played >= 10 * 60 * 60,
+        }
+    }
+}
@@ -0,0 +1,186 @@
+// -- Headless bot tournaments
+//
+// Every round each bot plays its own game on the same board, with the same
+// seed, and the highest score takes the round (ties are draws). The seeds
+// count up from `--seed`, or from a random one. Rounds run in parallel,
+// each rayon worker compiles its own copy of the bots because a Rhai engine
+// can't be shared between threads.
+
+use crate::bot::{Bot, BotError};
+use crate::events::{DeathCause, GameEvent};
+use crate::game::{Game, GameOptions, TickOutcome};
+use crate::snake::SnakeDirection;
+use rayon::prelude::*;
+use std::path::PathBuf;
+use std::time::Duration;
+
+#[derive(Clone, Copy, Debug)]
+pub struct ArenaConfig {
+    pub games: usize,
+    pub cols: u16,
+    pub rows: u16,
+    // Bots that never die would loop forever without this
+    pub max_ticks: u64,
+    pub time_limit: Duration,
+    pub options: GameOptions,
+}
+
+impl Default for ArenaConfig {
+    fn default() -> Self {
+        ArenaConfig {
+            games: 100,
+            cols: 40,
+            rows: 20,
+            max_ticks: 10_000,
+            time_limit: crate::bot::DEFAULT_TIME_LIMIT,
+            options: GameOptions::default(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct GameResult {
+    // The one it played, to play it again
+    pub seed: u64,
+    pub score: u32,
+    pub length: usize,
+    pub ticks: u64,
+    pub died: bool,
+    // Why it ended, `None` when it didn't (the board filled up or the bot
+    // crashed)
+    pub cause: Option<DeathCause>,
+    pub timeouts: u64,
+    pub crashed: bool,
+}
+
+#[derive(Debug, Default)]
+pub struct BotStats {
+    pub name: String,
+    pub wins: usize,
+    pub draws: usize,
+    pub total_score: u64,
+    pub best_score: u32,
+    pub deaths: usize,
+    pub timeouts: u64,
+    pub crashes: usize,
+}
+
+impl BotStats {
+    pub fn average_score(&self, games: usize) -> f64 {
+        if games == 0 {
+            return 0.0;
+        }
+        self.total_score as f64 / games as f64
+    }
+}
+
+/// Play one game with no terminal, as fast as the bot can answer.
+pub fn play_headless(bot: &mut Bot, config: &ArenaConfig) -> GameResult {
+    let mut game = Game::with_options(
+        config.cols,
+        config.rows,
+        SnakeDirection::Right,
+        &config.options,
+    );
+    let mut result = GameResult::default();
+
+    while game.ticks < config.max_ticks {
+        match bot.next_move(&game) {
+            Ok(Some(direction)) => game.turn(direction),
+            Ok(None) => {}
+            Err(BotError::Timeout) => result.timeouts += 1,
+            Err(_) => {
+                result.crashed = true;
+                break;
+            }
+        }
+
+        if game.tick() == TickOutcome::Died {
+            result.died = true;
+            result.cause = game.death_cause();
+            break;
+        }
+        if game.events.contains(&GameEvent::BoardFull) {
+            break; // Nothing left to eat
+        }
+    }
+
+    if game.ticks >= config.max_ticks {
+        result.cause = Some(DeathCause::Timeout);
+    }
+    result.seed = game.seed;
+    result.score = game.score;
+    result.length = game.snake.body.len();
+    result.ticks = game.ticks;
+    result
+}
+
+pub fn run_arena(paths: &[PathBuf], config: &ArenaConfig) -> Result<Vec<BotStats>, BotError> {
+    let sources = paths
+        .iter()
+        .map(|path| {
+            std::fs::read_to_string(path)
+                .map_err(|e| BotError::Load(format!("{}: {e}", path.display())))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // Fail early on scripts that don't even compile
+    for source in &sources {
+        Bot::from_source(source, config.time_limit)?;
+    }
+
+    let seed = config.options.seed.unwrap_or_else(rand::random);
+    let rounds: Vec<Vec<GameResult>> = (0..config.games)
+        .into_par_iter()
+        .map_init(
+            || {
+                sources
+                    .iter()
+                    .map(|s| Bot::from_source(s, config.time_limit).expect("bot compiled before"))
+                    .collect::<Vec<_>>()
+            },
+            |bots, round| {
+                // Every bot gets the round's board
+                let mut config = *config;
+                config.options.seed = Some(seed.wrapping_add(round as u64));
+                bots.iter_mut()
+                    .map(|bot| play_headless(bot, &config))
+                    .collect()
+            },
+        )
+        .collect();
+
+    let mut stats: Vec<BotStats> = paths
+        .iter()
+        .map(|p| BotStats {
+            name: p.display().to_string(),
+            ..Default::default()
+        })
+        .collect();
+
+    for round in rounds {
+        let best = round.iter().map(|r| r.score).max().unwrap_or(0);
+        let leaders = round.iter().filter(|r| r.score == best).count();
+
+        for (stat, result) in stats.iter_mut().zip(&round) {
+            if result.score == best && round.len() > 1 {
+                if leaders == 1 {
+                    stat.wins += 1;
+                } else {
+                    stat.draws += 1;
+                }
+            }
+            stat.total_score += u64::from(result.score);
+            stat.best_score = stat.best_score.max(result.score);
+            stat.timeouts += result.timeouts;
+            if result.died {
+                stat.deaths += 1;
+            }
+            if result.crashed {
+                stat.crashes += 1;
+            }
+        }
+    }
+
+    Ok(stats)
+}
@@ -0,0 +1,146 @@
+// -- Where frames end up
+//
+// The renderer only talks to a `Backend`, so the same drawing code can feed
+// crossterm on a real terminal or build plain ANSI text for xterm.js in the
+// browser, where crossterm doesn't exist.
+
+use std::fmt::Write as _;
+use std::io;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Color {
+    Green,
+    Red,
+    Yellow,
+    Blue,
+    Magenta,
+    Cyan,
+    // Needs a terminal with true color, only the day/night cycle uses it
+    Rgb(u8, u8, u8),
+}
+
+impl Color {
+    fn ansi_code(self) -> String {
+        match self {
+            Color::Red => "31".to_string(),
+            Color::Green => "32".to_string(),
+            Color::Yellow => "33".to_string(),
+            Color::Blue => "34".to_string(),
+            Color::Magenta => "35".to_string(),
+            Color::Cyan => "36".to_string(),
+            Color::Rgb(r, g, b) => format!("38;2;{r};{g};{b}"),
+        }
+    }
+}
+
+/// How a piece of text looks, `color: None` keeps the terminal default.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Style {
+    pub color: Option<Color>,
+    pub bold: bool,
+    pub reverse: bool,
+    // Fainter than usual, not every terminal does it
+    pub dim: bool,
+}
+
+impl Style {
+    pub fn plain() -> Style {
+        Style::default()
+    }
+
+    pub fn fg(color: Color) -> Style {
+        Style {
+            color: Some(color),
+            ..Style::default()
+        }
+    }
+
+    pub fn bold(self) -> Style {
+        Style { bold: true, ..self }
+    }
+
+    pub fn reverse(self) -> Style {
+        Style {
+            reverse: true,
+            ..self
+        }
+    }
+
+    pub fn dim(self) -> Style {
+        Style { dim: true, ..self }
+    }
+}
+
+pub trait Backend {
+    fn clear(&mut self) -> io::Result<()>;
+    fn print(&mut self, x: u16, y: u16, text: &str, style: Style) -> io::Result<()>;
+    fn flush(&mut self) -> io::Result<()>;
+}
+
+/// Collects a frame as ANSI escape sequences, `take_frame` hands it over.
+#[derive(Default)]
+pub struct AnsiBackend {
+    buffer: String,
+}
+
+impl AnsiBackend {
+    pub fn new() -> Self {
+        AnsiBackend::default()
+    }
+
+    pub fn take_frame(&mut self) -> String {
+        std::mem::take(&mut self.buffer)
+    }
+}
+
+impl Backend for AnsiBackend {
+    fn clear(&mut self) -> io::Result<()> {
+        self.buffer.push_str("\x1b[2J");
+        Ok(())
+    }
+
+    fn print(&mut self, x: u16, y: u16, text: &str, style: Style) -> io::Result<()> {
+        // ANSI positions start at 1
+        let _ = write!(self.buffer, "\x1b[{};{}H", y + 1, x + 1);
+        if let Some(color) = style.color {
+            let _ = write!(self.buffer, "\x1b[{}m", color.ansi_code());
+        }
+        if style.bold {
+            self.buffer.push_str("\x1b[1m");
+        }
+        if style.reverse {
+            self.buffer.push_str("\x1b[7m");
+        }
+        if style.dim {
+            self.buffer.push_str("\x1b[2m");
+        }
+        let _ = write!(self.buffer, "{text}\x1b[0m");
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Draws to `out` moved `x` columns right and `y` rows down, for drawing
+/// code that starts from the top-left corner of the screen.
+pub struct Offset<'a, B: Backend> {
+    pub out: &'a mut B,
+    pub x: u16,
+    pub y: u16,
+}
+
+impl<B: Backend> Backend for Offset<'_, B> {
+    fn clear(&mut self) -> io::Result<()> {
+        self.out.clear()
+    }
+
+    fn print(&mut self, x: u16, y: u16, text: &str, style: Style) -> io::Result<()> {
+        self.out.print(self.x + x, self.y + y, text, style)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
@@ -0,0 +1,129 @@
+// -- Built-in bot benchmark
+//
+// `snake2 bench-bots` plays every built-in bot (see `strategy.rs`) on the
+// same boards, seeds counting up from `--seed`, so the scores are fair to
+// compare and come out the same every time. Each bot plays on a thread of
+// its own, no `scripting` feature needed.
+
+use crate::events::GameEvent;
+use crate::game::{Game, GameOptions, TickOutcome};
+use crate::snake::SnakeDirection;
+use crate::strategy::Strategy;
+use std::thread;
+
+#[derive(Clone, Copy, Debug)]
+pub struct BenchConfig {
+    pub games: usize,
+    pub cols: u16,
+    pub rows: u16,
+    // The hamiltonian bot never dies, it would go on forever without this
+    pub max_ticks: u64,
+    // Of the first game, the next one plays the one after it and so on
+    pub seed: u64,
+    pub options: GameOptions,
+}
+
+impl Default for BenchConfig {
+    fn default() -> Self {
+        BenchConfig {
+            games: 20,
+            cols: 40,
+            rows: 20,
+            max_ticks: 10_000,
+            seed: 1,
+            options: GameOptions::default(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct BenchStats {
+    pub strategy: Strategy,
+    pub total_score: u64,
+    pub best_score: u32,
+    pub total_length: u64,
+    pub total_ticks: u64,
+    pub deaths: usize,
+    // Games it filled the whole board in
+    pub full_boards: usize,
+}
+
+impl BenchStats {
+    pub fn average_score(&self, games: usize) -> f64 {
+        self.total_score as f64 / games.max(1) as f64
+    }
+
+    pub fn average_length(&self, games: usize) -> f64 {
+        self.total_length as f64 / games.max(1) as f64
+    }
+}
+
+/// Play `strategy` on a board until it dies, fills it up or runs out of
+/// ticks, and the game as it ended.
+pub fn play_strategy(
+    strategy: Strategy,
+    cols: u16,
+    rows: u16,
+    options: &GameOptions,
+    max_ticks: u64,
+) -> Game {
+    let mut game = Game::with_options(cols, rows, SnakeDirection::Right, options);
+    while game.ticks < max_ticks {
+        if let Some(direction) = strategy.next_move(&game) {
+            game.turn(direction);
+        }
+        if game.tick() == TickOutcome::Died || game.events.contains(&GameEvent::BoardFull) {
+            break;
+        }
+    }
+    game
+}
+
+/// Every bot's results, in the order of `Strategy::ALL`.
+pub fn bench_bots(config: &BenchConfig) -> Vec<BenchStats> {
+    thread::scope(|scope| {
+        let bots: Vec<_> = Strategy::ALL
+            .into_iter()
+            .map(|strategy| scope.spawn(move || bench(strategy, config)))
+            .collect();
+        bots.into_iter()
+            .map(|bot| bot.join().expect("bots don't panic"))
+            .collect()
+    })
+}
+
+fn bench(strategy: Strategy, config: &BenchConfig) -> BenchStats {
+    let mut stats = BenchStats {
+        strategy,
+        total_score: 0,
+        best_score: 0,
+        total_length: 0,
+        total_ticks: 0,
+        deaths: 0,
+        full_boards: 0,
+    };
+    for game in 0..config.games {
+        let options = GameOptions {
+            seed: Some(config.seed.wrapping_add(game as u64)),
+            ..config.options
+        };
+        let g = play_strategy(
+            strategy,
+            config.cols,
+            config.rows,
+            &options,
+            config.max_ticks,
+        );
+        stats.total_score += u64::from(g.score);
+        stats.best_score = stats.best_score.max(g.score);
+        stats.total_length += g.snake.body.len() as u64;
+        stats.total_ticks += g.ticks;
+        if g.death_cause().is_some() {
+            stats.deaths += 1;
+        }
+        if g.events.contains(&GameEvent::BoardFull) {
+            stats.full_boards += 1;
+        }
+    }
+    stats
+}
@@ -0,0 +1,114 @@
+// -- Compact binary encoding
+//
+// What binary saves and replays are written with (see `save.rs` and
+// `replay.rs`). Numbers are unsigned LEB128 varints: seven bits a byte, low
+// bits first, the top bit set on every byte but the last. Small numbers,
+// which is nearly all of them, take one byte. Seeds and generator states are
+// random all the way up, those go as 8 little-endian bytes instead.
+//
+// Every file starts with `S2`, a byte for what it is (`s` a save, `r` a
+// replay) and the version of its layout as a varint.
+
+pub const MAGIC: &[u8; 2] = b"S2";
+
+#[derive(Default)]
+pub struct Writer {
+    pub bytes: Vec<u8>,
+}
+
+impl Writer {
+    /// A new file of `kind` in layout `version`.
+    pub fn new(kind: u8, version: u64) -> Self {
+        let mut writer = Writer::default();
+        writer.bytes.extend(MAGIC);
+        writer.bytes.push(kind);
+        writer.uint(version);
+        writer
+    }
+
+    pub fn uint(&mut self, mut n: u64) {
+        while n >= 0x80 {
+            self.bytes.push(n as u8 | 0x80);
+            n >>= 7;
+        }
+        self.bytes.push(n as u8);
+    }
+
+    pub fn fixed(&mut self, n: u64) {
+        self.bytes.extend(n.to_le_bytes());
+    }
+
+    /// A length and then the bytes.
+    pub fn bytes(&mut self, bytes: &[u8]) {
+        self.uint(bytes.len() as u64);
+        self.bytes.extend(bytes);
+    }
+}
+
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+    at: usize,
+}
+
+impl<'a> Reader<'a> {
+    /// Check the header is a `kind` file, gives back its version.
+    pub fn open(bytes: &'a [u8], kind: u8, name: &str) -> Result<(Reader<'a>, u64), String> {
+        if bytes.len() < 3 || &bytes[..2] != MAGIC || bytes[2] != kind {
+            return Err(format!("not a snake2 {name} file"));
+        }
+        let mut reader = Reader { bytes, at: 3 };
+        let version = reader.uint("version")?;
+        Ok((reader, version))
+    }
+
+    pub fn uint(&mut self, what: &str) -> Result<u64, String> {
+        let mut n = 0u64;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte(what)?;
+            n |= u64::from(byte & 0x7F) << shift;
+            if byte & 0x80 == 0 {
+                // More than 64 bits on the last byte don't fit
+                if shift == 63 && byte > 1 {
+                    break;
+                }
+                return Ok(n);
+            }
+        }
+        Err(format!("bad `{what}`"))
+    }
+
+    /// A varint that has to fit in `T`.
+    pub fn number<T: TryFrom<u64>>(&mut self, what: &str) -> Result<T, String> {
+        T::try_from(self.uint(what)?).map_err(|_| format!("bad `{what}`"))
+    }
+
+    pub fn fixed(&mut self, what: &str) -> Result<u64, String> {
+        let bytes = self.take(8, what)?;
+        Ok(u64::from_le_bytes(bytes.try_into().expect("8 bytes")))
+    }
+
+    pub fn bytes(&mut self, what: &str) -> Result<&'a [u8], String> {
+        let len = self.number(what)?;
+        self.take(len, what)
+    }
+
+    pub fn byte(&mut self, what: &str) -> Result<u8, String> {
+        Ok(self.take(1, what)?[0])
+    }
+
+    /// Nothing may be left over.
+    pub fn finish(&self) -> Result<(), String> {
+        match self.bytes.len() - self.at {
+            0 => Ok(()),
+            n => Err(format!("{n} bytes left over at the end")),
+        }
+    }
+
+    fn take(&mut self, len: usize, what: &str) -> Result<&'a [u8], String> {
+        if self.bytes.len() - self.at < len {
+            return Err(format!("cut short reading `{what}`"));
+        }
+        self.at += len;
+        Ok(&self.bytes[self.at - len..self.at])
+    }
+}
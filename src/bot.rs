@@ -0,0 +1,161 @@
+// -- Bots written in Rhai
+//
+// A bot is a script with a `next_move(state)` function. It is called once
+// per tick with a map describing the board and must return "up", "down",
+// "left" or "right" (anything else keeps the current direction):
+//
+//     fn next_move(state) {
+//         if state.food.x > state.head.x { "right" } else { "left" }
+//     }
+//
+// `state` has `cols`, `rows`, `tick`, `score`, `direction`, `head` (#{x, y}),
+// `body` (array of #{x, y}, head first), `food` (#{x, y} or ()) and
+// `walls` (array of #{x, y}, bosses count as walls).
+//
+// Scripts can't touch files, the network or the terminal, and each call is
+// cut off once it runs longer than the per-tick time limit.
+
+use crate::game::Game;
+use crate::snake::{SnakeBodyPoint, SnakeDirection};
+use rhai::{AST, Array, Dynamic, Engine, EvalAltResult, Map, Scope};
+use std::cell::Cell;
+use std::fmt;
+use std::path::Path;
+use std::rc::Rc;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_TIME_LIMIT: Duration = Duration::from_millis(50);
+
+#[derive(Debug)]
+pub enum BotError {
+    Load(String),
+    Script(String),
+    Timeout,
+}
+
+impl fmt::Display for BotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BotError::Load(e) => write!(f, "could not load bot: {e}"),
+            BotError::Script(e) => write!(f, "bot script failed: {e}"),
+            BotError::Timeout => write!(f, "bot ran out of time for this tick"),
+        }
+    }
+}
+
+impl std::error::Error for BotError {}
+
+pub struct Bot {
+    engine: Engine,
+    ast: AST,
+    deadline: Rc<Cell<Option<Instant>>>,
+    time_limit: Duration,
+}
+
+impl Bot {
+    pub fn load(path: &Path, time_limit: Duration) -> Result<Self, BotError> {
+        let source = std::fs::read_to_string(path)
+            .map_err(|e| BotError::Load(format!("{}: {e}", path.display())))?;
+        Bot::from_source(&source, time_limit)
+    }
+
+    pub fn from_source(source: &str, time_limit: Duration) -> Result<Self, BotError> {
+        let mut engine = Engine::new();
+
+        // Keep scripts away from the terminal and from eating all the memory
+        engine.on_print(|_| {});
+        engine.on_debug(|_, _, _| {});
+        engine.disable_symbol("eval");
+        engine.set_max_call_levels(64);
+        engine.set_max_expr_depths(64, 64);
+        engine.set_max_string_size(10_000);
+        engine.set_max_array_size(100_000);
+        engine.set_max_map_size(10_000);
+
+        let deadline: Rc<Cell<Option<Instant>>> = Rc::new(Cell::new(None));
+        let progress_deadline = Rc::clone(&deadline);
+        engine.on_progress(move |_| match progress_deadline.get() {
+            Some(d) if Instant::now() > d => Some(Dynamic::UNIT),
+            _ => None,
+        });
+
+        let ast = engine
+            .compile(source)
+            .map_err(|e| BotError::Load(e.to_string()))?;
+
+        if !ast.iter_functions().any(|f| f.name == "next_move") {
+            return Err(BotError::Load("the script has no `next_move(state)` function".into()));
+        }
+
+        Ok(Bot {
+            engine,
+            ast,
+            deadline,
+            time_limit,
+        })
+    }
+
+    /// Ask the script where to go next, `None` keeps the current direction.
+    pub fn next_move(&mut self, game: &Game) -> Result<Option<SnakeDirection>, BotError> {
+        let state = game_state(game);
+        let mut scope = Scope::new();
+
+        self.deadline.set(Some(Instant::now() + self.time_limit));
+        let result = self
+            .engine
+            .call_fn::<Dynamic>(&mut scope, &self.ast, "next_move", (state,));
+        self.deadline.set(None);
+
+        match result {
+            Ok(value) => Ok(value
+                .into_immutable_string()
+                .ok()
+                .and_then(|s| SnakeDirection::from_name(&s))),
+            Err(e) => match *e {
+                EvalAltResult::ErrorTerminated(..) => Err(BotError::Timeout),
+                other => Err(BotError::Script(other.to_string())),
+            },
+        }
+    }
+}
+
+fn point(x: u16, y: u16) -> Dynamic {
+    let mut map = Map::new();
+    map.insert("x".into(), Dynamic::from_int(x.into()));
+    map.insert("y".into(), Dynamic::from_int(y.into()));
+    Dynamic::from_map(map)
+}
+
+fn game_state(game: &Game) -> Map {
+    let body: Array = game
+        .snake
+        .body
+        .iter()
+        .map(|&SnakeBodyPoint { x, y, .. }| point(x, y))
+        .collect();
+    let walls: Array = game
+        .entities
+        .iter()
+        .filter(|e| e.kind.is_deadly())
+        .map(|e| point(e.x, e.y))
+        .collect();
+    let head = game.snake.head();
+
+    let mut state = Map::new();
+    state.insert("cols".into(), Dynamic::from_int(game.cols.into()));
+    state.insert("rows".into(), Dynamic::from_int(game.rows.into()));
+    state.insert("tick".into(), Dynamic::from_int(game.ticks as i64));
+    state.insert("score".into(), Dynamic::from_int(game.score.into()));
+    state.insert("direction".into(), game.snake.direction.name().into());
+    state.insert("head".into(), point(head.x, head.y));
+    state.insert("body".into(), Dynamic::from_array(body));
+    state.insert("walls".into(), Dynamic::from_array(walls));
+    state.insert(
+        "food".into(),
+        match game.food_position() {
+            Some((x, y)) => point(x, y),
+            None => Dynamic::UNIT,
+        },
+    );
+    state
+}
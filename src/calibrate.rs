@@ -0,0 +1,101 @@
+// -- Measuring the terminal
+//
+// `snake2 calibrate` times how long input takes to get from the terminal to
+// the game and how late the game's ticks wake up, on whatever terminal it
+// runs in, and suggests an `--input-grace` to make up for it. A key can't
+// be timed from when it's pressed, so a cursor position report stands in
+// for one: the terminal answers `ESC [6n` down the same way a key comes in,
+// over the same SSH connection if there is one, and half the round trip is
+// about how long a key takes. Ticks are waited for the way the game waits
+// for them, with a frame drawn on every one.
+
+use crate::input::{Input, next_input};
+use crossterm::{QueueableCommand, cursor, style::Print};
+use std::io::{self, Write};
+use std::time::{Duration, Instant};
+
+const ROUND_TRIPS: usize = 20;
+const TICKS: usize = 30;
+const TICK: Duration = Duration::from_millis(100);
+// More than this is a terminal grace can't fix
+const MAX_GRACE: Duration = Duration::from_millis(150);
+
+pub struct Calibration {
+    // Asking for the cursor position and reading the answer, each time
+    pub round_trips: Vec<Duration>,
+    // How long after it was due each tick woke up
+    pub lateness: Vec<Duration>,
+}
+
+impl Calibration {
+    /// About how long a key takes from the terminal to the game.
+    pub fn key_latency(&self) -> Duration {
+        average(&self.round_trips) / 2
+    }
+
+    /// How late a tick is on average.
+    pub fn jitter(&self) -> Duration {
+        average(&self.lateness)
+    }
+
+    pub fn worst_jitter(&self) -> Duration {
+        self.lateness.iter().copied().max().unwrap_or_default()
+    }
+
+    /// Enough grace to cover a key on its way and a late tick, in steps of
+    /// 10 ms. None at all on a terminal that's as good as local.
+    pub fn suggested_grace(&self) -> Duration {
+        let total = self.key_latency() + self.jitter();
+        if total < Duration::from_millis(5) {
+            return Duration::ZERO;
+        }
+        let steps = total.as_millis().div_ceil(10) as u64;
+        Duration::from_millis(steps * 10).min(MAX_GRACE)
+    }
+}
+
+fn average(samples: &[Duration]) -> Duration {
+    match samples.len() {
+        0 => Duration::ZERO,
+        n => samples.iter().sum::<Duration>() / n as u32,
+    }
+}
+
+/// Measure the terminal on `out`, which has to be in raw mode. `None` if
+/// the player quit halfway.
+pub fn measure(out: &mut io::Stdout) -> io::Result<Option<Calibration>> {
+    let mut round_trips = Vec::with_capacity(ROUND_TRIPS);
+    for _ in 0..ROUND_TRIPS {
+        let asked = Instant::now();
+        cursor::position()?;
+        round_trips.push(asked.elapsed());
+    }
+
+    let mut lateness = Vec::with_capacity(TICKS);
+    let mut due = Instant::now() + TICK;
+    for tick in 0..TICKS {
+        // Keys in the meantime wait like they do in the game, only quit counts
+        loop {
+            let left = due.saturating_duration_since(Instant::now());
+            if left.is_zero() {
+                break;
+            }
+            if next_input(left)? == Some(Input::Quit) {
+                return Ok(None);
+            }
+        }
+        lateness.push(Instant::now().duration_since(due));
+        out.queue(cursor::MoveTo(0, 0))?;
+        out.queue(Print(format!(
+            "Timing ticks {}{}",
+            "#".repeat(tick + 1),
+            ".".repeat(TICKS - tick - 1)
+        )))?;
+        out.flush()?;
+        due += TICK;
+    }
+    Ok(Some(Calibration {
+        round_trips,
+        lateness,
+    }))
+}
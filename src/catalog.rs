@@ -0,0 +1,368 @@
+// -- Level browser
+//
+// `snake2 levels` lists the levels in the levels directory (`levels/` in the
+// data dir, next to the profiles) with their author, difficulty and the
+// profile's best score on each, to pick one and play it. With an index URL
+// (`--levels-index URL`, or `levels-index = URL` in the profile config) the
+// levels it lists that aren't there yet are downloaded first.
+//
+// An index is a text file of level files, one a line, either a URL or a
+// name relative to the index:
+//
+//     snake2-level-index 1
+//     box.txt
+//     http://example.com/more/canyon.txt
+//
+// Just enough HTTP is written out here, like the server's WebSockets: plain
+// `http://` only, a GET that asks for the connection to close after it and
+// reads to the end.
+
+use crate::backend::{Backend, Style};
+use crate::input::{IDLE_WAIT, next_event};
+use crate::layout::Rect;
+use crate::level::Level;
+use crate::profile::data_dir;
+use crate::render::StatusBar;
+use crate::terminal::{
+    CrosstermBackend, disable_game_mode, enable_game_mode, setup_panic_hook, suspend, take_resumed,
+    watch_job_control,
+};
+use crate::theme::Theme;
+use crate::toast::Toasts;
+use crate::versioned::{Format, unchanged};
+use crossterm::event::{Event, KeyCode, KeyModifiers};
+use crossterm::terminal;
+use std::io::{self, Read, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+pub const INDEX: Format = Format {
+    kind: "level-index",
+    // 1: the first one
+    migrations: &[unchanged],
+};
+
+// Levels are a few kilobytes, anything this big isn't one
+const MAX_RESPONSE: u64 = 1024 * 1024;
+const TIMEOUT: Duration = Duration::from_secs(5);
+
+pub fn levels_dir() -> PathBuf {
+    data_dir().join("levels")
+}
+
+/// A level file in the levels directory or a built-in arena (see
+/// `shape.rs`), or why it can't be played.
+pub struct Entry {
+    pub path: PathBuf,
+    pub level: Result<Level, String>,
+}
+
+impl Entry {
+    pub fn file_name(&self) -> String {
+        self.path
+            .file_name()
+            .map_or_else(String::new, |n| n.to_string_lossy().into_owned())
+    }
+}
+
+/// Every `.txt` file in `dir`, by name. No directory is no levels.
+pub fn list(dir: &Path) -> io::Result<Vec<Entry>> {
+    let files = match std::fs::read_dir(dir) {
+        Ok(files) => files,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let mut entries = Vec::new();
+    for file in files {
+        let path = file?.path();
+        if path.extension().is_none_or(|e| e != "txt") {
+            continue;
+        }
+        let level = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|text| Level::parse(&text));
+        entries.push(Entry { path, level });
+    }
+    entries.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(entries)
+}
+
+/// The level files an index lists, blank lines and `#` comments left out.
+pub fn parse_index(text: &str) -> Result<Vec<String>, String> {
+    Ok(INDEX
+        .upgrade(text)?
+        .lines()
+        .map(str::trim)
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .map(str::to_string)
+        .collect())
+}
+
+/// What came of downloading an index's levels.
+#[derive(Debug, Default)]
+pub struct Fetched {
+    // File names written to the levels directory
+    pub added: Vec<String>,
+    // A level's URL and what went wrong with it
+    pub failed: Vec<(String, String)>,
+}
+
+/// Download the levels `index_url` lists into `dir`, leaving the ones
+/// already there alone. Only an index that can't be read is an error.
+pub fn fetch(index_url: &str, dir: &Path) -> io::Result<Fetched> {
+    let index = parse_index(&get(index_url)?).map_err(invalid_data)?;
+    std::fs::create_dir_all(dir)?;
+    let mut fetched = Fetched::default();
+    for entry in index {
+        let url = if entry.starts_with("http://") {
+            entry
+        } else {
+            let base = index_url.rfind('/').map_or(index_url, |i| &index_url[..i]);
+            format!("{base}/{entry}")
+        };
+        // Only the last part of it, an index can't write anywhere else
+        let name = url.rsplit('/').next().unwrap_or_default().to_string();
+        if !name.ends_with(".txt") || name.starts_with('.') {
+            fetched
+                .failed
+                .push((url, "not a `.txt` file name".to_string()));
+            continue;
+        }
+        let path = dir.join(&name);
+        if path.exists() {
+            continue;
+        }
+        let text = match get(&url) {
+            Ok(text) => text,
+            Err(e) => {
+                fetched.failed.push((url, e.to_string()));
+                continue;
+            }
+        };
+        if let Err(e) = Level::parse(&text) {
+            fetched.failed.push((url, e));
+            continue;
+        }
+        std::fs::write(path, text)?;
+        fetched.added.push(name);
+    }
+    Ok(fetched)
+}
+
+/// The body of `url`, as long as the server answers 200.
+fn get(url: &str) -> io::Result<String> {
+    let rest = url
+        .strip_prefix("http://")
+        .ok_or_else(|| invalid_data(format!("`{url}` isn't an http:// URL")))?;
+    let (host, path) = match rest.find('/') {
+        Some(slash) => (&rest[..slash], &rest[slash..]),
+        None => (rest, "/"),
+    };
+    let address = if host.contains(':') {
+        host.to_string()
+    } else {
+        format!("{host}:80")
+    };
+    let address = address
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| invalid_data(format!("no address for `{host}`")))?;
+    let mut stream = TcpStream::connect_timeout(&address, TIMEOUT)?;
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    write!(
+        stream,
+        "GET {path} HTTP/1.0\r\nHost: {host}\r\nUser-Agent: snake2\r\nConnection: close\r\n\r\n"
+    )?;
+
+    let mut response = Vec::new();
+    stream.take(MAX_RESPONSE).read_to_end(&mut response)?;
+    let response = String::from_utf8(response).map_err(|_| invalid_data("not text".into()))?;
+    let (head, body) = response
+        .split_once("\r\n\r\n")
+        .ok_or_else(|| invalid_data("no end to the headers".into()))?;
+    let status = head.lines().next().unwrap_or_default();
+    if status.split_whitespace().nth(1) != Some("200") {
+        return Err(invalid_data(format!("the server said `{status}`")));
+    }
+    Ok(body.to_string())
+}
+
+fn invalid_data(error: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+/// The list of `entries` with each one's best score in `scores`, `None` when
+/// nothing was picked. `notices` show up as toasts.
+pub fn pick(
+    entries: &[Entry],
+    scores: &[(String, u32)],
+    notices: Vec<String>,
+    theme: &Theme,
+) -> io::Result<Option<Level>> {
+    setup_panic_hook();
+    watch_job_control();
+    let mut stdout = io::stdout();
+    enable_game_mode(&mut stdout)?;
+    let mut out = CrosstermBackend::new(stdout);
+
+    let (cols, rows) = terminal::size()?;
+    let mut screen_area = Rect::screen(cols, rows);
+    let lines = table(entries, scores);
+    let mut selected: usize = 0;
+    // The first entry on screen, moved to keep `selected` there
+    let mut top = 0;
+    // How many entries fit on screen, what PageUp and PageDown move by
+    let mut shown = 1;
+    let mut toasts = Toasts::new();
+    for notice in notices {
+        toasts.push(notice);
+    }
+    let mut redraw = true;
+
+    let picked = loop {
+        redraw |= take_resumed(&mut out.stdout)?;
+        let now = Instant::now();
+        toasts.update(now);
+        if redraw {
+            let (list_area, status_area) = screen_area.split_bottom(1);
+            // The header takes a row
+            shown = list_area.height.saturating_sub(1).max(1) as usize;
+            top = top.clamp(selected.saturating_sub(shown - 1), selected);
+
+            out.clear()?;
+            let width = list_area.width as usize;
+            let fit = |line: &str| -> String { line.chars().take(width).collect() };
+            out.print(0, 0, &fit(&format!("  {}", lines[0])), theme.text().bold())?;
+            for (row, line) in lines[1..].iter().enumerate().skip(top).take(shown) {
+                // The first nine on screen can be picked by their number
+                let number = match row - top {
+                    n @ 0..=8 => (n + 1).to_string(),
+                    _ => " ".to_string(),
+                };
+                let line = format!("{number} {line}");
+                let style = if row == selected {
+                    theme.text().reverse()
+                } else if entries[row].level.is_err() {
+                    Style::plain()
+                } else {
+                    theme.text()
+                };
+                out.print(0, (row - top + 1) as u16, &fit(&line), style)?;
+            }
+            toasts.draw(&mut out, list_area, theme)?;
+            let status = StatusBar {
+                mode: "levels",
+                hints: "arrows pick  Enter or 1-9 play  q quit",
+                elapsed: None,
+                rival: None,
+            };
+            status.draw(&mut out, None, status_area, theme)?;
+            out.flush()?;
+        }
+
+        let timeout = toasts
+            .next_expiry()
+            .map_or(IDLE_WAIT, |at| at.saturating_duration_since(now));
+        let event = next_event(timeout)?;
+        redraw = event.is_some() || toasts.next_expiry().is_some_and(|at| at <= Instant::now());
+        let key = match event {
+            Some(Event::Resize(cols, rows)) => {
+                screen_area = Rect::screen(cols, rows);
+                continue;
+            }
+            Some(Event::Key(key)) => key,
+            _ => continue,
+        };
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let last = entries.len().saturating_sub(1);
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => break None,
+            KeyCode::Char('c') if ctrl => break None,
+            KeyCode::Char('z') if ctrl => suspend(&mut out.stdout)?,
+            KeyCode::Up => selected = selected.saturating_sub(1),
+            KeyCode::Down => selected = (selected + 1).min(last),
+            KeyCode::PageUp => selected = selected.saturating_sub(shown),
+            KeyCode::PageDown => selected = (selected + shown).min(last),
+            KeyCode::Home => selected = 0,
+            KeyCode::End => selected = last,
+            KeyCode::Enter => {
+                if let Some(level) = activate(entries, selected, &mut toasts) {
+                    break Some(level);
+                }
+            }
+            KeyCode::Char(digit @ '1'..='9') => {
+                // Only the rows on screen have a number
+                let row = digit as usize - '1' as usize;
+                let index = top + row;
+                if row < shown && index < entries.len() {
+                    selected = index;
+                    if let Some(level) = activate(entries, index, &mut toasts) {
+                        break Some(level);
+                    }
+                }
+            }
+            _ => {}
+        }
+    };
+
+    disable_game_mode(&mut out.stdout)?;
+    Ok(picked)
+}
+
+/// The level of the entry at `index` to play, or why it can't be on a toast.
+fn activate(entries: &[Entry], index: usize, toasts: &mut Toasts) -> Option<Level> {
+    match &entries.get(index)?.level {
+        Ok(level) => Some(level.clone()),
+        // Only the first problem fits on a toast
+        Err(e) => {
+            toasts.push(e.lines().next().unwrap_or_default());
+            None
+        }
+    }
+}
+
+// A header and a line for each entry, in columns
+fn table(entries: &[Entry], scores: &[(String, u32)]) -> Vec<String> {
+    let rows: Vec<[String; 5]> = entries
+        .iter()
+        .map(|entry| match &entry.level {
+            Ok(level) => [
+                level.name.clone(),
+                level.author.clone(),
+                level.difficulty.clone(),
+                format!("{}x{}", level.cols, level.rows),
+                scores
+                    .iter()
+                    .find(|(name, _)| *name == level.name)
+                    .map_or_else(|| "-".to_string(), |(_, best)| best.to_string()),
+            ],
+            Err(_) => [
+                entry.file_name(),
+                String::new(),
+                String::new(),
+                String::new(),
+                "broken".to_string(),
+            ],
+        })
+        .collect();
+    let header = ["Level", "Author", "Difficulty", "Size", "Best"].map(String::from);
+    let mut widths = [0; 5];
+    for row in rows.iter().chain([&header]) {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+    [header]
+        .iter()
+        .chain(&rows)
+        .map(|row| {
+            let cells: Vec<String> = row
+                .iter()
+                .zip(widths)
+                .map(|(cell, width)| format!("{cell:<width$}"))
+                .collect();
+            cells.join("  ").trim_end().to_string()
+        })
+        .collect()
+}
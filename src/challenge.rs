@@ -0,0 +1,157 @@
+// -- Challenge codes
+//
+// Everything that decides how a game starts (board size, mode, modifiers,
+// snake length, spawn and the random seed) packed into a short code a friend can paste
+// into `snake2 --challenge CODE` to get the same board and the same food.
+// The code is URL-safe base64 of a few big-endian numbers, with a version
+// byte in front so the layout can change later. Version 1 codes, from before
+// modifiers, still work.
+
+use crate::game::GameOptions;
+use crate::rules::{Mode, Modifiers};
+use crate::snake::Spawn;
+
+const VERSION: u8 = 2;
+// Bytes in a code of each version, version 2 added the modifiers at the end
+const LEN: [usize; 2] = [21, 22];
+const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789-_";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Challenge {
+    pub seed: u64,
+    pub cols: u16,
+    pub rows: u16,
+    pub mode: Mode,
+    pub modifiers: Modifiers,
+    pub start_length: u16,
+    pub spawn: Spawn,
+}
+
+impl Challenge {
+    /// The challenge for a game started with `options`, `seed` is the one the game ended up with.
+    pub fn new(cols: u16, rows: u16, seed: u64, options: &GameOptions) -> Self {
+        Challenge {
+            seed,
+            cols,
+            rows,
+            mode: options.mode,
+            modifiers: options.modifiers,
+            start_length: options.start_length,
+            spawn: options.spawn,
+        }
+    }
+
+    pub fn options(&self) -> GameOptions {
+        GameOptions {
+            start_length: self.start_length,
+            spawn: self.spawn,
+            mode: self.mode,
+            modifiers: self.modifiers,
+            seed: Some(self.seed),
+            // The usual speed, whatever the one playing it likes
+            ..Default::default()
+        }
+    }
+
+    pub fn code(&self) -> String {
+        let (spawn, x, y) = match self.spawn {
+            Spawn::Center => (0, 0, 0),
+            Spawn::Random => (1, 0, 0),
+            Spawn::At(x, y) => (2, x, y),
+        };
+
+        let mut bytes = vec![VERSION];
+        bytes.extend(self.seed.to_be_bytes());
+        bytes.extend(self.cols.to_be_bytes());
+        bytes.extend(self.rows.to_be_bytes());
+        bytes.push(self.mode.code());
+        bytes.extend(self.start_length.to_be_bytes());
+        bytes.push(spawn);
+        bytes.extend(x.to_be_bytes());
+        bytes.extend(y.to_be_bytes());
+        bytes.push(self.modifiers.bits());
+        encode_base64(&bytes)
+    }
+}
+
+impl std::str::FromStr for Challenge {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let bad = || format!("`{s}` is not a challenge code");
+        let bytes = decode_base64(s.trim()).ok_or_else(bad)?;
+        if !LEN.contains(&bytes.len()) {
+            return Err(bad());
+        }
+        let version = bytes[0];
+        if !(1..=VERSION).contains(&version) {
+            return Err(format!("`{s}` comes from another version of snake2"));
+        }
+        if bytes.len() != LEN[version as usize - 1] {
+            return Err(bad());
+        }
+
+        let u16_at = |i: usize| u16::from_be_bytes([bytes[i], bytes[i + 1]]);
+        let mut seed = [0; 8];
+        seed.copy_from_slice(&bytes[1..9]);
+        let mode = Mode::from_code(bytes[13]).ok_or_else(bad)?;
+        let spawn = match bytes[16] {
+            0 => Spawn::Center,
+            1 => Spawn::Random,
+            2 => Spawn::At(u16_at(17), u16_at(19)),
+            _ => return Err(bad()),
+        };
+        let modifiers = match bytes.get(21) {
+            Some(&bits) => Modifiers::from_bits(bits).ok_or_else(bad)?,
+            None => Modifiers::default(),
+        };
+        let (cols, rows) = (u16_at(9), u16_at(11));
+        if cols < 2 || rows < 2 {
+            return Err(bad());
+        }
+
+        Ok(Challenge {
+            seed: u64::from_be_bytes(seed),
+            cols,
+            rows,
+            mode,
+            modifiers,
+            start_length: u16_at(14),
+            spawn,
+        })
+    }
+}
+
+/// URL-safe base64 without padding, what codes are written in.
+pub fn encode_base64(bytes: &[u8]) -> String {
+    let mut out = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        // 1 byte makes 2 characters, 2 make 3 and 3 make 4, no padding
+        for i in 0..chunk.len() + 1 {
+            out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+        }
+    }
+    out
+}
+
+pub fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let mut bytes = Vec::new();
+    for chunk in text.as_bytes().chunks(4) {
+        if chunk.len() < 2 {
+            return None;
+        }
+        let mut n = 0u32;
+        for (i, c) in chunk.iter().enumerate() {
+            let value = ALPHABET.iter().position(|a| a == c)? as u32;
+            n |= value << (18 - 6 * i);
+        }
+        for i in 0..chunk.len() - 1 {
+            bytes.push((n >> (16 - 8 * i)) as u8);
+        }
+    }
+    Some(bytes)
+}
@@ -0,0 +1,40 @@
+// -- Checkpoints
+//
+// Running over a checkpoint tile (halfway through each arcade level) keeps a
+// snapshot of the game, the very bytes a binary save is, and dying goes back
+// to the last one instead of ending the run, a few times per run.
+
+use crate::game::Game;
+use crate::save::{load_game_binary, save_game_binary};
+
+// Times a run can go back to a checkpoint
+pub const RETRIES: u32 = 3;
+
+pub struct Checkpoints {
+    snapshot: Option<Vec<u8>>,
+    pub retries_left: u32,
+}
+
+impl Checkpoints {
+    pub fn new(retries: u32) -> Self {
+        Checkpoints {
+            snapshot: None,
+            retries_left: retries,
+        }
+    }
+
+    pub fn record(&mut self, game: &Game) {
+        self.snapshot = Some(save_game_binary(game));
+    }
+
+    /// The game as it was at the last checkpoint, `None` without one or
+    /// without retries left.
+    pub fn resume(&mut self) -> Option<Game> {
+        if self.retries_left == 0 {
+            return None;
+        }
+        let game = load_game_binary(self.snapshot.as_ref()?).ok()?;
+        self.retries_left -= 1;
+        Some(game)
+    }
+}
@@ -0,0 +1,104 @@
+// -- Adaptive difficulty
+//
+// For casual players (`--adaptive`): instead of only ever getting harder, the
+// game keeps an eye on how it's going and nudges itself towards a band.
+// Every few foods it looks at how many ticks they took. Quick ones make the
+// snake faster and put a wall down. Slow ones, going a long time without
+// food, or dying back to a checkpoint slow it down and take one of those
+// walls away. It only ever removes walls it put there itself, and it counts
+// ticks rather than seconds, so the same moves still play the same.
+
+use crate::entity::{Entity, EntityKind};
+use crate::game::Game;
+use std::collections::VecDeque;
+
+// Foods looked at together, and the ticks per food either side of the band
+const WINDOW: usize = 4;
+const TOO_EASY: u64 = 30;
+const TOO_HARD: u64 = 120;
+// How much one nudge changes the tick, and how far it goes
+const STEP: u64 = 20;
+const FASTEST: u64 = 60;
+const SLOWEST: u64 = 600;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Nudge {
+    Harder,
+    Easier,
+}
+
+#[derive(Default)]
+pub struct Adaptive {
+    // Ticks each of the last few foods took
+    recent: VecDeque<u64>,
+    // Set on the first tick it sees, a loaded game doesn't start at 0
+    last_food: Option<u64>,
+    // The walls it added, the last one first to go
+    walls: Vec<(u16, u16)>,
+}
+
+impl Adaptive {
+    pub fn new() -> Self {
+        Adaptive::default()
+    }
+
+    /// After every tick, `Some` when it changed something.
+    pub fn update(&mut self, game: &mut Game, ate: bool) -> Option<Nudge> {
+        let since = game
+            .ticks
+            .saturating_sub(*self.last_food.get_or_insert(game.ticks));
+        if ate {
+            self.recent.push_back(since);
+            self.last_food = Some(game.ticks);
+            if self.recent.len() < WINDOW {
+                return None;
+            }
+            let average = self.recent.iter().sum::<u64>() / self.recent.len() as u64;
+            self.recent.clear();
+            if average < TOO_EASY {
+                return self.harder(game);
+            }
+            if average > TOO_HARD {
+                return self.easier(game);
+            }
+            return None;
+        }
+        // Stuck without food for that long doesn't wait for the window
+        if since > TOO_HARD * WINDOW as u64 {
+            self.last_food = Some(game.ticks);
+            return self.easier(game);
+        }
+        None
+    }
+
+    /// The run went back to a checkpoint.
+    pub fn died(&mut self, game: &mut Game) -> Option<Nudge> {
+        self.recent.clear();
+        self.last_food = Some(game.ticks);
+        self.easier(game)
+    }
+
+    fn harder(&mut self, game: &mut Game) -> Option<Nudge> {
+        // Games started faster or slower than these never go back towards them
+        game.timer = game.timer.saturating_sub(STEP).max(FASTEST).min(game.timer);
+        if let Some((x, y)) = game.random_free_cell() {
+            game.entities.push(Entity::new(EntityKind::Wall, x, y));
+            self.walls.push((x, y));
+        }
+        Some(Nudge::Harder)
+    }
+
+    fn easier(&mut self, game: &mut Game) -> Option<Nudge> {
+        let timer = (game.timer + STEP).min(SLOWEST.max(game.timer));
+        let wall = self.walls.pop();
+        if timer == game.timer && wall.is_none() {
+            return None;
+        }
+        game.timer = timer;
+        if let Some((x, y)) = wall {
+            game.entities
+                .retain(|e| !(e.kind == EntityKind::Wall && e.at(x, y)));
+        }
+        Some(Nudge::Easier)
+    }
+}
@@ -0,0 +1,194 @@
+// -- Discord Rich Presence
+//
+// Shows what's being played (mode, score, length) on the player's Discord
+// profile. The Discord client listens on a local socket (a named pipe on
+// Windows) that takes frames of `opcode, length, JSON`, little-endian. A
+// handshake with the application id and then one SET_ACTIVITY frame per
+// update is all it takes.
+//
+// Everything runs on its own thread: the game loop only drops the latest
+// activity in a channel, so a slow or missing Discord never holds up a tick.
+// Updates are throttled, Discord drops the ones that come too fast anyway.
+
+use crate::json;
+use std::io::{self, Read, Write};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const HANDSHAKE: u32 = 0;
+const FRAME: u32 = 1;
+const UPDATE_EVERY: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Activity {
+    pub mode: String,
+    pub score: u32,
+    pub length: usize,
+    pub paused: bool,
+    // Seconds since the epoch when the run started, Discord counts up from it
+    pub started: u64,
+}
+
+/// The game loop's end, sending never blocks or fails.
+pub struct Presence {
+    sender: Sender<Activity>,
+    last: Option<Activity>,
+}
+
+impl Presence {
+    /// Start the thread that talks to Discord, as the application `client_id`.
+    pub fn start(client_id: String) -> Presence {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || run(&client_id, receiver));
+        Presence { sender, last: None }
+    }
+
+    /// Call as often as you like, only changes go to the thread.
+    pub fn update(&mut self, activity: Activity) {
+        if self.last.as_ref() != Some(&activity) {
+            self.last = Some(activity.clone());
+            // The thread may have given up on Discord, that's fine
+            let _ = self.sender.send(activity);
+        }
+    }
+}
+
+fn run(client_id: &str, receiver: Receiver<Activity>) {
+    let mut connection: Option<Connection> = None;
+    let mut latest: Option<Activity> = None;
+    let mut last_sent = Instant::now() - UPDATE_EVERY;
+
+    loop {
+        let wait = UPDATE_EVERY.saturating_sub(last_sent.elapsed());
+        match receiver.recv_timeout(wait) {
+            Ok(activity) => {
+                latest = Some(activity);
+                // Keep only the newest of whatever piled up
+                while let Ok(activity) = receiver.try_recv() {
+                    latest = Some(activity);
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            // The game is over
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+
+        if last_sent.elapsed() < UPDATE_EVERY {
+            continue;
+        }
+        let Some(activity) = latest.take() else {
+            continue;
+        };
+        last_sent = Instant::now();
+
+        if connection.is_none() {
+            connection = Connection::open(client_id).ok();
+        }
+        if let Some(ref mut conn) = connection
+            && conn.set_activity(&activity).is_err()
+        {
+            // Discord went away, try again with the next update
+            connection = None;
+            latest = Some(activity);
+        }
+    }
+}
+
+struct Connection {
+    #[cfg(unix)]
+    stream: std::os::unix::net::UnixStream,
+    #[cfg(not(unix))]
+    stream: std::fs::File,
+    nonce: u64,
+}
+
+impl Connection {
+    fn open(client_id: &str) -> io::Result<Connection> {
+        let mut conn = Connection {
+            stream: connect()?,
+            nonce: 0,
+        };
+        conn.send(
+            HANDSHAKE,
+            &format!(r#"{{"v":1,"client_id":{}}}"#, json::string(client_id)),
+        )?;
+        conn.receive()?;
+        Ok(conn)
+    }
+
+    fn set_activity(&mut self, activity: &Activity) -> io::Result<()> {
+        self.nonce += 1;
+        let state = if activity.paused {
+            "Paused".to_string()
+        } else {
+            format!("Score {}, length {}", activity.score, activity.length)
+        };
+        let payload = format!(
+            r#"{{"cmd":"SET_ACTIVITY","nonce":"{}","args":{{"pid":{},"activity":{{"details":{},"state":{},"timestamps":{{"start":{}}}}}}}}}"#,
+            self.nonce,
+            std::process::id(),
+            json::string(&format!("Playing {}", activity.mode)),
+            json::string(&state),
+            activity.started
+        );
+        self.send(FRAME, &payload)?;
+        self.receive()
+    }
+
+    fn send(&mut self, opcode: u32, payload: &str) -> io::Result<()> {
+        let mut frame = Vec::with_capacity(8 + payload.len());
+        frame.extend(opcode.to_le_bytes());
+        frame.extend((payload.len() as u32).to_le_bytes());
+        frame.extend(payload.as_bytes());
+        self.stream.write_all(&frame)
+    }
+
+    // The replies don't matter, but they have to be read
+    fn receive(&mut self) -> io::Result<()> {
+        let mut header = [0; 8];
+        self.stream.read_exact(&mut header)?;
+        let len = u32::from_le_bytes([header[4], header[5], header[6], header[7]]);
+        io::copy(&mut (&mut self.stream).take(len as u64), &mut io::sink())?;
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn connect() -> io::Result<std::os::unix::net::UnixStream> {
+    let dir = ["XDG_RUNTIME_DIR", "TMPDIR", "TMP", "TEMP"]
+        .iter()
+        .find_map(std::env::var_os)
+        .map_or_else(
+            || std::path::PathBuf::from("/tmp"),
+            std::path::PathBuf::from,
+        );
+    let mut last_error = io::Error::from(io::ErrorKind::NotFound);
+    // Discord takes the first free one of ten
+    for i in 0..10 {
+        match std::os::unix::net::UnixStream::connect(dir.join(format!("discord-ipc-{i}"))) {
+            Ok(stream) => {
+                stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+                return Ok(stream);
+            }
+            Err(e) => last_error = e,
+        }
+    }
+    Err(last_error)
+}
+
+#[cfg(not(unix))]
+fn connect() -> io::Result<std::fs::File> {
+    let mut last_error = io::Error::from(io::ErrorKind::NotFound);
+    for i in 0..10 {
+        match std::fs::OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(format!(r"\\?\pipe\discord-ipc-{i}"))
+        {
+            Ok(file) => return Ok(file),
+            Err(e) => last_error = e,
+        }
+    }
+    Err(last_error)
+}
@@ -0,0 +1,405 @@
+// -- Finding game servers on the LAN
+//
+// `snake2 server` answers multicast DNS (RFC 6762) for `_snake2._tcp.local`
+// the way printers and speakers do, so `snake2 join` without an address can
+// ask who's around and list them. Nothing is sent unless asked: a query
+// gets the usual DNS-SD records back (RFC 6763), the instance by its name,
+// its port, its address and a TXT record with what it plays and how it's
+// going, players connected and the best score among them.
+//
+// Just enough DNS is written out here, like WebSocket is in `server.rs`:
+// questions and the four kinds of records this needs, names compressed or
+// not. Queries go out from a port of their own, which asks responders for
+// a unicast answer straight back ("legacy unicast"), so browsing doesn't
+// need 5353. Answering does, and other responders (Avahi, Bonjour) may
+// have it already, which is fine on unix where it's shared.
+
+use std::collections::BTreeMap;
+use std::io;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, SocketAddrV4, UdpSocket};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub const SERVICE: &str = "_snake2._tcp.local";
+const GROUP: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+const PORT: u16 = 5353;
+// Seconds others may keep the answers
+const TTL: u32 = 120;
+
+const PTR: u16 = 12;
+const TXT: u16 = 16;
+const A: u16 = 1;
+const SRV: u16 = 33;
+const ANY: u16 = 255;
+
+/// A server that answered, as `snake2 join` lists it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Host {
+    pub name: String,
+    pub address: SocketAddr,
+    // The TXT record's `key=value` strings
+    pub info: Vec<String>,
+}
+
+impl Host {
+    pub fn url(&self) -> String {
+        format!("ws://{}", self.address)
+    }
+}
+
+/// Answer queries for the game server on `port` as `name`, on a thread of
+/// its own, for as long as the server runs. `info` is asked for the TXT
+/// record every time, so it's always how things are right then.
+pub fn advertise(
+    name: &str,
+    port: u16,
+    info: impl Fn() -> Vec<String> + Send + 'static,
+) -> io::Result<()> {
+    let socket = bind_shared(PORT)?;
+    socket.join_multicast_v4(&GROUP, &Ipv4Addr::UNSPECIFIED)?;
+    // Dots would split the name in more labels
+    let label: String = name.replace('.', "-").chars().take(63).collect();
+    let instance = format!("{label}.{SERVICE}");
+    let target = format!("{}.local", label.replace(' ', "-"));
+    thread::spawn(move || {
+        let mut buffer = [0; 1500];
+        loop {
+            let Ok((len, from)) = socket.recv_from(&mut buffer) else {
+                continue;
+            };
+            let packet = &buffer[..len];
+            if !asked_for_us(packet) {
+                continue;
+            }
+            let Some(ip) = local_ip(from) else {
+                continue;
+            };
+            let id = u16::from_be_bytes([packet[0], packet[1]]);
+            let answer = answer(id, &instance, &target, port, ip, &info());
+            // A querier on 5353 listens to the group, anyone else for a reply
+            let to = match from.port() {
+                PORT => SocketAddr::V4(SocketAddrV4::new(GROUP, PORT)),
+                _ => from,
+            };
+            let _ = socket.send_to(&answer, to);
+        }
+    });
+    Ok(())
+}
+
+/// Ask the LAN for game servers and list the ones that answer in `wait`.
+pub fn browse(wait: Duration) -> io::Result<Vec<Host>> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+    let mut query = header(0, 0, 1, 0, 0);
+    write_name(&mut query, SERVICE);
+    query.extend(PTR.to_be_bytes());
+    query.extend(1u16.to_be_bytes());
+    socket.send_to(&query, (GROUP, PORT))?;
+
+    let mut hosts: BTreeMap<String, Host> = BTreeMap::new();
+    let deadline = Instant::now() + wait;
+    let mut buffer = [0; 1500];
+    loop {
+        let left = deadline.saturating_duration_since(Instant::now());
+        if left.is_zero() {
+            break;
+        }
+        socket.set_read_timeout(Some(left))?;
+        let (len, from) = match socket.recv_from(&mut buffer) {
+            Ok(received) => received,
+            Err(e)
+                if matches!(
+                    e.kind(),
+                    io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                ) =>
+            {
+                break;
+            }
+            Err(e) => return Err(e),
+        };
+        for host in read_answer(&buffer[..len], from.ip()) {
+            hosts.insert(host.name.clone(), host);
+        }
+    }
+    Ok(hosts.into_values().collect())
+}
+
+// Whether `packet` is a query with a question about us in it
+fn asked_for_us(packet: &[u8]) -> bool {
+    let asked = || -> Option<bool> {
+        let flags = u16::from_be_bytes([*packet.get(2)?, *packet.get(3)?]);
+        // Answers aren't questions
+        if flags & 0x8000 != 0 {
+            return Some(false);
+        }
+        let questions = u16::from_be_bytes([*packet.get(4)?, *packet.get(5)?]);
+        let mut at = 12;
+        for _ in 0..questions {
+            let (name, next) = read_name(packet, at)?;
+            let kind = u16::from_be_bytes([*packet.get(next)?, *packet.get(next + 1)?]);
+            at = next + 4;
+            if name.eq_ignore_ascii_case(SERVICE) && (kind == PTR || kind == ANY) {
+                return Some(true);
+            }
+        }
+        Some(false)
+    };
+    asked().unwrap_or(false)
+}
+
+// The records for query `id`, with the question again as legacy unicast
+// wants
+fn answer(
+    id: u16,
+    instance: &str,
+    target: &str,
+    port: u16,
+    ip: Ipv4Addr,
+    info: &[String],
+) -> Vec<u8> {
+    let mut packet = header(id, 0x8400, 1, 1, 3);
+    write_name(&mut packet, SERVICE);
+    packet.extend(PTR.to_be_bytes());
+    packet.extend(1u16.to_be_bytes());
+
+    let mut rdata = Vec::new();
+    write_name(&mut rdata, instance);
+    record(&mut packet, SERVICE, PTR, &rdata);
+
+    let mut rdata = [0, 0, 0, 0].to_vec();
+    rdata.extend(port.to_be_bytes());
+    write_name(&mut rdata, target);
+    record(&mut packet, instance, SRV, &rdata);
+
+    let mut rdata = Vec::new();
+    for item in info {
+        let item = &item.as_bytes()[..item.len().min(255)];
+        rdata.push(item.len() as u8);
+        rdata.extend(item);
+    }
+    record(&mut packet, instance, TXT, &rdata);
+
+    record(&mut packet, target, A, &ip.octets());
+    packet
+}
+
+// Every host in an answer, at `from` when it doesn't say its address
+fn read_answer(packet: &[u8], from: IpAddr) -> Vec<Host> {
+    let Some(records) = read_records(packet) else {
+        return Vec::new();
+    };
+    let mut hosts = Vec::new();
+    for (name, kind, rdata_at, _) in &records {
+        if *kind != PTR || !name.eq_ignore_ascii_case(SERVICE) {
+            continue;
+        }
+        let Some((instance, _)) = read_name(packet, *rdata_at) else {
+            continue;
+        };
+        let find = |wanted: u16, owner: &str| {
+            records
+                .iter()
+                .find(|(name, kind, ..)| *kind == wanted && name.eq_ignore_ascii_case(owner))
+        };
+        let Some(&(_, _, srv, _)) = find(SRV, &instance) else {
+            continue;
+        };
+        let Some(port) = packet.get(srv + 4..srv + 6) else {
+            continue;
+        };
+        let port = u16::from_be_bytes([port[0], port[1]]);
+        let target = read_name(packet, srv + 6)
+            .map(|(t, _)| t)
+            .unwrap_or_default();
+        let ip = match find(A, &target) {
+            Some(&(_, _, at, 4)) => packet
+                .get(at..at + 4)
+                .map_or(from, |o| IpAddr::from([o[0], o[1], o[2], o[3]])),
+            _ => from,
+        };
+        let info = find(TXT, &instance).map_or(Vec::new(), |&(_, _, at, len)| {
+            read_strings(packet.get(at..at + len).unwrap_or_default())
+        });
+        let name = instance
+            .strip_suffix(&format!(".{SERVICE}"))
+            .unwrap_or(&instance)
+            .to_string();
+        hosts.push(Host {
+            name,
+            address: SocketAddr::new(ip, port),
+            info,
+        });
+    }
+    hosts
+}
+
+// Every record after the questions: its name, type, where its data starts
+// and how long it is
+fn read_records(packet: &[u8]) -> Option<Vec<(String, u16, usize, usize)>> {
+    let count = |at: usize| -> Option<u16> {
+        Some(u16::from_be_bytes([*packet.get(at)?, *packet.get(at + 1)?]))
+    };
+    let flags = count(2)?;
+    if flags & 0x8000 == 0 {
+        return None;
+    }
+    let questions = count(4)?;
+    // Three counts off the wire, in `usize` so they can't overflow adding up
+    let records = [6, 8, 10]
+        .into_iter()
+        .map(|at| count(at).map(usize::from))
+        .sum::<Option<usize>>()?;
+    let mut at = 12;
+    for _ in 0..questions {
+        at = read_name(packet, at)?.1 + 4;
+    }
+    let mut out = Vec::new();
+    for _ in 0..records {
+        let (name, next) = read_name(packet, at)?;
+        let kind = count(next)?;
+        let len = usize::from(count(next + 8)?);
+        let rdata_at = next + 10;
+        if rdata_at + len > packet.len() {
+            return None;
+        }
+        out.push((name, kind, rdata_at, len));
+        at = rdata_at + len;
+    }
+    Some(out)
+}
+
+// A name at `at`, following compression pointers, and where it ends
+fn read_name(packet: &[u8], mut at: usize) -> Option<(String, usize)> {
+    let mut labels = Vec::new();
+    let mut end = None;
+    // Pointers only ever go back, more jumps than bytes is a loop
+    for _ in 0..packet.len() {
+        let len = *packet.get(at)?;
+        match len {
+            0 => {
+                return Some((labels.join("."), end.unwrap_or(at + 1)));
+            }
+            len if len & 0xC0 == 0xC0 => {
+                let pointer = usize::from(u16::from_be_bytes([len & 0x3F, *packet.get(at + 1)?]));
+                end.get_or_insert(at + 2);
+                at = pointer;
+            }
+            len => {
+                let label = packet.get(at + 1..at + 1 + usize::from(len))?;
+                labels.push(String::from_utf8_lossy(label).into_owned());
+                at += 1 + usize::from(len);
+            }
+        }
+    }
+    None
+}
+
+fn read_strings(mut data: &[u8]) -> Vec<String> {
+    let mut strings = Vec::new();
+    while let Some((&len, rest)) = data.split_first() {
+        let len = usize::from(len).min(rest.len());
+        strings.push(String::from_utf8_lossy(&rest[..len]).into_owned());
+        data = &rest[len..];
+    }
+    strings
+}
+
+fn header(id: u16, flags: u16, questions: u16, answers: u16, additional: u16) -> Vec<u8> {
+    [id, flags, questions, answers, 0, additional]
+        .iter()
+        .flat_map(|n| n.to_be_bytes())
+        .collect()
+}
+
+fn write_name(packet: &mut Vec<u8>, name: &str) {
+    for label in name.split('.').filter(|l| !l.is_empty()) {
+        let label = &label.as_bytes()[..label.len().min(63)];
+        packet.push(label.len() as u8);
+        packet.extend(label);
+    }
+    packet.push(0);
+}
+
+fn record(packet: &mut Vec<u8>, name: &str, kind: u16, rdata: &[u8]) {
+    write_name(packet, name);
+    packet.extend(kind.to_be_bytes());
+    packet.extend(1u16.to_be_bytes());
+    packet.extend(TTL.to_be_bytes());
+    packet.extend((rdata.len() as u16).to_be_bytes());
+    packet.extend(rdata);
+}
+
+/// What this computer calls itself, for the name a server goes by.
+pub fn host_name() -> String {
+    #[cfg(unix)]
+    {
+        let mut name = [0u8; 256];
+        let got = unsafe { libc::gethostname(name.as_mut_ptr().cast(), name.len()) };
+        let end = name.iter().position(|&b| b == 0).unwrap_or(name.len());
+        if got == 0 && end > 0 {
+            return String::from_utf8_lossy(&name[..end]).into_owned();
+        }
+    }
+    std::env::var("COMPUTERNAME").unwrap_or_else(|_| "snake2".to_string())
+}
+
+// Our address on the network `to` is on, what goes in the A record
+fn local_ip(to: SocketAddr) -> Option<Ipv4Addr> {
+    let socket = UdpSocket::bind((Ipv4Addr::UNSPECIFIED, 0)).ok()?;
+    // Nothing is sent, connecting only picks the interface
+    socket.connect(to).ok()?;
+    match socket.local_addr().ok()?.ip() {
+        IpAddr::V4(ip) if !ip.is_unspecified() => Some(ip),
+        _ => None,
+    }
+}
+
+// A UDP socket on `port` other programs can have too
+#[cfg(unix)]
+fn bind_shared(port: u16) -> io::Result<UdpSocket> {
+    use std::os::fd::FromRawFd;
+
+    let fd = unsafe { libc::socket(libc::AF_INET, libc::SOCK_DGRAM, 0) };
+    if fd < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    // Owned from here, so it's closed whatever goes wrong
+    let socket = unsafe { UdpSocket::from_raw_fd(fd) };
+    let on: libc::c_int = 1;
+    for option in [libc::SO_REUSEADDR, libc::SO_REUSEPORT] {
+        let set = unsafe {
+            libc::setsockopt(
+                fd,
+                libc::SOL_SOCKET,
+                option,
+                &on as *const libc::c_int as *const libc::c_void,
+                size_of::<libc::c_int>() as libc::socklen_t,
+            )
+        };
+        if set < 0 {
+            return Err(io::Error::last_os_error());
+        }
+    }
+    let address = libc::sockaddr_in {
+        sin_family: libc::AF_INET as libc::sa_family_t,
+        sin_port: port.to_be(),
+        sin_addr: libc::in_addr { s_addr: 0 },
+        ..unsafe { std::mem::zeroed() }
+    };
+    let bound = unsafe {
+        libc::bind(
+            fd,
+            &address as *const libc::sockaddr_in as *const libc::sockaddr,
+            size_of::<libc::sockaddr_in>() as libc::socklen_t,
+        )
+    };
+    if bound < 0 {
+        return Err(io::Error::last_os_error());
+    }
+    Ok(socket)
+}
+
+#[cfg(not(unix))]
+fn bind_shared(port: u16) -> io::Result<UdpSocket> {
+    UdpSocket::bind((Ipv4Addr::UNSPECIFIED, port))
+}
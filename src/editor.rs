@@ -0,0 +1,362 @@
+// -- Level editor
+//
+// `snake2 edit level.txt` opens a level (see `level.rs`), or starts a new
+// one the size of the terminal, with a cursor to paint it. A key for each
+// kind of cell picks it as the brush and paints the cell under the cursor,
+// Enter puts the brush down so moving paints too. `t` plays the level as
+// it is right there, `w` writes it to the file. Both check it first (see
+// `Level::problems`) and take the cursor to the first thing wrong, a level
+// that isn't done yet is still written but not played.
+
+use crate::backend::{Backend, Style};
+use crate::game::{GameOptions, TickOutcome};
+use crate::input::{IDLE_WAIT, Input, next_event, next_input};
+use crate::layout::Rect;
+use crate::level::{Cell, Level};
+use crate::render::{StatusBar, draw_game, draw_overlay};
+use crate::snake::SnakeDirection;
+use crate::terminal::{
+    CrosstermBackend, disable_game_mode, enable_game_mode, setup_panic_hook, suspend, take_resumed,
+    watch_job_control,
+};
+use crate::theme::Theme;
+use crate::toast::Toasts;
+use crossterm::event::{Event, KeyCode, KeyModifiers};
+use crossterm::terminal;
+use std::io;
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+const KEYS: [(&str, &str); 8] = [
+    ("arrows", "move the cursor"),
+    ("# ^ 1-9", "wall, hazard, portal"),
+    ("S f", "where the snake starts, food zone"),
+    ("space .", "erase"),
+    ("Enter", "paint while moving, on or off"),
+    ("t", "test play, q comes back"),
+    ("w", "write the file"),
+    ("q", "quit"),
+];
+
+pub fn run(path: &Path, theme: &Theme) -> io::Result<()> {
+    let (cols, rows) = terminal::size()?;
+    let level = match std::fs::read_to_string(path) {
+        Ok(text) => Level::parse_unchecked(&text).map_err(|e| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("{}: {e}", path.display()),
+            )
+        })?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {
+            // The last row is for the status bar
+            let mut level = Level::new(cols, rows.saturating_sub(1).max(2));
+            if let Some(stem) = path.file_stem() {
+                level.name = stem.to_string_lossy().into_owned();
+            }
+            level
+        }
+        Err(e) => return Err(e),
+    };
+    if level.cols > cols || level.rows + 1 > rows {
+        eprintln!(
+            "This level needs a terminal of at least {}x{}.",
+            level.cols,
+            level.rows + 1
+        );
+        std::process::exit(1)
+    }
+
+    setup_panic_hook();
+    watch_job_control();
+    let mut stdout = io::stdout();
+    enable_game_mode(&mut stdout)?;
+    let mut out = CrosstermBackend::new(stdout);
+    let mut editor = Editor {
+        level,
+        name: path.file_name().map_or_else(
+            || path.display().to_string(),
+            |n| n.to_string_lossy().into_owned(),
+        ),
+        x: 0,
+        y: 0,
+        brush: Cell::Wall,
+        pen_down: false,
+        changed: false,
+        show_help: false,
+        toasts: Toasts::new(),
+    };
+    let mut screen_area = Rect::screen(cols, rows);
+    // Quitting with changes not written asks for a second `q`
+    let mut quitting = false;
+    // Only when something changed, a cursor sitting still needs no frames
+    let mut redraw = true;
+
+    loop {
+        redraw |= take_resumed(&mut out.stdout)?;
+        let now = Instant::now();
+        editor.toasts.update(now);
+        if redraw {
+            editor.draw(&mut out, screen_area, theme)?;
+        }
+
+        let timeout = editor
+            .toasts
+            .next_expiry()
+            .map_or(IDLE_WAIT, |at| at.saturating_duration_since(now));
+        let event = next_event(timeout)?;
+        // A toast going away needs a frame too
+        redraw = event.is_some()
+            || editor
+                .toasts
+                .next_expiry()
+                .is_some_and(|at| at <= Instant::now());
+        let key = match event {
+            Some(Event::Resize(cols, rows)) => {
+                screen_area = Rect::screen(cols, rows);
+                continue;
+            }
+            Some(Event::Key(key)) => key,
+            _ => continue,
+        };
+        editor.show_help = false;
+
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        let quit = matches!(key.code, KeyCode::Char('q') | KeyCode::Esc)
+            || (ctrl && key.code == KeyCode::Char('c'));
+        if quit {
+            if !editor.changed || quitting {
+                break;
+            }
+            quitting = true;
+            editor
+                .toasts
+                .push("Not written, w writes it, q again quits without");
+            continue;
+        }
+        quitting = false;
+
+        match key.code {
+            KeyCode::Char('z') if ctrl => suspend(&mut out.stdout)?,
+            KeyCode::Up => editor.move_to(editor.x, editor.y.saturating_sub(1)),
+            KeyCode::Down => editor.move_to(editor.x, editor.y + 1),
+            KeyCode::Left => editor.move_to(editor.x.saturating_sub(1), editor.y),
+            KeyCode::Right => editor.move_to(editor.x + 1, editor.y),
+            KeyCode::Enter => {
+                editor.pen_down = !editor.pen_down;
+                if editor.pen_down {
+                    editor.paint(editor.brush);
+                }
+            }
+            KeyCode::Char('?') => editor.show_help = true,
+            KeyCode::Char('t') => match editor.first_problem() {
+                Some(problem) => editor.toasts.push(format!("Can't play it yet: {problem}")),
+                None => test_play(&mut out, &editor.level, theme, &mut screen_area)?,
+            },
+            KeyCode::Char('w') => {
+                let written = std::fs::write(path, editor.level.to_text());
+                let toast = match (written, editor.first_problem()) {
+                    (Err(e), _) => format!("Couldn't write it: {e}"),
+                    (Ok(()), problem) => {
+                        editor.changed = false;
+                        match problem {
+                            Some(problem) => format!("Written, but {problem}"),
+                            None => format!("Written to {}", editor.name),
+                        }
+                    }
+                };
+                editor.toasts.push(toast);
+            }
+            code => {
+                let cell = match code {
+                    KeyCode::Char('s') => Some(Cell::Spawn),
+                    KeyCode::Char(' ') | KeyCode::Backspace | KeyCode::Delete => Some(Cell::Empty),
+                    KeyCode::Char(c) => Cell::from_symbol(c),
+                    _ => None,
+                };
+                if let Some(cell) = cell {
+                    editor.brush = cell;
+                    editor.paint(cell);
+                }
+            }
+        }
+    }
+
+    disable_game_mode(&mut out.stdout)
+}
+
+struct Editor {
+    level: Level,
+    // The file's, for the status bar
+    name: String,
+    // The cursor
+    x: u16,
+    y: u16,
+    brush: Cell,
+    // Moving paints with the brush
+    pen_down: bool,
+    // Since it was last written
+    changed: bool,
+    show_help: bool,
+    toasts: Toasts,
+}
+
+impl Editor {
+    fn paint(&mut self, cell: Cell) {
+        if self.level.get(self.x, self.y) != cell {
+            self.level.set(self.x, self.y, cell);
+            self.changed = true;
+        }
+    }
+
+    // What `Level::parse` would say about it, with the cursor taken there
+    fn first_problem(&mut self) -> Option<String> {
+        let problem = self.level.problems().into_iter().next()?;
+        match problem.at {
+            Some((x, y)) => {
+                (self.x, self.y) = (x, y);
+                Some(format!("{x},{y}: {}", problem.message))
+            }
+            None => Some(problem.message),
+        }
+    }
+
+    // Kept on the level, painting on the way when the pen is down
+    fn move_to(&mut self, x: u16, y: u16) {
+        self.x = x.min(self.level.cols - 1);
+        self.y = y.min(self.level.rows - 1);
+        if self.pen_down {
+            self.paint(self.brush);
+        }
+    }
+
+    fn draw(&self, out: &mut CrosstermBackend, screen_area: Rect, theme: &Theme) -> io::Result<()> {
+        out.clear()?;
+        let (board_area, status_area) = screen_area.split_bottom(1);
+        for (x, y, cell) in self.level.iter() {
+            let (glyph, style) = cell_look(cell, theme);
+            let style = if (x, y) == (self.x, self.y) {
+                style.reverse()
+            } else {
+                style
+            };
+            out.print(x, y, glyph, style)?;
+        }
+        self.toasts.draw(out, board_area, theme)?;
+        if self.show_help {
+            let lines: Vec<String> = KEYS
+                .iter()
+                .map(|(keys, what)| format!("{keys:<8} {what}"))
+                .collect();
+            draw_overlay(out, "Keys", &lines, board_area, theme)?;
+        }
+        let mode = format!(
+            "edit {}{}  {},{}  brush {}{}",
+            self.name,
+            if self.changed { "*" } else { "" },
+            self.x,
+            self.y,
+            self.brush.symbol(),
+            if self.pen_down { " (down)" } else { "" }
+        );
+        let status = StatusBar {
+            mode: &mode,
+            hints: "? keys  t test  w write  q quit",
+            elapsed: None,
+            rival: None,
+        };
+        status.draw(out, None, status_area, theme)?;
+        out.flush()
+    }
+}
+
+/// How a cell of the level looks in the editor, like it does in a game.
+fn cell_look(cell: Cell, theme: &Theme) -> (&'static str, Style) {
+    match cell {
+        Cell::Empty => (".", Style::plain()),
+        Cell::Spawn => (theme.head_glyph(SnakeDirection::Right), theme.snake_head()),
+        Cell::Food => ("f", theme.food()),
+        cell => match cell.entity_kind() {
+            Some(kind) => (theme.entity_glyph(kind), theme.entity_style(kind)),
+            None => ("?", Style::plain()),
+        },
+    }
+}
+
+/// Play the level until the snake dies or `q`, the first arrow starts it.
+fn test_play(
+    out: &mut CrosstermBackend,
+    level: &Level,
+    theme: &Theme,
+    screen_area: &mut Rect,
+) -> io::Result<()> {
+    let options = GameOptions {
+        seed: Some(rand::random()),
+        ..Default::default()
+    };
+    let mut game = level.start(SnakeDirection::Right, &options);
+    let mut playing = false;
+    let mut over = false;
+    let mut next_tick = Instant::now();
+
+    loop {
+        if take_resumed(&mut out.stdout)? {
+            playing = false;
+        }
+        let now = Instant::now();
+        if playing && !over && now >= next_tick {
+            over = game.tick() == TickOutcome::Died;
+            next_tick = (next_tick + Duration::from_millis(game.timer)).max(now);
+        }
+
+        out.clear()?;
+        let (board_area, status_area) = screen_area.split_bottom(1);
+        draw_game(out, &game, theme)?;
+        if over {
+            let lines = [
+                format!("Score {}, length {}", game.score, game.snake.body.len()),
+                "Any key goes back to the editor".to_string(),
+            ];
+            draw_overlay(out, "Game over", &lines, board_area, theme)?;
+        }
+        let status = StatusBar {
+            mode: "testing",
+            hints: if playing {
+                "q back to the editor"
+            } else {
+                "arrows start  q back to the editor"
+            },
+            elapsed: None,
+            rival: None,
+        };
+        status.draw(out, Some(&game), status_area, theme)?;
+        out.flush()?;
+
+        let timeout = if playing && !over {
+            next_tick.saturating_duration_since(now)
+        } else {
+            IDLE_WAIT
+        };
+        match next_input(timeout)? {
+            Some(Input::Resize(cols, rows)) => *screen_area = Rect::screen(cols, rows),
+            Some(Input::Quit) => return Ok(()),
+            Some(_) if over => return Ok(()),
+            Some(Input::Suspend) => {
+                suspend(&mut out.stdout)?;
+                playing = false;
+            }
+            // Stopped after Ctrl+Z, an arrow carries on
+            Some(Input::Turn(direction)) if !playing && game.ticks > 0 => {
+                game.turn(direction);
+                playing = true;
+                next_tick = Instant::now();
+            }
+            Some(Input::Turn(direction)) if !playing => {
+                game = level.start(direction, &options);
+                playing = true;
+                next_tick = Instant::now();
+            }
+            Some(Input::Turn(direction)) => game.turn(direction),
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,335 @@
+// -- Everything on the board that isn't the snake
+//
+// Food, walls and whatever comes next are all `Entity`s: where it is, what
+// it is (which decides what happens when the head runs into it and how the
+// theme draws it) and how it moves on its own every tick.
+//
+// Power-ups are entities too: the magnet doesn't grow the snake, it makes
+// the food close to the head drift towards it for a while. So are bosses,
+// a block of `Boss` cells patrolling together with numbered weak points that
+// have to be eaten in order. Hazards and portals come from hand-made levels
+// (see `level.rs`). Food chains, numbered too, come and go on a clock (see
+// `objective.rs`). In word mode the food is letters (see `rules::Words`),
+// and in mirror mode the snake's own ghost is made of entities too (see
+// `rules::Mirror`). Hill zones are a patch of cells to keep the head on for
+// points while they last (see `objective.rs`). In hydra mode some food
+// splits the snake in two (see `rules::Hydra`), and in surround mode where
+// the snake was fades away a cell at a time (see `rules::Surround`).
+
+use crate::rng::GameRng;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EntityKind {
+    // Eating it grows the snake and scores a point
+    Food,
+    // Running into it kills the snake
+    Wall,
+    // Picking it up pulls food in for a while
+    Magnet,
+    // Going through it leads to another room
+    Door,
+    // A piece of a boss, as deadly as a wall
+    Boss,
+    // Eating all of a boss's in order, 1 first, beats it
+    WeakPoint(u8),
+    // Running over it keeps the game as it is to go back to, see `checkpoint.rs`
+    Checkpoint,
+    // Running over it costs the snake the end of its tail, `HAZARD_CUT` cells
+    Hazard,
+    // The head goes in and comes out of the other portal with the same number
+    Portal(u8),
+    // A piece of a food chain, eating all of them in order in time is a bonus
+    Chain(u8),
+    // Food with an uppercase ASCII letter on it, which the snake carries after
+    Letter(u8),
+    // Where the snake was a while ago, as deadly as a wall
+    Ghost,
+    // A cell of a hill zone, the head in it scores now and then
+    Zone,
+    // Food that splits the tail half of the snake off into another head
+    Split,
+    // Where the snake's tail was, as deadly as a wall until it's gone, from
+    // `FADING_STAGES` just left down to 1 about to go
+    Fading(u8),
+}
+
+/// How many looks a `Fading` cell goes through on its way out.
+pub const FADING_STAGES: usize = 3;
+
+impl EntityKind {
+    pub const ALL: [EntityKind; 15] = [
+        EntityKind::Food,
+        EntityKind::Wall,
+        EntityKind::Magnet,
+        EntityKind::Door,
+        EntityKind::Boss,
+        EntityKind::WeakPoint(1),
+        EntityKind::Checkpoint,
+        EntityKind::Hazard,
+        EntityKind::Portal(1),
+        EntityKind::Chain(1),
+        EntityKind::Letter(b'A'),
+        EntityKind::Ghost,
+        EntityKind::Zone,
+        EntityKind::Split,
+        EntityKind::Fading(1),
+    ];
+
+    pub fn describe(self) -> &'static str {
+        match self {
+            EntityKind::Food => "food, eat it to grow and score",
+            EntityKind::Wall => "wall, don't run into it",
+            EntityKind::Magnet => "magnet, nearby food comes to you for a while",
+            EntityKind::Door => "door, to the next room",
+            EntityKind::Boss => "boss, don't run into it",
+            EntityKind::WeakPoint(_) => "boss weak point, eat them in order",
+            EntityKind::Checkpoint => "checkpoint, dying takes you back here",
+            EntityKind::Hazard => "hazard, running over it costs the end of the tail",
+            EntityKind::Portal(_) => "portal, out of the other one with the same number",
+            EntityKind::Chain(_) => "food chain, eat 1, 2, 3 in order before it's gone",
+            EntityKind::Letter(_) => "letter, food that spells the word when eaten in order",
+            EntityKind::Ghost => "your ghost, where you were a while ago, don't run into it",
+            EntityKind::Zone => "hill zone, points for keeping your head in it while it lasts",
+            EntityKind::Split => "hydra food, splits off your tail as another head",
+            EntityKind::Fading(_) => "where your tail was, don't run into it before it fades",
+        }
+    }
+
+    /// How saves and other programs call it: `food`, `wall`, `weak1`,
+    /// `letterA`...
+    pub fn name(self) -> String {
+        match self {
+            EntityKind::Food => "food".to_string(),
+            EntityKind::Wall => "wall".to_string(),
+            EntityKind::Magnet => "magnet".to_string(),
+            EntityKind::Door => "door".to_string(),
+            EntityKind::Boss => "boss".to_string(),
+            EntityKind::WeakPoint(n) => format!("weak{n}"),
+            EntityKind::Checkpoint => "checkpoint".to_string(),
+            EntityKind::Hazard => "hazard".to_string(),
+            EntityKind::Portal(n) => format!("portal{n}"),
+            EntityKind::Chain(n) => format!("chain{n}"),
+            EntityKind::Letter(c) => format!("letter{}", c as char),
+            EntityKind::Ghost => "ghost".to_string(),
+            EntityKind::Zone => "zone".to_string(),
+            EntityKind::Split => "split".to_string(),
+            EntityKind::Fading(n) => format!("fading{n}"),
+        }
+    }
+
+    /// The other way round from `name`.
+    pub fn from_name(name: &str) -> Option<EntityKind> {
+        match name {
+            "food" => Some(EntityKind::Food),
+            "wall" => Some(EntityKind::Wall),
+            "magnet" => Some(EntityKind::Magnet),
+            "door" => Some(EntityKind::Door),
+            "boss" => Some(EntityKind::Boss),
+            "checkpoint" => Some(EntityKind::Checkpoint),
+            "hazard" => Some(EntityKind::Hazard),
+            "ghost" => Some(EntityKind::Ghost),
+            "zone" => Some(EntityKind::Zone),
+            "split" => Some(EntityKind::Split),
+            _ if name.starts_with("letter") => match name.as_bytes() {
+                [.., c] if name.len() == 7 && c.is_ascii_uppercase() => {
+                    Some(EntityKind::Letter(*c))
+                }
+                _ => None,
+            },
+            _ => {
+                let (kind, n): (fn(u8) -> EntityKind, &str) =
+                    match ["weak", "portal", "chain", "fading"].map(|p| name.strip_prefix(p)) {
+                        [Some(n), _, _, _] => (EntityKind::WeakPoint, n),
+                        [_, Some(n), _, _] => (EntityKind::Portal, n),
+                        [_, _, Some(n), _] => (EntityKind::Chain, n),
+                        [_, _, _, Some(n)] => (EntityKind::Fading, n),
+                        _ => return None,
+                    };
+                match n.parse().ok()? {
+                    n @ 1..=9 => Some(kind(n)),
+                    _ => None,
+                }
+            }
+        }
+    }
+
+    /// For binary saves, weak points go from 11 up, portals from 21, chains
+    /// from 31 and fading cells from 41. Letters are their own ASCII code, 65
+    /// to 90.
+    pub fn code(self) -> u8 {
+        match self {
+            EntityKind::Food => 0,
+            EntityKind::Wall => 1,
+            EntityKind::Magnet => 2,
+            EntityKind::Door => 3,
+            EntityKind::Boss => 4,
+            EntityKind::Checkpoint => 5,
+            EntityKind::Hazard => 6,
+            EntityKind::Ghost => 7,
+            EntityKind::Zone => 8,
+            EntityKind::Split => 9,
+            EntityKind::WeakPoint(n) => 10 + n,
+            EntityKind::Portal(n) => 20 + n,
+            EntityKind::Chain(n) => 30 + n,
+            EntityKind::Fading(n) => 40 + n,
+            EntityKind::Letter(c) => c,
+        }
+    }
+
+    pub fn from_code(code: u8) -> Option<EntityKind> {
+        match code {
+            0 => Some(EntityKind::Food),
+            1 => Some(EntityKind::Wall),
+            2 => Some(EntityKind::Magnet),
+            3 => Some(EntityKind::Door),
+            4 => Some(EntityKind::Boss),
+            5 => Some(EntityKind::Checkpoint),
+            6 => Some(EntityKind::Hazard),
+            7 => Some(EntityKind::Ghost),
+            8 => Some(EntityKind::Zone),
+            9 => Some(EntityKind::Split),
+            11..=19 => Some(EntityKind::WeakPoint(code - 10)),
+            21..=29 => Some(EntityKind::Portal(code - 20)),
+            31..=39 => Some(EntityKind::Chain(code - 30)),
+            41..=49 => Some(EntityKind::Fading(code - 40)),
+            b'A'..=b'Z' => Some(EntityKind::Letter(code)),
+            _ => None,
+        }
+    }
+
+    /// Eating it grows the snake and scores, like the plain kind.
+    pub fn is_food(self) -> bool {
+        matches!(
+            self,
+            EntityKind::Food | EntityKind::Letter(_) | EntityKind::Split
+        )
+    }
+
+    /// Running into it kills the snake.
+    pub fn is_deadly(self) -> bool {
+        matches!(
+            self,
+            EntityKind::Wall | EntityKind::Boss | EntityKind::Ghost | EntityKind::Fading(_)
+        )
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Behavior {
+    #[default]
+    Still,
+    // Steps to a random free neighbour cell every `every` ticks
+    Wander {
+        every: u64,
+    },
+    // Steps one cell towards the target every tick once it's within `radius`
+    Pulled {
+        radius: u16,
+    },
+    // Steps right `span` times then left as many, one step every `every`
+    // ticks, over whatever is in the way. Everything with the same patrol
+    // moves together, it only depends on the tick.
+    Patrol {
+        every: u64,
+        span: u64,
+    },
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Entity {
+    pub kind: EntityKind,
+    pub x: u16,
+    pub y: u16,
+    pub behavior: Behavior,
+}
+
+impl Entity {
+    pub fn new(kind: EntityKind, x: u16, y: u16) -> Self {
+        Entity {
+            kind,
+            x,
+            y,
+            behavior: Behavior::Still,
+        }
+    }
+
+    pub fn at(&self, x: u16, y: u16) -> bool {
+        self.x == x && self.y == y
+    }
+
+    /// Where the entity wants to be after this tick, `is_free` tells which
+    /// cells it may move into and `target` is what pulled things go after
+    /// (the snake's head).
+    pub fn next_position(
+        &self,
+        tick: u64,
+        target: (u16, u16),
+        is_free: impl Fn(u16, u16) -> bool,
+        rng: &mut GameRng,
+    ) -> (u16, u16) {
+        match self.behavior {
+            Behavior::Still => (self.x, self.y),
+            Behavior::Patrol { every, span } => {
+                if every == 0 || span == 0 || !tick.is_multiple_of(every) {
+                    return (self.x, self.y);
+                }
+                if (tick / every) % (2 * span) < span {
+                    (self.x + 1, self.y)
+                } else {
+                    (self.x.saturating_sub(1), self.y)
+                }
+            }
+            Behavior::Pulled { radius } => {
+                let (dx, dy) = (target.0.abs_diff(self.x), target.1.abs_diff(self.y));
+                if dx + dy > radius {
+                    return (self.x, self.y);
+                }
+                let step_x = (
+                    if target.0 > self.x {
+                        self.x + 1
+                    } else {
+                        self.x.saturating_sub(1)
+                    },
+                    self.y,
+                );
+                let step_y = (
+                    self.x,
+                    if target.1 > self.y {
+                        self.y + 1
+                    } else {
+                        self.y.saturating_sub(1)
+                    },
+                );
+                // Along the longer way first, the other one if that's blocked
+                let steps = if dx >= dy {
+                    [(dx, step_x), (dy, step_y)]
+                } else {
+                    [(dy, step_y), (dx, step_x)]
+                };
+                steps
+                    .into_iter()
+                    .find(|&(distance, (x, y))| distance > 0 && is_free(x, y))
+                    .map_or((self.x, self.y), |(_, cell)| cell)
+            }
+            Behavior::Wander { every } => {
+                if every == 0 || !tick.is_multiple_of(every) {
+                    return (self.x, self.y);
+                }
+                let neighbours = [
+                    (self.x.wrapping_sub(1), self.y),
+                    (self.x + 1, self.y),
+                    (self.x, self.y.wrapping_sub(1)),
+                    (self.x, self.y + 1),
+                ];
+                let free: Vec<(u16, u16)> = neighbours
+                    .into_iter()
+                    .filter(|&(x, y)| is_free(x, y))
+                    .collect();
+                if free.is_empty() {
+                    return (self.x, self.y);
+                }
+                *rng.pick(&free)
+            }
+        }
+    }
+}
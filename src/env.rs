@@ -0,0 +1,186 @@
+// -- The game as a reinforcement learning environment
+//
+// `SnakeEnv` wraps a headless `Game` the way Gym environments look:
+// `reset` starts a game and gives the first observation, `step` takes an
+// action and gives the next one with the reward and whether it's over. It's
+// the same engine the terminal plays, with nothing drawn and no clock.
+//
+// Observations are flat `f32`s in one of two encodings: the whole board as
+// a grid with a channel per kind of thing, or a handful of features around
+// the head for small networks. Rewards are the points scored that step, and
+// -1 for dying.
+
+use crate::game::{Game, GameOptions, TickOutcome};
+use crate::snake::SnakeDirection;
+
+/// How an observation is laid out.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Encoding {
+    /// `GRID_CHANNELS` planes of `rows` by `cols`, channel first then row by
+    /// row: the body, the head, food, and whatever kills. 1.0 where there is
+    /// one, 0.0 elsewhere.
+    #[default]
+    Grid,
+    /// `FEATURES` values: whether the cell up, down, left and right of the
+    /// head is deadly, the way the snake is going (one hot, same order) and
+    /// whether the food is up, down, left or right of it.
+    Features,
+}
+
+pub const GRID_CHANNELS: usize = 4;
+pub const FEATURES: usize = 12;
+
+// Up, down, left, right, the order of the features and of the actions
+pub const ACTIONS: [SnakeDirection; 4] = [
+    SnakeDirection::Up,
+    SnakeDirection::Down,
+    SnakeDirection::Left,
+    SnakeDirection::Right,
+];
+
+#[derive(Clone, Copy, Debug)]
+pub struct EnvConfig {
+    pub cols: u16,
+    pub rows: u16,
+    pub options: GameOptions,
+    pub encoding: Encoding,
+    // An episode that goes on this long is cut off, `done` without dying
+    pub max_steps: u64,
+}
+
+impl Default for EnvConfig {
+    fn default() -> Self {
+        EnvConfig {
+            cols: 20,
+            rows: 20,
+            options: GameOptions::default(),
+            encoding: Encoding::default(),
+            max_steps: 10_000,
+        }
+    }
+}
+
+/// What a step gives back.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Step {
+    pub observation: Vec<f32>,
+    pub reward: f32,
+    pub done: bool,
+}
+
+pub struct SnakeEnv {
+    pub config: EnvConfig,
+    pub game: Game,
+}
+
+impl SnakeEnv {
+    pub fn new(config: EnvConfig) -> Self {
+        let game = Game::with_options(
+            config.cols,
+            config.rows,
+            SnakeDirection::Right,
+            &config.options,
+        );
+        SnakeEnv { config, game }
+    }
+
+    /// How many values an observation has.
+    pub fn observation_len(&self) -> usize {
+        match self.config.encoding {
+            Encoding::Grid => GRID_CHANNELS * self.config.cols as usize * self.config.rows as usize,
+            Encoding::Features => FEATURES,
+        }
+    }
+
+    /// A new game, with `seed` for the same one every time.
+    pub fn reset(&mut self, seed: Option<u64>) -> Vec<f32> {
+        let options = GameOptions {
+            seed: seed.or(self.config.options.seed),
+            ..self.config.options
+        };
+        self.game = Game::with_options(
+            self.config.cols,
+            self.config.rows,
+            SnakeDirection::Right,
+            &options,
+        );
+        self.observe()
+    }
+
+    /// Turn towards `ACTIONS[action]` (anything else goes straight on) and tick.
+    pub fn step(&mut self, action: usize) -> Step {
+        if let Some(&direction) = ACTIONS.get(action) {
+            self.game.turn(direction);
+        }
+        let score = self.game.score;
+        let outcome = self.game.tick();
+        let died = outcome == TickOutcome::Died;
+        let reward = if died {
+            -1.0
+        } else {
+            self.game.score.saturating_sub(score) as f32
+        };
+        Step {
+            observation: self.observe(),
+            reward,
+            done: died || self.game.ticks >= self.config.max_steps,
+        }
+    }
+
+    pub fn observe(&self) -> Vec<f32> {
+        match self.config.encoding {
+            Encoding::Grid => self.grid(),
+            Encoding::Features => self.features(),
+        }
+    }
+
+    fn grid(&self) -> Vec<f32> {
+        let (cols, rows) = (self.game.cols as usize, self.game.rows as usize);
+        let mut grid = vec![0.0; GRID_CHANNELS * cols * rows];
+        let mut set = |channel: usize, x: u16, y: u16| {
+            if (x as usize) < cols && (y as usize) < rows {
+                grid[channel * cols * rows + y as usize * cols + x as usize] = 1.0;
+            }
+        };
+        for (i, p) in self.game.snake.body.iter().enumerate() {
+            set(if i == 0 { 1 } else { 0 }, p.x, p.y);
+        }
+        for e in &self.game.entities {
+            if e.kind.is_deadly() {
+                set(3, e.x, e.y);
+            } else if e.kind.is_food() {
+                set(2, e.x, e.y);
+            }
+        }
+        grid
+    }
+
+    fn features(&self) -> Vec<f32> {
+        let game = &self.game;
+        let head = game.snake.head();
+        let (cols, rows) = (game.cols, game.rows);
+        let one = |yes: bool| if yes { 1.0 } else { 0.0 };
+
+        let mut features = Vec::with_capacity(FEATURES);
+        for direction in ACTIONS {
+            // The board wraps around
+            let (x, y) = match direction {
+                SnakeDirection::Up => (head.x, (head.y + rows - 1) % rows),
+                SnakeDirection::Down => (head.x, (head.y + 1) % rows),
+                SnakeDirection::Left => ((head.x + cols - 1) % cols, head.y),
+                SnakeDirection::Right => ((head.x + 1) % cols, head.y),
+            };
+            let deadly = game.snake.occupies(x, y)
+                || game.entity_at(x, y).is_some_and(|e| e.kind.is_deadly());
+            features.push(one(deadly));
+        }
+        for direction in ACTIONS {
+            features.push(one(game.snake.direction == direction));
+        }
+        let (dx, dy) = game
+            .food_position()
+            .map_or((0, 0), |(x, y)| game.offset_from_head(x, y));
+        features.extend([one(dy < 0), one(dy > 0), one(dx < 0), one(dx > 0)]);
+        features
+    }
+}
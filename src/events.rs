@@ -0,0 +1,152 @@
+// -- Things that happen during a tick
+//
+// `Game::tick` records what happened in `game.events` instead of calling into
+// the UI, stats or whatever else cares. Frontends hand them to an `EventBus`
+// after each tick, which passes every event to each subscriber in the order
+// they were registered.
+
+use crate::entity::EntityKind;
+use crate::game::Game;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameEvent {
+    FoodEaten { x: u16, y: u16, score: u32 },
+    // The tick interval went down, in milliseconds
+    SpeedUp { timer: u64 },
+    // Every `LEVEL_EVERY` points
+    LevelUp { level: u32 },
+    // Food near the head is pulled in for the next `ticks`
+    MagnetPicked { ticks: u32 },
+    // Went through a door, see `Game::rooms`
+    RoomChanged { room: usize },
+    // A boss showed up, or its last weak point was eaten
+    BossAppeared,
+    BossDefeated,
+    // The head ran over a checkpoint tile
+    CheckpointReached,
+    // Ended a tick right next to the tail, worth a bonus
+    CloseCall { x: u16, y: u16 },
+    // Went into a portal and came out here
+    Teleported { x: u16, y: u16 },
+    // Ran over a hazard here and lost the end of the tail
+    Hurt { x: u16, y: u16 },
+    // A food chain showed up, see `objective.rs`
+    ChainAppeared,
+    // Piece `piece` of it was eaten, a point like food
+    ChainPieceEaten { piece: u8, score: u32 },
+    // All of it eaten in order and in time, worth `bonus` points
+    ChainCompleted { bonus: u32 },
+    // Eaten out of order or too late, the rest of it is gone
+    ChainBroken,
+    // A hill zone opened or closed, see `objective.rs`
+    ZoneOpened,
+    ZoneClosed,
+    // A point for keeping the head in the zone
+    ZoneScored { score: u32 },
+    // The snake's letters spelled the word, worth `bonus` points
+    WordSpelled { bonus: u32 },
+    // Hydra food split the snake, there are `heads` of them now
+    Split { heads: usize },
+    Died { x: u16, y: u16, cause: DeathCause },
+    // No free cell left for food, the snake fills the board
+    BoardFull,
+}
+
+/// Why a run ended, kept in the lifetime stats.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DeathCause {
+    // Its own body, another hydra head, its ghost or its fading trail
+    Yourself,
+    Wall,
+    // A boss, or one of its weak points out of order
+    Enemy,
+    // A hazard with no tail left to lose
+    Poison,
+    // Still alive when a headless game ran out of ticks
+    Timeout,
+    // Went hungry down to the head, see `rules::Hunger`
+    Starved,
+}
+
+impl DeathCause {
+    pub const ALL: [DeathCause; 6] = [
+        DeathCause::Yourself,
+        DeathCause::Wall,
+        DeathCause::Enemy,
+        DeathCause::Poison,
+        DeathCause::Timeout,
+        DeathCause::Starved,
+    ];
+
+    /// Running into a deadly `kind`, or into a snake with `None`.
+    pub fn of_hitting(kind: Option<EntityKind>) -> DeathCause {
+        match kind {
+            Some(EntityKind::Wall) => DeathCause::Wall,
+            Some(EntityKind::Boss | EntityKind::WeakPoint(_)) => DeathCause::Enemy,
+            Some(EntityKind::Hazard) => DeathCause::Poison,
+            _ => DeathCause::Yourself,
+        }
+    }
+
+    /// How stats files and the CSV of `snake2 simulate` call it.
+    pub fn name(self) -> &'static str {
+        match self {
+            DeathCause::Yourself => "yourself",
+            DeathCause::Wall => "wall",
+            DeathCause::Enemy => "enemy",
+            DeathCause::Poison => "poison",
+            DeathCause::Timeout => "timeout",
+            DeathCause::Starved => "starved",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<DeathCause> {
+        DeathCause::ALL.into_iter().find(|c| c.name() == name)
+    }
+
+    /// For the game over screen, `You ...`.
+    pub fn describe(self) -> &'static str {
+        match self {
+            DeathCause::Yourself => "hit yourself",
+            DeathCause::Wall => "hit a wall",
+            DeathCause::Enemy => "ran into the boss",
+            DeathCause::Poison => "ran over a hazard with no tail left",
+            DeathCause::Timeout => "ran out of time",
+            DeathCause::Starved => "starved",
+        }
+    }
+}
+
+pub trait Subscriber {
+    fn on_event(&mut self, event: &GameEvent, game: &Game);
+}
+
+// Any closure works as a quick subscriber
+impl<F: FnMut(&GameEvent, &Game)> Subscriber for F {
+    fn on_event(&mut self, event: &GameEvent, game: &Game) {
+        self(event, game)
+    }
+}
+
+#[derive(Default)]
+pub struct EventBus<'a> {
+    subscribers: Vec<Box<dyn Subscriber + 'a>>,
+}
+
+impl<'a> EventBus<'a> {
+    pub fn new() -> Self {
+        EventBus::default()
+    }
+
+    pub fn subscribe(&mut self, subscriber: impl Subscriber + 'a) {
+        self.subscribers.push(Box::new(subscriber));
+    }
+
+    pub fn publish(&mut self, events: &[GameEvent], game: &Game) {
+        for event in events {
+            for subscriber in &mut self.subscribers {
+                subscriber.on_event(event, game);
+            }
+        }
+    }
+}
@@ -0,0 +1,251 @@
+// -- C bindings
+//
+// With the `ffi` feature the cdylib exports the headless engine to C, for
+// frontends written in anything that can call it (SDL, homebrew for
+// consoles...). `include/snake2.h` is generated from this file with
+// cbindgen, `cbindgen --config cbindgen.toml --output include/snake2.h`
+// after any change here.
+//
+// A game is an opaque `Snake2Game *` from `snake2_new`, freed with
+// `snake2_free`. The caller owns the clock: turn with `snake2_turn`, call
+// `snake2_tick` every `snake2_timer` milliseconds and draw from the
+// accessors. Every function takes a pointer `snake2_new` gave and hasn't been
+// freed yet, from one thread at a time; NULL is fine and does nothing (or
+// gives 0). Strings from `snake2_state_json` go back through
+// `snake2_string_free`.
+
+// The rules above are the same for every function
+#![allow(clippy::missing_safety_doc)]
+
+use crate::game::{Game, GameOptions, TickOutcome};
+use crate::protocol::state_json;
+use crate::rules::Mode;
+use crate::snake::SnakeDirection;
+use std::ffi::{CStr, CString, c_char};
+
+/// A game and what its last tick did.
+pub struct Snake2Game {
+    game: Game,
+    last: Option<TickOutcome>,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Snake2Direction {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Snake2Outcome {
+    Moved,
+    Ate,
+    Died,
+}
+
+/// What's on a cell, to draw it.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Snake2Cell {
+    Empty,
+    Head,
+    Body,
+    Food,
+    // Walls and bosses, anything that kills
+    Wall,
+    // Magnets, doors, weak points and checkpoints
+    Other,
+}
+
+/// A new game on a `cols` by `rows` board. `mode` is a mode name like
+/// `--mode` takes (NULL for classic) and `seed` 0 picks one at random. NULL
+/// for an unknown mode or a board smaller than 2x2.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn snake2_new(
+    cols: u16,
+    rows: u16,
+    mode: *const c_char,
+    seed: u64,
+) -> *mut Snake2Game {
+    if cols < 2 || rows < 2 {
+        return std::ptr::null_mut();
+    }
+    let mode = if mode.is_null() {
+        Mode::Classic
+    } else {
+        match unsafe { CStr::from_ptr(mode) }.to_str().map(str::parse) {
+            Ok(Ok(mode)) => mode,
+            _ => return std::ptr::null_mut(),
+        }
+    };
+    let options = GameOptions {
+        mode,
+        seed: (seed != 0).then_some(seed),
+        ..Default::default()
+    };
+    Box::into_raw(Box::new(Snake2Game {
+        game: Game::with_options(cols, rows, SnakeDirection::Right, &options),
+        last: None,
+    }))
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn snake2_free(game: *mut Snake2Game) {
+    if !game.is_null() {
+        drop(unsafe { Box::from_raw(game) });
+    }
+}
+
+/// Turn on the next tick, turning back on itself is ignored.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn snake2_turn(game: *mut Snake2Game, direction: Snake2Direction) {
+    let Some(game) = (unsafe { game.as_mut() }) else {
+        return;
+    };
+    game.game.turn(match direction {
+        Snake2Direction::Up => SnakeDirection::Up,
+        Snake2Direction::Down => SnakeDirection::Down,
+        Snake2Direction::Left => SnakeDirection::Left,
+        Snake2Direction::Right => SnakeDirection::Right,
+    });
+}
+
+/// Move the snake one cell. A dead game stays dead, ticking it again gives
+/// `Died` with nothing changed.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn snake2_tick(game: *mut Snake2Game) -> Snake2Outcome {
+    let Some(game) = (unsafe { game.as_mut() }) else {
+        return Snake2Outcome::Died;
+    };
+    if game.last == Some(TickOutcome::Died) {
+        return Snake2Outcome::Died;
+    }
+    let outcome = game.game.tick();
+    game.last = Some(outcome);
+    match outcome {
+        TickOutcome::Moved => Snake2Outcome::Moved,
+        TickOutcome::Ate => Snake2Outcome::Ate,
+        TickOutcome::Died => Snake2Outcome::Died,
+    }
+}
+
+unsafe fn get<'a>(game: *const Snake2Game) -> Option<&'a Game> {
+    unsafe { game.as_ref() }.map(|g| &g.game)
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn snake2_cols(game: *const Snake2Game) -> u16 {
+    unsafe { get(game) }.map_or(0, |g| g.cols)
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn snake2_rows(game: *const Snake2Game) -> u16 {
+    unsafe { get(game) }.map_or(0, |g| g.rows)
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn snake2_score(game: *const Snake2Game) -> u32 {
+    unsafe { get(game) }.map_or(0, |g| g.score)
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn snake2_level(game: *const Snake2Game) -> u32 {
+    unsafe { get(game) }.map_or(0, |g| g.level)
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn snake2_ticks(game: *const Snake2Game) -> u64 {
+    unsafe { get(game) }.map_or(0, |g| g.ticks)
+}
+
+/// The seed the game plays, to play it again.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn snake2_seed(game: *const Snake2Game) -> u64 {
+    unsafe { get(game) }.map_or(0, |g| g.seed)
+}
+
+/// Milliseconds until the next tick should happen.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn snake2_timer(game: *const Snake2Game) -> u64 {
+    unsafe { get(game) }.map_or(0, |g| g.timer)
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn snake2_length(game: *const Snake2Game) -> usize {
+    unsafe { get(game) }.map_or(0, |g| g.snake.body.len())
+}
+
+/// Where segment `index` is, 0 being the head. False past the tail.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn snake2_segment(
+    game: *const Snake2Game,
+    index: usize,
+    x: *mut u16,
+    y: *mut u16,
+) -> bool {
+    let Some(p) = unsafe { get(game) }.and_then(|g| g.snake.body.get(index)) else {
+        return false;
+    };
+    unsafe { write_position(x, y, p.x, p.y) };
+    true
+}
+
+/// Where the food is, false when there's none.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn snake2_food(game: *const Snake2Game, x: *mut u16, y: *mut u16) -> bool {
+    let Some((fx, fy)) = unsafe { get(game) }.and_then(Game::food_position) else {
+        return false;
+    };
+    unsafe { write_position(x, y, fx, fy) };
+    true
+}
+
+unsafe fn write_position(x: *mut u16, y: *mut u16, px: u16, py: u16) {
+    if let Some(x) = unsafe { x.as_mut() } {
+        *x = px;
+    }
+    if let Some(y) = unsafe { y.as_mut() } {
+        *y = py;
+    }
+}
+
+/// What's on a cell, `Empty` off the board.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn snake2_cell(game: *const Snake2Game, x: u16, y: u16) -> Snake2Cell {
+    let Some(game) = (unsafe { get(game) }) else {
+        return Snake2Cell::Empty;
+    };
+    if game.snake.head().x == x && game.snake.head().y == y {
+        return Snake2Cell::Head;
+    }
+    if game.snake.occupies(x, y) {
+        return Snake2Cell::Body;
+    }
+    match game.entity_at(x, y).map(|e| e.kind) {
+        None => Snake2Cell::Empty,
+        Some(kind) if kind.is_food() => Snake2Cell::Food,
+        Some(kind) if kind.is_deadly() => Snake2Cell::Wall,
+        Some(_) => Snake2Cell::Other,
+    }
+}
+
+/// The whole board as JSON, the same line `--io-protocol` writes. Free it
+/// with `snake2_string_free`.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn snake2_state_json(game: *const Snake2Game) -> *mut c_char {
+    let Some(game) = (unsafe { game.as_ref() }) else {
+        return std::ptr::null_mut();
+    };
+    // JSON escapes control characters, there's no NUL to trip on
+    CString::new(state_json(&game.game, game.last)).map_or(std::ptr::null_mut(), CString::into_raw)
+}
+
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn snake2_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(unsafe { CString::from_raw(string) });
+    }
+}
@@ -0,0 +1,937 @@
+use crate::entity::{Behavior, Entity, EntityKind};
+use crate::events::{DeathCause, GameEvent};
+use crate::objective;
+use crate::rng::GameRng;
+use crate::rules::{Mode, Modifiers, Rule};
+use crate::snake::{Snake, SnakeBodyPoint, SnakeDirection, Spawn};
+use rand::SeedableRng;
+use std::collections::VecDeque;
+
+// Turns pressed faster than the snake moves wait here, one per tick
+const MAX_QUEUED_TURNS: usize = 3;
+// Points per level
+pub const LEVEL_EVERY: u32 = 5;
+// Walls and such don't appear closer than this to the head
+const SAFE_DISTANCE: u16 = 3;
+// Food doesn't appear this many cells straight ahead of the head, too close
+// to turn away from whatever comes with it
+const FOOD_AHEAD: usize = 2;
+// Points for ending a tick with the head right next to the tail
+pub const CLOSE_CALL_BONUS: u32 = 1;
+// A magnet shows up every this many points
+const MAGNET_EVERY: u32 = 7;
+// How long a magnet pulls food, and from how far
+pub const MAGNET_TICKS: u32 = 40;
+const MAGNET_RADIUS: u16 = 8;
+// Cells a hazard takes off the tail, a snake that short dies on it instead
+pub const HAZARD_CUT: usize = 2;
+// Milliseconds between ticks a game starts with, unless told otherwise
+pub const START_TIMER: u64 = 500;
+// Eating speeds the game up until ticks are down to this
+pub const FASTEST_TIMER: u64 = 50;
+// What eating takes off the timer
+const SPEED_UP: u64 = 20;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TickOutcome {
+    Moved,
+    Ate,
+    Died,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct GameOptions {
+    pub start_length: u16,
+    pub spawn: Spawn,
+    pub mode: Mode,
+    // On top of the mode's rules
+    pub modifiers: Modifiers,
+    // Same seed, same food (as long as the snake goes the same way), `None` picks one
+    pub seed: Option<u64>,
+    // Milliseconds between ticks to start with, eating takes it down from there
+    pub timer: u64,
+}
+
+impl Default for GameOptions {
+    fn default() -> Self {
+        GameOptions {
+            start_length: 1,
+            spawn: Spawn::Center,
+            mode: Mode::Classic,
+            modifiers: Modifiers::default(),
+            seed: None,
+            timer: START_TIMER,
+        }
+    }
+}
+
+/// The whole game state, with no terminal attached, so it can be driven by
+/// the TUI, a bot or a headless simulation alike.
+pub struct Game {
+    pub cols: u16,
+    pub rows: u16,
+    pub mode: Mode,
+    pub modifiers: Modifiers,
+    pub snake: Snake,
+    // Food, walls and the rest, see `entity.rs`
+    pub entities: Vec<Entity>,
+    // Milliseconds between ticks, it goes down every time the snake eats
+    pub timer: u64,
+    // Whether `+` or `-` changed `timer` along the way, the high scores
+    // say so
+    pub custom_speed: bool,
+    pub score: u32,
+    // Starts at 1 and goes up every `LEVEL_EVERY` points
+    pub level: u32,
+    pub ticks: u64,
+    pub queued_turns: VecDeque<SnakeDirection>,
+    // What happened during the last tick
+    pub events: Vec<GameEvent>,
+    // Ticks left in which the tail stays put, eating adds one
+    pub pending_growth: u32,
+    // Which room the snake is in, for modes with more than one
+    pub room: usize,
+    // What every room had in it when the snake last left, `entities` has
+    // the room it's in. Empty in modes without rooms.
+    pub rooms: Vec<Vec<Entity>>,
+    // Ticks the snake spent on each cell, row by row, for the heatmap. Not
+    // saved, a loaded game starts counting again.
+    pub visits: Vec<u32>,
+    // Whether the head was next to the tail after the last tick, the bonus
+    // is for getting there, not for staying
+    pub close_call: bool,
+    // How many close call bonuses the score has in it
+    pub close_calls: u32,
+    // Ticks left of a magnet picked up, food near the head moves towards it meanwhile
+    pub magnet_ticks: u32,
+    // Ticks left before the snake starts starving, with the hunger modifier
+    // (see `rules::Hunger`)
+    pub hunger: u32,
+    // Ticks left to finish the food chain on the board, see `objective.rs`
+    pub chain_ticks: u32,
+    // The word to spell in word mode (see `rules::Words`), empty in the others
+    pub word: String,
+    // Where the head was, the last tick's first, for the ghost in mirror
+    // mode (see `rules::Mirror`) and the fading cells in surround mode (see
+    // `rules::Surround`). Empty in the others.
+    pub trail: VecDeque<SnakeBodyPoint>,
+    // The other heads in hydra mode (see `rules::Hydra`), each a snake of its
+    // own taking the same turns as `snake`. They don't eat, but any of them
+    // running into something ends the game. Empty in the other modes.
+    pub heads: Vec<Snake>,
+    // What the random number generator started from, see `challenge.rs`
+    pub seed: u64,
+    // Saved with the game, see `rng.rs`
+    pub rng: GameRng,
+    rules: Vec<Box<dyn Rule>>,
+}
+
+impl Game {
+    pub fn new(cols: u16, rows: u16, initial_direction: SnakeDirection) -> Self {
+        Game::with_options(cols, rows, initial_direction, &GameOptions::default())
+    }
+
+    pub fn with_options(
+        cols: u16,
+        rows: u16,
+        initial_direction: SnakeDirection,
+        options: &GameOptions,
+    ) -> Self {
+        Game::with_rules(cols, rows, initial_direction, options, Vec::new())
+    }
+
+    /// Like `with_options`, with `rules` after the mode's and the modifiers',
+    /// set up with them before the first food.
+    pub fn with_rules(
+        cols: u16,
+        rows: u16,
+        initial_direction: SnakeDirection,
+        options: &GameOptions,
+        rules: Vec<Box<dyn Rule>>,
+    ) -> Self {
+        let seed = options.seed.unwrap_or_else(rand::random);
+        let mut rng = GameRng::seed_from_u64(seed);
+        // Random spawns come from the game's generator too
+        let spawn = match options.spawn {
+            Spawn::Random => Spawn::At(
+                rng.below(u32::from(cols)) as u16,
+                rng.below(u32::from(rows)) as u16,
+            ),
+            spawn => spawn,
+        };
+        let snake = Snake::new(&cols, &rows, initial_direction, options.start_length, spawn);
+        let mut game = Game {
+            cols,
+            rows,
+            mode: options.mode,
+            modifiers: options.modifiers,
+            snake,
+            entities: Vec::new(),
+            timer: options.timer,
+            custom_speed: false,
+            score: 0,
+            level: 1,
+            ticks: 0,
+            queued_turns: VecDeque::new(),
+            events: Vec::new(),
+            pending_growth: 0,
+            room: 0,
+            rooms: Vec::new(),
+            visits: vec![0; cols as usize * rows as usize],
+            close_call: false,
+            close_calls: 0,
+            magnet_ticks: 0,
+            hunger: 0,
+            chain_ticks: 0,
+            word: String::new(),
+            trail: VecDeque::new(),
+            heads: Vec::new(),
+            seed,
+            rng,
+            rules: Vec::new(),
+        };
+        for rule in options
+            .mode
+            .rules()
+            .into_iter()
+            .chain(options.modifiers.rules())
+            .chain(rules)
+        {
+            game.add_rule(rule);
+        }
+        game.run_rules(|rule, game| rule.on_start(game));
+        game.place_food();
+        game
+    }
+
+    /// Where the (first) food is, if there's any.
+    pub fn food_position(&self) -> Option<(u16, u16)> {
+        self.entities
+            .iter()
+            .find(|e| e.kind.is_food())
+            .map(|e| (e.x, e.y))
+    }
+
+    pub fn entity_at(&self, x: u16, y: u16) -> Option<&Entity> {
+        self.entities.iter().find(|e| e.at(x, y))
+    }
+
+    pub fn is_free(&self, x: u16, y: u16) -> bool {
+        x < self.cols
+            && y < self.rows
+            && !self.snake.occupies(x, y)
+            && !self.heads.iter().any(|head| head.occupies(x, y))
+            && self.entity_at(x, y).is_none()
+    }
+
+    /// How far from the head the player sees, `None` is everywhere (see `Fog`).
+    pub fn view_radius(&self) -> Option<u16> {
+        self.rules.iter().filter_map(|r| r.view_radius()).min()
+    }
+
+    /// The longest the snake may get, `None` is no limit (see `Nibbles`).
+    pub fn max_length(&self) -> Option<usize> {
+        self.rules.iter().filter_map(|r| r.max_length()).min()
+    }
+
+    /// From the head to `(x, y)` the short way, the board wraps around.
+    pub fn offset_from_head(&self, x: u16, y: u16) -> (i32, i32) {
+        let head = self.snake.head();
+        let wrap = |from: u16, to: u16, size: u16| {
+            let d = (i32::from(to) - i32::from(from)).rem_euclid(i32::from(size));
+            if d > i32::from(size) / 2 {
+                d - i32::from(size)
+            } else {
+                d
+            }
+        };
+        (wrap(head.x, x, self.cols), wrap(head.y, y, self.rows))
+    }
+
+    /// Whether the player can see the cell, rows count double for distance
+    /// so the view looks round.
+    pub fn is_visible(&self, x: u16, y: u16) -> bool {
+        let Some(radius) = self.view_radius() else {
+            return true;
+        };
+        let (dx, dy) = self.offset_from_head(x, y);
+        dx * dx + 4 * dy * dy <= i32::from(radius) * i32::from(radius)
+    }
+
+    /// A random free cell not right next to the head, for rules that add
+    /// things to the board.
+    pub fn random_free_cell(&mut self) -> Option<(u16, u16)> {
+        let head = self.snake.head();
+        let cells: Vec<(u16, u16)> = (0..self.cols)
+            .flat_map(|x| (0..self.rows).map(move |y| (x, y)))
+            .filter(|&(x, y)| {
+                x.abs_diff(head.x) + y.abs_diff(head.y) >= SAFE_DISTANCE && self.is_free(x, y)
+            })
+            .collect();
+        if cells.is_empty() {
+            return None;
+        }
+        Some(*self.rng.pick(&cells))
+    }
+
+    /// The top-left corner of a random `width` by `height` block of free
+    /// cells, clear of the head, for rules that add bigger things.
+    pub fn random_free_area(&mut self, width: u16, height: u16) -> Option<(u16, u16)> {
+        let head = self.snake.head();
+        let (cols, rows) = (self.cols, self.rows);
+        let corners: Vec<(u16, u16)> = (0..cols.saturating_sub(width) + 1)
+            .flat_map(|x| (0..rows.saturating_sub(height) + 1).map(move |y| (x, y)))
+            .filter(|&(x, y)| {
+                x + width <= cols
+                    && y + height <= rows
+                    && (x..x + width).all(|cx| {
+                        (y..y + height).all(|cy| {
+                            self.is_free(cx, cy)
+                                && cx.abs_diff(head.x) + cy.abs_diff(head.y) >= SAFE_DISTANCE
+                        })
+                    })
+            })
+            .collect();
+        if corners.is_empty() {
+            return None;
+        }
+        Some(*self.rng.pick(&corners))
+    }
+
+    /// How far the game has sped up from `START_TIMER` to `FASTEST_TIMER`,
+    /// in thousandths: 0 at that speed or slower, 1000 there or faster.
+    pub fn speed_progress(&self) -> u32 {
+        let gone = START_TIMER.saturating_sub(self.timer);
+        (gone * 1000 / (START_TIMER - FASTEST_TIMER)).min(1000) as u32
+    }
+
+    /// How much of `word` the letters at the end of the snake spell so far,
+    /// the first one eaten the furthest from the tail.
+    pub fn spelled(&self) -> usize {
+        let body = &self.snake.body;
+        let word = self.word.as_bytes();
+        // The head never has a letter
+        (1..=word.len().min(body.len() - 1))
+            .rev()
+            .find(|&n| {
+                body[body.len() - n..]
+                    .iter()
+                    .map(|p| p.letter)
+                    .eq(word[..n].iter().map(|&c| Some(c)))
+            })
+            .unwrap_or(0)
+    }
+
+    /// The weak point of a boss that has to be eaten next, the lowest one left.
+    pub fn next_weak_point(&self) -> Option<u8> {
+        self.entities
+            .iter()
+            .filter_map(|e| match e.kind {
+                EntityKind::WeakPoint(n) => Some(n),
+                _ => None,
+            })
+            .min()
+    }
+
+    /// Register a rule, its hooks run after the ones already there.
+    pub fn add_rule(&mut self, rule: Box<dyn Rule>) {
+        self.rules.push(rule);
+    }
+
+    pub fn rule_names(&self) -> Vec<&'static str> {
+        self.rules.iter().map(|r| r.name()).collect()
+    }
+
+    /// What each rule does, for the help screen.
+    pub fn rule_descriptions(&self) -> Vec<String> {
+        self.rules.iter().map(|r| r.describe()).collect()
+    }
+
+    // Rules get `&mut Game`, so they're taken out while their hooks run
+    fn run_rules<T>(&mut self, mut hook: impl FnMut(&mut dyn Rule, &mut Game) -> T) -> Vec<T> {
+        let mut rules = std::mem::take(&mut self.rules);
+        let results = rules.iter_mut().map(|r| hook(r.as_mut(), self)).collect();
+        rules.append(&mut self.rules);
+        self.rules = rules;
+        results
+    }
+
+    /// Put a new food on the board, false when there's no room (or a rule says no).
+    fn place_food(&mut self) -> bool {
+        let mut position = generate_food(
+            &self.cols,
+            &self.rows,
+            &self.snake,
+            &self.entities,
+            &mut self.rng,
+        );
+        self.run_rules(|rule, game| position = rule.modify_spawn(game, position));
+        match position {
+            Some((x, y)) => {
+                let mut kind = EntityKind::Food;
+                self.run_rules(|rule, game| kind = rule.food_kind(game, kind));
+                let mut food = Entity::new(kind, x, y);
+                food.behavior = self.food_behavior();
+                self.entities.push(food);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn food_behavior(&self) -> Behavior {
+        if self.magnet_ticks > 0 {
+            Behavior::Pulled {
+                radius: MAGNET_RADIUS,
+            }
+        } else {
+            Behavior::Still
+        }
+    }
+
+    /// Turn the magnet on for `ticks` (or off with 0).
+    pub fn set_magnet(&mut self, ticks: u32) {
+        self.magnet_ticks = ticks;
+        let behavior = self.food_behavior();
+        for entity in &mut self.entities {
+            if entity.kind.is_food() {
+                entity.behavior = behavior;
+            }
+        }
+    }
+
+    // Let every entity with a behavior take its step
+    fn update_entities(&mut self) {
+        // A copy, `is_free` needs to look at the whole game meanwhile
+        let mut rng = self.rng.clone();
+        let head = self.snake.head();
+        for i in 0..self.entities.len() {
+            let entity = self.entities[i];
+            let (x, y) = entity.next_position(
+                self.ticks,
+                (head.x, head.y),
+                |x, y| self.is_free(x, y),
+                &mut rng,
+            );
+            // Nothing leaves the board, whatever its behavior says
+            if x < self.cols && y < self.rows {
+                self.entities[i].x = x;
+                self.entities[i].y = y;
+            }
+        }
+        self.rng = rng;
+    }
+
+    /// The head went through the door at `door`: the room is put away and
+    /// the next one (the one before for the door on the left) taken out, the
+    /// snake coming in through the door on the other side.
+    fn go_through_door(&mut self, door: SnakeBodyPoint) {
+        let count = self.rooms.len();
+        if count == 0 {
+            return;
+        }
+        let (next, arrival_x) = if door.x == 0 {
+            ((self.room + count - 1) % count, self.cols - 2)
+        } else {
+            ((self.room + 1) % count, 1)
+        };
+        self.rooms[self.room] = std::mem::take(&mut self.entities);
+        self.room = next;
+        self.entities = self.rooms[next].clone();
+
+        // In on a single cell, the rest of the snake follows as it moves
+        let length = self.snake.body.len() as u32;
+        self.snake.body = vec![SnakeBodyPoint {
+            x: arrival_x,
+            y: door.y,
+            letter: None,
+        }];
+        self.pending_growth += length - 1;
+        if self.food_position().is_none() {
+            self.place_food();
+        }
+        self.events.push(GameEvent::RoomChanged { room: next });
+    }
+
+    /// How many ticks the snake has been on `(x, y)`.
+    pub fn visits_at(&self, x: u16, y: u16) -> u32 {
+        if x >= self.cols || y >= self.rows {
+            return 0;
+        }
+        self.visits[y as usize * self.cols as usize + x as usize]
+    }
+
+    fn count_visits(&mut self) {
+        for point in &self.snake.body {
+            if point.x < self.cols && point.y < self.rows {
+                let cell = point.y as usize * self.cols as usize + point.x as usize;
+                self.visits[cell] = self.visits[cell].saturating_add(1);
+            }
+        }
+    }
+
+    /// Whether the head is one step from the tail, across an edge too
+    /// (the snake has to be at least 4 long for that to mean anything).
+    pub fn head_next_to_tail(&self) -> bool {
+        let body = &self.snake.body;
+        if body.len() < 4 {
+            return false;
+        }
+        let (head, tail) = (body[0], body[body.len() - 1]);
+        let dx = head.x.abs_diff(tail.x);
+        let dy = head.y.abs_diff(tail.y);
+        // Across an edge counts too, the board wraps around
+        let near = |d: u16, size: u16| d == 1 || (size > 2 && d == size - 1);
+        (dx == 0 && near(dy, self.rows)) || (dy == 0 && near(dx, self.cols))
+    }
+
+    // A bonus for coming within a step of the tail and living
+    fn check_close_call(&mut self) {
+        let close = self.head_next_to_tail();
+        if close && !self.close_call {
+            self.score += CLOSE_CALL_BONUS;
+            self.close_calls += 1;
+            let head = self.snake.head();
+            self.events.push(GameEvent::CloseCall {
+                x: head.x,
+                y: head.y,
+            });
+        }
+        self.close_call = close;
+    }
+
+    /// What the snake died of, if the last tick killed it.
+    pub fn death_cause(&self) -> Option<DeathCause> {
+        self.events.iter().find_map(|event| match event {
+            GameEvent::Died { cause, .. } => Some(*cause),
+            _ => None,
+        })
+    }
+
+    // Whether running into `kind` kills the snake as it is now
+    fn kills(&self, kind: EntityKind) -> bool {
+        match kind {
+            // Weak points out of order are as bad as the rest of the boss
+            EntityKind::WeakPoint(n) => Some(n) != self.next_weak_point(),
+            // Nothing left to lose
+            EntityKind::Hazard => self.snake.body.len() <= HAZARD_CUT,
+            kind => kind.is_deadly(),
+        }
+    }
+
+    /// Whether the head moving onto (x, y) next tick kills the snake, the
+    /// tail counts as it only moves after the head. Unless a rule forgives
+    /// it, which it doesn't know about.
+    pub fn is_fatal(&self, x: u16, y: u16) -> bool {
+        self.snake.occupies(x, y)
+            || self.heads.iter().any(|h| h.occupies(x, y))
+            || self.entity_at(x, y).is_some_and(|e| self.kills(e.kind))
+    }
+
+    /// Queue a turn for the coming ticks, so quick left-up-right presses
+    /// play out one per tick instead of the last one reversing the snake.
+    pub fn turn(&mut self, mut direction: SnakeDirection) {
+        self.run_rules(|rule, game| direction = rule.modify_turn(game, direction));
+        let last = self
+            .queued_turns
+            .back()
+            .copied()
+            .unwrap_or(self.snake.direction);
+        if direction != last && self.queued_turns.len() < MAX_QUEUED_TURNS {
+            self.queued_turns.push_back(direction);
+        }
+    }
+
+    // The other heads take the snake's turn (unless it would reverse them)
+    // and move along, `ahead` being where the snake's own head goes. Where
+    // and into what one of them crashes, if any does, and then none of them
+    // have moved.
+    fn move_heads(&mut self, ahead: SnakeBodyPoint) -> Option<(SnakeBodyPoint, DeathCause)> {
+        let direction = self.snake.direction;
+        let steps: Vec<SnakeBodyPoint> = self
+            .heads
+            .iter_mut()
+            .map(|head| {
+                head.turn(direction);
+                head.next_head(&self.cols, &self.rows)
+            })
+            .collect();
+        for (i, step) in steps.iter().enumerate() {
+            let deadly = self
+                .entity_at(step.x, step.y)
+                .map(|e| e.kind)
+                .filter(|kind| kind.is_deadly());
+            if deadly.is_some() {
+                return Some((*step, DeathCause::of_hitting(deadly)));
+            }
+            let crashed = self.snake.occupies(step.x, step.y)
+                || (step.x, step.y) == (ahead.x, ahead.y)
+                || self.heads.iter().any(|h| h.occupies(step.x, step.y))
+                || steps[..i].iter().any(|s| (s.x, s.y) == (step.x, step.y));
+            if crashed {
+                return Some((*step, DeathCause::Yourself));
+            }
+        }
+        for (head, step) in self.heads.iter_mut().zip(steps) {
+            head.body.insert(0, step);
+            head.body.pop();
+            head.last_moved = head.direction;
+        }
+        None
+    }
+
+    // The back half of the snake goes off on its own as another head, the
+    // segment right behind the cut leading
+    fn split(&mut self) {
+        let length = self.snake.body.len();
+        if length < 2 {
+            return;
+        }
+        let body = self.snake.body.split_off(length.div_ceil(2));
+        let moved = match body.get(1) {
+            Some(&neck) => [
+                SnakeDirection::Up,
+                SnakeDirection::Down,
+                SnakeDirection::Left,
+                SnakeDirection::Right,
+            ]
+            .into_iter()
+            .find(|&d| {
+                let step = neck.step(d, self.cols, self.rows);
+                (step.x, step.y) == (body[0].x, body[0].y)
+            })
+            .unwrap_or(self.snake.last_moved),
+            None => self.snake.last_moved,
+        };
+        self.heads.push(Snake {
+            direction: moved,
+            last_moved: moved,
+            body,
+        });
+        self.events.push(GameEvent::Split {
+            heads: self.heads.len() + 1,
+        });
+    }
+
+    fn apply_queued_turn(&mut self) {
+        while let Some(direction) = self.queued_turns.pop_front() {
+            if direction != self.snake.direction && self.snake.can_turn(direction) {
+                self.snake.direction = direction;
+                return;
+            }
+        }
+    }
+
+    /// Move the snake one cell, eating and growing if it lands on food.
+    pub fn tick(&mut self) -> TickOutcome {
+        self.events.clear();
+        self.apply_queued_turn();
+        if let Some(cause) = self
+            .run_rules(|rule, game| rule.kills(game))
+            .into_iter()
+            .flatten()
+            .next()
+        {
+            let head = self.snake.head();
+            self.events.push(GameEvent::Died {
+                x: head.x,
+                y: head.y,
+                cause,
+            });
+            return TickOutcome::Died;
+        }
+        self.run_rules(|rule, game| rule.on_tick(game));
+        if self.magnet_ticks > 0 {
+            self.set_magnet(self.magnet_ticks - 1);
+        }
+        objective::on_tick(self);
+        self.update_entities();
+        let mut new_head = self.snake.next_head(&self.cols, &self.rows);
+        self.run_rules(|rule, game| new_head = rule.modify_head(game, new_head));
+        // In through a portal and out of the other one, a step past it
+        if let Some(EntityKind::Portal(n)) = self.entity_at(new_head.x, new_head.y).map(|e| e.kind)
+            && let Some(exit) = self
+                .entities
+                .iter()
+                .find(|e| e.kind == EntityKind::Portal(n) && !e.at(new_head.x, new_head.y))
+        {
+            let exit = SnakeBodyPoint {
+                x: exit.x,
+                y: exit.y,
+                letter: None,
+            };
+            new_head = exit.step(self.snake.direction, self.cols, self.rows);
+            self.events.push(GameEvent::Teleported {
+                x: new_head.x,
+                y: new_head.y,
+            });
+        }
+        let hit = self.entity_at(new_head.x, new_head.y).map(|e| e.kind);
+        let deadly = hit.is_some_and(|kind| self.kills(kind));
+
+        // Game over if the new head collides with body or a wall, unless a rule forgives it
+        if self.is_fatal(new_head.x, new_head.y)
+            && self
+                .run_rules(|rule, game| rule.on_collision(game, new_head))
+                .into_iter()
+                .all(|fatal| fatal)
+        {
+            self.events.push(GameEvent::Died {
+                x: new_head.x,
+                y: new_head.y,
+                cause: DeathCause::of_hitting(hit.filter(|_| deadly)),
+            });
+            return TickOutcome::Died;
+        }
+        if let Some((crash, cause)) = self.move_heads(new_head) {
+            self.events.push(GameEvent::Died {
+                x: crash.x,
+                y: crash.y,
+                cause,
+            });
+            return TickOutcome::Died;
+        }
+
+        self.ticks += 1;
+
+        // Shift the body
+        self.snake.body.insert(0, new_head);
+        // Letters stay with their segment as it moves up to the cell of the
+        // one ahead of it, which leaves the head without one
+        for i in 0..self.snake.body.len() - 1 {
+            self.snake.body[i].letter = self.snake.body[i + 1].letter;
+        }
+        self.snake.last_moved = self.snake.direction;
+
+        if hit == Some(EntityKind::Magnet) {
+            self.entities
+                .retain(|e| !(e.kind == EntityKind::Magnet && e.at(new_head.x, new_head.y)));
+            self.set_magnet(MAGNET_TICKS);
+            self.events.push(GameEvent::MagnetPicked {
+                ticks: MAGNET_TICKS,
+            });
+        }
+
+        let ate = hit.is_some_and(EntityKind::is_food);
+        if ate {
+            self.entities
+                .retain(|e| !(e.kind.is_food() && e.at(new_head.x, new_head.y)));
+            self.pending_growth += 1;
+        }
+        // A piece of a chain grows it the same, see `objective.rs`
+        if let Some(EntityKind::Chain(_)) = hit {
+            self.pending_growth += 1;
+        }
+
+        // Remove the tail unless the snake is growing
+        let full = self
+            .max_length()
+            .is_some_and(|max| self.snake.body.len() > max);
+        if full {
+            self.pending_growth = 0;
+            self.snake.body.pop();
+        } else if self.pending_growth > 0 {
+            self.pending_growth -= 1;
+            // What grew is a new end of the tail, carrying the letter just eaten
+            let tail = self.snake.body.len() - 1;
+            self.snake.body[tail].letter = match hit {
+                Some(EntityKind::Letter(letter)) => Some(letter),
+                _ => None,
+            };
+        } else {
+            self.snake.body.pop();
+        }
+
+        if hit == Some(EntityKind::Split) {
+            self.split();
+        }
+
+        if hit == Some(EntityKind::Hazard) {
+            let length = self.snake.body.len().saturating_sub(HAZARD_CUT).max(1);
+            self.snake.body.truncate(length);
+            self.events.push(GameEvent::Hurt {
+                x: new_head.x,
+                y: new_head.y,
+            });
+        }
+
+        if hit == Some(EntityKind::Door) {
+            self.go_through_door(new_head);
+        }
+        self.count_visits();
+        self.check_close_call();
+        objective::after_move(self);
+
+        if hit == Some(EntityKind::Checkpoint) {
+            self.entities
+                .retain(|e| !(e.kind == EntityKind::Checkpoint && e.at(new_head.x, new_head.y)));
+            self.events.push(GameEvent::CheckpointReached);
+        }
+
+        if let Some(EntityKind::WeakPoint(_)) = hit {
+            self.entities.retain(|e| {
+                !(matches!(e.kind, EntityKind::WeakPoint(_)) && e.at(new_head.x, new_head.y))
+            });
+            self.pending_growth += 1;
+            if self.next_weak_point().is_none() {
+                // That was the last one, the rest of the boss goes with it
+                self.entities.retain(|e| e.kind != EntityKind::Boss);
+                self.events.push(GameEvent::BossDefeated);
+            }
+        }
+
+        if let Some(EntityKind::Chain(n)) = hit {
+            self.entities
+                .retain(|e| !(e.kind == EntityKind::Chain(n) && e.at(new_head.x, new_head.y)));
+            objective::on_piece_eaten(self, n);
+        }
+
+        if !ate {
+            return TickOutcome::Moved;
+        }
+
+        self.score += 1;
+        self.events.push(GameEvent::FoodEaten {
+            x: new_head.x,
+            y: new_head.y,
+            score: self.score,
+        });
+        if self.timer > FASTEST_TIMER {
+            self.timer -= SPEED_UP;
+            self.events.push(GameEvent::SpeedUp { timer: self.timer });
+        }
+        self.run_rules(|rule, game| rule.on_eat(game));
+        // Close call bonuses may have skipped the exact multiple
+        while self.level < self.score / LEVEL_EVERY + 1 {
+            self.level += 1;
+            self.events.push(GameEvent::LevelUp { level: self.level });
+            self.run_rules(|rule, game| rule.on_level_up(game));
+        }
+        if self.score.is_multiple_of(MAGNET_EVERY)
+            && !self.entities.iter().any(|e| e.kind == EntityKind::Magnet)
+            && let Some((x, y)) = self.random_free_cell()
+        {
+            self.entities.push(Entity::new(EntityKind::Magnet, x, y));
+        }
+        objective::on_food_eaten(self);
+        if !self.place_food() && self.food_position().is_none() {
+            self.events.push(GameEvent::BoardFull);
+        }
+        TickOutcome::Ate
+    }
+}
+
+/// Pick a random free cell, `None` means the snake fills the whole screen.
+/// Only cells the head can get to and not right in front of it, unless
+/// there's nowhere else.
+pub fn generate_food(
+    cols: &u16,
+    rows: &u16,
+    snake: &Snake,
+    entities: &[Entity],
+    rng: &mut GameRng,
+) -> Option<(u16, u16)> {
+    let board = Board::new(*cols, *rows, entities);
+    let reachable = board.reachable_from(snake.head());
+    let mut ahead = Vec::new();
+    let mut point = snake.head();
+    for _ in 0..FOOD_AHEAD {
+        point = point.step(snake.direction, *cols, *rows);
+        ahead.push((point.x, point.y));
+    }
+
+    // Every free cell, the ones the head can get to and of those the ones
+    // not right ahead of it
+    let (mut available_positions, mut within_reach, mut fair) =
+        (Vec::new(), Vec::new(), Vec::new());
+    for x in 0..*cols {
+        for y in 0..*rows {
+            let i = board.index(x, y);
+            if board.things[i].is_some() || snake.occupies(x, y) {
+                continue;
+            }
+            available_positions.push((x, y));
+            if reachable.as_ref().is_none_or(|reached| reached[i]) {
+                within_reach.push((x, y));
+                if !ahead.contains(&(x, y)) {
+                    fair.push((x, y));
+                }
+            }
+        }
+    }
+
+    [fair, within_reach, available_positions]
+        .iter()
+        .find(|cells| !cells.is_empty())
+        .map(|cells| *rng.pick(cells))
+}
+
+// What's on each cell, by row, for going over the whole board without going
+// over every entity for each cell
+struct Board {
+    cols: u16,
+    rows: u16,
+    things: Vec<Option<EntityKind>>,
+}
+
+impl Board {
+    fn new(cols: u16, rows: u16, entities: &[Entity]) -> Self {
+        let mut board = Board {
+            cols,
+            rows,
+            things: vec![None; usize::from(cols) * usize::from(rows)],
+        };
+        // The first one on a cell is the one `entity_at` finds
+        for entity in entities.iter().rev() {
+            if entity.x < cols && entity.y < rows {
+                let i = board.index(entity.x, entity.y);
+                board.things[i] = Some(entity.kind);
+            }
+        }
+        board
+    }
+
+    fn index(&self, x: u16, y: u16) -> usize {
+        usize::from(y) * usize::from(self.cols) + usize::from(x)
+    }
+
+    // Every cell the head could get to some day, a breadth-first search over
+    // cell indices: the snake's body moves out of the way, walls don't, and
+    // a portal leads to the other one. `None` is all of them, with nothing
+    // to wall anything off on a board that wraps around.
+    fn reachable_from(&self, head: SnakeBodyPoint) -> Option<Vec<bool>> {
+        let (cols, rows) = (usize::from(self.cols), usize::from(self.rows));
+        if !self.things.contains(&Some(EntityKind::Wall)) {
+            return None;
+        }
+        let start = self.index(head.x, head.y);
+        let mut reached = vec![false; self.things.len()];
+        reached[start] = true;
+        let mut queue = VecDeque::from([start]);
+        while let Some(i) = queue.pop_front() {
+            let (x, y) = (i % cols, i / cols);
+            let through_portal = match self.things[i] {
+                Some(EntityKind::Portal(n)) => self.other_portal(n, i),
+                _ => None,
+            };
+            // Across the edges too
+            let next = [
+                y * cols + (x + 1) % cols,
+                y * cols + (x + cols - 1) % cols,
+                (y + 1) % rows * cols + x,
+                (y + rows - 1) % rows * cols + x,
+            ];
+            for j in next.into_iter().chain(through_portal) {
+                if !reached[j] && self.things[j] != Some(EntityKind::Wall) {
+                    reached[j] = true;
+                    queue.push_back(j);
+                }
+            }
+        }
+        Some(reached)
+    }
+
+    fn other_portal(&self, n: u8, from: usize) -> Option<usize> {
+        (0..self.things.len()).find(|&i| i != from && self.things[i] == Some(EntityKind::Portal(n)))
+    }
+}
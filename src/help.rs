@@ -0,0 +1,53 @@
+// -- The help screen
+//
+// Made from what the game actually does, so it can't go stale: the key
+// bindings in `input.rs`, the rules the mode registers and the things that
+// can be on the board.
+
+use crate::entity::EntityKind;
+use crate::game::{Game, GameOptions};
+use crate::input::key_help;
+use crate::theme::Theme;
+
+/// One line per key, rule and kind of thing, for `game` or, before it
+/// starts, the game `options` would make.
+pub fn help_lines(game: Option<&Game>, options: &GameOptions, theme: &Theme) -> Vec<String> {
+    let mut lines = Vec::new();
+    for (keys, what) in key_help() {
+        lines.push(format!("{keys:<16} {what}"));
+    }
+
+    let (mode, modifiers, rules) = match game {
+        Some(g) => (g.mode, g.modifiers, g.rule_descriptions()),
+        None => (
+            options.mode,
+            options.modifiers,
+            options
+                .mode
+                .rules()
+                .into_iter()
+                .chain(options.modifiers.rules())
+                .map(|r| r.describe())
+                .collect(),
+        ),
+    };
+    lines.push(String::new());
+    lines.push(format!("Mode: {}", mode.name()));
+    if !modifiers.is_empty() {
+        lines.push(format!("Modifiers: {modifiers}"));
+    }
+    if mode.rules().is_empty() {
+        lines.push("Eat to grow, don't bite yourself.".to_string());
+    }
+    lines.extend(rules.into_iter().filter(|r| !r.is_empty()));
+
+    lines.push(String::new());
+    for kind in EntityKind::ALL {
+        lines.push(format!(
+            "{:<16} {}",
+            theme.entity_glyph(kind),
+            kind.describe()
+        ));
+    }
+    lines
+}
@@ -0,0 +1,85 @@
+// -- The input HUD
+//
+// With `--input-hud` the top-left corner of the board shows the turns
+// waiting for their tick, the last one that went in, how long the last one
+// the snake made waited for its tick and how many presses didn't go in at
+// all (the way it's going already, or the queue was full). For streams, to
+// show what's being pressed, and for tracking down key presses a terminal
+// loses: one that never shows up here never got to the game.
+
+use crate::backend::Backend;
+use crate::game::Game;
+use crate::layout::Rect;
+use crate::snake::SnakeDirection;
+use crate::theme::Theme;
+use std::collections::VecDeque;
+use std::io;
+use std::time::{Duration, Instant};
+
+#[derive(Default)]
+pub struct InputHud {
+    last: Option<SnakeDirection>,
+    // When each turn in the game's queue went in, the oldest first
+    waiting: VecDeque<Instant>,
+    latency: Option<Duration>,
+    dropped: u32,
+}
+
+impl InputHud {
+    pub fn new() -> Self {
+        InputHud::default()
+    }
+
+    /// Right after `game.turn(direction)`, with how many turns were queued
+    /// before it.
+    pub fn turned(&mut self, game: &Game, direction: SnakeDirection, queued: usize, now: Instant) {
+        if game.queued_turns.len() > queued {
+            self.last = Some(direction);
+            self.waiting.push_back(now);
+        } else {
+            self.dropped += 1;
+        }
+    }
+
+    /// Right after a tick, which takes the next turn out of the queue.
+    pub fn ticked(&mut self, game: &Game, now: Instant) {
+        while self.waiting.len() > game.queued_turns.len() {
+            if let Some(pressed) = self.waiting.pop_front() {
+                self.latency = Some(now - pressed);
+            }
+        }
+    }
+
+    /// `keys ↑ ←  last ←  waited 41ms  dropped 0`, in the top-left corner
+    /// of `area`.
+    pub fn draw(
+        &self,
+        out: &mut impl Backend,
+        game: &Game,
+        area: Rect,
+        theme: &Theme,
+    ) -> io::Result<()> {
+        let queued: Vec<&str> = game
+            .queued_turns
+            .iter()
+            .map(|&direction| theme.arrow_glyph(direction))
+            .collect();
+        let queued = if queued.is_empty() {
+            "-".to_string()
+        } else {
+            queued.join(" ")
+        };
+        let last = self
+            .last
+            .map_or("-", |direction| theme.arrow_glyph(direction));
+        let waited = self.latency.map_or("-".to_string(), |latency| {
+            format!("{}ms", latency.as_millis())
+        });
+        let text = format!(
+            " keys {queued}  last {last}  waited {waited}  dropped {} ",
+            self.dropped
+        );
+        let text: String = text.chars().take(area.width as usize).collect();
+        out.print(area.x, area.y, &text, theme.status_bar())
+    }
+}
@@ -0,0 +1,229 @@
+// -- Keyboard input
+//
+// Windows (and terminals with enhanced keyboard reporting) send a release
+// event after every press and repeat events while a key is held. Only
+// presses count here, anything else is swallowed without ending the wait,
+// so a single key press never turns the snake or advances the game twice.
+
+use crate::snake::SnakeDirection;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use std::io;
+use std::time::{Duration, Instant};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Input {
+    Quit,
+    Pause,
+    // Save to the current slot, only does anything while paused
+    Save,
+    // Ctrl+Z, raw mode keeps the terminal from turning it into SIGTSTP
+    Suspend,
+    Turn(SnakeDirection),
+    Help,
+    // Large cells on or off, see `zoom.rs`
+    Zoom,
+    // The hamiltonian bot's route over the board on or off
+    Cycle,
+    // A tenth more or fewer ticks a second, in casual games
+    Faster,
+    Slower,
+    // Open the chat line, only playing on a server (see `remote.rs`)
+    Chat,
+    // Not a key, the terminal is now this many columns and rows
+    Resize(u16, u16),
+}
+
+impl Input {
+    pub fn describe(self) -> &'static str {
+        match self {
+            Input::Quit => "quit",
+            Input::Pause => "pause / resume",
+            Input::Save => "save (while paused)",
+            Input::Suspend => "suspend to the shell",
+            Input::Turn(SnakeDirection::Up) => "turn up",
+            Input::Turn(SnakeDirection::Down) => "turn down",
+            Input::Turn(SnakeDirection::Left) => "turn left",
+            Input::Turn(SnakeDirection::Right) => "turn right",
+            Input::Help => "this help",
+            Input::Zoom => "large cells on / off",
+            Input::Cycle => "safe route on / off",
+            Input::Faster => "faster (casual games)",
+            Input::Slower => "slower (casual games)",
+            Input::Chat => "chat (playing online)",
+            Input::Resize(..) => "resize the terminal",
+        }
+    }
+}
+
+/// A key and what it does, the help screen is made from these.
+pub struct Binding {
+    pub code: KeyCode,
+    pub modifiers: KeyModifiers,
+    pub input: Input,
+    pub label: &'static str,
+}
+
+const fn key(code: KeyCode, input: Input, label: &'static str) -> Binding {
+    Binding {
+        code,
+        modifiers: KeyModifiers::NONE,
+        input,
+        label,
+    }
+}
+
+const fn ctrl(c: char, input: Input, label: &'static str) -> Binding {
+    Binding {
+        code: KeyCode::Char(c),
+        modifiers: KeyModifiers::CONTROL,
+        input,
+        label,
+    }
+}
+
+pub const BINDINGS: &[Binding] = &[
+    key(KeyCode::Up, Input::Turn(SnakeDirection::Up), "Up"),
+    key(KeyCode::Down, Input::Turn(SnakeDirection::Down), "Down"),
+    key(KeyCode::Left, Input::Turn(SnakeDirection::Left), "Left"),
+    key(KeyCode::Right, Input::Turn(SnakeDirection::Right), "Right"),
+    key(KeyCode::Char('p'), Input::Pause, "p"),
+    key(KeyCode::Char('s'), Input::Save, "s"),
+    key(KeyCode::Char('?'), Input::Help, "?"),
+    key(KeyCode::F(1), Input::Help, "F1"),
+    key(KeyCode::Char('z'), Input::Zoom, "z"),
+    key(KeyCode::Char('c'), Input::Cycle, "c"),
+    key(KeyCode::Char('+'), Input::Faster, "+"),
+    key(KeyCode::Char('='), Input::Faster, "="),
+    key(KeyCode::Char('-'), Input::Slower, "-"),
+    key(KeyCode::Enter, Input::Chat, "Enter"),
+    ctrl('z', Input::Suspend, "Ctrl+Z"),
+    key(KeyCode::Char('q'), Input::Quit, "q"),
+    ctrl('c', Input::Quit, "Ctrl+C"),
+];
+
+/// Every input with the keys for it, in the order of `BINDINGS`.
+pub fn key_help() -> Vec<(String, &'static str)> {
+    let mut help: Vec<(Input, Vec<&str>)> = Vec::new();
+    for binding in BINDINGS {
+        match help.iter_mut().find(|(input, _)| *input == binding.input) {
+            Some((_, labels)) => labels.push(binding.label),
+            None => help.push((binding.input, vec![binding.label])),
+        }
+    }
+    help.into_iter()
+        .map(|(input, labels)| (labels.join(", "), input.describe()))
+        .collect()
+}
+
+/// How long screens where nothing moves wait for a key at a time. Only so
+/// being resumed after Ctrl+Z is noticed (see `take_resumed`), waking up
+/// for nothing doesn't redraw anything.
+pub const IDLE_WAIT: Duration = Duration::from_secs(1);
+
+/// Wait up to `timeout` for a key press we care about.
+pub fn next_input(timeout: Duration) -> io::Result<Option<Input>> {
+    let deadline = Instant::now() + timeout;
+
+    loop {
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if !poll(remaining)? {
+            return Ok(None);
+        }
+
+        match event::read()? {
+            Event::Key(key) => {
+                if let Some(input) = map_key(key) {
+                    return Ok(Some(input));
+                }
+            }
+            Event::Resize(cols, rows) => return Ok(Some(Input::Resize(cols, rows))),
+            _ => {}
+        }
+
+        if Instant::now() >= deadline {
+            return Ok(None);
+        }
+    }
+}
+
+/// Wait up to `timeout` for any key press or a resize, for screens that take
+/// more keys than `BINDINGS` has, like the level editor. `None` for anything
+/// else too.
+pub fn next_event(timeout: Duration) -> io::Result<Option<Event>> {
+    if !poll(timeout)? {
+        return Ok(None);
+    }
+    match event::read()? {
+        Event::Key(key) if key.kind == KeyEventKind::Press => Ok(Some(Event::Key(key))),
+        event @ Event::Resize(..) => Ok(Some(event)),
+        _ => Ok(None),
+    }
+}
+
+// crossterm keeps reading a terminal that hung up (an SSH connection that
+// dropped) over and over, it never returns. So the terminal is waited on
+// here first, a hangup is an error, and crossterm is only asked once there's
+// something to read or it still has events it read before.
+#[cfg(unix)]
+fn poll(timeout: Duration) -> io::Result<bool> {
+    use std::io::IsTerminal;
+    use std::os::fd::AsRawFd;
+
+    let stdin = io::stdin();
+    // crossterm reads /dev/tty then, which isn't ours to look at
+    if !stdin.is_terminal() {
+        return event::poll(timeout);
+    }
+    let fd = stdin.as_raw_fd();
+    wait_for_terminal(fd, Duration::ZERO)?;
+    if event::poll(Duration::ZERO)? {
+        return Ok(true);
+    }
+    wait_for_terminal(fd, timeout)?;
+    // Again, the wait may have been cut short by the SIGHUP of a hangup.
+    // Signals like a resize are crossterm's to tell
+    wait_for_terminal(fd, Duration::ZERO)?;
+    event::poll(Duration::ZERO)
+}
+
+#[cfg(not(unix))]
+fn poll(timeout: Duration) -> io::Result<bool> {
+    event::poll(timeout)
+}
+
+// Whether the terminal has something to read within `timeout`
+#[cfg(unix)]
+fn wait_for_terminal(fd: std::os::fd::RawFd, timeout: Duration) -> io::Result<bool> {
+    let mut pollfd = libc::pollfd {
+        fd,
+        events: libc::POLLIN,
+        revents: 0,
+    };
+    let millis = timeout.as_millis().min(i32::MAX as u128) as i32;
+    match unsafe { libc::poll(&mut pollfd, 1, millis) } {
+        -1 => match io::Error::last_os_error() {
+            e if e.kind() == io::ErrorKind::Interrupted => Ok(false),
+            e => Err(e),
+        },
+        0 => Ok(false),
+        _ if pollfd.revents & (libc::POLLHUP | libc::POLLERR | libc::POLLNVAL) != 0 => Err(
+            io::Error::new(io::ErrorKind::BrokenPipe, "the terminal hung up"),
+        ),
+        _ => Ok(true),
+    }
+}
+
+/// What a key does, `None` for keys that do nothing and for anything but a
+/// press. The modifiers have to be the binding's, so `z` isn't Ctrl+Z, but
+/// for Shift: `+` and `?` take it on most keyboards.
+pub fn map_key(key: KeyEvent) -> Option<Input> {
+    if key.kind != KeyEventKind::Press {
+        return None;
+    }
+
+    let modifiers = key.modifiers - KeyModifiers::SHIFT;
+    BINDINGS
+        .iter()
+        .find(|b| b.code == key.code && modifiers == b.modifiers)
+        .map(|b| b.input)
+}
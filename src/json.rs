@@ -0,0 +1,70 @@
+// -- Just enough JSON
+//
+// What snake2 hands to other programs (Discord, run summaries) is small and
+// flat, so it's written out by hand instead of pulling in a serializer.
+// These are the bits that are easy to get wrong. Reading goes as far as
+// picking a field out of an object, for the messages of a game server.
+
+/// `text` as a JSON string, quotes included.
+pub fn string(text: &str) -> String {
+    let mut out = String::with_capacity(text.len() + 2);
+    out.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if u32::from(c) < 0x20 => out.push_str(&format!("\\u{:04x}", u32::from(c))),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// `[a,b,c]` out of values already written as JSON.
+pub fn array(items: impl IntoIterator<Item = String>) -> String {
+    format!("[{}]", items.into_iter().collect::<Vec<_>>().join(","))
+}
+
+/// The value of `key` in the JSON object `json` as it's written there, a
+/// string with its quotes, an array with its brackets. Only the object's own
+/// fields, not the ones of objects inside it.
+pub fn field<'a>(json: &'a str, key: &str) -> Option<&'a str> {
+    let inner = json.trim().strip_prefix('{')?.strip_suffix('}')?;
+    let mut depth = 0;
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut start = 0;
+    // Each `key:value` of the object, split at the commas outside anything
+    let mut members = Vec::new();
+    for (i, c) in inner.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if in_string => escaped = true,
+            '"' => in_string = !in_string,
+            _ if in_string => {}
+            '[' | '{' => depth += 1,
+            ']' | '}' => depth -= 1,
+            ',' if depth == 0 => {
+                members.push(&inner[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    members.push(&inner[start..]);
+    let quoted = string(key);
+    members.into_iter().find_map(|member| {
+        let (name, value) = member.split_once(':')?;
+        (name.trim() == quoted).then_some(value.trim())
+    })
+}
+
+/// A JSON string's text, for the simple ones snake2 writes: quotes taken
+/// off, escapes left alone.
+pub fn unquote(value: &str) -> Option<&str> {
+    value.strip_prefix('"')?.strip_suffix('"')
+}
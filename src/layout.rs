@@ -0,0 +1,105 @@
+// -- Where things go on the screen
+//
+// UI code asks for a `Rect` instead of working out coordinates by hand: the
+// whole terminal is split into the board and the status bar, a panel is
+// split off the side, a box is centered in what's left. Everything is
+// computed again from the terminal size, so a resize only means laying
+// things out once more.
+
+/// Rows and columns along the edges of the terminal the game stays off,
+/// left to a terminal prompt or a multiplexer's status bar.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Margins {
+    pub top: u16,
+    pub bottom: u16,
+    pub left: u16,
+    pub right: u16,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Rect {
+    pub x: u16,
+    pub y: u16,
+    pub width: u16,
+    pub height: u16,
+}
+
+impl Rect {
+    pub fn new(x: u16, y: u16, width: u16, height: u16) -> Rect {
+        Rect {
+            x,
+            y,
+            width,
+            height,
+        }
+    }
+
+    /// The whole terminal.
+    pub fn screen(cols: u16, rows: u16) -> Rect {
+        Rect::new(0, 0, cols, rows)
+    }
+
+    /// The same size in the top-left corner.
+    pub fn at_origin(&self) -> Rect {
+        Rect::new(0, 0, self.width, self.height)
+    }
+
+    pub fn right(&self) -> u16 {
+        self.x + self.width
+    }
+
+    pub fn bottom(&self) -> u16 {
+        self.y + self.height
+    }
+
+    /// The top and the last `height` rows.
+    pub fn split_bottom(&self, height: u16) -> (Rect, Rect) {
+        let height = height.min(self.height);
+        let top = Rect::new(self.x, self.y, self.width, self.height - height);
+        let bottom = Rect::new(self.x, top.bottom(), self.width, height);
+        (top, bottom)
+    }
+
+    /// The left and the last `width` columns.
+    pub fn split_right(&self, width: u16) -> (Rect, Rect) {
+        let width = width.min(self.width);
+        let left = Rect::new(self.x, self.y, self.width - width, self.height);
+        let right = Rect::new(left.right(), self.y, width, self.height);
+        (left, right)
+    }
+
+    /// A `width` by `height` box in the middle, cut to fit.
+    pub fn centered(&self, width: u16, height: u16) -> Rect {
+        let (width, height) = (width.min(self.width), height.min(self.height));
+        Rect::new(
+            self.x + (self.width - width) / 2,
+            self.y + (self.height - height) / 2,
+            width,
+            height,
+        )
+    }
+
+    /// What's left inside `margins`, nothing if they take up all of it.
+    pub fn without(&self, margins: Margins) -> Rect {
+        let left = margins.left.min(self.width);
+        let top = margins.top.min(self.height);
+        Rect::new(
+            self.x + left,
+            self.y + top,
+            (self.width - left).saturating_sub(margins.right),
+            (self.height - top).saturating_sub(margins.bottom),
+        )
+    }
+
+    /// Smaller by `margin` on every side.
+    pub fn inset(&self, margin: u16) -> Rect {
+        let width = self.width.saturating_sub(2 * margin);
+        let height = self.height.saturating_sub(2 * margin);
+        Rect::new(
+            self.x + margin.min(self.width / 2),
+            self.y + margin.min(self.height / 2),
+            width,
+            height,
+        )
+    }
+}
@@ -0,0 +1,456 @@
+// -- Hand-made levels
+//
+// A level is a board drawn by hand, made with `snake2 edit level.txt` or
+// any text editor, and played with `--level level.txt`. After the version
+// header come `key value` lines about it, then `map` and the board itself,
+// a character a cell:
+//
+//     snake2-level 1
+//     name Box canyon
+//     author alice
+//     difficulty hard
+//     map
+//     ##########
+//     #S....ff.#
+//     #.1..^^1.#
+//     ##########
+//
+// `#` is a wall, `^` a hazard, `1` to `9` portals (two of each, going into
+// one comes out of the other), `S` where the snake starts and `f` the food
+// zone: with any, food only shows up on those cells. `.` (or a space) is an
+// empty cell, short rows are filled with empty ones.
+//
+// Levels are checked when they're read: a spawn, at most one, both ends of
+// every portal, and enough room around the spawn. The board wraps around
+// its edges and portals lead to their other end, so what counts as room is
+// what the snake can get to that way, food zone cells included.
+//
+// Playing one is the mode as usual plus a `LevelRule` that puts the level's
+// walls, hazards and portals on the board and keeps food in the zone.
+
+use crate::entity::{Entity, EntityKind};
+use crate::game::{Game, GameOptions};
+use crate::rules::Rule;
+use crate::save::MAX_BOARD;
+use crate::snake::{SnakeBodyPoint, SnakeDirection, Spawn};
+use crate::versioned::{Format, unchanged};
+
+pub const LEVEL: Format = Format {
+    kind: "level",
+    // 1: the first one
+    migrations: &[unchanged],
+};
+
+// Free cells the snake has to be able to get to from the spawn
+pub const MIN_ROOM: usize = 10;
+
+const DIRECTIONS: [SnakeDirection; 4] = [
+    SnakeDirection::Up,
+    SnakeDirection::Down,
+    SnakeDirection::Left,
+    SnakeDirection::Right,
+];
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Cell {
+    #[default]
+    Empty,
+    Wall,
+    Hazard,
+    Portal(u8),
+    Spawn,
+    Food,
+}
+
+impl Cell {
+    pub fn symbol(self) -> char {
+        match self {
+            Cell::Empty => '.',
+            Cell::Wall => '#',
+            Cell::Hazard => '^',
+            Cell::Portal(n) => char::from(b'0' + n),
+            Cell::Spawn => 'S',
+            Cell::Food => 'f',
+        }
+    }
+
+    pub fn from_symbol(symbol: char) -> Option<Cell> {
+        match symbol {
+            '.' | ' ' => Some(Cell::Empty),
+            '#' => Some(Cell::Wall),
+            '^' => Some(Cell::Hazard),
+            '1'..='9' => Some(Cell::Portal(symbol as u8 - b'0')),
+            'S' => Some(Cell::Spawn),
+            'f' => Some(Cell::Food),
+            _ => None,
+        }
+    }
+
+    /// What it puts on the board, spawn and food cells aren't anything there.
+    /// Nothing on it in a game, the snake can move there.
+    pub fn is_free(self) -> bool {
+        self.entity_kind().is_none()
+    }
+
+    pub fn entity_kind(self) -> Option<EntityKind> {
+        match self {
+            Cell::Wall => Some(EntityKind::Wall),
+            Cell::Hazard => Some(EntityKind::Hazard),
+            Cell::Portal(n) => Some(EntityKind::Portal(n)),
+            Cell::Empty | Cell::Spawn | Cell::Food => None,
+        }
+    }
+}
+
+/// Something wrong with a level, at a cell or (`None`) with all of it.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Problem {
+    pub at: Option<(u16, u16)>,
+    pub message: String,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Level {
+    pub name: String,
+    pub author: String,
+    // Whatever the author says, `easy`, `3/5`...
+    pub difficulty: String,
+    pub cols: u16,
+    pub rows: u16,
+    // Row by row
+    pub cells: Vec<Cell>,
+}
+
+impl Level {
+    /// An empty `cols` by `rows` level.
+    pub fn new(cols: u16, rows: u16) -> Level {
+        Level {
+            name: "untitled".to_string(),
+            author: String::new(),
+            difficulty: String::new(),
+            cols,
+            rows,
+            cells: vec![Cell::Empty; cols as usize * rows as usize],
+        }
+    }
+
+    /// A level file, checked (see `problems`), errors say where in it.
+    pub fn parse(text: &str) -> Result<Level, String> {
+        let (level, map_line) = Level::parse_map(text)?;
+        let problems: Vec<String> = level
+            .problems()
+            .into_iter()
+            .map(|problem| match problem.at {
+                Some((x, y)) => format!(
+                    "line {}, column {}: {}",
+                    map_line + y as usize,
+                    x + 1,
+                    problem.message
+                ),
+                None => problem.message,
+            })
+            .collect();
+        if !problems.is_empty() {
+            return Err(problems.join("\n"));
+        }
+        Ok(level)
+    }
+
+    /// A level file as it is, problems and all, for the editor to fix them.
+    pub fn parse_unchecked(text: &str) -> Result<Level, String> {
+        Level::parse_map(text).map(|(level, _)| level)
+    }
+
+    // With the line the map starts on
+    fn parse_map(text: &str) -> Result<(Level, usize), String> {
+        let body = LEVEL.upgrade(text)?;
+        // Line numbers in errors are the file's, with the header as line 1
+        let first_line = if LEVEL.version_of(text)? > 0 { 2 } else { 1 };
+        let mut lines = body.lines().zip(first_line..);
+
+        let mut level = Level::new(0, 0);
+        let map_line = loop {
+            let Some((line, number)) = lines.next() else {
+                return Err("no `map` line, the board goes after it".to_string());
+            };
+            let line = line.trim();
+            if line == "map" {
+                break number + 1;
+            }
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line.split_once(' ').unwrap_or((line, ""));
+            match key {
+                "name" => level.name = value.trim().to_string(),
+                "author" => level.author = value.trim().to_string(),
+                "difficulty" => level.difficulty = value.trim().to_string(),
+                _ => return Err(format!("line {number}: unknown `{key}`")),
+            }
+        };
+
+        let mut rows: Vec<Vec<Cell>> = Vec::new();
+        for (line, number) in lines {
+            if rows.len() == MAX_BOARD as usize {
+                return Err(format!("line {number}: a map has at most {MAX_BOARD} rows"));
+            }
+            let line = line.trim_end_matches('\r');
+            if line.chars().count() > MAX_BOARD as usize {
+                return Err(format!(
+                    "line {number}, column {}: a map has at most {MAX_BOARD} columns",
+                    MAX_BOARD + 1
+                ));
+            }
+            let row = line
+                .chars()
+                .enumerate()
+                .map(|(column, symbol)| {
+                    Cell::from_symbol(symbol).ok_or(format!(
+                        "line {number}, column {}: `{symbol}` isn't a cell",
+                        column + 1
+                    ))
+                })
+                .collect::<Result<_, _>>()?;
+            rows.push(row);
+        }
+        // Blank lines at the end are only the end of the file
+        while rows.last().is_some_and(Vec::is_empty) {
+            rows.pop();
+        }
+        let width = rows.iter().map(Vec::len).max().unwrap_or(0);
+        if width < 2 || rows.len() < 2 {
+            return Err("the map needs at least 2x2 cells".to_string());
+        }
+        // Both checked against MAX_BOARD above
+        level.cols = width as u16;
+        level.rows = rows.len() as u16;
+        level.cells = rows
+            .into_iter()
+            .flat_map(|mut row| {
+                row.resize(width, Cell::Empty);
+                row
+            })
+            .collect();
+        Ok((level, map_line))
+    }
+
+    /// What's wrong with the level, if anything, in the order it's read.
+    pub fn problems(&self) -> Vec<Problem> {
+        let mut problems = Vec::new();
+        let spawns: Vec<(u16, u16)> = self.cells_of(Cell::Spawn).collect();
+        match spawns[..] {
+            [] => problems.push(Problem {
+                at: None,
+                message: "no spawn, put an `S` where the snake starts".to_string(),
+            }),
+            [_, ref others @ ..] => problems.extend(others.iter().map(|&at| Problem {
+                at: Some(at),
+                message: "a second spawn, there's only one".to_string(),
+            })),
+        }
+        for n in 1..=9 {
+            let ends: Vec<(u16, u16)> = self.cells_of(Cell::Portal(n)).collect();
+            let message = match ends.len() {
+                0 | 2 => continue,
+                1 => format!("portal {n} has no other end"),
+                _ => format!("portal {n} has more than two ends"),
+            };
+            problems.push(Problem {
+                // The one too many
+                at: Some(ends[if ends.len() == 1 { 0 } else { 2 }]),
+                message,
+            });
+        }
+        if let [spawn] = spawns[..] {
+            let reachable = self.reachable(spawn);
+            let room = self
+                .iter()
+                .filter(|&(x, y, cell)| cell.is_free() && reachable[self.index(x, y)])
+                .count();
+            if room < MIN_ROOM {
+                problems.push(Problem {
+                    at: Some(spawn),
+                    message: format!(
+                        "the snake only has {room} cells to move in from here, it needs {MIN_ROOM}"
+                    ),
+                });
+            }
+            problems.extend(
+                self.cells_of(Cell::Food)
+                    .filter(|&(x, y)| !reachable[self.index(x, y)])
+                    .map(|at| Problem {
+                        at: Some(at),
+                        message: "food zone walled off, the snake can't get to it".to_string(),
+                    }),
+            );
+        }
+        problems.sort_by_key(|p| p.at.map(|(x, y)| (y, x)));
+        problems
+    }
+
+    fn index(&self, x: u16, y: u16) -> usize {
+        y as usize * self.cols as usize + x as usize
+    }
+
+    fn cells_of(&self, cell: Cell) -> impl Iterator<Item = (u16, u16)> + '_ {
+        self.iter()
+            .filter(move |&(_, _, c)| c == cell)
+            .map(|(x, y, _)| (x, y))
+    }
+
+    // Every cell the head can get to from `from`, the way `Game::tick` moves
+    // it: around the edges and through portals, over hazards but not walls
+    fn reachable(&self, from: (u16, u16)) -> Vec<bool> {
+        let mut seen = vec![false; self.cells.len()];
+        seen[self.index(from.0, from.1)] = true;
+        let mut todo = vec![SnakeBodyPoint {
+            x: from.0,
+            y: from.1,
+            letter: None,
+        }];
+        while let Some(point) = todo.pop() {
+            for direction in DIRECTIONS {
+                let mut next = point.step(direction, self.cols, self.rows);
+                if let Cell::Portal(n) = self.get(next.x, next.y) {
+                    let Some((x, y)) = self
+                        .cells_of(Cell::Portal(n))
+                        .find(|&(x, y)| (x, y) != (next.x, next.y))
+                    else {
+                        continue;
+                    };
+                    next =
+                        SnakeBodyPoint { x, y, letter: None }.step(direction, self.cols, self.rows);
+                }
+                let i = self.index(next.x, next.y);
+                if !seen[i] && self.cells[i] != Cell::Wall {
+                    seen[i] = true;
+                    todo.push(next);
+                }
+            }
+        }
+        seen
+    }
+
+    /// The file, as `parse` reads it.
+    pub fn to_text(&self) -> String {
+        let mut body = format!("name {}\n", self.name);
+        if !self.author.is_empty() {
+            body.push_str(&format!("author {}\n", self.author));
+        }
+        if !self.difficulty.is_empty() {
+            body.push_str(&format!("difficulty {}\n", self.difficulty));
+        }
+        body.push_str("map\n");
+        for row in self.cells.chunks(self.cols as usize) {
+            body.extend(row.iter().map(|c| c.symbol()));
+            body.push('\n');
+        }
+        LEVEL.write(&body)
+    }
+
+    /// `Empty` off the map.
+    pub fn get(&self, x: u16, y: u16) -> Cell {
+        if x >= self.cols || y >= self.rows {
+            return Cell::Empty;
+        }
+        self.cells[y as usize * self.cols as usize + x as usize]
+    }
+
+    /// There's only one spawn, putting it somewhere takes it from where it was.
+    pub fn set(&mut self, x: u16, y: u16, cell: Cell) {
+        if x >= self.cols || y >= self.rows {
+            return;
+        }
+        if cell == Cell::Spawn {
+            for c in &mut self.cells {
+                if *c == Cell::Spawn {
+                    *c = Cell::Empty;
+                }
+            }
+        }
+        self.cells[y as usize * self.cols as usize + x as usize] = cell;
+    }
+
+    /// Every cell with its place, row by row.
+    pub fn iter(&self) -> impl Iterator<Item = (u16, u16, Cell)> + '_ {
+        let cols = self.cols as usize;
+        self.cells
+            .iter()
+            .enumerate()
+            .map(move |(i, &cell)| ((i % cols) as u16, (i / cols) as u16, cell))
+    }
+
+    pub fn spawn(&self) -> Option<(u16, u16)> {
+        self.iter()
+            .find(|&(_, _, cell)| cell == Cell::Spawn)
+            .map(|(x, y, _)| (x, y))
+    }
+
+    /// A new game on the level, starting at its spawn if it has one.
+    pub fn start(&self, direction: SnakeDirection, options: &GameOptions) -> Game {
+        let options = GameOptions {
+            spawn: self.spawn().map_or(options.spawn, |(x, y)| Spawn::At(x, y)),
+            ..*options
+        };
+        let rule = LevelRule {
+            entities: self
+                .iter()
+                .filter_map(|(x, y, cell)| Some(Entity::new(cell.entity_kind()?, x, y)))
+                .collect(),
+            food_zone: self
+                .iter()
+                .filter(|&(_, _, cell)| cell == Cell::Food)
+                .map(|(x, y, _)| (x, y))
+                .collect(),
+        };
+        Game::with_rules(
+            self.cols,
+            self.rows,
+            direction,
+            &options,
+            vec![Box::new(rule)],
+        )
+    }
+}
+
+/// Puts a level's walls, hazards and portals on the board, and keeps food
+/// on its food zone while there's room in it.
+pub struct LevelRule {
+    pub entities: Vec<Entity>,
+    pub food_zone: Vec<(u16, u16)>,
+}
+
+impl Rule for LevelRule {
+    fn name(&self) -> &'static str {
+        "level"
+    }
+
+    fn describe(&self) -> String {
+        if self.food_zone.is_empty() {
+            "A hand-made board.".to_string()
+        } else {
+            "A hand-made board, food only shows up in its food zone.".to_string()
+        }
+    }
+
+    fn on_start(&mut self, game: &mut Game) {
+        game.entities.extend(&self.entities);
+    }
+
+    fn modify_spawn(
+        &mut self,
+        game: &mut Game,
+        position: Option<(u16, u16)>,
+    ) -> Option<(u16, u16)> {
+        let free: Vec<(u16, u16)> = self
+            .food_zone
+            .iter()
+            .copied()
+            .filter(|&(x, y)| game.is_free(x, y))
+            .collect();
+        if free.is_empty() {
+            return position;
+        }
+        Some(*game.rng.pick(&free))
+    }
+}
@@ -0,0 +1,79 @@
+#[cfg(not(target_arch = "wasm32"))]
+pub mod achievement;
+#[cfg(feature = "scripting")]
+pub mod arena;
+pub mod backend;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod bench;
+pub mod binary;
+#[cfg(feature = "scripting")]
+pub mod bot;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod calibrate;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod catalog;
+pub mod challenge;
+pub mod checkpoint;
+pub mod clock;
+pub mod difficulty;
+#[cfg(all(feature = "discord", not(target_arch = "wasm32")))]
+pub mod discord;
+#[cfg(all(feature = "server", not(target_arch = "wasm32")))]
+pub mod discovery;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod editor;
+pub mod entity;
+pub mod env;
+pub mod events;
+#[cfg(all(feature = "ffi", not(target_arch = "wasm32")))]
+pub mod ffi;
+pub mod game;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod help;
+pub mod hud;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod input;
+pub mod json;
+pub mod layout;
+pub mod level;
+pub mod objective;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod painter;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod profile;
+pub mod protocol;
+#[cfg(all(feature = "python", not(target_arch = "wasm32")))]
+pub mod python;
+#[cfg(all(feature = "server", not(target_arch = "wasm32")))]
+pub mod remote;
+pub mod render;
+pub mod replay;
+pub mod rng;
+pub mod royale;
+pub mod rules;
+#[cfg(all(feature = "tokio", not(target_arch = "wasm32")))]
+pub mod runtime;
+pub mod save;
+#[cfg(all(feature = "server", not(target_arch = "wasm32")))]
+pub mod server;
+pub mod shape;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod signature;
+#[cfg(feature = "scripting")]
+pub mod simulate;
+pub mod snake;
+pub mod strategy;
+pub mod summary;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod terminal;
+pub mod theme;
+pub mod toast;
+#[cfg(all(feature = "ratatui", not(target_arch = "wasm32")))]
+pub mod tui;
+#[cfg(all(feature = "twitch", not(target_arch = "wasm32")))]
+pub mod twitch;
+pub mod versioned;
+#[cfg(target_arch = "wasm32")]
+pub mod web;
+pub mod weekly;
+pub mod zoom;
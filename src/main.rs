@@ -1,300 +1,2167 @@
-use core::panic;
-use crossterm::{
-    ExecutableCommand,
-    cursor::{Hide, MoveTo, Show},
-    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
-    style::{self, Stylize},
-    terminal::{self, Clear, ClearType},
+use crossterm::terminal;
+use snake2::achievement::Achievement;
+use snake2::backend::{Backend, Offset};
+use snake2::calibrate::measure;
+use snake2::challenge::Challenge;
+use snake2::checkpoint::{Checkpoints, RETRIES};
+use snake2::clock::{Stopwatch, format_duration};
+use snake2::difficulty::{Adaptive, Nudge};
+#[cfg(feature = "discord")]
+use snake2::discord::{Activity, Presence};
+use snake2::events::{DeathCause, GameEvent};
+use snake2::game::{CLOSE_CALL_BONUS, FASTEST_TIMER, Game, GameOptions, TickOutcome};
+use snake2::help::help_lines;
+use snake2::hud::InputHud;
+use snake2::input::{IDLE_WAIT, Input, next_input};
+use snake2::layout::{Margins, Rect};
+use snake2::level::Level;
+use snake2::painter::Painter;
+use snake2::profile::{DEFAULT_PROFILE, Profile, RunRecord, now as now_secs};
+use snake2::render::{
+    StatusBar, draw_cycle, draw_danger, draw_game, draw_head_flash, draw_heatmap, draw_overlay,
 };
-use rand::Rng;
-use std::io::{self, Write};
-use std::time::Duration;
-
-enum SnakeDirection {
-    Up,
-    Down,
-    Left,
-    Right,
-}
-
-#[derive(Clone)]
-struct SnakeBodyPoint {
-    x: u16,
-    y: u16,
-}
-
-struct Snake {
-    direction: SnakeDirection,
-    body: Vec<SnakeBodyPoint>,
-}
-
-impl Snake {
-    fn new(cols: &u16, rows: &u16, initial_direction: SnakeDirection) -> Self {
-        let x = cols / 2;
-        let y = rows / 2;
-        let snake_body_point = SnakeBodyPoint { x, y };
-        Snake {
-            direction: initial_direction,
-            body: vec![snake_body_point],
-        }
-    }
-    fn print_body(
-        &mut self,
-        stdout: &mut std::io::Stdout,
-        food_position: Option<(u16, u16)>,
-        cols: &u16,
-        rows: &u16,
-    ) -> io::Result<Option<(u16, u16)>> {
-        let mut new_head = self.body[0].clone();
-
-        match self.direction {
-            SnakeDirection::Up => {
-                if new_head.y == 0 {
-                    new_head.y = *rows - 1;
-                } else {
-                    new_head.y -= 1;
-                }
-            }
-            SnakeDirection::Down => {
-                new_head.y = (new_head.y + 1) % *rows;
-            }
-            SnakeDirection::Left => {
-                if new_head.x == 0 {
-                    new_head.x = *cols - 1;
-                } else {
-                    new_head.x -= 1;
-                }
-            }
-            SnakeDirection::Right => {
-                new_head.x = (new_head.x + 1) % *cols;
-            }
-        }
+use snake2::replay::{Playback, Recent, Replay};
+use snake2::rules::Mode;
+use snake2::shape::Shape;
+use snake2::signature::Signed;
+use snake2::strategy::{Strategy, hamiltonian_cycle};
+use snake2::summary::{Meal, RunSummary};
+use snake2::terminal::{
+    CrosstermBackend, Multiplexer, check_hangup, copy_to_clipboard, disable_game_mode,
+    enable_game_mode, restore_terminal, setup_panic_hook, suspend, take_resumed, watch_hangup,
+    watch_job_control,
+};
+use snake2::theme::{Glyphs, Season, Theme};
+use snake2::toast::Toasts;
+use snake2::weekly::Week;
+use snake2::zoom::{CellSize, Zoom};
+use std::cmp::Ordering;
+use std::io;
+use std::time::{Duration, Instant};
 
-        // Game over if the new head collides with body
-        if self
-            .body
-            .iter()
-            .any(|segment| segment.x == new_head.x && segment.y == new_head.y)
-        {
-            println!("\n\n\tGame Over! You hit yourself.\n\n");
-            disable_game_mode(stdout)?;
-            std::process::exit(0)
-        }
+const USAGE: &str =
+    "Usage: snake2 [--profile NAME] [--slot N] [--load] [--no-color] [--day-night] [--seasonal]
+                     [--idle-pause SECS] [--input-grace MS] [--tps N] [--adaptive] [--heatmap]
+                     [--summary-json PATH|-] [--record PATH] [--length N] [--spawn center|random|X,Y]
+                     [--mode classic|tron|survival|arcade|fog|rooms|nibbles|words|mirror|hydra|surround]
+                     [--modifiers teleport,gravity,hunger,fog,inverted,fleeting,hills] [--weekly] [--race] [--challenge CODE] [--level FILE] [--arena donut|cross|spiral]
+                     [--discord CLIENT_ID] [--ui default|ratatui] [--no-photo-finish] [--glyph-NAME GLYPH]
+                     [--grid dots|checker] [--large-cells 2x1|2x2] [--emoji] [--cycle] [--danger]
+                     [--input-hud] [--no-clipboard] [--margin-top|bottom|left|right N]
+                     [bot <script.rhai>] [--io-protocol [--size COLSxROWS]]
+       snake2 arena <bot.rhai>... [--games N] [--seed N] [--size COLSxROWS] [--max-ticks N]
+       snake2 simulate --bot <bot.rhai> [--episodes N] [--threads N] [--out FILE.csv]
+                       [--seed N] [--size COLSxROWS] [--max-ticks N]
+       snake2 bench-bots [--games N] [--seed N] [--size COLSxROWS] [--max-ticks N]
+       snake2 twitch <channel> [--votes-every TICKS]
+       snake2 server [--port N] [--size COLSxROWS] [--royale [--players N] [--teams]] [--no-lan]
+       snake2 connect <ws://host:port> [--predict]
+       snake2 join [host:port] [--predict]
+       snake2 replay <run.replay> [--notation PATH|-] [--binary PATH]
+       snake2 edit <level.txt>
+       snake2 levels [--levels-index URL]
+       snake2 profiles
+       snake2 calibrate";
 
-        // Shift the body
-        self.body.insert(0, new_head.clone());
+// TODO
+//  End screen, points,
+//  play again,
+//  Possible refactors,
+//  can I update just the body instead of cleaning all?
 
-        let mut grew = false;
+fn main() -> io::Result<()> {
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
 
-        if let Some(fp) = food_position {
-            if new_head.x == fp.0 && new_head.y == fp.1 {
-                grew = true;
-            }
+    // The profile's config goes after the real arguments, so those win
+    let profile_name =
+        take_option(&mut args, "--profile").unwrap_or_else(|| DEFAULT_PROFILE.to_string());
+    let profile = match Profile::open(&profile_name) {
+        Ok(profile) => profile,
+        Err(e) => {
+            eprintln!("Can't open profile `{profile_name}`: {e}");
+            std::process::exit(1)
         }
+    };
+    args.extend(profile.config_args()?);
 
-        if !grew {
-            self.body.pop(); // Remove the tail unless food was eaten
+    // Options that apply to every command
+    let no_color = take_flag(&mut args, "--no-color");
+    let seasonal = take_flag(&mut args, "--seasonal");
+    let emoji = take_flag(&mut args, "--emoji");
+    let glyphs = take_glyphs(&mut args, emoji).unwrap_or_else(|e| {
+        eprintln!("Bad glyph: {e}.");
+        std::process::exit(2)
+    });
+    let theme = Theme {
+        day_night: take_flag(&mut args, "--day-night"),
+        season: seasonal.then(|| Season::at(now_secs())).flatten(),
+        // Kept for as long as the game runs, the theme only holds on to it
+        glyphs: glyphs.map(|g| &*Box::leak(Box::new(g))),
+        grid: take_option(&mut args, "--grid").map(|grid| flag_value("--grid", Some(&grid))),
+        ..Theme::from_env(no_color)
+    };
+    let mut settings = Settings {
+        theme,
+        idle_pause: Some(Duration::from_secs(30)),
+        input_grace: Duration::ZERO,
+        slot: 1,
+        load: take_flag(&mut args, "--load"),
+        adaptive: take_flag(&mut args, "--adaptive"),
+        heatmap: take_flag(&mut args, "--heatmap"),
+        photo_finish: !take_flag(&mut args, "--no-photo-finish"),
+        race: take_flag(&mut args, "--race"),
+        cycle: take_flag(&mut args, "--cycle"),
+        danger: take_flag(&mut args, "--danger"),
+        input_hud: take_flag(&mut args, "--input-hud"),
+        speed_keys: true,
+        clipboard: !take_flag(&mut args, "--no-clipboard"),
+        summary_json: take_option(&mut args, "--summary-json"),
+        record: take_option(&mut args, "--record"),
+        profile,
+        board: None,
+        level: None,
+        discord: take_option(&mut args, "--discord"),
+        weekly: None,
+        large_cells: None,
+        margins: Margins::default(),
+    };
+    if settings.discord.is_some() && !cfg!(feature = "discord") {
+        eprintln!("This build has no Discord support, rebuild with `--features discord`.");
+        std::process::exit(1)
+    }
+    // The walls it adds and takes away aren't turns a replay could play back
+    if settings.adaptive && settings.record.is_some() {
+        eprintln!("--adaptive games can't be recorded.");
+        std::process::exit(2)
+    }
+    if let Some(slot) = take_option(&mut args, "--slot") {
+        settings.slot = flag_value("--slot", Some(&slot));
+    }
+    if let Some(size) = take_option(&mut args, "--large-cells") {
+        settings.large_cells = Some(flag_value("--large-cells", Some(&size)));
+    }
+    // Emoji are two columns wide, so are large cells
+    if emoji {
+        settings.large_cells.get_or_insert(CellSize::Wide);
+    }
+    // A multiplexer's messages turn up on the last row, `--margin-bottom 0`
+    // plays on it anyway
+    if Multiplexer::detect().is_some() {
+        settings.margins.bottom = 1;
+    }
+    for (flag, margin) in [
+        ("--margin-top", &mut settings.margins.top),
+        ("--margin-bottom", &mut settings.margins.bottom),
+        ("--margin-left", &mut settings.margins.left),
+        ("--margin-right", &mut settings.margins.right),
+    ] {
+        if let Some(rows) = take_option(&mut args, flag) {
+            *margin = flag_value(flag, Some(&rows));
         }
-
-        // Render snake
-        for i in 0..self.body.len() {
-            let current = &self.body[i];
-            let ch = if i == 0 {
-                // Head
-                match self.direction {
-                    SnakeDirection::Up => '^',
-                    SnakeDirection::Down => 'v',
-                    SnakeDirection::Left => '<',
-                    SnakeDirection::Right => '>',
-                }
-            } else {
-                // Tail or body segment
-                let prev = &self.body[i - 1];
-                if current.x == prev.x {
-                    '|'
-                } else if current.y == prev.y {
-                    '-'
-                } else {
-                    's'
-                }
-            };
-
-            stdout
-                .execute(MoveTo(current.x, current.y))?
-                .execute(style::PrintStyledContent(ch.green()))?;
+    }
+    if let Some(secs) = take_option(&mut args, "--idle-pause") {
+        let secs: u64 = flag_value("--idle-pause", Some(&secs));
+        settings.idle_pause = (secs > 0).then(|| Duration::from_secs(secs));
+    }
+    if let Some(ms) = take_option(&mut args, "--input-grace") {
+        settings.input_grace = Duration::from_millis(flag_value("--input-grace", Some(&ms)));
+    }
+    let mut options = GameOptions::default();
+    if let Some(length) = take_option(&mut args, "--length") {
+        options.start_length = flag_value("--length", Some(&length));
+    }
+    if let Some(tps) = take_option(&mut args, "--tps") {
+        let tps: f64 = flag_value("--tps", Some(&tps));
+        if !(0.1..=1000.0).contains(&tps) {
+            bad_flag("--tps")
         }
-
-        if grew {
-            Ok(generate_food(cols, rows, &self.body))
-        } else {
-            Ok(food_position)
+        options.timer = (1000.0 / tps).round() as u64;
+    }
+    if let Some(spawn) = take_option(&mut args, "--spawn") {
+        options.spawn = flag_value("--spawn", Some(&spawn));
+    }
+    if let Some(mode) = take_option(&mut args, "--mode") {
+        options.mode = flag_value("--mode", Some(&mode));
+    }
+    if let Some(modifiers) = take_option(&mut args, "--modifiers") {
+        options.modifiers = flag_value("--modifiers", Some(&modifiers));
+    }
+    // Classic with this week's modifiers, in place of the mode and modifiers
+    // asked for
+    if take_flag(&mut args, "--weekly") {
+        let week = Week::at(now_secs());
+        options.mode = Mode::Classic;
+        options.modifiers = week.modifiers();
+        settings.weekly = Some(week);
+    }
+    // Replaces all of the above, the point is playing the very same game
+    let challenge = take_option(&mut args, "--challenge");
+    // Scores that are compared with somebody else's are played without help
+    if settings.weekly.is_some() || challenge.is_some() {
+        settings.danger = false;
+    }
+    settings.speed_keys = settings.weekly.is_none() && challenge.is_none() && !settings.race;
+    if let Some(code) = challenge {
+        let challenge: Challenge = flag_value("--challenge", Some(&code));
+        options = challenge.options();
+        settings.board = Some((challenge.cols, challenge.rows));
+    }
+    if let Some(path) = take_option(&mut args, "--level") {
+        // A save or a replay only has the mode's rules, not the level's
+        if settings.board.is_some() || settings.load || settings.record.is_some() {
+            eprintln!("--level doesn't go with --challenge, --load or --record.");
+            std::process::exit(2)
         }
+        let level = match std::fs::read_to_string(&path).map_err(|e| e.to_string()) {
+            Ok(text) => Level::parse(&text),
+            Err(e) => Err(e),
+        };
+        let level = level.unwrap_or_else(|e| {
+            eprintln!("Can't read level `{path}`: {e}");
+            std::process::exit(1)
+        });
+        settings.board = Some((level.cols, level.rows));
+        settings.level = Some(level);
+    }
+    if let Some(name) = take_option(&mut args, "--arena") {
+        if settings.board.is_some() || settings.load || settings.record.is_some() {
+            eprintln!("--arena doesn't go with --level, --challenge, --load or --record.");
+            std::process::exit(2)
+        }
+        let shape: Shape = flag_value("--arena", Some(&name));
+        let level = board_size(settings.margins)
+            .map_err(|e| e.to_string())
+            .and_then(|(cols, rows)| shape.level(cols, rows));
+        let level = level.unwrap_or_else(|e| {
+            eprintln!("Can't make the {name} arena: {e}");
+            std::process::exit(1)
+        });
+        settings.board = Some((level.cols, level.rows));
+        settings.level = Some(level);
+    }
+    // Everybody's week is played on the same terms
+    if settings.weekly.is_some() && (settings.board.is_some() || settings.load || settings.adaptive)
+    {
+        eprintln!("--weekly doesn't go with --challenge, --level, --arena, --load or --adaptive.");
+        std::process::exit(2)
     }
-}
 
-fn generate_food(cols: &u16, rows: &u16, snake_body: &Vec<SnakeBodyPoint>) -> Option<(u16, u16)> {
-    let mut available_positions = Vec::new();
+    // The bot's way round the board doesn't go round walls, and it would
+    // start from scratch against a game half played
+    if settings.race && (settings.level.is_some() || settings.load) {
+        eprintln!("--race doesn't go with --level, --arena or --load.");
+        std::process::exit(2)
+    }
 
-    for x in 0..*cols {
-        for y in 0..*rows {
-            if !snake_body.iter().any(|p| p.x == x && p.y == y) {
-                available_positions.push((x, y));
+    if take_flag(&mut args, "--io-protocol") {
+        return run_protocol(&mut args, &settings, &options);
+    }
+
+    let ui = take_option(&mut args, "--ui");
+    let levels_index = take_option(&mut args, "--levels-index");
+    match args.first().map(String::as_str) {
+        None => match ui.as_deref() {
+            None | Some("default") => play(&settings, &options),
+            Some("ratatui") => run_ratatui(&theme, &options),
+            Some(other) => {
+                eprintln!("Unknown frontend `{other}`.\n{USAGE}");
+                std::process::exit(2)
+            }
+        },
+        Some("bot") => match args.get(1) {
+            Some(path) => run_bot(path, &theme, &options),
+            None => {
+                eprintln!("{USAGE}");
+                std::process::exit(2)
             }
+        },
+        Some("arena") => run_arena(&args[1..], &options),
+        Some("simulate") => run_simulate(&args[1..], &options),
+        Some("bench-bots") => run_bench_bots(&args[1..], &options),
+        Some("twitch") => run_twitch(&args[1..], &theme, &options),
+        Some("server") => run_server(&args[1..], &options),
+        Some("connect") => run_connect(&args[1..], &theme),
+        Some("join") => run_join(&args[1..], &theme),
+        Some("replay") => run_replay(&args[1..], &theme),
+        Some("edit") => match args.get(1) {
+            Some(path) => snake2::editor::run(std::path::Path::new(path), &theme),
+            None => {
+                eprintln!("{USAGE}");
+                std::process::exit(2)
+            }
+        },
+        Some("levels") => run_levels(settings, &options, levels_index.as_deref()),
+        Some("profiles") => list_profiles(),
+        Some("calibrate") => run_calibrate(&settings),
+        Some(other) => {
+            eprintln!("Unknown command `{other}`.\n{USAGE}");
+            std::process::exit(2)
         }
     }
+}
+
+/// How the terminal frontend behaves, as opposed to the game rules.
+struct Settings {
+    theme: Theme,
+    // Pause by itself after this long without any key press, `None` never does
+    idle_pause: Option<Duration>,
+    // A turn this close to the next tick makes it happen right away
+    input_grace: Duration,
+    profile: Profile,
+    // Where `s` saves while paused, and what `--load` resumes
+    slot: u8,
+    load: bool,
+    // Speed and walls follow how the run is going, see `difficulty.rs`
+    adaptive: bool,
+    // Where the snake spent its time, shown after the game is over
+    heatmap: bool,
+    // The end of a run again at half speed before the game over screen
+    photo_finish: bool,
+    // The challenge code of a run goes on the clipboard when it's over
+    clipboard: bool,
+    // The hamiltonian bot plays the same board alongside (see `strategy.rs`)
+    race: bool,
+    // Start with its route drawn over the board, `c` turns it on and off
+    cycle: bool,
+    // Mark the cells next to the head that would kill the snake, up to
+    // `DANGER_UNTIL_LEVEL`
+    danger: bool,
+    // Show the turns pressed and how long they waited, see `hud.rs`
+    input_hud: bool,
+    // `+` and `-` change the speed, in games nobody compares scores with
+    speed_keys: bool,
+    // Where to write the run as JSON when it's over, `-` is stdout on exit
+    summary_json: Option<String>,
+    // Where to write the replay of the run when it's over
+    record: Option<String>,
+    // Play on a board this size instead of the whole terminal, for challenges
+    // and levels
+    board: Option<(u16, u16)>,
+    // New games are on this hand-made board, see `level.rs`
+    level: Option<Level>,
+    // Discord application id to show the game on Discord with
+    discord: Option<String>,
+    // Playing this week's mutator, its runs go on the week's leaderboard too
+    weekly: Option<Week>,
+    // Start with large cells this size, `z` turns them on and off
+    large_cells: Option<CellSize>,
+    // Left alone around the board and the status bar
+    margins: Margins,
+}
+
+const COUNTDOWN: Duration = Duration::from_secs(3);
+const LEVEL_BANNER: Duration = Duration::from_millis(1500);
+// The slowest `-` goes, half a tick a second
+const SLOWEST_TIMER: u64 = 2000;
+// From this level on there's no more danger highlighting, it's for learning
+const DANGER_UNTIL_LEVEL: u32 = 5;
+const CLOSE_CALL_FLASH: Duration = Duration::from_millis(300);
+// 60 frames a second, about as many as terminals keep up with
+const FRAME: Duration = Duration::from_micros(16_667);
+// The game in progress is autosaved every this many ticks
+const AUTOSAVE_EVERY: u64 = 50;
+// How much of a run the photo finish plays again, and how long it stays on
+// the end of it
+const PHOTO_FINISH: Duration = Duration::from_secs(5);
+const FINISH_HOLD: Duration = Duration::from_millis(1500);
+// Kept for it, the photo finish's worth of ticks at full speed
+const RECENT_TICKS: u64 = PHOTO_FINISH.as_millis() as u64 / FASTEST_TIMER;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Screen {
+    Start,
+    // The last run didn't end cleanly, its game can be picked up again
+    Resume,
+    // 3, 2, 1 before the snake starts moving, until the given instant
+    Countdown(Instant),
+    Playing,
+    Paused,
+}
 
-    if snake_body.is_empty() {
-        panic!("The game ended on perfect score");
+impl Screen {
+    fn countdown() -> Screen {
+        Screen::Countdown(Instant::now() + COUNTDOWN)
     }
 
-    let mut rng = rand::rng();
-    Some(available_positions[rng.random_range(0..available_positions.len())])
+    fn hints(self) -> &'static str {
+        match self {
+            Screen::Start => "arrows start  ? help  q quit",
+            Screen::Resume => "p resume  arrows new game  q quit",
+            Screen::Countdown(_) | Screen::Playing => "p pause  ? help  q quit",
+            Screen::Paused => "p resume  s save  ? help  q quit",
+        }
+    }
 }
 
-// TODO
-///*
-///  End screen, points,
-///  play again,
-///  Possible refactors,
-///  can I update just the body instead of cleaning all?
-///  */
+// The board the terminal has room for inside `margins`, under it goes the
+// status bar
+fn board_size(margins: Margins) -> io::Result<(u16, u16)> {
+    let (cols, rows) = terminal::size()?;
+    let (board_area, _) = Rect::screen(cols, rows).without(margins).split_bottom(1);
+    Ok((board_area.width, board_area.height))
+}
 
-fn main() -> io::Result<()> {
+fn play(settings: &Settings, options: &GameOptions) -> io::Result<()> {
     setup_panic_hook();
+    watch_job_control();
+    watch_hangup();
     // Note: Windows implementation of this stream does not support non-UTF-8 byte sequences
     let mut stdout = io::stdout();
     enable_game_mode(&mut stdout)?;
-
-    // Are we starting the game?
-    let start_text = "Press arrows to move, or (q, Ctrl+c) to quit.";
-    let mut arrow_press = false;
+    // Frames go out on a thread of their own, a slow terminal doesn't hold
+    // up the ticks
+    let mut out = Painter::new(stdout);
 
     let (cols, rows) = terminal::size()?;
-    let mut snake: Option<Snake> = None;
-    let mut food_position: Option<(u16, u16)> = None;
-    let mut timer = 500;
+    // Everything is drawn inside the margins
+    let margins = settings.margins;
+    let mut screen_area = Rect::screen(cols, rows).without(margins);
+    // The last row is for the status bar
+    let (board_area, _) = screen_area.split_bottom(1);
+    // The terminal a board that doesn't fit would need, `None` when it fits
+    let needs = |board_cols: u16, board_rows: u16| {
+        (board_cols > board_area.width || board_rows > board_area.height).then(|| {
+            format!(
+                "{}x{}",
+                board_cols + margins.left + margins.right,
+                board_rows + 1 + margins.top + margins.bottom
+            )
+        })
+    };
+    if let Some((board_cols, board_rows)) = settings.board
+        && let Some(size) = needs(board_cols, board_rows)
+    {
+        disable_game_mode(&mut out.stdout)?;
+        eprintln!("This board needs a terminal of at least {size}.");
+        std::process::exit(1)
+    }
+    // The size of a cell when they're large, see `zoom.rs`
+    let mut large = settings.large_cells;
+    // The hamiltonian bot's route drawn over the board
+    let mut cycle = settings.cycle;
 
-    // Game loop
-    loop {
-        // Clear the whole screen
-        stdout.execute(Clear(ClearType::All))?;
-
-        // Draw to the screen
-        if arrow_press == false {
-            stdout
-                .execute(MoveTo(0, 0))?
-                .execute(style::PrintStyledContent(start_text.magenta()))?;
-        } else if let Some(ref mut s) = snake {
-            // Print the snake
-            let new_food_pos: Option<(u16, u16)> =
-                s.print_body(&mut stdout, food_position, &cols, &rows)?;
-
-            if let Some(nfp) = new_food_pos {
-                if Some(nfp) != food_position && timer > 50 {
-                    timer -= 20;
-                }
-                food_position = Some(nfp);
-            } else {
-                food_position = None;
-            }
+    let mut screen = Screen::Start;
+    let mut game: Option<Game> = None;
+    let mut clock = Stopwatch::new();
+    let mut toasts = Toasts::new();
+    // "LEVEL n" in the middle of the board until then
+    let mut banner: Option<(u32, Instant)> = None;
+    // Beating it shows a toast, once, the week's best in a weekly game
+    let weekly_best = |week| {
+        let scores = settings.profile.weekly_scores(week).ok()?;
+        scores.value.iter().map(|r| r.score).max()
+    };
+    let mut best_to_beat = match settings.weekly {
+        Some(week) => weekly_best(week),
+        None => settings.profile.stats().ok().map(|s| s.value.best_score),
+    }
+    .filter(|&best| best > 0);
+    if let Some(week) = settings.weekly {
+        toasts.push(format!("Week {week}: {}", week.modifiers()));
+    }
+    // The head flashes until then after a close call
+    let mut flash_until: Option<Instant> = None;
+    let mut show_help = false;
+    let mut checkpoints = Checkpoints::new(RETRIES);
+    let mut adaptive = settings.adaptive.then(Adaptive::new);
 
-            // Print the food
-            if let Some(f) = food_position {
-                stdout
-                    .execute(MoveTo(f.0, f.1))?
-                    .execute(style::PrintStyledContent("o".red()))?;
-            } else {
-                food_position = generate_food(&cols, &rows, &s.body);
+    if settings.load {
+        let loaded = match settings.profile.load_game(settings.slot) {
+            Ok(loaded) => loaded,
+            Err(e) => {
+                disable_game_mode(&mut out.stdout)?;
+                eprintln!("Can't load slot {}: {e}", settings.slot);
+                std::process::exit(1)
+            }
+        };
+        if let Some(size) = needs(loaded.cols, loaded.rows) {
+            disable_game_mode(&mut out.stdout)?;
+            eprintln!("This saved game needs a terminal of at least {size}.");
+            std::process::exit(1)
+        }
+        game = Some(loaded);
+        screen = Screen::countdown();
+    } else if settings.board.is_none()
+        && let Some(interrupted) = settings.profile.interrupted_game()
+    {
+        // Kept for a terminal it fits in
+        match needs(interrupted.cols, interrupted.rows) {
+            Some(size) => toasts.push(format!("The interrupted game needs a {size} terminal")),
+            None => {
+                game = Some(interrupted);
+                screen = Screen::Resume;
             }
         }
-        stdout.flush()?;
+    }
+    // Only with `--record`, from the start of the game or from where it was loaded
+    let mut replay = game
+        .as_ref()
+        .filter(|_| settings.record.is_some())
+        .map(Replay::new);
+    // The walls adaptive play adds and a level's rules aren't in a replay,
+    // those would play back differently
+    let photo_finish = settings.photo_finish && !settings.adaptive && settings.level.is_none();
+    let new_recent = |g: &Game| photo_finish.then(|| Recent::new(g, RECENT_TICKS));
+    let mut recent = game.as_ref().and_then(new_recent);
+    // What to play again once the game is over
+    let mut finish: Option<Replay> = None;
+    let mut next_tick = Instant::now();
+    let mut next_frame = Instant::now();
+    // Where the tick brought forward by a turn was due, the ones after it
+    // keep to that beat
+    let mut pulled_tick: Option<Instant> = None;
+    let mut summary: Option<String> = None;
+    // For `--summary-json`
+    let mut meals: Vec<Meal> = Vec::new();
+    let mut turns: u64 = 0;
+    let mut summary_json: Option<String> = None;
+    // The run's challenge code, copied on the way out
+    let mut code: Option<String> = None;
+    // The bot's game with `--race`, it ticks along with the player's
+    let mut rival: Option<Game> = None;
+    let mut hud = settings.input_hud.then(InputHud::new);
+    let mut last_input = Instant::now();
+    let mut idle_paused = false;
+    #[cfg(feature = "discord")]
+    let mut presence = settings.discord.clone().map(Presence::start);
+
+    // Nothing happened since the last frame and nothing moves on this screen
+    let mut idle = false;
+
+    // Game loop, in a closure so an error from a terminal that went away is
+    // caught below with the game still there to save
+    let played = (|| -> io::Result<()> {
+        loop {
+            check_hangup()?;
+
+            // Continued after being stopped from outside, the shell has had the terminal
+            if take_resumed(&mut out.stdout)? {
+                idle = false;
+                if matches!(screen, Screen::Playing | Screen::Countdown(_)) {
+                    screen = Screen::Paused;
+                    clock.stop();
+                }
+            }
+
+            let now = Instant::now();
 
-        // Handle input
-        if event::poll(Duration::from_millis(timer))? {
-            if let Event::Key(KeyEvent {
-                code, modifiers, ..
-            }) = event::read()?
+            // Nobody's there, don't let the snake run into itself on its own
+            if let Some(idle_pause) = settings.idle_pause
+                && matches!(screen, Screen::Playing | Screen::Countdown(_))
+                && now.duration_since(last_input) >= idle_pause
             {
-                match code {
-                    KeyCode::Char('q') => break,
-                    KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => break,
-                    KeyCode::Left => {
-                        if arrow_press == false {
-                            arrow_press = true;
-                            snake = Some(Snake::new(&cols, &rows, SnakeDirection::Left));
+                screen = Screen::Paused;
+                idle_paused = true;
+                clock.stop();
+            }
+
+            // Move things along
+            match screen {
+                Screen::Countdown(ends) if now >= ends => {
+                    screen = Screen::Playing;
+                    next_tick = now;
+                    pulled_tick = None;
+                }
+                Screen::Playing if now >= next_tick => {
+                    if let Some(ref mut g) = game {
+                        clock.start();
+                        let outcome = g.tick();
+                        let died = outcome == TickOutcome::Died;
+                        if let Some(ref mut hud) = hud {
+                            hud.ticked(g, now);
+                        }
+                        // Neck and neck, the tick that kills the player doesn't count
+                        if !died && let Some(ref mut r) = rival {
+                            race_tick(r);
                         }
-                        if let Some(ref mut s) = snake {
-                            if !matches!(s.direction, SnakeDirection::Right) {
-                                s.direction = SnakeDirection::Left;
+                        if died && let Some(snapshot) = checkpoints.resume() {
+                            *g = snapshot;
+                            // What led to the checkpoint is gone, the replay starts from it
+                            if let Some(ref mut replay) = replay {
+                                *replay = Replay::new(g);
                             }
+                            recent = new_recent(g);
+                            if let Some(ref mut adaptive) = adaptive {
+                                adaptive.died(g);
+                            }
+                            clock.stop();
+                            toasts.push(format!(
+                                "Back to the checkpoint, {} left",
+                                checkpoints.retries_left
+                            ));
+                            screen = Screen::countdown();
+                        } else if died {
+                            clock.stop();
+                            finish = recent.as_ref().map(|recent| recent.replay(g));
+                            let cause = g.death_cause().unwrap_or(DeathCause::Yourself);
+                            let run = RunRecord {
+                                score: g.score,
+                                length: g.snake.body.len(),
+                                seconds: clock.elapsed().as_secs(),
+                                mode: g.mode.name().to_string(),
+                                when: now_secs(),
+                                modifiers: g.modifiers.to_string(),
+                                custom_speed: g.custom_speed,
+                                assisted: settings.danger,
+                            };
+                            let (earned, recorded) = match settings.profile.record_run(&run, cause)
+                            {
+                                Ok(earned) => (earned, Ok(())),
+                                Err(e) => (Vec::new(), Err(e)),
+                            };
+                            let recorded = recorded.and_then(|()| match settings.level {
+                                Some(ref level) => {
+                                    settings.profile.record_level_score(&level.name, g.score)
+                                }
+                                None => Ok(()),
+                            });
+                            // Not a game from before that was resumed
+                            let weekly = settings.weekly.filter(|week| {
+                                g.mode == Mode::Classic && g.modifiers == week.modifiers()
+                            });
+                            let week_best = weekly.and_then(weekly_best);
+                            let recorded = recorded.and_then(|()| match weekly {
+                                Some(week) => settings.profile.record_weekly(week, &run),
+                                None => Ok(()),
+                            });
+                            let mut text = format!(
+                                "Game Over! You {}.\n\tScore {}, length {}, time {}.",
+                                cause.describe(),
+                                g.score,
+                                g.snake.body.len(),
+                                format_duration(clock.elapsed())
+                            );
+                            if g.custom_speed {
+                                text.push_str(" At a custom speed.");
+                            }
+                            if settings.danger {
+                                text.push_str(" With --danger.");
+                            }
+                            for achievement in earned {
+                                text.push_str(&format!(
+                                    "\n\tAchievement: {}!",
+                                    achievement.describe()
+                                ));
+                            }
+                            // Loaded, back from a checkpoint or adaptive, it isn't what the seed plays
+                            if !settings.load
+                                && !settings.adaptive
+                                && settings.level.is_none()
+                                && checkpoints.retries_left == RETRIES
+                            {
+                                let challenge = Challenge::new(g.cols, g.rows, g.seed, options);
+                                text.push_str(&format!(
+                                    "\n\tChallenge a friend: snake2 --challenge {}",
+                                    challenge.code()
+                                ));
+                                code = Some(challenge.code());
+                            }
+                            match (weekly, week_best) {
+                                (Some(week), Some(best)) if best >= g.score => text
+                                    .push_str(&format!("\n\tThe best of week {week} is {best}.")),
+                                (Some(week), _) => {
+                                    text.push_str(&format!("\n\tThe best of week {week}!"))
+                                }
+                                _ => {}
+                            }
+                            if let Some(ref r) = rival {
+                                text.push_str(&match g.score.cmp(&r.score) {
+                                    Ordering::Greater => format!(
+                                        "\n\tYou beat the hamiltonian bot, {} to {}!",
+                                        g.score, r.score
+                                    ),
+                                    Ordering::Equal => format!(
+                                        "\n\tA draw with the hamiltonian bot, {} all.",
+                                        g.score
+                                    ),
+                                    Ordering::Less => format!(
+                                        "\n\tThe hamiltonian bot won the race, {} to {}.",
+                                        r.score, g.score
+                                    ),
+                                });
+                            }
+                            if let Err(e) = recorded {
+                                text.push_str(&format!("\n\tThe score wasn't kept: {e}"));
+                            }
+                            if let Some(ref path) = settings.summary_json {
+                                let json = RunSummary {
+                                    score: g.score,
+                                    length: g.snake.body.len(),
+                                    seconds: clock.elapsed().as_secs(),
+                                    ticks: g.ticks,
+                                    seed: g.seed,
+                                    mode: g.mode.name().to_string(),
+                                    modifiers: g.modifiers.to_string(),
+                                    inputs: turns,
+                                    food: std::mem::take(&mut meals),
+                                }
+                                .to_json();
+                                if path == "-" {
+                                    summary_json = Some(json);
+                                } else if let Err(e) = std::fs::write(path, json + "\n") {
+                                    text.push_str(&format!("\n\tThe summary wasn't written: {e}"));
+                                }
+                            }
+                            if let (Some(path), Some(replay)) = (&settings.record, &mut replay) {
+                                replay.finish(g, true);
+                                if let Err(e) = std::fs::write(path, replay.to_bytes()) {
+                                    text.push_str(&format!("\n\tThe replay wasn't written: {e}"));
+                                }
+                            }
+                            summary = Some(text);
+                            break;
                         }
-                    }
-                    KeyCode::Right => {
-                        if arrow_press == false {
-                            arrow_press = true;
-                            snake = Some(Snake::new(&cols, &rows, SnakeDirection::Right));
+                        if let Some(ref mut recent) = recent {
+                            recent.ticked(g);
                         }
-                        if let Some(ref mut s) = snake {
-                            if !matches!(s.direction, SnakeDirection::Left) {
-                                s.direction = SnakeDirection::Right;
+                        // Kept in case this run doesn't get to end cleanly
+                        if g.ticks % AUTOSAVE_EVERY == 0
+                            && settings.level.is_none()
+                            && let Err(e) = settings.profile.autosave(g)
+                        {
+                            toasts.push(format!("Couldn't autosave: {e}"));
+                        }
+                        let nudge = adaptive
+                            .as_mut()
+                            .and_then(|a| a.update(g, outcome == TickOutcome::Ate));
+                        match nudge {
+                            Some(Nudge::Harder) => toasts.push("Picking up the pace"),
+                            Some(Nudge::Easier) => toasts.push("Easing off"),
+                            None => {}
+                        }
+                        for event in &g.events {
+                            match event {
+                                GameEvent::FoodEaten { score, .. } => meals.push(Meal {
+                                    tick: g.ticks,
+                                    score: *score,
+                                }),
+                                GameEvent::LevelUp { level } => {
+                                    banner = Some((*level, now + LEVEL_BANNER));
+                                }
+                                GameEvent::BossAppeared => {
+                                    toasts.push("A boss! Eat 1, 2, 3 in order")
+                                }
+                                GameEvent::BossDefeated => toasts.push("Boss beaten!"),
+                                GameEvent::ChainAppeared => {
+                                    toasts.push("A food chain! Eat 1, 2, 3 in order")
+                                }
+                                GameEvent::ChainCompleted { bonus } => {
+                                    toasts.push(format!("Chain done! +{bonus}"))
+                                }
+                                GameEvent::ChainBroken => toasts.push("Chain broken"),
+                                GameEvent::ZoneOpened => {
+                                    toasts.push("A hill zone! Keep your head in it for points")
+                                }
+                                GameEvent::WordSpelled { bonus } => {
+                                    toasts.push(format!("Spelled it! +{bonus}"))
+                                }
+                                GameEvent::CheckpointReached => {
+                                    checkpoints.record(g);
+                                    toasts.push("Checkpoint");
+                                }
+                                GameEvent::Split { heads } => {
+                                    toasts.push(format!("Split! {heads} heads"))
+                                }
+                                GameEvent::CloseCall { .. } => {
+                                    flash_until = Some(now + CLOSE_CALL_FLASH);
+                                    toasts.push(format!("Close call! +{CLOSE_CALL_BONUS}"));
+                                }
+                                _ => {}
                             }
                         }
+                        if let Some(best) = best_to_beat
+                            && g.score > best
+                        {
+                            toasts.push("New high score!");
+                            best_to_beat = None;
+                        }
+                        // Scheduled from the previous tick, so input doesn't make it drift
+                        let due = pulled_tick.take().unwrap_or(next_tick);
+                        next_tick = (due + Duration::from_millis(g.timer)).max(now);
+                    }
+                }
+                _ => {}
+            }
+
+            // Coalesce redraws, a terminal can only show so many frames a second
+            let drawing = now >= next_frame && !idle;
+            if drawing {
+                // Clear the whole screen
+                out.clear()?;
+
+                // Draw to the screen
+                let theme = &settings.theme.at_time(clock.elapsed());
+                let (board_area, status_area) = screen_area.split_bottom(1);
+                match screen {
+                    Screen::Start => {
+                        out.print(
+                            board_area.x,
+                            board_area.y,
+                            "Press arrows to move, or (q, Ctrl+c) to quit.",
+                            theme.text(),
+                        )?;
+                    }
+                    Screen::Resume => {
+                        if let Some(ref g) = game {
+                            let overlays = Overlays {
+                                cycle,
+                                ..Overlays::default()
+                            };
+                            draw_board(&mut out, g, board_area, theme, large, overlays)?;
+                        }
+                        let lines = ["p carries on, arrows start a new game".to_string()];
+                        draw_overlay(
+                            &mut out,
+                            "Resume interrupted game?",
+                            &lines,
+                            board_area,
+                            theme,
+                        )?;
                     }
-                    KeyCode::Up => {
-                        if arrow_press == false {
-                            arrow_press = true;
-                            snake = Some(Snake::new(&cols, &rows, SnakeDirection::Up));
+                    Screen::Countdown(ends) => {
+                        if let Some(ref g) = game {
+                            let overlays = Overlays {
+                                cycle,
+                                ..Overlays::default()
+                            };
+                            draw_board(&mut out, g, board_area, theme, large, overlays)?;
                         }
-                        if let Some(ref mut s) = snake {
-                            if !matches!(s.direction, SnakeDirection::Down) {
-                                s.direction = SnakeDirection::Up;
-                            }
+                        let seconds_left = ends
+                            .saturating_duration_since(now)
+                            .as_millis()
+                            .div_ceil(1000);
+                        let spot = board_area.centered(1, 1);
+                        out.print(
+                            spot.x,
+                            spot.y.saturating_sub(2),
+                            &seconds_left.to_string(),
+                            theme.text(),
+                        )?;
+                    }
+                    Screen::Playing | Screen::Paused => {
+                        if let Some(ref g) = game {
+                            let overlays = Overlays {
+                                cycle,
+                                danger: settings.danger && g.level < DANGER_UNTIL_LEVEL,
+                                flash: flash_until.is_some_and(|until| now < until),
+                            };
+                            draw_board(&mut out, g, board_area, theme, large, overlays)?;
+                        }
+                        if screen == Screen::Paused {
+                            let text = if idle_paused {
+                                "Paused due to inactivity."
+                            } else {
+                                "Paused."
+                            };
+                            out.print(board_area.x, board_area.y, text, theme.text())?;
+                        }
+                    }
+                }
+
+                #[cfg(feature = "discord")]
+                if let (Some(presence), Some(g)) = (&mut presence, &game) {
+                    presence.update(Activity {
+                        mode: g.mode.name().to_string(),
+                        score: g.score,
+                        length: g.snake.body.len(),
+                        paused: screen == Screen::Paused,
+                        // So Discord's timer matches the stopwatch, pauses left out
+                        started: now_secs().saturating_sub(clock.elapsed().as_secs()),
+                    });
+                }
+
+                toasts.update(now);
+                toasts.draw(&mut out, board_area, theme)?;
+                if let (Some(hud), Some(g)) = (&hud, &game) {
+                    hud.draw(&mut out, g, board_area, theme)?;
+                }
+
+                if let Some((level, until)) = banner {
+                    if now < until {
+                        let title = format!("LEVEL {level}");
+                        draw_overlay(&mut out, &title, &[], board_area, theme)?;
+                    } else {
+                        banner = None;
+                    }
+                }
+
+                if show_help {
+                    let lines = help_lines(game.as_ref(), options, theme);
+                    draw_overlay(&mut out, "Help", &lines, board_area, theme)?;
+                }
+
+                let status = StatusBar {
+                    mode: options.mode.name(),
+                    hints: screen.hints(),
+                    elapsed: game.as_ref().map(|_| clock.elapsed()),
+                    rival: rival.as_ref().map(|r| r.score),
+                };
+                status.draw(&mut out, game.as_ref(), status_area, theme)?;
+                out.flush()?;
+                next_frame = now + FRAME;
+            }
+
+            let timeout = match screen {
+                Screen::Playing => {
+                    let tick_in = next_tick.saturating_duration_since(now);
+                    match settings.idle_pause {
+                        Some(idle_pause) => {
+                            tick_in.min((last_input + idle_pause).saturating_duration_since(now))
                         }
+                        None => tick_in,
+                    }
+                }
+                // Wake up right when the number changes
+                Screen::Countdown(ends) => {
+                    let remaining = ends.saturating_duration_since(now);
+                    match remaining.as_millis() % 1000 {
+                        0 => remaining.min(Duration::from_secs(1)),
+                        ms => Duration::from_millis(ms as u64),
                     }
-                    KeyCode::Down => {
-                        if arrow_press == false {
-                            arrow_press = true;
-                            snake = Some(Snake::new(&cols, &rows, SnakeDirection::Down));
+                }
+                Screen::Start | Screen::Resume | Screen::Paused => IDLE_WAIT,
+            };
+            // Back soon to draw what was left out
+            let frame = (!drawing && !idle).then_some(next_frame);
+            let wake_up = toasts
+                .next_expiry()
+                .into_iter()
+                .chain(banner.map(|b| b.1))
+                .chain(frame);
+            let timeout = wake_up.fold(timeout, |timeout, at| {
+                timeout.min(at.saturating_duration_since(now))
+            });
+
+            // Handle input
+            let input = next_input(timeout)?;
+            idle = input.is_none()
+                && matches!(screen, Screen::Start | Screen::Resume | Screen::Paused)
+                && timeout == IDLE_WAIT;
+            if input.is_some_and(|i| !matches!(i, Input::Resize(..))) {
+                last_input = Instant::now();
+            }
+            match input {
+                Some(Input::Quit) => break,
+                Some(Input::Resize(cols, rows)) => {
+                    screen_area = Rect::screen(cols, rows).without(margins)
+                }
+                Some(Input::Suspend) => {
+                    if matches!(screen, Screen::Playing | Screen::Countdown(_)) {
+                        screen = Screen::Paused;
+                        clock.stop();
+                    }
+                    out.sync()?;
+                    suspend(&mut out.stdout)?;
+                }
+                Some(Input::Help) => {
+                    // Nobody reads with the snake running
+                    if matches!(screen, Screen::Playing | Screen::Countdown(_)) {
+                        screen = Screen::Paused;
+                        clock.stop();
+                    }
+                    show_help = !show_help;
+                }
+                Some(Input::Pause) => {
+                    show_help = false;
+                    screen = match screen {
+                        Screen::Playing | Screen::Countdown(_) => Screen::Paused,
+                        Screen::Paused | Screen::Resume => Screen::countdown(),
+                        Screen::Start => Screen::Start,
+                    };
+                    idle_paused = false;
+                    clock.stop();
+                }
+                Some(Input::Save) => {
+                    if screen == Screen::Paused && settings.level.is_some() {
+                        toasts.push("Games on a level can't be saved");
+                    } else if screen == Screen::Paused
+                        && let Some(ref g) = game
+                    {
+                        toasts.push(match settings.profile.save_game(settings.slot, g) {
+                            Ok(()) => format!("Saved to slot {}", settings.slot),
+                            Err(e) => format!("Couldn't save: {e}"),
+                        });
+                    }
+                }
+                Some(Input::Turn(direction)) => match screen {
+                    Screen::Start | Screen::Resume => {
+                        show_help = false;
+                        // A new game instead of the interrupted one, games on
+                        // a level aren't autosaved and leave it alone
+                        if settings.level.is_none() {
+                            let _ = settings.profile.clear_autosave();
+                        }
+                        let g = match settings.level {
+                            Some(ref level) => level.start(direction, options),
+                            None => {
+                                // As big as the terminal is now, in large
+                                // cells if they're on, or the challenge's size
+                                let (board_area, _) = screen_area.split_bottom(1);
+                                let cells =
+                                    large.map_or(board_area, |size| size.cells_in(board_area));
+                                let (cols, rows) = settings
+                                    .board
+                                    .unwrap_or((cells.width.max(2), cells.height.max(2)));
+                                Game::with_options(cols, rows, direction, options)
+                            }
+                        };
+                        if settings.record.is_some() {
+                            replay = Some(Replay::new(&g));
                         }
-                        if let Some(ref mut s) = snake {
-                            if !matches!(s.direction, SnakeDirection::Up) {
-                                s.direction = SnakeDirection::Down;
+                        // On the same board with the same food to start with
+                        rival = settings.race.then(|| {
+                            let options = GameOptions {
+                                seed: Some(g.seed),
+                                ..*options
+                            };
+                            Game::with_options(g.cols, g.rows, direction, &options)
+                        });
+                        recent = new_recent(&g);
+                        hud = settings.input_hud.then(InputHud::new);
+                        game = Some(g);
+                        screen = Screen::countdown();
+                    }
+                    // Turns during the countdown wait in the same queue, the
+                    // first tick makes them
+                    Screen::Playing | Screen::Countdown(_) => {
+                        if let Some(ref mut g) = game {
+                            let queued = g.queued_turns.len();
+                            if let Some(ref mut replay) = replay {
+                                replay.turn(g, direction);
+                            }
+                            if let Some(ref mut recent) = recent {
+                                recent.turn(g, direction);
                             }
+                            g.turn(direction);
+                            turns += 1;
+                            let now = Instant::now();
+                            if let Some(ref mut hud) = hud {
+                                hud.turned(g, direction, queued, now);
+                            }
+                            // Nearly time anyway, no need to wait for it
+                            if screen == Screen::Playing
+                                && queued == 0
+                                && !g.queued_turns.is_empty()
+                                && next_tick.saturating_duration_since(now) <= settings.input_grace
+                                && pulled_tick.is_none()
+                            {
+                                pulled_tick = Some(next_tick);
+                                next_tick = now;
+                            }
+                        }
+                    }
+                    Screen::Paused => {}
+                },
+                Some(Input::Zoom) => {
+                    large = match large {
+                        Some(_) => None,
+                        None => Some(settings.large_cells.unwrap_or(CellSize::Big)),
+                    };
+                    // A game that's on keeps its board, it only gets bigger
+                    // if there's room
+                    let (board_area, _) = screen_area.split_bottom(1);
+                    if let (Some(size), Some(g)) = (large, &game)
+                        && !size.fits(g.cols, g.rows, board_area)
+                    {
+                        toasts.push("Large cells from the next game on, this board doesn't fit");
+                    }
+                }
+                Some(input @ (Input::Faster | Input::Slower)) => match game {
+                    // Not in levels either, their bests are kept as they are
+                    Some(ref mut g) if settings.speed_keys && settings.level.is_none() => {
+                        // Only ever the way it was asked to go, even when
+                        // `--tps` started it past the bounds
+                        g.timer = if input == Input::Faster {
+                            (g.timer * 10 / 11).max(FASTEST_TIMER).min(g.timer)
+                        } else {
+                            (g.timer * 11).div_ceil(10).min(SLOWEST_TIMER).max(g.timer)
+                        };
+                        if !g.custom_speed {
+                            toasts.push("Custom speed, the high scores will say so");
                         }
+                        g.custom_speed = true;
                     }
-                    _ => {}
+                    Some(_) => toasts.push("The speed stays as it is in this game"),
+                    None => {}
+                },
+                Some(Input::Cycle) => {
+                    cycle = !cycle;
+                    if cycle
+                        && let Some(ref g) = game
+                        && hamiltonian_cycle(g.cols, g.rows).is_none()
+                    {
+                        toasts
+                            .push("No safe route on this board, its rows and columns are both odd");
+                    }
+                }
+                // Only for playing on a server
+                Some(Input::Chat) | None => {}
+            }
+        }
+
+        if let Some(ref replay) = finish {
+            show_photo_finish(&mut out, replay, margins, &settings.theme)?;
+        }
+        if settings.heatmap
+            && summary.is_some()
+            && let Some(ref g) = game
+        {
+            show_heatmap(&mut out, g, margins, &settings.theme)?;
+        }
+        Ok(())
+    })();
+    if let Err(e) = played {
+        // A finished game has nothing left to keep
+        let unfinished = game.as_ref().filter(|_| summary.is_none());
+        terminal_lost(&mut out.stdout, e, unfinished, &settings.profile);
+    }
+    // Ended cleanly, unless the interrupted game was neither picked up nor
+    // replaced
+    if screen != Screen::Resume && settings.level.is_none() {
+        let _ = settings.profile.clear_autosave();
+    }
+
+    out.sync()?;
+    disable_game_mode(&mut out.stdout)?;
+    // Just the JSON on stdout, for whatever reads it
+    match (summary_json, summary) {
+        (Some(json), _) => println!("{json}"),
+        (None, Some(summary)) => {
+            println!("\t{summary}\n");
+            if let Some(code) = code.filter(|_| settings.clipboard) {
+                copy_to_clipboard(&mut out.stdout, &code)?;
+            }
+        }
+        (None, None) => {}
+    }
+    Ok(())
+}
+
+/// The terminal is gone (an SSH connection dropped, the pipe closed): give
+/// back what can be of it, save an unfinished game and say so on stderr.
+fn terminal_lost(
+    stdout: &mut io::Stdout,
+    error: io::Error,
+    game: Option<&Game>,
+    profile: &Profile,
+) -> ! {
+    use std::io::Write;
+
+    restore_terminal(stdout);
+    let mut message = match error.kind() {
+        io::ErrorKind::BrokenPipe => "The terminal went away".to_string(),
+        _ => format!("The terminal stopped working ({error})"),
+    };
+    match game.map(|g| profile.autosave(g)) {
+        Some(Ok(())) => {
+            let command = match profile.name.as_str() {
+                DEFAULT_PROFILE => "snake2".to_string(),
+                name => format!("snake2 --profile {name}"),
+            };
+            message.push_str(&format!(
+                ", the game was saved. `{command}` offers to carry on with it."
+            ));
+        }
+        Some(Err(e)) => message.push_str(&format!(" and the game couldn't be saved: {e}")),
+        None => message.push('.'),
+    }
+    // Stderr may be the same terminal, nothing to do if it's gone too
+    let _ = writeln!(io::stderr(), "{message}");
+    std::process::exit(1)
+}
+
+/// The last `PHOTO_FINISH` of a run played again at half speed, with the
+/// cell the snake died on marked, until it's over or a key is pressed.
+fn show_photo_finish(
+    out: &mut impl Backend,
+    replay: &Replay,
+    margins: Margins,
+    theme: &Theme,
+) -> io::Result<()> {
+    let Ok(end) = replay.play() else {
+        return Ok(());
+    };
+    let fatal = end.events.iter().find_map(|event| match event {
+        GameEvent::Died { x, y, .. } => Some((*x, *y)),
+        _ => None,
+    });
+    let Some((x, y)) = fatal else {
+        return Ok(());
+    };
+    let shown = (PHOTO_FINISH.as_millis() as u64 / end.timer.max(1)).max(1);
+    let mut playback = Playback::new(replay).expect("it played through just now");
+    while playback.game.ticks + shown < replay.end && playback.step().is_some() {}
+    let (cols, rows) = terminal::size()?;
+    let mut screen_area = Rect::screen(cols, rows).without(margins);
+    let mut over = false;
+    loop {
+        out.clear()?;
+        let (board_area, status_area) = screen_area.split_bottom(1);
+        let board = &mut Offset {
+            out: &mut *out,
+            x: board_area.x,
+            y: board_area.y,
+        };
+        draw_game(board, &playback.game, theme)?;
+        draw_board_edge(board, &playback.game, board_area.at_origin(), theme)?;
+        board.print(x, y, "X", theme.fatal())?;
+        let status = StatusBar {
+            mode: "photo finish",
+            hints: "any key skips",
+            elapsed: None,
+            rival: None,
+        };
+        status.draw(out, Some(&playback.game), status_area, theme)?;
+        out.flush()?;
+        let wait = if over {
+            FINISH_HOLD
+        } else {
+            Duration::from_millis(playback.game.timer * 2)
+        };
+        match next_input(wait)? {
+            Some(Input::Resize(cols, rows)) => {
+                screen_area = Rect::screen(cols, rows).without(margins)
+            }
+            Some(_) => return Ok(()),
+            None if over => return Ok(()),
+            None => over = playback.step().is_none_or(|o| o == TickOutcome::Died),
+        }
+    }
+}
+
+/// The heatmap of a finished game, until q (or any other key it knows).
+fn show_heatmap(
+    out: &mut impl Backend,
+    game: &Game,
+    margins: Margins,
+    theme: &Theme,
+) -> io::Result<()> {
+    let (cols, rows) = terminal::size()?;
+    let mut screen_area = Rect::screen(cols, rows).without(margins);
+    loop {
+        out.clear()?;
+        let (board_area, status_area) = screen_area.split_bottom(1);
+        let board = &mut Offset {
+            out: &mut *out,
+            x: board_area.x,
+            y: board_area.y,
+        };
+        draw_heatmap(board, game, theme)?;
+        let status = StatusBar {
+            mode: "heatmap",
+            hints: "q to leave",
+            elapsed: None,
+            rival: None,
+        };
+        status.draw(out, None, status_area, theme)?;
+        out.flush()?;
+        // Nothing moves, only a resize needs drawing again
+        loop {
+            match next_input(IDLE_WAIT)? {
+                Some(Input::Resize(cols, rows)) => {
+                    screen_area = Rect::screen(cols, rows).without(margins);
+                    break;
                 }
+                Some(_) => return Ok(()),
+                None => {}
             }
         }
     }
+}
+
+/// No screen, a program on stdin and stdout plays, see `protocol.rs`.
+fn run_protocol(
+    args: &mut Vec<String>,
+    settings: &Settings,
+    options: &GameOptions,
+) -> io::Result<()> {
+    use snake2::snake::SnakeDirection;
 
-    disable_game_mode(&mut stdout)
+    let size =
+        take_option(args, "--size").map(|v| parse_size(&v).unwrap_or_else(|| bad_flag("--size")));
+    // A challenge's or a level's board wins, it's the same game or nothing
+    let (cols, rows) = settings.board.or(size).unwrap_or((40, 20));
+    let mut game = match settings.level {
+        Some(ref level) => level.start(SnakeDirection::Right, options),
+        None => Game::with_options(cols, rows, SnakeDirection::Right, options),
+    };
+    snake2::protocol::run(&mut game, io::stdin().lock(), io::stdout().lock())
 }
 
-// -- Enable and disable terminal functionalities for the game to work
+/// One tick of the bot's game in a race, unless it's over already.
+fn race_tick(rival: &mut Game) {
+    if rival.death_cause().is_some() || rival.events.contains(&GameEvent::BoardFull) {
+        return;
+    }
+    if let Some(direction) = Strategy::Hamiltonian.next_move(rival) {
+        rival.turn(direction);
+    }
+    rival.tick();
+}
+
+// What's drawn over the game on the board
+#[derive(Clone, Copy, Default)]
+struct Overlays {
+    // The hamiltonian bot's route, see `c`
+    cycle: bool,
+    // The cells that would kill the snake next tick, see `--danger`
+    danger: bool,
+    // The head lit up after a close call
+    flash: bool,
+}
 
-fn enable_game_mode(stdout: &mut std::io::Stdout) -> io::Result<()> {
-    // Prevents input to be forwaded to the screen but also disables Ctrl+C
-    terminal::enable_raw_mode()?;
-    // Hide the cursor
-    stdout.execute(Hide)?;
+/// The game in `area` with `overlays` over it, in `large` cells if they're
+/// on and the board fits that way.
+fn draw_board(
+    out: &mut impl Backend,
+    game: &Game,
+    area: Rect,
+    theme: &Theme,
+    large: Option<CellSize>,
+    overlays: Overlays,
+) -> io::Result<()> {
+    // The renderer draws from the top-left corner, the margins move it
+    let out = &mut Offset {
+        out,
+        x: area.x,
+        y: area.y,
+    };
+    let area = area.at_origin();
+    match large.filter(|size| size.fits(game.cols, game.rows, area)) {
+        Some(size) => draw_cells(
+            &mut Zoom { out, size },
+            game,
+            size.cells_in(area),
+            &Theme {
+                wide_cells: true,
+                ..*theme
+            },
+            overlays,
+        ),
+        None => draw_cells(out, game, area, theme, overlays),
+    }
+}
+
+fn draw_cells(
+    out: &mut impl Backend,
+    game: &Game,
+    area: Rect,
+    theme: &Theme,
+    overlays: Overlays,
+) -> io::Result<()> {
+    draw_game(out, game, theme)?;
+    if overlays.cycle {
+        draw_cycle(out, game, theme)?;
+    }
+    draw_board_edge(out, game, area, theme)?;
+    if overlays.danger {
+        draw_danger(out, game, theme)?;
+    }
+    if overlays.flash {
+        draw_head_flash(out, game, theme)?;
+    }
+    Ok(())
+}
+
+/// Where a board smaller than the terminal ends, the snake wraps around there.
+fn draw_board_edge(
+    out: &mut impl Backend,
+    game: &Game,
+    area: Rect,
+    theme: &Theme,
+) -> io::Result<()> {
+    if game.cols < area.width {
+        for y in 0..game.rows.min(area.height) {
+            out.print(
+                area.x + game.cols,
+                area.y + y,
+                theme.edge_glyph(),
+                theme.edge(),
+            )?;
+        }
+    }
+    if game.rows < area.height {
+        let width = game.cols.min(area.width) as usize;
+        out.print(
+            area.x,
+            area.y + game.rows,
+            &theme.edge_glyph().repeat(width),
+            theme.edge(),
+        )?;
+    }
+    Ok(())
+}
+
+/// Watch a replay, binary or notation, or with `--notation` or `--binary`
+/// write it out in that format instead.
+fn run_replay(args: &[String], theme: &Theme) -> io::Result<()> {
+    let mut args = args.to_vec();
+    let notation = take_option(&mut args, "--notation");
+    let binary = take_option(&mut args, "--binary");
+    let [path] = args.as_slice() else {
+        eprintln!("{USAGE}");
+        std::process::exit(2)
+    };
+    let replay = std::fs::read(path)
+        .map_err(|e| e.to_string())
+        .and_then(|bytes| Replay::load(&bytes))
+        .unwrap_or_else(|e| {
+            eprintln!("Can't play {path}: {e}");
+            std::process::exit(1)
+        });
+    match (notation, binary) {
+        (None, None) => watch_replay(&replay, theme),
+        (Some(out), _) if out == "-" => {
+            print!("{}", replay.to_notation());
+            Ok(())
+        }
+        (Some(out), _) => std::fs::write(out, replay.to_notation()),
+        (None, Some(out)) => std::fs::write(out, replay.to_bytes()),
+    }
+}
+
+/// Play a `--record`ed run back at its own speed, until it's over or q.
+fn watch_replay(replay: &Replay, theme: &Theme) -> io::Result<()> {
+    let mut playback = Playback::new(replay).expect("the start was checked when it was read");
+    let (cols, rows) = terminal::size()?;
+    let (game_cols, game_rows) = (playback.game.cols, playback.game.rows);
+    if game_cols > cols || game_rows + 1 > rows {
+        eprintln!(
+            "This replay needs a terminal of at least {game_cols}x{}.",
+            game_rows + 1
+        );
+        std::process::exit(1)
+    }
+
+    setup_panic_hook();
+    let mut stdout = io::stdout();
+    enable_game_mode(&mut stdout)?;
+    let mut out = CrosstermBackend::new(stdout);
+    let mut screen_area = Rect::screen(cols, rows);
+
+    loop {
+        out.clear()?;
+        let (board_area, status_area) = screen_area.split_bottom(1);
+        draw_game(&mut out, &playback.game, theme)?;
+        draw_board_edge(&mut out, &playback.game, board_area, theme)?;
+        let status = StatusBar {
+            mode: "replay",
+            hints: "q quit",
+            elapsed: None,
+            rival: None,
+        };
+        status.draw(&mut out, Some(&playback.game), status_area, theme)?;
+        out.flush()?;
+
+        match next_input(Duration::from_millis(playback.game.timer))? {
+            Some(Input::Quit) => break,
+            Some(Input::Resize(cols, rows)) => screen_area = Rect::screen(cols, rows),
+            Some(Input::Suspend) => suspend(&mut out.stdout)?,
+            _ => {}
+        }
+        if playback.step().is_none() {
+            break;
+        }
+    }
+
+    disable_game_mode(&mut out.stdout)?;
+    println!("Replay over, score {}.", playback.game.score);
+    Ok(())
+}
+
+/// Pick a level from the levels directory, after downloading what's new in
+/// `index`, and play it.
+fn run_levels(
+    mut settings: Settings,
+    options: &GameOptions,
+    index: Option<&str>,
+) -> io::Result<()> {
+    use snake2::catalog::{Entry, fetch, levels_dir, list, pick};
+
+    let dir = levels_dir();
+    let mut notices = Vec::new();
+    if let Some(url) = index {
+        match fetch(url, &dir) {
+            Ok(fetched) => {
+                if !fetched.added.is_empty() {
+                    notices.push(format!("{} new from the index", fetched.added.len()));
+                }
+                notices.extend(
+                    fetched
+                        .failed
+                        .iter()
+                        .map(|(url, e)| format!("Skipped {url}: {e}")),
+                );
+            }
+            Err(e) => notices.push(format!("Couldn't get the index: {e}")),
+        }
+    }
+    let files = list(&dir)?;
+    if files.is_empty() {
+        notices.push(format!(
+            "No levels in {} yet, make one there with `snake2 edit`",
+            dir.display()
+        ));
+    }
+    // The arenas first, made for this terminal
+    let (cols, rows) = board_size(settings.margins)?;
+    let entries: Vec<Entry> = Shape::ALL
+        .into_iter()
+        .map(|shape| Entry {
+            path: shape.name().into(),
+            level: shape.level(cols, rows),
+        })
+        .chain(files)
+        .collect();
+    let scores = settings
+        .profile
+        .level_scores()
+        .map(|s| s.value)
+        .unwrap_or_default();
+    let Some(level) = pick(&entries, &scores, notices, &settings.theme)? else {
+        return Ok(());
+    };
+    settings.board = Some((level.cols, level.rows));
+    settings.level = Some(level);
+    play(&settings, options)
+}
+
+fn list_profiles() -> io::Result<()> {
+    for name in Profile::list()? {
+        let Signed {
+            value: stats,
+            verified,
+        } = Profile::open(&name)?.stats()?;
+        println!(
+            "{name:<32} {:>6} games  best {:>5}  played {}{}",
+            stats.games,
+            stats.best_score,
+            format_duration(Duration::from_secs(stats.seconds_played)),
+            if verified { "" } else { "  (unverified)" }
+        );
+        let deaths: Vec<String> = stats
+            .death_shares()
+            .iter()
+            .map(|(cause, share)| format!("{} {share}%", cause.name()))
+            .collect();
+        if !deaths.is_empty() {
+            println!("{:<32} deaths: {}", "", deaths.join(", "));
+        }
+        let Signed {
+            value: earned,
+            verified,
+        } = Profile::open(&name)?.achievements()?;
+        if !earned.is_empty() {
+            let names: Vec<&str> = earned.iter().map(|(a, _)| a.name()).collect();
+            println!(
+                "{:<32} achievements: {} of {} ({}){}",
+                "",
+                earned.len(),
+                Achievement::ALL.len(),
+                names.join(", "),
+                if verified { "" } else { "  (unverified)" }
+            );
+        }
+    }
+    Ok(())
+}
+
+fn run_calibrate(settings: &Settings) -> io::Result<()> {
+    use std::io::Write;
+
+    let mut out = CrosstermBackend::new(io::stdout());
+    enable_game_mode(&mut out.stdout)?;
+    out.clear()?;
+    let measured = measure(&mut out.stdout);
+    restore_terminal(&mut out.stdout);
+    println!();
+    let Some(calibration) = measured? else {
+        return Ok(());
+    };
+    let grace = calibration.suggested_grace();
+    println!(
+        "Keys take about {} ms to get here.",
+        calibration.key_latency().as_millis()
+    );
+    println!(
+        "Ticks wake up {} ms late on average, {} ms at worst.",
+        calibration.jitter().as_millis(),
+        calibration.worst_jitter().as_millis()
+    );
+    println!(
+        "Suggested --input-grace: {} ms (now {} ms).",
+        grace.as_millis(),
+        settings.input_grace.as_millis()
+    );
+    if grace == settings.input_grace {
+        return Ok(());
+    }
+    print!("Save it to profile `{}`? [y/N] ", settings.profile.name);
+    io::stdout().flush()?;
+    let mut line = String::new();
+    io::stdin().read_line(&mut line)?;
+    if line.trim().eq_ignore_ascii_case("y") {
+        settings
+            .profile
+            .set_config("input-grace", &grace.as_millis().to_string())?;
+        println!("Saved.");
+    }
+    Ok(())
+}
+
+#[cfg(not(feature = "scripting"))]
+fn run_bot(_path: &str, _theme: &Theme, _options: &GameOptions) -> io::Result<()> {
+    eprintln!("This build has no bot support, rebuild with `--features scripting`.");
+    std::process::exit(1)
+}
+
+#[cfg(not(feature = "twitch"))]
+fn run_twitch(_args: &[String], _theme: &Theme, _options: &GameOptions) -> io::Result<()> {
+    eprintln!("This build has no Twitch support, rebuild with `--features twitch`.");
+    std::process::exit(1)
+}
+
+#[cfg(feature = "server")]
+fn run_server(args: &[String], options: &GameOptions) -> io::Result<()> {
+    use snake2::royale::{ROYALE_COLS, ROYALE_ROWS};
+    use snake2::server::{ServerConfig, serve};
+
+    let mut args = args.to_vec();
+    let mut config = ServerConfig {
+        options: *options,
+        ..Default::default()
+    };
+    if let Some(port) = take_option(&mut args, "--port") {
+        config.port = flag_value("--port", Some(&port));
+    }
+    config.advertise = !take_flag(&mut args, "--no-lan");
+    if take_flag(&mut args, "--royale") {
+        config.teams = take_flag(&mut args, "--teams");
+        // Two on two, unless said otherwise
+        let default = if config.teams { 4 } else { 8 };
+        let players = take_option(&mut args, "--players");
+        config.royale = Some(players.map_or(default, |n| flag_value("--players", Some(&n))));
+        (config.cols, config.rows) = (ROYALE_COLS, ROYALE_ROWS);
+    }
+    if let Some(size) = take_option(&mut args, "--size") {
+        (config.cols, config.rows) = parse_size(&size).unwrap_or_else(|| bad_flag("--size"));
+    }
+    match config.royale {
+        Some(players) => eprintln!(
+            "Battle royale for up to {players}{} on ws://localhost:{}",
+            if config.teams { " in teams" } else { "" },
+            config.port
+        ),
+        None => eprintln!("Games on ws://localhost:{}", config.port),
+    }
+    serve(&config)
+}
+
+#[cfg(not(feature = "server"))]
+fn run_server(_args: &[String], _options: &GameOptions) -> io::Result<()> {
+    eprintln!("This build has no game server, rebuild with `--features server`.");
+    std::process::exit(1)
+}
+
+#[cfg(feature = "server")]
+fn run_connect(args: &[String], theme: &Theme) -> io::Result<()> {
+    let mut args = args.to_vec();
+    let predict = take_flag(&mut args, "--predict");
+    let [url] = args.as_slice() else {
+        eprintln!("{USAGE}");
+        std::process::exit(2)
+    };
+    snake2::remote::run(url, predict, theme)
+}
+
+#[cfg(not(feature = "server"))]
+fn run_connect(_args: &[String], _theme: &Theme) -> io::Result<()> {
+    eprintln!("This build can't play on a game server, rebuild with `--features server`.");
+    std::process::exit(1)
+}
+
+/// Like `connect`, but without an address it looks for servers on the
+/// local network and asks which one.
+#[cfg(feature = "server")]
+fn run_join(args: &[String], theme: &Theme) -> io::Result<()> {
+    use snake2::discovery::browse;
+    use std::io::Write;
+
+    let mut args = args.to_vec();
+    let predict = take_flag(&mut args, "--predict");
+    let url = match args.as_slice() {
+        [address] if address.starts_with("ws://") => address.clone(),
+        [address] => format!("ws://{address}"),
+        [] => {
+            eprintln!("Looking for games on the local network...");
+            let hosts = browse(Duration::from_secs(2))?;
+            if hosts.is_empty() {
+                eprintln!("No games found. Is a `snake2 server` running nearby?");
+                std::process::exit(1)
+            }
+            for (n, host) in hosts.iter().enumerate() {
+                let info = |key: &str| {
+                    host.info
+                        .iter()
+                        .find_map(|pair| pair.strip_prefix(key)?.strip_prefix('='))
+                        .unwrap_or("?")
+                };
+                let royale = host.info.iter().any(|pair| pair.starts_with("royale="));
+                let mode = if royale { "royale" } else { info("mode") };
+                println!(
+                    "{:>2}. {} ({}) {mode}, {} playing, best {}",
+                    n + 1,
+                    host.name,
+                    host.address,
+                    info("players"),
+                    info("best")
+                );
+            }
+            let choice = if hosts.len() == 1 {
+                1
+            } else {
+                print!("Join which? ");
+                io::stdout().flush()?;
+                let mut line = String::new();
+                io::stdin().read_line(&mut line)?;
+                match line.trim().parse::<usize>() {
+                    Ok(n) if (1..=hosts.len()).contains(&n) => n,
+                    _ => std::process::exit(0),
+                }
+            };
+            hosts[choice - 1].url()
+        }
+        _ => {
+            eprintln!("{USAGE}");
+            std::process::exit(2)
+        }
+    };
+    snake2::remote::run(&url, predict, theme)
+}
+
+#[cfg(not(feature = "server"))]
+fn run_join(_args: &[String], _theme: &Theme) -> io::Result<()> {
+    eprintln!("This build can't play on a game server, rebuild with `--features server`.");
+    std::process::exit(1)
+}
+
+#[cfg(feature = "ratatui")]
+fn run_ratatui(theme: &Theme, options: &GameOptions) -> io::Result<()> {
+    snake2::tui::run(theme, options)
+}
+
+#[cfg(not(feature = "ratatui"))]
+fn run_ratatui(_theme: &Theme, _options: &GameOptions) -> io::Result<()> {
+    eprintln!("This build has no ratatui frontend, rebuild with `--features ratatui`.");
+    std::process::exit(1)
+}
+
+#[cfg(not(feature = "scripting"))]
+fn run_simulate(_args: &[String], options: &GameOptions) -> io::Result<()> {
+    run_bot("", &Theme::default(), options)
+}
+
+#[cfg(feature = "scripting")]
+fn run_simulate(args: &[String], options: &GameOptions) -> io::Result<()> {
+    use snake2::simulate::{SimulateConfig, simulate, write_csv};
+
+    let mut config = SimulateConfig::default();
+    config.game.options = *options;
+    let mut bot = None;
+    let mut out = None;
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--bot" => bot = Some(flag_value::<String>(arg, args.next())),
+            "--episodes" => config.episodes = flag_value(arg, args.next()),
+            "--threads" => config.threads = flag_value(arg, args.next()),
+            "--out" => out = Some(flag_value::<String>(arg, args.next())),
+            "--seed" => config.game.options.seed = Some(flag_value(arg, args.next())),
+            "--max-ticks" => config.game.max_ticks = flag_value(arg, args.next()),
+            "--size" => {
+                (config.game.cols, config.game.rows) = args
+                    .next()
+                    .and_then(|v| parse_size(v))
+                    .unwrap_or_else(|| bad_flag(arg));
+            }
+            _ => bad_flag(arg),
+        }
+    }
+    let Some(bot) = bot else {
+        eprintln!("{USAGE}");
+        std::process::exit(2)
+    };
+
+    let results = std::fs::read_to_string(&bot)
+        .map_err(|e| snake2::bot::BotError::Load(format!("{bot}: {e}")))
+        .and_then(|source| simulate(&source, &config));
+    let results = match results {
+        Ok(results) => results,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1)
+        }
+    };
+    match out {
+        Some(path) => write_csv(&results, io::BufWriter::new(std::fs::File::create(path)?)),
+        None => write_csv(&results, io::stdout().lock()),
+    }
+}
+
+#[cfg(not(feature = "scripting"))]
+fn run_arena(_args: &[String], options: &GameOptions) -> io::Result<()> {
+    run_bot("", &Theme::default(), options)
+}
+
+#[cfg(feature = "scripting")]
+fn run_arena(args: &[String], options: &GameOptions) -> io::Result<()> {
+    use snake2::arena::{ArenaConfig, run_arena};
+
+    let mut config = ArenaConfig {
+        options: *options,
+        ..Default::default()
+    };
+    let mut paths = Vec::new();
+    let mut args = args.iter();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--games" => config.games = flag_value(arg, args.next()),
+            "--seed" => config.options.seed = Some(flag_value(arg, args.next())),
+            "--max-ticks" => config.max_ticks = flag_value(arg, args.next()),
+            "--size" => {
+                (config.cols, config.rows) = args
+                    .next()
+                    .and_then(|v| parse_size(v))
+                    .unwrap_or_else(|| bad_flag(arg));
+            }
+            path => paths.push(std::path::PathBuf::from(path)),
+        }
+    }
+
+    if paths.is_empty() {
+        eprintln!("{USAGE}");
+        std::process::exit(2)
+    }
+
+    let stats = match run_arena(&paths, &config) {
+        Ok(stats) => stats,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1)
+        }
+    };
+
+    println!(
+        "{} games on a {}x{} board\n",
+        config.games, config.cols, config.rows
+    );
+    println!(
+        "{:<24} {:>6} {:>6} {:>9} {:>6} {:>7} {:>9} {:>8}",
+        "bot", "wins", "draws", "avg score", "best", "deaths", "timeouts", "crashes"
+    );
+    for s in &stats {
+        println!(
+            "{:<24} {:>6} {:>6} {:>9.2} {:>6} {:>7} {:>9} {:>8}",
+            s.name,
+            s.wins,
+            s.draws,
+            s.average_score(config.games),
+            s.best_score,
+            s.deaths,
+            s.timeouts,
+            s.crashes
+        );
+    }
+    Ok(())
+}
+
+fn run_bench_bots(args: &[String], options: &GameOptions) -> io::Result<()> {
+    use snake2::bench::{BenchConfig, bench_bots};
+
+    let mut config = BenchConfig {
+        options: *options,
+        ..Default::default()
+    };
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--games" => config.games = flag_value(arg, args.next()),
+            "--max-ticks" => config.max_ticks = flag_value(arg, args.next()),
+            "--seed" => config.seed = flag_value(arg, args.next()),
+            "--size" => {
+                (config.cols, config.rows) = args
+                    .next()
+                    .and_then(|v| parse_size(v))
+                    .unwrap_or_else(|| bad_flag(arg));
+            }
+            _ => bad_flag(arg),
+        }
+    }
+
+    let stats = bench_bots(&config);
+    println!(
+        "{} games on a {}x{} board, seeds {} on, at most {} ticks\n",
+        config.games, config.cols, config.rows, config.seed, config.max_ticks
+    );
+    println!(
+        "{:<12} {:>9} {:>6} {:>10} {:>9} {:>7} {:>6}",
+        "bot", "avg score", "best", "avg length", "avg ticks", "deaths", "full"
+    );
+    for s in &stats {
+        println!(
+            "{:<12} {:>9.2} {:>6} {:>10.1} {:>9} {:>7} {:>6}",
+            s.strategy.name(),
+            s.average_score(config.games),
+            s.best_score,
+            s.average_length(config.games),
+            s.total_ticks / config.games.max(1) as u64,
+            s.deaths,
+            s.full_boards
+        );
+    }
     Ok(())
 }
 
-fn disable_game_mode(stdout: &mut std::io::Stdout) -> io::Result<()> {
-    // Enable normal input again
-    terminal::disable_raw_mode()?;
-    // Show cursor again
-    stdout.execute(Show)?;
-    // Clear terminal screen
-    stdout.execute(Clear(ClearType::All))?;
-    Ok(println!("\n\n\t\tThe program ended.\n\n"))
+/// Remove a boolean `--flag` from anywhere in the arguments, telling if it was there.
+fn take_flag(args: &mut Vec<String>, flag: &str) -> bool {
+    let before = args.len();
+    args.retain(|a| a != flag);
+    args.len() != before
+}
+
+/// Remove a `--flag value` pair from anywhere in the arguments, returning the value.
+/// When it's given more than once (say on the command line and in the
+/// profile config) the first one counts.
+fn take_option(args: &mut Vec<String>, flag: &str) -> Option<String> {
+    let mut first = None;
+    while let Some(i) = args.iter().position(|a| a == flag) {
+        if i + 1 >= args.len() {
+            bad_flag(flag);
+        }
+        args.remove(i);
+        let value = args.remove(i);
+        first.get_or_insert(value);
+    }
+    first
+}
+
+/// Every `--glyph-NAME GLYPH` (`glyph-wall = █` in the config file), the
+/// first one of a name wins like with other options, two columns wide if
+/// `emoji`. `None` without any.
+fn take_glyphs(args: &mut Vec<String>, emoji: bool) -> Result<Option<Glyphs>, String> {
+    let mut set = Vec::new();
+    while let Some(i) = args.iter().position(|a| a.starts_with("--glyph-")) {
+        if i + 1 >= args.len() {
+            bad_flag(&args[i]);
+        }
+        let name = args.remove(i)["--glyph-".len()..].to_string();
+        set.push((name, args.remove(i)));
+    }
+    if set.is_empty() {
+        return Ok(None);
+    }
+    let mut glyphs = Glyphs::default();
+    glyphs.emoji = emoji;
+    for (name, glyph) in set.iter().rev() {
+        glyphs.set(name, glyph)?;
+    }
+    Ok(Some(glyphs))
+}
+
+/// Parse the value that follows a `--flag`, bailing out with the usage on junk.
+fn flag_value<T: std::str::FromStr>(flag: &str, value: Option<&String>) -> T {
+    value
+        .and_then(|v| v.parse().ok())
+        .unwrap_or_else(|| bad_flag(flag))
+}
+
+fn bad_flag(flag: &str) -> ! {
+    eprintln!("Bad value for `{flag}`.\n{USAGE}");
+    std::process::exit(2)
+}
+
+fn parse_size(value: &str) -> Option<(u16, u16)> {
+    let (cols, rows) = value.split_once('x')?;
+    let (cols, rows) = (cols.parse().ok()?, rows.parse().ok()?);
+    (cols > 1 && rows > 1).then_some((cols, rows))
+}
+
+#[cfg(feature = "scripting")]
+fn run_bot(path: &str, theme: &Theme, options: &GameOptions) -> io::Result<()> {
+    use snake2::bot::{Bot, BotError, DEFAULT_TIME_LIMIT};
+    use snake2::snake::SnakeDirection;
+
+    let mut bot = match Bot::load(std::path::Path::new(path), DEFAULT_TIME_LIMIT) {
+        Ok(bot) => bot,
+        Err(e) => {
+            eprintln!("{e}");
+            std::process::exit(1)
+        }
+    };
+
+    setup_panic_hook();
+    let mut stdout = io::stdout();
+    enable_game_mode(&mut stdout)?;
+    let mut out = CrosstermBackend::new(stdout);
+
+    let (cols, rows) = terminal::size()?;
+    let mut screen_area = Rect::screen(cols, rows);
+    let (board_area, _) = screen_area.split_bottom(1);
+    let mut game = Game::with_options(
+        board_area.width,
+        board_area.height,
+        SnakeDirection::Right,
+        options,
+    );
+    let mut failure: Option<BotError> = None;
+    let mut clock = Stopwatch::new();
+    clock.start();
+
+    loop {
+        // A slow bot just keeps going straight this tick
+        match bot.next_move(&game) {
+            Ok(Some(direction)) => game.turn(direction),
+            Ok(None) | Err(BotError::Timeout) => {}
+            Err(e) => {
+                failure = Some(e);
+                break;
+            }
+        }
+
+        out.clear()?;
+        if game.tick() == TickOutcome::Died {
+            break;
+        }
+        draw_game(&mut out, &game, theme)?;
+        let status = StatusBar {
+            mode: "bot",
+            hints: "q quit",
+            elapsed: Some(clock.elapsed()),
+            rival: None,
+        };
+        status.draw(&mut out, Some(&game), screen_area.split_bottom(1).1, theme)?;
+        out.flush()?;
+
+        // Only quitting (and Ctrl+Z) is up to the human
+        match next_input(Duration::from_millis(game.timer))? {
+            Some(Input::Quit) => break,
+            Some(Input::Resize(cols, rows)) => screen_area = Rect::screen(cols, rows),
+            Some(Input::Suspend) => {
+                clock.stop();
+                suspend(&mut out.stdout)?;
+                clock.start();
+            }
+            _ => {}
+        }
+    }
+
+    disable_game_mode(&mut out.stdout)?;
+    match failure {
+        Some(e) => eprintln!("{e}"),
+        None => println!(
+            "Bot finished with a score of {} in {}.",
+            game.score,
+            format_duration(clock.elapsed())
+        ),
+    }
+    Ok(())
 }
 
-fn setup_panic_hook() {
-    std::panic::set_hook(Box::new(|info| {
-        let _ = crossterm::terminal::disable_raw_mode();
-        let _ = std::io::stdout().execute(crossterm::cursor::Show);
-        eprintln!("Panic: {info}");
-    }));
+#[cfg(feature = "twitch")]
+fn run_twitch(args: &[String], theme: &Theme, options: &GameOptions) -> io::Result<()> {
+    use snake2::snake::SnakeDirection;
+    use snake2::twitch::{Chat, PANEL_WIDTH, Tally};
+
+    let mut args = args.to_vec();
+    let votes_every: u64 = match take_option(&mut args, "--votes-every") {
+        Some(ticks) => flag_value("--votes-every", Some(&ticks)),
+        None => 3,
+    };
+    let Some(channel) = args.first() else {
+        eprintln!("{USAGE}");
+        std::process::exit(2)
+    };
+    if votes_every == 0 {
+        bad_flag("--votes-every");
+    }
+
+    let chat = match Chat::connect(channel) {
+        Ok(chat) => chat,
+        Err(e) => {
+            eprintln!("Can't connect to Twitch chat: {e}");
+            std::process::exit(1)
+        }
+    };
+
+    setup_panic_hook();
+    let mut stdout = io::stdout();
+    enable_game_mode(&mut stdout)?;
+    let mut out = CrosstermBackend::new(stdout);
+
+    // The votes go on a panel to the right of the board
+    let (cols, rows) = terminal::size()?;
+    let mut screen_area = Rect::screen(cols, rows);
+    let (board_area, _) = screen_area.split_bottom(1).0.split_right(PANEL_WIDTH + 1);
+    let mut game = Game::with_options(
+        board_area.width.max(2),
+        board_area.height,
+        SnakeDirection::Right,
+        options,
+    );
+    let mut tally = Tally::default();
+    let mut clock = Stopwatch::new();
+    clock.start();
+
+    loop {
+        chat.drain(&mut tally);
+        if game.ticks.is_multiple_of(votes_every) {
+            if let Some(direction) = tally.winner() {
+                game.turn(direction);
+            }
+            tally.clear();
+        }
+
+        out.clear()?;
+        if game.tick() == TickOutcome::Died {
+            break;
+        }
+        draw_game(&mut out, &game, theme)?;
+        let ticks_left = votes_every - game.ticks % votes_every;
+        let (top, status_area) = screen_area.split_bottom(1);
+        tally.draw(
+            &mut out,
+            top.split_right(PANEL_WIDTH + 1).1,
+            ticks_left,
+            theme,
+        )?;
+        let status = StatusBar {
+            mode: "twitch",
+            hints: "q quit",
+            elapsed: Some(clock.elapsed()),
+            rival: None,
+        };
+        status.draw(&mut out, Some(&game), status_area, theme)?;
+        out.flush()?;
+
+        match next_input(Duration::from_millis(game.timer))? {
+            Some(Input::Quit) => break,
+            Some(Input::Resize(cols, rows)) => screen_area = Rect::screen(cols, rows),
+            Some(Input::Suspend) => {
+                clock.stop();
+                suspend(&mut out.stdout)?;
+                clock.start();
+            }
+            _ => {}
+        }
+    }
+
+    disable_game_mode(&mut out.stdout)?;
+    println!(
+        "Chat finished with a score of {} in {}.",
+        game.score,
+        format_duration(clock.elapsed())
+    );
+    Ok(())
 }
@@ -0,0 +1,159 @@
+// -- Objectives: food chains and hill zones
+//
+// Every `CHAIN_EVERY` points a chain shows up next to the food: pieces
+// numbered 1 to `CHAIN_LENGTH` around the board, to be eaten in order within
+// `CHAIN_TICKS` ticks for `CHAIN_BONUS` points on top. Each piece is worth a
+// point and grows the snake like food does, the bonus only comes with the
+// whole chain. Eating one out of order breaks the chain, and so does running
+// out of time: whatever is left of it goes away and there's no bonus.
+//
+// With the hills modifier (`--modifiers hills`, see `rules::Hills`), every
+// `ZONE_EVERY` ticks a hill zone opens somewhere, a `ZONE_WIDTH` by
+// `ZONE_HEIGHT` patch of `Zone` cells that stays for `ZONE_TICKS` ticks.
+// The snake can go over it, and with the head in it a point comes every
+// `ZONE_POINT_EVERY` ticks, enough to make going there and staying worth
+// more than the food for a while. Zones go by the tick count, so there's
+// nothing to save but the cells.
+//
+// `Game::tick` calls in here when food is eaten, when a piece is, every
+// tick for the chain's clock and after the snake moved for the zone's
+// points, the hills rule every tick to open and close zones. The
+// chain's ticks left are saved with the game, the pieces are entities like
+// the rest.
+
+use crate::entity::{Entity, EntityKind};
+use crate::events::GameEvent;
+use crate::game::Game;
+use crate::rules::Mode;
+
+// A chain shows up every this many points
+pub const CHAIN_EVERY: u32 = 10;
+pub const CHAIN_LENGTH: u8 = 3;
+// Ticks to eat the whole chain in
+pub const CHAIN_TICKS: u32 = 60;
+// Points for eating all of it in order
+pub const CHAIN_BONUS: u32 = 5;
+
+pub const ZONE_EVERY: u64 = 200;
+pub const ZONE_TICKS: u64 = 60;
+pub const ZONE_WIDTH: u16 = 5;
+pub const ZONE_HEIGHT: u16 = 3;
+// With the head in the zone, a point every this many ticks
+pub const ZONE_POINT_EVERY: u64 = 5;
+
+/// The piece of the chain that has to be eaten next, the lowest one left.
+pub fn next_in_chain(game: &Game) -> Option<u8> {
+    game.entities
+        .iter()
+        .filter_map(|e| match e.kind {
+            EntityKind::Chain(n) => Some(n),
+            _ => None,
+        })
+        .min()
+}
+
+/// After the snake ate food: a new chain if the score is there and there
+/// isn't one already.
+pub fn on_food_eaten(game: &mut Game) {
+    if !game.score.is_multiple_of(CHAIN_EVERY) || next_in_chain(game).is_some() {
+        return;
+    }
+    for n in 1..=CHAIN_LENGTH {
+        // A board too full for the whole chain gets none of it
+        let Some((x, y)) = game.random_free_cell() else {
+            game.entities
+                .retain(|e| !matches!(e.kind, EntityKind::Chain(_)));
+            return;
+        };
+        game.entities.push(Entity::new(EntityKind::Chain(n), x, y));
+    }
+    game.chain_ticks = CHAIN_TICKS;
+    game.events.push(GameEvent::ChainAppeared);
+}
+
+/// The head just landed on piece `n`, which is already off the board and
+/// has grown the snake.
+pub fn on_piece_eaten(game: &mut Game, n: u8) {
+    game.score += 1;
+    game.events.push(GameEvent::ChainPieceEaten {
+        piece: n,
+        score: game.score,
+    });
+    match next_in_chain(game) {
+        // The ones before it are gone, so anything lower left means too early
+        Some(next) if next < n => break_chain(game),
+        Some(_) => {}
+        None => {
+            game.chain_ticks = 0;
+            game.score += CHAIN_BONUS;
+            game.events
+                .push(GameEvent::ChainCompleted { bonus: CHAIN_BONUS });
+        }
+    }
+}
+
+/// Once a tick before the snake moves: the chain breaks when its time runs
+/// out.
+pub fn on_tick(game: &mut Game) {
+    if game.chain_ticks > 0 {
+        game.chain_ticks -= 1;
+        if game.chain_ticks == 0 && next_in_chain(game).is_some() {
+            break_chain(game);
+        }
+    }
+}
+
+/// Once a tick before the snake moves, with the hills modifier: zones open
+/// and close.
+pub fn open_and_close_zones(game: &mut Game) {
+    // In tron the score is the ticks lived, there's nothing a zone could add
+    if game.mode == Mode::Tron || game.ticks == 0 {
+        return;
+    }
+    let since = game.ticks % ZONE_EVERY;
+    if since == 0
+        && let Some((x, y)) = game.random_free_area(ZONE_WIDTH, ZONE_HEIGHT)
+    {
+        for cx in x..x + ZONE_WIDTH {
+            for cy in y..y + ZONE_HEIGHT {
+                game.entities.push(Entity::new(EntityKind::Zone, cx, cy));
+            }
+        }
+        game.events.push(GameEvent::ZoneOpened);
+    } else if since == ZONE_TICKS && zone_ticks_left(game).is_some() {
+        game.entities.retain(|e| e.kind != EntityKind::Zone);
+        // Left behind in another room, it's gone from there too
+        for room in &mut game.rooms {
+            room.retain(|e| e.kind != EntityKind::Zone);
+        }
+        game.events.push(GameEvent::ZoneClosed);
+    }
+}
+
+/// After the snake moved: a point now and then with the head in a zone.
+pub fn after_move(game: &mut Game) {
+    let head = game.snake.head();
+    let in_zone = game
+        .entity_at(head.x, head.y)
+        .is_some_and(|e| e.kind == EntityKind::Zone);
+    if in_zone && game.ticks.is_multiple_of(ZONE_POINT_EVERY) {
+        game.score += 1;
+        game.events
+            .push(GameEvent::ZoneScored { score: game.score });
+    }
+}
+
+/// How long the zone on the board stays, `None` without one.
+pub fn zone_ticks_left(game: &Game) -> Option<u64> {
+    game.entities
+        .iter()
+        .any(|e| e.kind == EntityKind::Zone)
+        .then(|| ZONE_TICKS.saturating_sub(game.ticks % ZONE_EVERY))
+}
+
+fn break_chain(game: &mut Game) {
+    game.entities
+        .retain(|e| !matches!(e.kind, EntityKind::Chain(_)));
+    game.chain_ticks = 0;
+    game.events.push(GameEvent::ChainBroken);
+}
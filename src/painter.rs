@@ -0,0 +1,134 @@
+// -- Drawing on a thread of its own
+//
+// Writing a frame to a slow terminal (SSH from far away) can take longer
+// than a tick. `Painter` is a `Backend` that only writes down what was
+// drawn, `flush` hands the frame to a thread that puts it on the terminal
+// with crossterm. Frames that pile up while that thread is busy are dropped
+// and only the newest one is drawn (the newest that starts with `clear`, and
+// whatever came after it), so the game loop never waits on the terminal and
+// ticks keep their beat however long a frame takes.
+//
+// Anything else writing to the terminal (leaving the game mode, suspending)
+// calls `sync` first, which waits for the frame being drawn.
+
+use crate::backend::{Backend, Style};
+use crate::terminal::CrosstermBackend;
+use std::io;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+enum Op {
+    Clear,
+    Print(u16, u16, String, Style),
+}
+
+enum Message {
+    Frame(Vec<Op>),
+    // Answered once every frame sent before it is drawn or dropped
+    Sync(Sender<()>),
+}
+
+pub struct Painter {
+    // For what isn't a frame, after `sync`
+    pub stdout: io::Stdout,
+    frame: Vec<Op>,
+    sender: Sender<Message>,
+    // The first error the thread ran into, the next `flush` or `sync` returns it
+    error: Arc<Mutex<Option<io::Error>>>,
+}
+
+impl Painter {
+    pub fn new(stdout: io::Stdout) -> Self {
+        let (sender, receiver) = mpsc::channel();
+        let error = Arc::new(Mutex::new(None));
+        let thread_error = Arc::clone(&error);
+        thread::spawn(move || paint(&receiver, &thread_error));
+        Painter {
+            stdout,
+            frame: Vec::new(),
+            sender,
+            error,
+        }
+    }
+
+    /// Wait for the frames handed over so far to be on the terminal.
+    pub fn sync(&mut self) -> io::Result<()> {
+        let (done, wait) = mpsc::channel();
+        if self.sender.send(Message::Sync(done)).is_ok() {
+            let _ = wait.recv();
+        }
+        self.take_error()
+    }
+
+    fn take_error(&mut self) -> io::Result<()> {
+        match self.error.lock().map(|mut e| e.take()) {
+            Ok(Some(e)) => Err(e),
+            _ => Ok(()),
+        }
+    }
+}
+
+impl Backend for Painter {
+    fn clear(&mut self) -> io::Result<()> {
+        self.frame.clear();
+        self.frame.push(Op::Clear);
+        Ok(())
+    }
+
+    fn print(&mut self, x: u16, y: u16, text: &str, style: Style) -> io::Result<()> {
+        self.frame.push(Op::Print(x, y, text.to_string(), style));
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        let frame = std::mem::take(&mut self.frame);
+        // Only gone if the thread panicked, and then there's no drawing anyway
+        let _ = self.sender.send(Message::Frame(frame));
+        self.take_error()
+    }
+}
+
+// The thread: the newest frame each time it's free, until `Painter` is dropped
+fn paint(receiver: &Receiver<Message>, error: &Mutex<Option<io::Error>>) {
+    let mut out = CrosstermBackend::new(io::stdout());
+    while let Ok(first) = receiver.recv() {
+        let mut frame: Option<Vec<Op>> = None;
+        let mut syncs = Vec::new();
+        for message in std::iter::once(first).chain(receiver.try_iter()) {
+            match message {
+                // One that starts over replaces what's waiting, one that
+                // doesn't goes on top of it
+                Message::Frame(ops) => match frame {
+                    Some(ref mut waiting) if !matches!(ops.first(), Some(Op::Clear)) => {
+                        waiting.extend(ops)
+                    }
+                    _ => frame = Some(ops),
+                },
+                Message::Sync(done) => syncs.push(done),
+            }
+        }
+        if let Some(ops) = frame
+            && let Err(e) = draw(&mut out, ops)
+            && let Ok(mut error) = error.lock()
+        {
+            error.get_or_insert(e);
+        }
+        for done in syncs {
+            let _ = done.send(());
+        }
+    }
+}
+
+fn draw(out: &mut CrosstermBackend, ops: Vec<Op>) -> io::Result<()> {
+    // A whole frame at once, nothing else gets to write in the middle of it
+    let stdout = io::stdout();
+    let _lock = stdout.lock();
+    for op in ops {
+        match op {
+            Op::Clear => out.clear()?,
+            Op::Print(x, y, text, style) => out.print(x, y, &text, style)?,
+        }
+    }
+    out.flush()
+}
@@ -0,0 +1,677 @@
+// -- Player profiles
+//
+// Everything the game keeps between runs lives in a profile directory,
+// `snake2 --profile alice` uses `<data dir>/profiles/alice/`:
+//
+//     config.txt   option overrides, `mode = tron`, `no-color`, ...
+//     scores.txt   the best runs, one per line
+//     stats.txt    lifetime totals, with how many runs ended each way
+//     levels.txt   the best score on each hand-made level, by its name
+//     achievements.txt   the ones earned and when, see `achievement.rs`
+//     weekly-2026-W42.txt   the best `--weekly` runs of that week
+//     saves/       saved games, `slot1.txt` ..., `slot0.txt` is the autosave
+//
+// Scores (weekly ones too), stats, level scores and achievements are
+// signed with a key kept next to the profiles, `install.key` (see
+// `signature.rs`).
+//
+// Scores, stats and saves carry a version (see `versioned.rs`). Before one
+// of them is rewritten in a newer format the old file is kept next to it as
+// `scores.txt.v0.bak` and the like, in case the migration got it wrong.
+//
+// The data dir is `$SNAKE2_HOME` if set, otherwise the usual per-user
+// data directory of the platform.
+
+use crate::achievement::Achievement;
+use crate::events::DeathCause;
+use crate::game::Game;
+use crate::save::{SAVE, load_game, save_game};
+use crate::signature::{Signed, from_hex, sign, to_hex, verify};
+use crate::versioned::{Format, unchanged};
+use crate::weekly::Week;
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub const DEFAULT_PROFILE: &str = "default";
+const MAX_HIGH_SCORES: usize = 10;
+/// Where the game in progress is kept every so often and when the terminal
+/// is lost in the middle of it, slots from `--slot` start at 1.
+pub const AUTOSAVE_SLOT: u8 = 0;
+
+pub const SCORES: Format = Format {
+    kind: "scores",
+    // 1: the header, 2: the modifiers of each run, 3: whether its speed was
+    // changed along the way, 4: whether it was played with `--danger`
+    migrations: &[unchanged, add_modifiers, add_speed, add_assisted],
+};
+
+pub const STATS: Format = Format {
+    kind: "stats",
+    // 1: the header, 2: nothing new, only to tell files from before
+    // signatures apart
+    migrations: &[unchanged, unchanged],
+};
+
+// The first versions of the scores and stats only ever written since there
+// are signatures, see `install_key`
+const SCORES_SIGNED_SINCE: u32 = 3;
+const STATS_SIGNED_SINCE: u32 = 2;
+
+pub const LEVEL_SCORES: Format = Format {
+    kind: "level-scores",
+    // 1: the first one
+    migrations: &[unchanged],
+};
+
+pub const ACHIEVEMENTS: Format = Format {
+    kind: "achievements",
+    // 1: the first one
+    migrations: &[unchanged],
+};
+
+pub fn data_dir() -> PathBuf {
+    if let Some(dir) = std::env::var_os("SNAKE2_HOME") {
+        return PathBuf::from(dir);
+    }
+    #[cfg(windows)]
+    if let Some(dir) = std::env::var_os("APPDATA") {
+        return PathBuf::from(dir).join("snake2");
+    }
+    if let Some(dir) = std::env::var_os("XDG_DATA_HOME") {
+        return PathBuf::from(dir).join("snake2");
+    }
+    if let Some(home) = std::env::var_os("HOME") {
+        return PathBuf::from(home).join(".local/share/snake2");
+    }
+    PathBuf::from(".snake2")
+}
+
+/// The key scores and stats are signed with, made the first time it's
+/// needed. The files already there from before there were signatures are
+/// signed with it then, the rest are left as they are.
+fn install_key() -> io::Result<Vec<u8>> {
+    let path = data_dir().join("install.key");
+    match fs::read_to_string(&path) {
+        Ok(text) => {
+            return from_hex(text.trim())
+                .ok_or_else(|| invalid_data(format!("bad key in {}", path.display())));
+        }
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    let key: [u8; 32] = rand::random();
+    fs::create_dir_all(data_dir())?;
+    fs::write(&path, to_hex(&key) + "\n")?;
+    for name in Profile::list()? {
+        Profile::open(&name)?.sign_files(&key)?;
+    }
+    Ok(key.to_vec())
+}
+
+/// Seconds since the epoch, what the files use for dates.
+pub fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs())
+}
+
+/// One finished run, as kept in the high scores.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct RunRecord {
+    pub score: u32,
+    pub length: usize,
+    pub seconds: u64,
+    pub mode: String,
+    pub when: u64,
+    // `none`, or the modifiers the run was played with
+    pub modifiers: String,
+    // Sped up or slowed down with `+` and `-` along the way
+    pub custom_speed: bool,
+    // With `--danger` marking the cells that would kill the snake
+    pub assisted: bool,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Stats {
+    pub games: u64,
+    pub total_score: u64,
+    pub best_score: u32,
+    pub seconds_played: u64,
+    pub longest_snake: usize,
+    // How many runs ended each way, in the order of `DeathCause::ALL`
+    pub deaths: [u64; DeathCause::ALL.len()],
+}
+
+impl Stats {
+    /// Each way runs ended and its share of them in percent, the most
+    /// common first, leaving out the ones that never happened.
+    pub fn death_shares(&self) -> Vec<(DeathCause, u64)> {
+        let total: u64 = self.deaths.iter().sum();
+        let mut shares: Vec<(DeathCause, u64)> = DeathCause::ALL
+            .into_iter()
+            .zip(self.deaths)
+            .filter(|&(_, n)| n > 0)
+            .map(|(cause, n)| (cause, n * 100 / total))
+            .collect();
+        shares.sort_by_key(|&(_, share)| std::cmp::Reverse(share));
+        shares
+    }
+}
+
+pub struct Profile {
+    pub name: String,
+    pub dir: PathBuf,
+}
+
+impl Profile {
+    /// Open a profile, creating its directory the first time.
+    pub fn open(name: &str) -> io::Result<Profile> {
+        let valid = !name.is_empty()
+            && name.len() <= 32
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("bad profile name `{name}`, use letters, digits, - and _"),
+            ));
+        }
+
+        let dir = data_dir().join("profiles").join(name);
+        fs::create_dir_all(dir.join("saves"))?;
+        Ok(Profile {
+            name: name.to_string(),
+            dir,
+        })
+    }
+
+    pub fn list() -> io::Result<Vec<String>> {
+        let mut names = Vec::new();
+        match fs::read_dir(data_dir().join("profiles")) {
+            Ok(entries) => {
+                for entry in entries {
+                    let entry = entry?;
+                    if entry.file_type()?.is_dir() {
+                        names.push(entry.file_name().to_string_lossy().into_owned());
+                    }
+                }
+            }
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e),
+        }
+        names.sort();
+        Ok(names)
+    }
+
+    fn read(&self, file: &str) -> io::Result<String> {
+        match fs::read_to_string(self.dir.join(file)) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(String::new()),
+            other => other,
+        }
+    }
+
+    /// The overrides in `config.txt` as command line options, see `parse_config`.
+    pub fn config_args(&self) -> io::Result<Vec<String>> {
+        Ok(parse_config(&self.read("config.txt")?))
+    }
+
+    /// Set `key = value` in `config.txt`, in place of what it was if it was
+    /// there, leaving the rest of the file as it is.
+    pub fn set_config(&self, key: &str, value: &str) -> io::Result<()> {
+        let text = self.read("config.txt")?;
+        let line = format!("{key} = {value}");
+        let mut found = false;
+        let mut lines: Vec<String> = text
+            .lines()
+            .map(|l| {
+                let name = l.split_once('=').map_or(l, |(name, _)| name).trim();
+                if name == key && !found {
+                    found = true;
+                    line.clone()
+                } else {
+                    l.to_string()
+                }
+            })
+            .collect();
+        if !found {
+            lines.push(line);
+        }
+        fs::write(self.dir.join("config.txt"), lines.join("\n") + "\n")
+    }
+
+    /// Write `text` to `file`, keeping a copy of what's there if it's in an
+    /// older version of `format`.
+    fn write(&self, file: &str, format: &Format, text: &str) -> io::Result<()> {
+        let path = self.dir.join(file);
+        let old = self.read(file)?;
+        if !old.is_empty()
+            && let Ok(version) = format.version_of(&old)
+            && version < format.version()
+        {
+            let backup = self.dir.join(format!("{file}.v{version}.bak"));
+            if !backup.exists() {
+                fs::write(backup, &old)?;
+            }
+        }
+        fs::write(path, text)
+    }
+
+    /// `file` without its signature line, and whether that matched. There's
+    /// nothing to fake in a file that isn't there.
+    fn read_signed(&self, file: &str) -> io::Result<Signed<String>> {
+        // First, making it signs the file if it's from before signatures
+        let key = install_key()?;
+        let text = self.read(file)?;
+        if text.is_empty() {
+            return Ok(Signed {
+                value: text,
+                verified: true,
+            });
+        }
+        let signed = verify(&key, &text);
+        Ok(Signed {
+            value: signed.value.to_string(),
+            verified: signed.verified,
+        })
+    }
+
+    /// Like `write`, with a signature unless what's in it can't be vouched for.
+    fn write_signed(
+        &self,
+        file: &str,
+        format: &Format,
+        body: &str,
+        signed: bool,
+    ) -> io::Result<()> {
+        let text = format.write(body);
+        let text = if signed {
+            sign(&install_key()?, &text)
+        } else {
+            text
+        };
+        self.write(file, format, &text)
+    }
+
+    // Sign the scores and stats from before signatures as they are, see
+    // `install_key`. One with a signature line, or in a version only written
+    // since, isn't: whichever key signed it, a new one doesn't vouch for it.
+    fn sign_files(&self, key: &[u8]) -> io::Result<()> {
+        for (file, format, signed_since) in [
+            ("scores.txt", &SCORES, SCORES_SIGNED_SINCE),
+            ("stats.txt", &STATS, STATS_SIGNED_SINCE),
+        ] {
+            let text = self.read(file)?;
+            let unsigned = verify(key, &text).value.len() == text.len();
+            if !text.is_empty()
+                && unsigned
+                && format.version_of(&text).is_ok_and(|v| v < signed_since)
+            {
+                self.write(file, format, &sign(key, &text))?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn high_scores(&self) -> io::Result<Signed<Vec<RunRecord>>> {
+        let text = self.read_signed("scores.txt")?;
+        Ok(Signed {
+            value: parse_high_scores(&text.value).map_err(invalid_data)?,
+            verified: text.verified,
+        })
+    }
+
+    pub fn stats(&self) -> io::Result<Signed<Stats>> {
+        let text = self.read_signed("stats.txt")?;
+        Ok(Signed {
+            value: parse_stats(&text.value).map_err(invalid_data)?,
+            verified: text.verified,
+        })
+    }
+
+    /// Add a finished run to the high scores and lifetime stats, `cause` is
+    /// what ended it. The achievements it earned for the first time come
+    /// back.
+    pub fn record_run(&self, run: &RunRecord, cause: DeathCause) -> io::Result<Vec<Achievement>> {
+        // Both read before anything is written, so one unreadable file
+        // doesn't leave the other half updated
+        let Signed {
+            value: mut scores,
+            verified: scores_verified,
+        } = self.high_scores()?;
+        let Signed {
+            value: mut stats,
+            verified: stats_verified,
+        } = self.stats()?;
+
+        // A file that didn't verify stays unsigned, a real run on top
+        // doesn't vouch for what was already in it
+        let text = add_high_score(&mut scores, run);
+        self.write_signed("scores.txt", &SCORES, &text, scores_verified)?;
+
+        // The files can be edited by hand, so no trusting the numbers
+        stats.games = stats.games.saturating_add(1);
+        stats.total_score = stats.total_score.saturating_add(u64::from(run.score));
+        stats.best_score = stats.best_score.max(run.score);
+        stats.seconds_played = stats.seconds_played.saturating_add(run.seconds);
+        stats.longest_snake = stats.longest_snake.max(run.length);
+        let died = &mut stats.deaths[cause as usize];
+        *died = died.saturating_add(1);
+        let mut text = format!(
+            "games {}\ntotal_score {}\nbest_score {}\nseconds_played {}\nlongest_snake {}\n",
+            stats.games,
+            stats.total_score,
+            stats.best_score,
+            stats.seconds_played,
+            stats.longest_snake
+        );
+        for (cause, n) in DeathCause::ALL.into_iter().zip(stats.deaths) {
+            text.push_str(&format!("died_{} {n}\n", cause.name()));
+        }
+        self.write_signed("stats.txt", &STATS, &text, stats_verified)?;
+        self.earn_achievements(run, &stats, stats_verified)
+    }
+
+    /// The achievements earned so far and when, in the order they were.
+    pub fn achievements(&self) -> io::Result<Signed<Vec<(Achievement, u64)>>> {
+        let text = self.read_signed("achievements.txt")?;
+        Ok(Signed {
+            value: parse_achievements(&text.value).map_err(invalid_data)?,
+            verified: text.verified,
+        })
+    }
+
+    // Add the ones `run` earns that weren't yet, and only those. Earned on
+    // stats that didn't verify they can't be vouched for either.
+    fn earn_achievements(
+        &self,
+        run: &RunRecord,
+        stats: &Stats,
+        stats_verified: bool,
+    ) -> io::Result<Vec<Achievement>> {
+        let Signed {
+            value: mut earned,
+            verified,
+        } = self.achievements()?;
+        let new: Vec<Achievement> = Achievement::ALL
+            .into_iter()
+            .filter(|a| a.earned(run, stats) && !earned.iter().any(|(e, _)| e == a))
+            .collect();
+        if new.is_empty() {
+            return Ok(new);
+        }
+        earned.extend(new.iter().map(|&a| (a, run.when)));
+        let text: String = earned
+            .iter()
+            .map(|(a, when)| format!("{} {when}\n", a.name()))
+            .collect();
+        let signed = verified && stats_verified;
+        self.write_signed("achievements.txt", &ACHIEVEMENTS, &text, signed)?;
+        Ok(new)
+    }
+
+    /// The best `--weekly` runs of `week`, see `weekly.rs`.
+    pub fn weekly_scores(&self, week: Week) -> io::Result<Signed<Vec<RunRecord>>> {
+        let text = self.read_signed(&Profile::weekly_file(week))?;
+        Ok(Signed {
+            value: parse_high_scores(&text.value).map_err(invalid_data)?,
+            verified: text.verified,
+        })
+    }
+
+    /// Add a `--weekly` run to its week's leaderboard, it goes through
+    /// `record_run` as well.
+    pub fn record_weekly(&self, week: Week, run: &RunRecord) -> io::Result<()> {
+        let Signed {
+            value: mut scores,
+            verified,
+        } = self.weekly_scores(week)?;
+        let text = add_high_score(&mut scores, run);
+        self.write_signed(&Profile::weekly_file(week), &SCORES, &text, verified)
+    }
+
+    fn weekly_file(week: Week) -> String {
+        format!("weekly-{week}.txt")
+    }
+
+    /// The best score on each level played, by the level's name.
+    pub fn level_scores(&self) -> io::Result<Signed<Vec<(String, u32)>>> {
+        let text = self.read_signed("levels.txt")?;
+        Ok(Signed {
+            value: parse_level_scores(&text.value).map_err(invalid_data)?,
+            verified: text.verified,
+        })
+    }
+
+    /// `score` on the level called `level`, kept if it's the best there.
+    pub fn record_level_score(&self, level: &str, score: u32) -> io::Result<()> {
+        let Signed {
+            value: mut scores,
+            verified,
+        } = self.level_scores()?;
+        match scores.iter_mut().find(|(name, _)| name == level) {
+            Some((_, best)) if *best >= score => return Ok(()),
+            Some((_, best)) => *best = score,
+            None => scores.push((level.to_string(), score)),
+        }
+        let text: String = scores
+            .iter()
+            .map(|(name, best)| format!("{best} {name}\n"))
+            .collect();
+        self.write_signed("levels.txt", &LEVEL_SCORES, &text, verified)
+    }
+
+    fn save_file(slot: u8) -> String {
+        format!("saves/slot{slot}.txt")
+    }
+
+    pub fn save_path(&self, slot: u8) -> PathBuf {
+        self.dir.join(Profile::save_file(slot))
+    }
+
+    pub fn save_game(&self, slot: u8, game: &Game) -> io::Result<()> {
+        self.write(&Profile::save_file(slot), &SAVE, &save_game(game))
+    }
+
+    pub fn load_game(&self, slot: u8) -> io::Result<Game> {
+        let text = fs::read_to_string(self.save_path(slot))?;
+        load_game(&text).map_err(invalid_data)
+    }
+
+    /// Keep `game` in `AUTOSAVE_SLOT`. It's written next to the last one and
+    /// then takes its place, dying halfway through leaves that one whole.
+    pub fn autosave(&self, game: &Game) -> io::Result<()> {
+        let path = self.save_path(AUTOSAVE_SLOT);
+        let temp = path.with_extension("tmp");
+        fs::write(&temp, save_game(game))?;
+        fs::rename(temp, path)
+    }
+
+    /// The game the last run left in `AUTOSAVE_SLOT` when it didn't end
+    /// cleanly. One that can't be read anymore is thrown away.
+    pub fn interrupted_game(&self) -> Option<Game> {
+        if !self.save_path(AUTOSAVE_SLOT).exists() {
+            return None;
+        }
+        let game = self.load_game(AUTOSAVE_SLOT);
+        if game.is_err() {
+            let _ = self.clear_autosave();
+        }
+        game.ok()
+    }
+
+    /// The run ended cleanly, there's nothing to resume.
+    pub fn clear_autosave(&self) -> io::Result<()> {
+        match fs::remove_file(self.save_path(AUTOSAVE_SLOT)) {
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            other => other,
+        }
+    }
+}
+
+/// `key = value` lines as `--key value`, a bare `key` as `--key`, `#` comments.
+pub fn parse_config(text: &str) -> Vec<String> {
+    let mut args = Vec::new();
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+        match line.split_once('=') {
+            Some((key, value)) => {
+                args.push(format!("--{}", key.trim()));
+                args.push(value.trim().to_string());
+            }
+            None => args.push(format!("--{line}")),
+        }
+    }
+    args
+}
+
+fn invalid_data(error: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+// `run` in its place among `scores`, and the lines of the file they make
+fn add_high_score(scores: &mut Vec<RunRecord>, run: &RunRecord) -> String {
+    scores.push(run.clone());
+    scores.sort_by(|a, b| b.score.cmp(&a.score).then(a.when.cmp(&b.when)));
+    scores.truncate(MAX_HIGH_SCORES);
+    scores
+        .iter()
+        .map(|r| {
+            format!(
+                "{} {} {} {} {} {} {} {}\n",
+                r.score,
+                r.length,
+                r.seconds,
+                r.mode,
+                r.when,
+                r.modifiers,
+                if r.custom_speed { "custom" } else { "usual" },
+                if r.assisted { "assisted" } else { "unassisted" }
+            )
+        })
+        .collect()
+}
+
+/// `scores.txt`, lines that don't make sense are skipped.
+pub fn parse_high_scores(text: &str) -> Result<Vec<RunRecord>, String> {
+    let text = SCORES.upgrade(text)?;
+    let mut scores = Vec::new();
+    for line in text.lines() {
+        let parts: Vec<&str> = line.split_whitespace().collect();
+        if let [score, length, seconds, mode, when, modifiers, speed, help] = parts[..]
+            && let (Ok(score), Ok(length), Ok(seconds), Ok(when)) =
+                (score.parse(), length.parse(), seconds.parse(), when.parse())
+        {
+            scores.push(RunRecord {
+                score,
+                length,
+                seconds,
+                mode: mode.to_string(),
+                when,
+                modifiers: modifiers.to_string(),
+                custom_speed: speed == "custom",
+                assisted: help == "assisted",
+            });
+        }
+    }
+    Ok(scores)
+}
+
+// Runs before version 2 had no modifiers
+fn add_modifiers(body: &str) -> Result<String, String> {
+    Ok(body
+        .lines()
+        .map(|line| {
+            if line.trim().is_empty() {
+                format!("{line}\n")
+            } else {
+                format!("{line} none\n")
+            }
+        })
+        .collect())
+}
+
+// Runs before version 3 were all played at the usual speed
+fn add_speed(body: &str) -> Result<String, String> {
+    Ok(body
+        .lines()
+        .map(|line| {
+            if line.trim().is_empty() {
+                format!("{line}\n")
+            } else {
+                format!("{line} usual\n")
+            }
+        })
+        .collect())
+}
+
+// Runs before version 4 didn't keep whether they had help, they go as
+// played without
+fn add_assisted(body: &str) -> Result<String, String> {
+    Ok(body
+        .lines()
+        .map(|line| {
+            if line.trim().is_empty() {
+                format!("{line}\n")
+            } else {
+                format!("{line} unassisted\n")
+            }
+        })
+        .collect())
+}
+
+/// `levels.txt`, a score and a level name a line, broken lines are skipped.
+pub fn parse_level_scores(text: &str) -> Result<Vec<(String, u32)>, String> {
+    let text = LEVEL_SCORES.upgrade(text)?;
+    Ok(text
+        .lines()
+        .filter_map(|line| {
+            let (score, name) = line.split_once(' ')?;
+            Some((name.trim().to_string(), score.parse().ok()?))
+        })
+        .filter(|(name, _)| !name.is_empty())
+        .collect())
+}
+
+/// `achievements.txt`, a name and when it was earned a line, broken lines
+/// and names this version doesn't know are skipped.
+pub fn parse_achievements(text: &str) -> Result<Vec<(Achievement, u64)>, String> {
+    let text = ACHIEVEMENTS.upgrade(text)?;
+    Ok(text
+        .lines()
+        .filter_map(|line| {
+            let (name, when) = line.split_once(' ')?;
+            Some((Achievement::from_name(name)?, when.trim().parse().ok()?))
+        })
+        .collect())
+}
+
+/// `stats.txt`, anything missing or broken counts as zero.
+pub fn parse_stats(text: &str) -> Result<Stats, String> {
+    let text = STATS.upgrade(text)?;
+    let mut stats = Stats::default();
+    for line in text.lines() {
+        let Some((key, value)) = line.split_once(' ') else {
+            continue;
+        };
+        let value = value.trim();
+        match key {
+            "games" => stats.games = value.parse().unwrap_or(0),
+            "total_score" => stats.total_score = value.parse().unwrap_or(0),
+            "best_score" => stats.best_score = value.parse().unwrap_or(0),
+            "seconds_played" => stats.seconds_played = value.parse().unwrap_or(0),
+            "longest_snake" => stats.longest_snake = value.parse().unwrap_or(0),
+            _ => {
+                if let Some(cause) = key.strip_prefix("died_").and_then(DeathCause::from_name) {
+                    stats.deaths[cause as usize] = value.parse().unwrap_or(0);
+                }
+            }
+        }
+    }
+    Ok(stats)
+}
@@ -0,0 +1,89 @@
+// -- Playing over stdin and stdout
+//
+// `snake2 --io-protocol` has no screen at all, a program on the other end of
+// a pipe plays, in whatever language it's written. Every tick snake2 writes
+// the board as one line of JSON and waits for one line back:
+//
+//     up, down, left, right   turn and tick (u, d, l, r work too)
+//     (empty line) or none    tick without turning
+//     quit                    stop here
+//
+// The first line goes out before any move. After the snake dies there's one
+// last line with `"over":true` and snake2 exits. Anything else gets an
+// `{"error":...}` line back and doesn't tick. There's no clock, the game
+// waits for the other side as long as it takes. `heads` is hydra mode's
+// other snakes, each a body like `snake`, and empty in every other mode.
+
+use crate::game::{Game, TickOutcome};
+use crate::json;
+use crate::snake::SnakeDirection;
+use std::io::{self, BufRead, Write};
+
+/// The board as one line of JSON, `outcome` is what the last tick did.
+pub fn state_json(game: &Game, outcome: Option<TickOutcome>) -> String {
+    let snake = json::array(game.snake.body.iter().map(|p| format!("[{},{}]", p.x, p.y)));
+    let heads = json::array(
+        game.heads
+            .iter()
+            .map(|head| json::array(head.body.iter().map(|p| format!("[{},{}]", p.x, p.y)))),
+    );
+    let entities = json::array(game.entities.iter().map(|e| {
+        format!(
+            r#"{{"kind":{},"x":{},"y":{}}}"#,
+            json::string(&e.kind.name()),
+            e.x,
+            e.y
+        )
+    }));
+    let outcome = match outcome {
+        None => "start",
+        Some(TickOutcome::Moved) => "moved",
+        Some(TickOutcome::Ate) => "ate",
+        Some(TickOutcome::Died) => "died",
+    };
+    format!(
+        r#"{{"tick":{},"cols":{},"rows":{},"mode":{},"score":{},"level":{},"direction":{},"snake":{},"heads":{},"entities":{},"outcome":{},"over":{}}}"#,
+        game.ticks,
+        game.cols,
+        game.rows,
+        json::string(game.mode.name()),
+        game.score,
+        game.level,
+        json::string(game.snake.direction.name()),
+        snake,
+        heads,
+        entities,
+        json::string(outcome),
+        outcome == "died"
+    )
+}
+
+/// Play `game` with moves from `input` until the snake dies, the other side
+/// says `quit` or hangs up.
+pub fn run(game: &mut Game, input: impl BufRead, mut output: impl Write) -> io::Result<()> {
+    writeln!(output, "{}", state_json(game, None))?;
+    output.flush()?;
+    for line in input.lines() {
+        let line = line?;
+        match line.trim() {
+            "quit" => break,
+            "" | "none" => {}
+            command => match SnakeDirection::from_name(command) {
+                Some(direction) => game.turn(direction),
+                None => {
+                    let error = format!("unknown command `{command}`");
+                    writeln!(output, r#"{{"error":{}}}"#, json::string(&error))?;
+                    output.flush()?;
+                    continue;
+                }
+            },
+        }
+        let outcome = game.tick();
+        writeln!(output, "{}", state_json(game, Some(outcome)))?;
+        output.flush()?;
+        if outcome == TickOutcome::Died {
+            break;
+        }
+    }
+    Ok(())
+}
@@ -0,0 +1,161 @@
+// -- Python bindings
+//
+// With the `python` feature the crate builds into a Python module (`maturin
+// develop --release`, see `pyproject.toml`) with the headless engine and
+// `SnakeEnv`, for scripting and training against the same game the terminal
+// plays:
+//
+//     import snake2
+//     env = snake2.SnakeEnv(cols=20, rows=20, encoding="features")
+//     observation = env.reset(seed=1)
+//     observation, reward, done = env.step(3)
+//
+// Both classes hold rules that can't move between threads, so Python keeps
+// each one on the thread that made it.
+
+use crate::env::{Encoding, EnvConfig, SnakeEnv};
+use crate::game::{Game, GameOptions, TickOutcome};
+use crate::protocol::state_json;
+use crate::rules::Mode;
+use crate::snake::SnakeDirection;
+use pyo3::exceptions::PyValueError;
+use pyo3::prelude::*;
+
+fn options(mode: &str, seed: Option<u64>) -> PyResult<GameOptions> {
+    Ok(GameOptions {
+        mode: mode.parse::<Mode>().map_err(PyValueError::new_err)?,
+        seed,
+        ..Default::default()
+    })
+}
+
+/// A game with nothing drawn, ticked by hand.
+#[pyclass(name = "Game", unsendable)]
+pub struct PyGame {
+    game: Game,
+    // What the last tick did, for `state`
+    last: Option<TickOutcome>,
+}
+
+#[pymethods]
+impl PyGame {
+    #[new]
+    #[pyo3(signature = (cols = 40, rows = 20, mode = "classic", seed = None))]
+    fn new(cols: u16, rows: u16, mode: &str, seed: Option<u64>) -> PyResult<Self> {
+        if cols < 2 || rows < 2 {
+            return Err(PyValueError::new_err("the board needs at least 2x2 cells"));
+        }
+        let options = options(mode, seed)?;
+        Ok(PyGame {
+            game: Game::with_options(cols, rows, SnakeDirection::Right, &options),
+            last: None,
+        })
+    }
+
+    /// Turn `"up"`, `"down"`, `"left"` or `"right"` on the next tick.
+    fn turn(&mut self, direction: &str) -> PyResult<()> {
+        let direction = SnakeDirection::from_name(direction)
+            .ok_or_else(|| PyValueError::new_err(format!("unknown direction `{direction}`")))?;
+        self.game.turn(direction);
+        Ok(())
+    }
+
+    /// `"moved"`, `"ate"` or `"died"`.
+    fn tick(&mut self) -> &'static str {
+        let outcome = self.game.tick();
+        self.last = Some(outcome);
+        match outcome {
+            TickOutcome::Moved => "moved",
+            TickOutcome::Ate => "ate",
+            TickOutcome::Died => "died",
+        }
+    }
+
+    /// The board as JSON, the same as `--io-protocol` writes.
+    fn state(&self) -> String {
+        state_json(&self.game, self.last)
+    }
+
+    /// The snake from the head back, as `(x, y)`.
+    fn snake(&self) -> Vec<(u16, u16)> {
+        self.game.snake.body.iter().map(|p| (p.x, p.y)).collect()
+    }
+
+    fn food(&self) -> Option<(u16, u16)> {
+        self.game.food_position()
+    }
+
+    #[getter]
+    fn score(&self) -> u32 {
+        self.game.score
+    }
+
+    #[getter]
+    fn ticks(&self) -> u64 {
+        self.game.ticks
+    }
+
+    #[getter]
+    fn seed(&self) -> u64 {
+        self.game.seed
+    }
+}
+
+/// `SnakeEnv` from Python, see `env.rs`.
+#[pyclass(name = "SnakeEnv", unsendable)]
+pub struct PySnakeEnv {
+    env: SnakeEnv,
+}
+
+#[pymethods]
+impl PySnakeEnv {
+    #[new]
+    #[pyo3(signature = (cols = 20, rows = 20, encoding = "grid", mode = "classic", max_steps = 10_000))]
+    fn new(cols: u16, rows: u16, encoding: &str, mode: &str, max_steps: u64) -> PyResult<Self> {
+        if cols < 2 || rows < 2 {
+            return Err(PyValueError::new_err("the board needs at least 2x2 cells"));
+        }
+        let encoding = match encoding {
+            "grid" => Encoding::Grid,
+            "features" => Encoding::Features,
+            other => {
+                return Err(PyValueError::new_err(format!(
+                    "unknown encoding `{other}`, `grid` or `features`"
+                )));
+            }
+        };
+        Ok(PySnakeEnv {
+            env: SnakeEnv::new(EnvConfig {
+                cols,
+                rows,
+                options: options(mode, None)?,
+                encoding,
+                max_steps,
+            }),
+        })
+    }
+
+    #[pyo3(signature = (seed = None))]
+    fn reset(&mut self, seed: Option<u64>) -> Vec<f32> {
+        self.env.reset(seed)
+    }
+
+    /// `(observation, reward, done)`, actions are 0 to 3 for up, down, left
+    /// and right.
+    fn step(&mut self, action: usize) -> (Vec<f32>, f32, bool) {
+        let step = self.env.step(action);
+        (step.observation, step.reward, step.done)
+    }
+
+    #[getter]
+    fn observation_len(&self) -> usize {
+        self.env.observation_len()
+    }
+}
+
+#[pymodule]
+fn snake2(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyGame>()?;
+    module.add_class::<PySnakeEnv>()?;
+    Ok(())
+}
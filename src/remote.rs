@@ -0,0 +1,546 @@
+// -- Playing on a game server
+//
+// `snake2 connect ws://host:9000` plays a game on `snake2 server` (see
+// `server.rs`) from the terminal. The server's game is the one that counts:
+// it ticks, and every tick sends the board. The first message also has the
+// game's challenge code, so the client sets up the very same game and
+// follows along, ticking its copy with the turns each message says the
+// server took in. Both heads are compared every tick, a server playing some
+// other snake2 shows up right away.
+//
+// A `ping` goes out every second and the server sends it back, the round
+// trip shows in the status bar, in red past `SLOW`.
+//
+// With `--predict` what's drawn isn't the server's last tick but a guess at
+// where the game is by now: the copy, with the turns the server hasn't taken
+// in yet, ticked ahead by half a round trip and however long it's been since
+// that tick. A turn shows up right away instead of a round trip later. When
+// the server's next tick comes in the guess is made again on top of it, so
+// a wrong one only lasts until then.
+//
+// A connection lost before the game is over is tried again every
+// `RETRY_EVERY` with the game's session (see `server.rs`), and the game goes
+// on from the server's save of it. After `RECONNECT_FOR`, or if the server
+// doesn't have the game anymore, the client takes over: the copy it kept is
+// the same game, so it goes on ticking it here, where the server left off.
+//
+// Enter opens a line at the bottom of the chat panel, right of the board:
+// keys go into it instead of steering until Enter sends it or Esc closes
+// it. What anyone on the server says (see `server.rs`) scrolls up the panel,
+// the newest at the bottom. Too narrow a terminal has no panel but the line.
+
+use crate::backend::Backend;
+use crate::challenge::Challenge;
+use crate::challenge::decode_base64;
+use crate::game::{Game, TickOutcome};
+use crate::input::next_event;
+use crate::input::{IDLE_WAIT, Input, next_input};
+use crate::json;
+use crate::layout::Rect;
+use crate::render::{StatusBar, draw_game, draw_overlay};
+use crate::save::{load_game_binary, save_game_binary};
+use crate::server::{CHAT_LENGTH, CLOSE, TEXT, accept_key, read_frame, write_client_frame};
+use crate::snake::SnakeDirection;
+use crate::terminal::{
+    CrosstermBackend, base64, disable_game_mode, enable_game_mode, setup_panic_hook, suspend,
+    take_resumed, watch_job_control,
+};
+use crate::theme::Theme;
+use crossterm::event::{Event, KeyCode};
+use std::collections::VecDeque;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+const PING_EVERY: Duration = Duration::from_secs(1);
+// A round trip this long is shown in red
+const SLOW: Duration = Duration::from_millis(150);
+// Ticks a guess goes ahead of the server at most
+const MAX_LEAD: u64 = 5;
+// A board as JSON, with room for a very long snake
+const MAX_STATE: u64 = 1024 * 1024;
+// How often the guess is drawn again
+const FRAME: Duration = Duration::from_micros(16_667);
+const HINTS: &str = "Enter chat  q quit";
+const RETRY_EVERY: Duration = Duration::from_millis(500);
+// Trying to get back on the server this long before playing on without it
+const RECONNECT_FOR: Duration = Duration::from_secs(10);
+// For the server to answer, a connection or its first message
+const CONNECT_WAIT: Duration = Duration::from_secs(2);
+// Chat messages kept for the panel
+const CHAT_LINES: usize = 50;
+// Narrower than this there's no chat panel
+const MIN_PANEL: u16 = 16;
+
+enum Incoming {
+    Text(String),
+    Closed,
+}
+
+// Where turns go out and a thread hands over what comes in
+type Connection = (TcpStream, Receiver<Incoming>);
+
+// Who's ticking the game
+#[derive(Clone, Copy)]
+enum Link {
+    Server,
+    // Lost at `since`, to be tried again at `next_try`
+    Reconnecting { since: Instant, next_try: Instant },
+    // The server is gone, the copy is the game now
+    Local,
+}
+
+pub fn run(url: &str, predict: bool, theme: &Theme) -> io::Result<()> {
+    let (mut stream, incoming) = connect(url)?;
+    let hello = match incoming.recv() {
+        Ok(Incoming::Text(text)) => text,
+        _ => return Err(broken("the server hung up right away")),
+    };
+    let challenge: Challenge = json::field(&hello, "challenge")
+        .and_then(json::unquote)
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| broken("the server didn't say which game it plays"))?;
+    let mut game = Game::with_options(
+        challenge.cols,
+        challenge.rows,
+        SnakeDirection::Right,
+        &challenge.options(),
+    );
+    check_head(&game, &hello)?;
+    let session = json::field(&hello, "session")
+        .and_then(json::unquote)
+        .map(str::to_string);
+
+    setup_panic_hook();
+    watch_job_control();
+    let mut stdout = io::stdout();
+    enable_game_mode(&mut stdout)?;
+    let mut out = CrosstermBackend::new(stdout);
+    let (cols, rows) = crossterm::terminal::size()?;
+    let mut screen_area = Rect::screen(cols, rows);
+
+    let started = Instant::now();
+    let mut next_ping = started;
+    let mut round_trip: Option<Duration> = None;
+    // Every turn sent, the server has taken in the first `taken`
+    let mut sent: Vec<SnakeDirection> = Vec::new();
+    let mut taken = 0;
+    let mut last_tick = Instant::now();
+    let mut over = false;
+    let mut chat = ChatPanel::default();
+    let mut link = Link::Server;
+    // Without a session there's no coming back
+    let lost = |now: Instant| match session {
+        Some(_) => Link::Reconnecting {
+            since: now,
+            next_try: now,
+        },
+        None => Link::Local,
+    };
+    let mut incoming = incoming;
+
+    let played = (|| -> io::Result<()> {
+        loop {
+            take_resumed(&mut out.stdout)?;
+            let now = Instant::now();
+            let online = matches!(link, Link::Server);
+            if online && !over && now >= next_ping {
+                let ping = format!("ping {}", now.duration_since(started).as_millis());
+                if write_client_frame(&mut stream, TEXT, ping.as_bytes()).is_err() {
+                    link = lost(now);
+                }
+                next_ping = now + PING_EVERY;
+            }
+
+            for message in incoming.try_iter() {
+                let text = match message {
+                    Incoming::Text(text) => text,
+                    Incoming::Closed if over => continue,
+                    Incoming::Closed => {
+                        link = lost(now);
+                        break;
+                    }
+                };
+                if chat.take(&text) {
+                    continue;
+                }
+                if let Some(sent_at) = text.strip_prefix("pong ") {
+                    if let Ok(ms) = sent_at.trim().parse() {
+                        let sent_at = started + Duration::from_millis(ms);
+                        round_trip = Some(now.saturating_duration_since(sent_at));
+                    }
+                    continue;
+                }
+                // The turns the server took in since the last tick, in the
+                // order they were sent, then the tick, like on the server
+                let now_taken = json::field(&text, "turns")
+                    .and_then(|n| n.parse().ok())
+                    .filter(|&n| n >= taken && n <= sent.len())
+                    .ok_or_else(|| broken("a message from the server without its turns"))?;
+                for &direction in &sent[taken..now_taken] {
+                    game.turn(direction);
+                }
+                taken = now_taken;
+                over = game.tick() == TickOutcome::Died;
+                check_head(&game, &text)?;
+                last_tick = now;
+            }
+
+            match (link, &session) {
+                (Link::Reconnecting { since, next_try }, Some(session)) if now >= next_try => {
+                    link = match resume(url, session) {
+                        Ok(Some(resumed)) => {
+                            ((stream, incoming), game, taken) = resumed;
+                            // What the server never got is lost with the connection
+                            sent.truncate(taken);
+                            last_tick = now;
+                            round_trip = None;
+                            Link::Server
+                        }
+                        Ok(None) => Link::Local,
+                        Err(_) if now.duration_since(since) >= RECONNECT_FOR => Link::Local,
+                        Err(_) => Link::Reconnecting {
+                            since,
+                            next_try: now + RETRY_EVERY,
+                        },
+                    };
+                    // Taking over where the game waited, not catching up, with
+                    // the turns the server never said it took in
+                    if matches!(link, Link::Local) {
+                        for &direction in &sent[taken..] {
+                            game.turn(direction);
+                        }
+                        last_tick = now;
+                    }
+                }
+                (Link::Local, _)
+                    if !over && now.duration_since(last_tick).as_millis() >= game.timer.into() =>
+                {
+                    over = game.tick() == TickOutcome::Died;
+                    last_tick += Duration::from_millis(game.timer);
+                }
+                _ => {}
+            }
+
+            let online = matches!(link, Link::Server);
+            let guess = (predict && online && !over).then(|| {
+                let half_trip = round_trip.unwrap_or_default() / 2;
+                let ahead = (half_trip + now.duration_since(last_tick)).as_millis()
+                    / u128::from(game.timer.max(1));
+                guess(&game, &sent[taken..], (ahead as u64).min(MAX_LEAD))
+            });
+            let shown = guess.as_ref().unwrap_or(&game);
+
+            out.clear()?;
+            let (board_area, status_area) = screen_area.split_bottom(1);
+            draw_game(&mut out, shown, theme)?;
+            if over {
+                let lines = [
+                    format!("Score {}, length {}", game.score, game.snake.body.len()),
+                    "q quits".to_string(),
+                ];
+                draw_overlay(&mut out, "Game over", &lines, board_area, theme)?;
+            }
+            let mode = match link {
+                Link::Server if predict => "remote, predicted",
+                Link::Server => "remote",
+                Link::Reconnecting { .. } => "reconnecting",
+                Link::Local => "local, server gone",
+            };
+            let status = StatusBar {
+                mode,
+                hints: HINTS,
+                elapsed: None,
+                rival: None,
+            };
+            status.draw(&mut out, Some(shown), status_area, theme)?;
+            if let Some(round_trip) = round_trip.filter(|_| online) {
+                let text = format!(" {}ms ", round_trip.as_millis());
+                let style = if round_trip >= SLOW {
+                    theme.lag()
+                } else {
+                    theme.status_bar()
+                };
+                // Just left of the hints
+                let x = status_area
+                    .right()
+                    .saturating_sub((text.len() + HINTS.len() + 1) as u16);
+                out.print(x, status_area.y, &text, style)?;
+            }
+            let panel = screen_area
+                .split_bottom(1)
+                .0
+                .split_right(screen_area.width.saturating_sub(game.cols + 1))
+                .1;
+            chat.draw(&mut out, panel, status_area, theme)?;
+            out.flush()?;
+
+            // Messages can't wake this up, it looks again every frame
+            let timeout = if over { IDLE_WAIT } else { FRAME };
+            if let Some(line) = &mut chat.typing {
+                match next_event(timeout)? {
+                    Some(Event::Key(key)) => match key.code {
+                        KeyCode::Enter => {
+                            if online && !line.trim().is_empty() {
+                                let say = format!("say {line}");
+                                if write_client_frame(&mut stream, TEXT, say.as_bytes()).is_err() {
+                                    link = lost(now);
+                                }
+                            }
+                            chat.typing = None;
+                        }
+                        KeyCode::Esc => chat.typing = None,
+                        KeyCode::Backspace => {
+                            line.pop();
+                        }
+                        KeyCode::Char(c) if line.chars().count() < CHAT_LENGTH => line.push(c),
+                        _ => {}
+                    },
+                    Some(Event::Resize(cols, rows)) => screen_area = Rect::screen(cols, rows),
+                    _ => {}
+                }
+                continue;
+            }
+            match next_input(timeout)? {
+                Some(Input::Quit) => return Ok(()),
+                Some(Input::Resize(cols, rows)) => screen_area = Rect::screen(cols, rows),
+                Some(Input::Suspend) => suspend(&mut out.stdout)?,
+                Some(Input::Chat) => chat.typing = Some(String::new()),
+                Some(Input::Turn(direction)) if !over => match link {
+                    Link::Server => {
+                        if write_client_frame(&mut stream, TEXT, direction.name().as_bytes())
+                            .is_err()
+                        {
+                            link = lost(now);
+                        }
+                        sent.push(direction);
+                    }
+                    // Nobody to send it to yet, the game is waiting anyway
+                    Link::Reconnecting { .. } => {}
+                    Link::Local => game.turn(direction),
+                },
+                _ => {}
+            }
+        }
+    })();
+
+    let _ = write_client_frame(&mut stream, CLOSE, &[]);
+    disable_game_mode(&mut out.stdout)?;
+    played?;
+    println!(
+        "\tScore {}, length {}.\n",
+        game.score,
+        game.snake.body.len()
+    );
+    Ok(())
+}
+
+#[derive(Default)]
+struct ChatPanel {
+    lines: VecDeque<String>,
+    // What's being written, while the chat line is open
+    typing: Option<String>,
+}
+
+impl ChatPanel {
+    /// Keep `message` if it's chat, true if it was.
+    fn take(&mut self, message: &str) -> bool {
+        let Some(chat) = json::field(message, "chat") else {
+            return false;
+        };
+        let from = json::field(chat, "from").unwrap_or("?");
+        let text = json::field(chat, "text")
+            .and_then(json::unquote)
+            .unwrap_or("");
+        self.lines.push_back(format!("{from}: {text}"));
+        if self.lines.len() > CHAT_LINES {
+            self.lines.pop_front();
+        }
+        true
+    }
+
+    /// The messages in `area`, wrapped, the newest at the bottom, and the
+    /// open chat line over `status_area`.
+    fn draw(
+        &self,
+        out: &mut impl Backend,
+        area: Rect,
+        status_area: Rect,
+        theme: &Theme,
+    ) -> io::Result<()> {
+        if let Some(line) = &self.typing {
+            let width = status_area.width as usize;
+            // The end of it, where the cursor is
+            let shown: String = format!(" say: {line}_");
+            let skip = shown.chars().count().saturating_sub(width);
+            let shown: String = shown.chars().skip(skip).collect();
+            out.print(
+                status_area.x,
+                status_area.y,
+                &format!("{shown:<width$}"),
+                theme.status_bar(),
+            )?;
+        }
+        let area = area.inset(1);
+        if area.width < MIN_PANEL - 2 || area.height < 2 {
+            return Ok(());
+        }
+        out.print(area.x, area.y, "Chat", theme.text().bold())?;
+        let width = area.width as usize;
+        let wrapped: Vec<String> = self
+            .lines
+            .iter()
+            .flat_map(|line| {
+                let chars: Vec<char> = line.chars().collect();
+                chars
+                    .chunks(width)
+                    .map(|chunk| chunk.iter().collect())
+                    .collect::<Vec<String>>()
+            })
+            .collect();
+        let room = area.height as usize - 1;
+        let shown = &wrapped[wrapped.len().saturating_sub(room)..];
+        for (i, line) in shown.iter().enumerate() {
+            out.print(area.x, area.y + 1 + i as u16, line, theme.text())?;
+        }
+        Ok(())
+    }
+}
+
+/// Connect to `url` again and pick up the game left there under `session`:
+/// the connection, the game as the server has it and the turns it took in.
+/// `None` when the server doesn't have it anymore.
+fn resume(url: &str, session: &str) -> io::Result<Option<(Connection, Game, usize)>> {
+    let path = match url.trim_start_matches("ws://").contains('/') {
+        true => url.to_string(),
+        false => format!("{url}/"),
+    };
+    let glue = if path.contains('?') { '&' } else { '?' };
+    let (mut stream, incoming) = connect(&format!("{path}{glue}session={session}"))?;
+    let hello = match incoming.recv_timeout(CONNECT_WAIT) {
+        Ok(Incoming::Text(text)) => text,
+        Ok(Incoming::Closed) | Err(RecvTimeoutError::Disconnected) => {
+            return Err(broken("the server hung up right away"));
+        }
+        Err(RecvTimeoutError::Timeout) => return Err(io::ErrorKind::TimedOut.into()),
+    };
+    let save = json::field(&hello, "save")
+        .and_then(json::unquote)
+        .and_then(decode_base64);
+    let Some(save) = save else {
+        // A new game, not ours
+        let _ = write_client_frame(&mut stream, CLOSE, &[]);
+        return Ok(None);
+    };
+    let game = load_game_binary(&save).map_err(|e| broken(&e))?;
+    let taken = json::field(&hello, "turns")
+        .and_then(|n| n.parse().ok())
+        .ok_or_else(|| broken("a message from the server without its turns"))?;
+    Ok(Some(((stream, incoming), game, taken)))
+}
+
+/// `game` with the turns the server hasn't taken in yet, `ticks` later.
+fn guess(game: &Game, turns: &[SnakeDirection], ticks: u64) -> Game {
+    let mut guess = load_game_binary(&save_game_binary(game)).expect("a game's own save loads");
+    for &direction in turns {
+        guess.turn(direction);
+    }
+    for _ in 0..ticks {
+        // Dying is for the server to say
+        if guess.tick() == TickOutcome::Died {
+            break;
+        }
+    }
+    guess
+}
+
+// The copy has to be where the server says the game is
+fn check_head(game: &Game, state: &str) -> io::Result<()> {
+    let head = json::field(state, "snake")
+        .and_then(|snake| snake.trim_start_matches('[').split(']').next())
+        .and_then(|point| point.split_once(','))
+        .and_then(|(x, y)| Some((x.trim().parse().ok()?, y.trim().parse().ok()?)));
+    let ours = game.snake.head();
+    if head != Some((ours.x, ours.y)) {
+        return Err(broken(
+            "this game went differently on the server, is it another version of snake2?",
+        ));
+    }
+    Ok(())
+}
+
+fn broken(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Open a WebSocket to `url`, with a thread handing over what comes in.
+fn connect(url: &str) -> io::Result<Connection> {
+    let rest = url
+        .strip_prefix("ws://")
+        .ok_or_else(|| broken("only ws:// URLs work"))?;
+    let (host, path) = match rest.find('/') {
+        Some(slash) => (&rest[..slash], &rest[slash..]),
+        None => (rest, "/"),
+    };
+    let address = if host.contains(':') {
+        host.to_string()
+    } else {
+        format!("{host}:80")
+    };
+    let address = address
+        .to_socket_addrs()?
+        .next()
+        .ok_or_else(|| broken("no address for that host"))?;
+    let mut stream = TcpStream::connect_timeout(&address, CONNECT_WAIT)?;
+    // Turns go out as soon as they're pressed
+    stream.set_nodelay(true)?;
+
+    let key = base64(&rand::random::<[u8; 16]>());
+    write!(
+        stream,
+        "GET {path} HTTP/1.1\r\nHost: {host}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Key: {key}\r\nSec-WebSocket-Version: 13\r\n\r\n"
+    )?;
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut status = String::new();
+    reader.read_line(&mut status)?;
+    if status.split_whitespace().nth(1) != Some("101") {
+        return Err(broken(&format!("the server said `{}`", status.trim())));
+    }
+    let mut accepted = false;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':')
+            && name.trim().eq_ignore_ascii_case("sec-websocket-accept")
+        {
+            accepted = value.trim() == accept_key(&key);
+        }
+    }
+    if !accepted {
+        return Err(broken("not a WebSocket server"));
+    }
+
+    let (sender, incoming) = mpsc::channel();
+    thread::spawn(move || {
+        loop {
+            let message = match read_frame(&mut reader, MAX_STATE) {
+                Ok((TEXT, payload)) => Incoming::Text(String::from_utf8_lossy(&payload).into()),
+                Ok((CLOSE, _)) | Err(_) => Incoming::Closed,
+                Ok(_) => continue,
+            };
+            let closed = matches!(message, Incoming::Closed);
+            if sender.send(message).is_err() || closed {
+                return;
+            }
+        }
+    });
+    Ok((stream, incoming))
+}
@@ -0,0 +1,399 @@
+use crate::backend::{Backend, Style};
+use crate::clock::format_duration;
+use crate::entity::EntityKind;
+use crate::game::Game;
+use crate::layout::Rect;
+use crate::objective::zone_ticks_left;
+use crate::rules::{HUNGER_FULL, Modifier};
+use crate::snake::{Snake, SnakeBodyPoint, SnakeDirection};
+use crate::strategy::hamiltonian_cycle;
+use crate::theme::{Segment, Theme};
+use std::io;
+use std::time::Duration;
+
+const DIRECTIONS: [SnakeDirection; 4] = [
+    SnakeDirection::Up,
+    SnakeDirection::Down,
+    SnakeDirection::Left,
+    SnakeDirection::Right,
+];
+
+pub fn draw_game(out: &mut impl Backend, game: &Game, theme: &Theme) -> io::Result<()> {
+    let body = &game.snake.body;
+    let theme = &theme.at_level(game.level).at_length(body.len());
+    draw_grid(out, game, theme)?;
+
+    // Zones go under the snake, it goes over them
+    for entity in game.entities.iter().filter(|e| e.kind == EntityKind::Zone) {
+        if game.is_visible(entity.x, entity.y) {
+            let (glyph, style) = (
+                theme.entity_glyph(entity.kind),
+                theme.entity_style(entity.kind),
+            );
+            out.print(entity.x, entity.y, glyph, style)?;
+        }
+    }
+
+    draw_snake(out, &game.snake, theme)?;
+    for head in &game.heads {
+        draw_snake(out, head, theme)?;
+    }
+
+    // Print food, walls and the rest
+    for entity in game.entities.iter().filter(|e| e.kind != EntityKind::Zone) {
+        if !game.is_visible(entity.x, entity.y) {
+            continue;
+        }
+        out.print(
+            entity.x,
+            entity.y,
+            theme.entity_glyph(entity.kind),
+            theme.entity_style(entity.kind),
+        )?;
+    }
+
+    draw_wrap_ghost(out, game, theme)?;
+    draw_food_hint(out, game, theme)
+}
+
+/// The `--grid` dots, under everything else. A whole row is one print, the
+/// spaces in it too, so a frame with the grid costs a line a row and not a
+/// cell a dot.
+fn draw_grid(out: &mut impl Backend, game: &Game, theme: &Theme) -> io::Result<()> {
+    let Some(grid) = theme.grid else {
+        return Ok(());
+    };
+    for y in 0..game.rows {
+        let row: String = (0..game.cols)
+            .map(|x| {
+                if grid.has_dot(x, y) {
+                    theme.grid_glyph()
+                } else {
+                    " "
+                }
+            })
+            .collect();
+        out.print(0, y, &row, theme.grid())?;
+    }
+    Ok(())
+}
+
+/// The hamiltonian bot's way round the board (see `strategy.rs`) as a faint
+/// line over the free cells, a route that never runs into itself to learn
+/// from, on boards that have one. A run of free cells is one print.
+pub fn draw_cycle(out: &mut impl Backend, game: &Game, theme: &Theme) -> io::Result<()> {
+    let Some(cycle) = hamiltonian_cycle(game.cols, game.rows) else {
+        return Ok(());
+    };
+    let cols = usize::from(game.cols);
+    // Which way it comes into every cell
+    let mut came = vec![SnakeDirection::Right; cycle.len()];
+    for (i, &direction) in cycle.iter().enumerate() {
+        let from = SnakeBodyPoint {
+            x: (i % cols) as u16,
+            y: (i / cols) as u16,
+            letter: None,
+        };
+        let to = from.step(direction, game.cols, game.rows);
+        came[usize::from(to.y) * cols + usize::from(to.x)] = direction;
+    }
+    for y in 0..game.rows {
+        let mut run = String::new();
+        let mut start = 0;
+        for x in 0..=game.cols {
+            if x < game.cols && game.is_free(x, y) {
+                if run.is_empty() {
+                    start = x;
+                }
+                let i = usize::from(y) * cols + usize::from(x);
+                run.push_str(cycle_glyph(came[i], cycle[i]));
+            } else if !run.is_empty() {
+                out.print(start, y, &run, theme.grid())?;
+                run.clear();
+            }
+        }
+    }
+    Ok(())
+}
+
+// The line through a cell the cycle comes into going `came` and leaves going
+// `goes`, by the two sides of the cell it touches
+fn cycle_glyph(came: SnakeDirection, goes: SnakeDirection) -> &'static str {
+    use SnakeDirection::*;
+
+    match (came.opposite(), goes) {
+        (Left, Right) | (Right, Left) => "─",
+        (Up, Down) | (Down, Up) => "│",
+        (Left, Down) | (Down, Left) => "┐",
+        (Left, Up) | (Up, Left) => "┘",
+        (Right, Down) | (Down, Right) => "┌",
+        (Right, Up) | (Up, Right) => "└",
+        // In and out the same side, a cycle doesn't do that
+        _ => "·",
+    }
+}
+
+fn draw_snake(out: &mut impl Backend, snake: &Snake, theme: &Theme) -> io::Result<()> {
+    let body = &snake.body;
+    for i in 0..body.len() {
+        let current = &body[i];
+        let (ch, style) = if i == 0 {
+            // Head
+            (theme.head_glyph(snake.direction), theme.snake_head())
+        } else {
+            // Tail or body segment
+            let prev = &body[i - 1];
+            let ch = theme.body_glyph(if current.x == prev.x {
+                Segment::Vertical
+            } else if current.y == prev.y {
+                Segment::Horizontal
+            } else {
+                Segment::Corner
+            });
+            match current.letter {
+                Some(letter) => (theme.letter_glyph(letter), theme.snake_body().bold()),
+                None => (ch, theme.snake_body()),
+            }
+        };
+
+        out.print(current.x, current.y, ch, style)?;
+    }
+    Ok(())
+}
+
+/// With the head on an edge and heading off it, a faint head where it comes
+/// back in on the other side, a tick early, so crossing doesn't look like a
+/// jump. Only on an empty cell, anything there is more worth seeing.
+fn draw_wrap_ghost(out: &mut impl Backend, game: &Game, theme: &Theme) -> io::Result<()> {
+    let head = game.snake.head();
+    let direction = game.snake.direction;
+    let leaving = match direction {
+        SnakeDirection::Up => head.y == 0,
+        SnakeDirection::Down => head.y == game.rows - 1,
+        SnakeDirection::Left => head.x == 0,
+        SnakeDirection::Right => head.x == game.cols - 1,
+    };
+    let next = game.snake.next_head(&game.cols, &game.rows);
+    if !leaving
+        || game.snake.occupies(next.x, next.y)
+        || game.entity_at(next.x, next.y).is_some()
+        || !game.is_visible(next.x, next.y)
+    {
+        return Ok(());
+    }
+    out.print(
+        next.x,
+        next.y,
+        theme.head_glyph(direction),
+        theme.wrap_ghost(),
+    )
+}
+
+/// The head lit up, for a moment after a close call.
+pub fn draw_head_flash(out: &mut impl Backend, game: &Game, theme: &Theme) -> io::Result<()> {
+    let theme = &theme.at_level(game.level).at_length(game.snake.body.len());
+    let head = game.snake.head();
+    out.print(
+        head.x,
+        head.y,
+        theme.head_glyph(game.snake.direction),
+        theme.snake_head().reverse(),
+    )
+}
+
+/// An `X` on every cell next to the head the snake would die on if it went
+/// there next tick, for beginners.
+pub fn draw_danger(out: &mut impl Backend, game: &Game, theme: &Theme) -> io::Result<()> {
+    let head = game.snake.head();
+    for direction in DIRECTIONS {
+        let next = head.step(direction, game.cols, game.rows);
+        if game.snake.can_turn(direction) && game.is_fatal(next.x, next.y) {
+            out.print(next.x, next.y, "X", theme.fatal())?;
+        }
+    }
+    Ok(())
+}
+
+/// In the fog, an arrow on the edge of the board the way the closest food is.
+fn draw_food_hint(out: &mut impl Backend, game: &Game, theme: &Theme) -> io::Result<()> {
+    if game.view_radius().is_none() {
+        return Ok(());
+    }
+    let closest = game
+        .entities
+        .iter()
+        .filter(|e| e.kind.is_food())
+        .map(|e| game.offset_from_head(e.x, e.y))
+        .min_by_key(|(dx, dy)| dx.abs() + 2 * dy.abs());
+    let Some((dx, dy)) = closest else {
+        return Ok(());
+    };
+    let head = game.snake.head();
+    if game.is_visible(
+        (i32::from(head.x) + dx).rem_euclid(i32::from(game.cols)) as u16,
+        (i32::from(head.y) + dy).rem_euclid(i32::from(game.rows)) as u16,
+    ) {
+        return Ok(());
+    }
+
+    // Rows count double, as in `Game::is_visible`
+    let (x, y, direction) = if dx.abs() >= 2 * dy.abs() {
+        if dx > 0 {
+            (game.cols - 1, head.y, SnakeDirection::Right)
+        } else {
+            (0, head.y, SnakeDirection::Left)
+        }
+    } else if dy > 0 {
+        (head.x, game.rows - 1, SnakeDirection::Down)
+    } else {
+        (head.x, 0, SnakeDirection::Up)
+    };
+    out.print(x, y, theme.arrow_glyph(direction), theme.food())
+}
+
+/// Every cell shaded by how long the snake spent on it, see `Game::visits`.
+pub fn draw_heatmap(out: &mut impl Backend, game: &Game, theme: &Theme) -> io::Result<()> {
+    let most = game.visits.iter().copied().max().unwrap_or(0).max(1);
+    for y in 0..game.rows {
+        for x in 0..game.cols {
+            let (glyph, style) = theme.heat(game.visits_at(x, y) as f32 / most as f32);
+            out.print(x, y, glyph, style)?;
+        }
+    }
+    Ok(())
+}
+
+/// The bottom row: what's going on on the left, the keys that matter now on the right.
+pub struct StatusBar<'a> {
+    pub mode: &'a str,
+    pub hints: &'a str,
+    // Time played so far, frontends without a clock leave it out
+    pub elapsed: Option<Duration>,
+    // The score of the bot racing on the same board, see `--race`
+    pub rival: Option<u32>,
+}
+
+impl StatusBar<'_> {
+    pub fn draw(
+        &self,
+        out: &mut impl Backend,
+        game: Option<&Game>,
+        area: Rect,
+        theme: &Theme,
+    ) -> io::Result<()> {
+        let mut left = format!(" {}", self.mode);
+        let theme = &theme.at_level(game.map_or(1, |g| g.level));
+        if let Some(g) = game {
+            left.push_str(&format!(
+                "  level {}  score {}  length {}",
+                g.level,
+                g.score,
+                g.snake.body.len()
+            ));
+            if let Some(score) = self.rival {
+                left.push_str(&format!("  bot {score}"));
+            }
+            left.push_str(&format!("  speed {}", speed_gauge(g)));
+            if g.custom_speed {
+                left.push_str(" custom");
+            }
+            if let Some(gauge) = hunger_gauge(g) {
+                left.push_str(&format!("  hunger {gauge}"));
+            }
+            if g.magnet_ticks > 0 {
+                left.push_str(&format!("  magnet {}", g.magnet_ticks));
+            }
+            if !g.word.is_empty() {
+                left.push_str(&format!(
+                    "  word {} {}/{}",
+                    g.word,
+                    g.spelled(),
+                    g.word.len()
+                ));
+            }
+            if let Some(ticks) = zone_ticks_left(g) {
+                left.push_str(&format!("  zone {ticks}"));
+            }
+            if g.chain_ticks > 0 {
+                left.push_str(&format!("  chain {}", g.chain_ticks));
+            }
+        }
+        if let Some(elapsed) = self.elapsed {
+            left.push_str(&format!("  time {}", format_duration(elapsed)));
+        }
+
+        let width = area.width as usize;
+        let hints = format!("{} ", self.hints);
+        let gap = width.saturating_sub(left.chars().count() + hints.chars().count());
+        let line: String = format!("{left}{}{hints}", " ".repeat(gap))
+            .chars()
+            .take(width)
+            .collect();
+
+        out.print(area.x, area.y, &line, theme.status_bar())
+    }
+}
+
+// Cells in the speed and hunger gauges
+const GAUGE_CELLS: u32 = 8;
+
+// `thousandths` of the way full as `[###.....]`, only full once it's there
+fn gauge(thousandths: u32) -> String {
+    let filled = (thousandths.min(1000) * GAUGE_CELLS / 1000) as usize;
+    format!(
+        "[{}{}]",
+        "#".repeat(filled),
+        ".".repeat(GAUGE_CELLS as usize - filled)
+    )
+}
+
+/// How close the game is to flat out.
+pub fn speed_gauge(game: &Game) -> String {
+    gauge(game.speed_progress())
+}
+
+/// How full the snake is, `None` without the hunger modifier.
+pub fn hunger_gauge(game: &Game) -> Option<String> {
+    game.modifiers
+        .contains(Modifier::Hunger)
+        .then(|| gauge(game.hunger * 1000 / HUNGER_FULL))
+}
+
+/// A box in the middle of `area` over whatever is drawn already, for the
+/// help screen and the like. Lines that don't fit are cut.
+pub fn draw_overlay(
+    out: &mut impl Backend,
+    title: &str,
+    lines: &[String],
+    area: Rect,
+    theme: &Theme,
+) -> io::Result<()> {
+    let longest = lines
+        .iter()
+        .map(|l| l.chars().count())
+        .chain([title.chars().count()])
+        .max()
+        .unwrap_or(0);
+    let width = u16::try_from(longest + 4).unwrap_or(u16::MAX);
+    let height = u16::try_from(lines.len() + 4).unwrap_or(u16::MAX);
+    let boxed = area.centered(width, height);
+    if boxed.width < 5 || boxed.height < 4 {
+        return Ok(());
+    }
+
+    let inner = boxed.width as usize - 4;
+    let fit = |text: &str| -> String {
+        let text: String = text.chars().take(inner).collect();
+        format!("| {text:<inner$} |")
+    };
+    let border = format!("+{}+", "-".repeat(inner + 2));
+
+    let (x, y) = (boxed.x, boxed.y);
+    out.print(x, y, &border, theme.text())?;
+    out.print(x, y + 1, &fit(title), theme.text())?;
+    for (i, line) in lines.iter().take(boxed.height as usize - 4).enumerate() {
+        out.print(x, y + 2 + i as u16, &fit(line), Style::plain())?;
+    }
+    out.print(x, boxed.bottom() - 2, &fit(""), Style::plain())?;
+    out.print(x, boxed.bottom() - 1, &border, theme.text())
+}
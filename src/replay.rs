@@ -0,0 +1,279 @@
+// -- Replays
+//
+// The engine plays the same given the same game and the same turns on the
+// same ticks, so a replay is only that: the game it started from (a binary
+// save, see `save.rs`) and every turn with the tick it came before. A whole
+// run is a few dozen bytes plus one or two for each turn.
+//
+// The layout after the header (see `binary.rs`), all varints:
+//
+//     start     the length of the binary save, then the save
+//     end       the game's tick count to play up to, one more than it
+//               had when the snake died on the last one
+//     turns     how many there are, then for each one the ticks since the
+//               one before (since 0 for the first) shifted left by two,
+//               ORed with the direction (up, down, left, right as 0-3)
+//
+// The same replay also goes as text, to paste in a bug report or a forum
+// post. The start is still a binary save, in URL-safe base64, but the turns
+// can be read and edited by hand: each one is the tick it came before and
+// the direction, and `#` starts a comment.
+//
+//     snake2 replay 2
+//     start UzJzBDwYAQAAAWQAAAAAO7M5DfP_Qt1Q...
+//     3:up 9:right 14:down 20:right
+//     end 41
+
+use crate::binary::{MAGIC, Reader, Writer};
+use crate::challenge::{decode_base64, encode_base64};
+use crate::game::{Game, TickOutcome};
+use crate::save::{direction_code, direction_from_code, load_game_binary, save_game_binary};
+use crate::snake::SnakeDirection;
+
+// Version 1 replays are from before food kept clear of the head's way,
+// their food would show up elsewhere now and the run go differently
+const VERSION: u64 = 2;
+// Turns to a line in the notation
+const TURNS_PER_LINE: usize = 8;
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Replay {
+    // The game when recording started
+    pub start: Vec<u8>,
+    // Each turn with the game's tick count when it was made, in order
+    pub turns: Vec<(u64, SnakeDirection)>,
+    // Ticks go up to this, the one the snake died on counts too
+    pub end: u64,
+}
+
+impl Replay {
+    /// Start recording from `game` as it is now.
+    pub fn new(game: &Game) -> Self {
+        Replay {
+            start: save_game_binary(game),
+            turns: Vec::new(),
+            end: game.ticks,
+        }
+    }
+
+    /// Call alongside `game.turn(direction)`, before it ticks.
+    pub fn turn(&mut self, game: &Game, direction: SnakeDirection) {
+        self.turns.push((game.ticks, direction));
+    }
+
+    /// Where the recording stops, with `died` the last tick killed the snake
+    /// (it doesn't count as one in `game.ticks`).
+    pub fn finish(&mut self, game: &Game, died: bool) {
+        self.end = game.ticks + u64::from(died);
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Writer::new(b'r', VERSION);
+        out.bytes(&self.start);
+        out.uint(self.end);
+        out.uint(self.turns.len() as u64);
+        let mut last = 0;
+        for &(tick, direction) in &self.turns {
+            out.uint((tick - last) << 2 | direction_code(direction));
+            last = tick;
+        }
+        out.bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Replay, String> {
+        let (mut input, version) = Reader::open(bytes, b'r', "replay")?;
+        if version != VERSION {
+            return Err(format!(
+                "this replay is version {version}, this snake2 reads version {VERSION}"
+            ));
+        }
+        let mut replay = Replay {
+            start: input.bytes("start")?.to_vec(),
+            end: input.uint("end")?,
+            turns: Vec::new(),
+        };
+        let mut tick = 0u64;
+        for _ in 0..input.uint("turns")? {
+            let turn = input.uint("turn")?;
+            tick = tick.checked_add(turn >> 2).ok_or("bad `turn`")?;
+            let direction = direction_from_code(turn & 3).expect("two bits");
+            replay.turns.push((tick, direction));
+        }
+        input.finish()?;
+        // Fails here rather than when it's played
+        replay.start_game()?;
+        Ok(replay)
+    }
+
+    /// Either format, told apart by the binary one's header.
+    pub fn load(bytes: &[u8]) -> Result<Replay, String> {
+        if bytes.starts_with(MAGIC) {
+            return Replay::from_bytes(bytes);
+        }
+        let text = std::str::from_utf8(bytes).map_err(|_| "not a snake2 replay file")?;
+        Replay::from_notation(text)
+    }
+
+    pub fn to_notation(&self) -> String {
+        let mut out = format!(
+            "snake2 replay {VERSION}\nstart {}\n",
+            encode_base64(&self.start)
+        );
+        for line in self.turns.chunks(TURNS_PER_LINE) {
+            let turns: Vec<String> = line
+                .iter()
+                .map(|(tick, direction)| format!("{tick}:{}", direction.name()))
+                .collect();
+            out.push_str(&turns.join(" "));
+            out.push('\n');
+        }
+        out.push_str(&format!("end {}\n", self.end));
+        out
+    }
+
+    pub fn from_notation(text: &str) -> Result<Replay, String> {
+        let mut words = text
+            .lines()
+            .map(|line| line.split('#').next().unwrap_or(""))
+            .flat_map(str::split_whitespace);
+        if (words.next(), words.next()) != (Some("snake2"), Some("replay")) {
+            return Err("not a snake2 replay file".to_string());
+        }
+        let version = words.next().ok_or("no version")?;
+        if version != VERSION.to_string() {
+            return Err(format!(
+                "this replay is version {version}, this snake2 reads version {VERSION}"
+            ));
+        }
+        if words.next() != Some("start") {
+            return Err("no `start`".to_string());
+        }
+        let start = words.next().and_then(decode_base64).ok_or("bad `start`")?;
+        let mut replay = Replay {
+            start,
+            turns: Vec::new(),
+            end: 0,
+        };
+        loop {
+            match words.next() {
+                Some("end") => {
+                    let end = words.next().and_then(|end| end.parse().ok());
+                    replay.end = end.ok_or("bad `end`")?;
+                    break;
+                }
+                Some(turn) => {
+                    let (tick, direction) = turn
+                        .split_once(':')
+                        .and_then(|(tick, direction)| {
+                            Some((tick.parse().ok()?, SnakeDirection::from_name(direction)?))
+                        })
+                        .ok_or_else(|| format!("bad turn `{turn}`"))?;
+                    if replay.turns.last().is_some_and(|&(last, _)| tick < last) {
+                        return Err(format!("turn `{turn}` comes before the one ahead of it"));
+                    }
+                    replay.turns.push((tick, direction));
+                }
+                None => return Err("no `end`".to_string()),
+            }
+        }
+        if let Some(word) = words.next() {
+            return Err(format!("`{word}` after the end"));
+        }
+        replay.start_game()?;
+        Ok(replay)
+    }
+
+    /// The game as it was when recording started.
+    pub fn start_game(&self) -> Result<Game, String> {
+        load_game_binary(&self.start)
+    }
+
+    /// Play it all at once, the game as it was when recording stopped.
+    pub fn play(&self) -> Result<Game, String> {
+        let mut playback = Playback::new(self)?;
+        while playback.step().is_some() {}
+        Ok(playback.game)
+    }
+}
+
+/// The end of a run as it's played, to play it again once the snake dies:
+/// a replay from a snapshot at least `ticks` back and no more than twice
+/// that, so it never grows with the run.
+pub struct Recent {
+    ticks: u64,
+    older: Replay,
+    newer: Replay,
+    // The tick `newer` starts on
+    since: u64,
+}
+
+impl Recent {
+    pub fn new(game: &Game, ticks: u64) -> Self {
+        Recent {
+            ticks,
+            older: Replay::new(game),
+            newer: Replay::new(game),
+            since: game.ticks,
+        }
+    }
+
+    /// Call alongside `game.turn(direction)`, before it ticks.
+    pub fn turn(&mut self, game: &Game, direction: SnakeDirection) {
+        self.newer.turn(game, direction);
+    }
+
+    /// Call after every tick the snake lived through.
+    pub fn ticked(&mut self, game: &Game) {
+        if game.ticks - self.since >= self.ticks {
+            self.older = std::mem::replace(&mut self.newer, Replay::new(game));
+            self.since = game.ticks;
+        }
+    }
+
+    /// All of it up to the tick that killed the snake in `game`.
+    pub fn replay(&self, game: &Game) -> Replay {
+        let mut replay = Replay {
+            start: self.older.start.clone(),
+            turns: [&self.older.turns[..], &self.newer.turns[..]].concat(),
+            end: 0,
+        };
+        replay.finish(game, true);
+        replay
+    }
+}
+
+/// A replay being played one tick at a time, to watch it.
+pub struct Playback<'a> {
+    replay: &'a Replay,
+    pub game: Game,
+    // The first turn not made yet
+    next: usize,
+    died: bool,
+}
+
+impl<'a> Playback<'a> {
+    pub fn new(replay: &'a Replay) -> Result<Self, String> {
+        Ok(Playback {
+            replay,
+            game: replay.start_game()?,
+            next: 0,
+            died: false,
+        })
+    }
+
+    /// Make the turns due and tick, `None` once the recording is over.
+    pub fn step(&mut self) -> Option<TickOutcome> {
+        if self.died || self.game.ticks >= self.replay.end {
+            return None;
+        }
+        while let Some(&(tick, direction)) = self.replay.turns.get(self.next)
+            && tick <= self.game.ticks
+        {
+            self.game.turn(direction);
+            self.next += 1;
+        }
+        let outcome = self.game.tick();
+        self.died = outcome == TickOutcome::Died;
+        Some(outcome)
+    }
+}
@@ -0,0 +1,72 @@
+// -- The game's random numbers
+//
+// The game owns a small generator (SplitMix64) instead of one of rand's,
+// since its whole state is a single number: saves, checkpoints and anything
+// else that snapshots a game keep it, and the food after loading is the food
+// there would have been. It plugs into rand, but the engine draws from it
+// with `below` and `pick` instead of rand's `random_range`: those are
+// nothing but integer math, written here so a new rand can't change them,
+// and a game plays out the same on every platform. They draw exactly what
+// rand 0.9's `random_range` did, so older saves and replays still hold.
+
+use rand::rand_core::{RngCore, SeedableRng, impls};
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct GameRng {
+    pub state: u64,
+}
+
+impl RngCore for GameRng {
+    fn next_u32(&mut self) -> u32 {
+        (self.next_u64() >> 32) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        impls::fill_bytes_via_next(self, dest)
+    }
+}
+
+impl GameRng {
+    /// A number in `0..n`, `n` not 0. Canon's method, as rand does it for
+    /// ranges that fit 32 bits: the top of a 64-bit product, and one more
+    /// draw only when the bottom is close enough to carry into it.
+    pub fn below(&mut self, n: u32) -> u32 {
+        let wide = |draw: u32| u64::from(draw) * u64::from(n);
+        let first = wide(self.next_u32());
+        let (mut result, low) = ((first >> 32) as u32, first as u32);
+        if low > n.wrapping_neg() {
+            let carry = (wide(self.next_u32()) >> 32) as u32;
+            result += u32::from(low.checked_add(carry).is_none());
+        }
+        result
+    }
+
+    /// One of `items`, which can't be empty.
+    pub fn pick<'a, T>(&mut self, items: &'a [T]) -> &'a T {
+        // Boards are nowhere near 2^32 cells
+        &items[self.below(items.len() as u32) as usize]
+    }
+}
+
+impl SeedableRng for GameRng {
+    type Seed = [u8; 8];
+
+    fn from_seed(seed: Self::Seed) -> Self {
+        GameRng {
+            state: u64::from_le_bytes(seed),
+        }
+    }
+
+    // The seed is the state, so a challenge's seed is all it takes
+    fn seed_from_u64(state: u64) -> Self {
+        GameRng { state }
+    }
+}
@@ -0,0 +1,460 @@
+// -- Battle royale: many snakes on one board
+//
+// `snake2 server --royale` puts everyone connected in the same round, on a
+// board much bigger than any of them sees (`VIEW_COLS` by `VIEW_ROWS` around
+// their own head, see `Royale::view_json`). The edges are walls, and inside
+// them is the safe zone: after `GRACE_TICKS` it closes in by a cell on every
+// side each `SHRINK_EVERY` ticks, down to `MIN_ZONE` across. A head outside
+// it dies, like one running into a wall or another snake does. Two heads
+// meeting both die. What's left of a dead snake turns into food, every other
+// segment of it, and the last snake alive wins the round.
+//
+// With teams (`--teams`) the players are split into `TEAMS` sides by when
+// they joined, for 2v2 and the like. Teammates go right through each other,
+// it's only the other team's snakes that kill, and the round goes to the
+// last team with anyone left. A team's score is what all of its snakes ate,
+// the dead ones too.
+//
+// This is only the simulation, the server runs it (see `server.rs`) and is
+// the one that decides: clients send turns and get their view back every
+// tick. It has nothing to do with `Game`, which is one snake and its rules,
+// but the snakes are the same `Snake` and the dice the same `GameRng`.
+
+use crate::json;
+use crate::rng::GameRng;
+use crate::snake::{Snake, SnakeBodyPoint, SnakeDirection, Spawn};
+use rand::SeedableRng;
+
+pub const ROYALE_COLS: u16 = 120;
+pub const ROYALE_ROWS: u16 = 60;
+// What each player sees of the board around their head
+pub const VIEW_COLS: u16 = 40;
+pub const VIEW_ROWS: u16 = 20;
+pub const START_LENGTH: u16 = 3;
+// Food on the board for each snake that started the round
+pub const FOOD_PER_PLAYER: usize = 2;
+// The zone stays the whole board for this long
+pub const GRACE_TICKS: u64 = 50;
+pub const SHRINK_EVERY: u64 = 20;
+// The zone doesn't get narrower or shorter than this
+pub const MIN_ZONE: u16 = 6;
+pub const TEAMS: u8 = 2;
+
+/// A rectangle of cells, both corners included.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Area {
+    pub left: u16,
+    pub top: u16,
+    pub right: u16,
+    pub bottom: u16,
+}
+
+impl Area {
+    pub fn contains(&self, x: u16, y: u16) -> bool {
+        (self.left..=self.right).contains(&x) && (self.top..=self.bottom).contains(&y)
+    }
+
+    fn json(&self) -> String {
+        format!(
+            r#"{{"left":{},"top":{},"right":{},"bottom":{}}}"#,
+            self.left, self.top, self.right, self.bottom
+        )
+    }
+}
+
+pub struct Player {
+    // The server's number for the connection, not an index
+    pub id: u32,
+    // Without teams everyone is on their own
+    pub team: Option<u8>,
+    pub snake: Snake,
+    pub alive: bool,
+    pub score: u32,
+}
+
+pub struct Royale {
+    pub cols: u16,
+    pub rows: u16,
+    pub players: Vec<Player>,
+    pub food: Vec<(u16, u16)>,
+    pub zone: Area,
+    pub ticks: u64,
+    pub rng: GameRng,
+}
+
+// Where the `i`th of `players` starts: evenly round the edge of a box 70%
+// the size of the board, from halfway down its right side clockwise, facing
+// in. Integers only, so a round starts the same anywhere.
+fn start_spot(cols: u16, rows: u16, i: usize, players: usize) -> (u16, u16, SnakeDirection) {
+    let (left, right) = (cols * 15 / 100, cols * 85 / 100);
+    let (top, bottom) = (rows * 15 / 100, rows * 85 / 100);
+    let (width, height) = (usize::from(right - left), usize::from(bottom - top));
+    let around = 2 * (width + height);
+    // From the top right corner, clockwise
+    let along = (i * around / players.max(1) + height / 2) % around;
+    if along < height {
+        (right, top + along as u16, SnakeDirection::Left)
+    } else if along < height + width {
+        (right - (along - height) as u16, bottom, SnakeDirection::Up)
+    } else if along < 2 * height + width {
+        let up = along - height - width;
+        (left, bottom - up as u16, SnakeDirection::Right)
+    } else {
+        let across = along - 2 * height - width;
+        (left + across as u16, top, SnakeDirection::Down)
+    }
+}
+
+impl Royale {
+    /// A round for `ids`, spread out round the middle of the board and all
+    /// heading for it. With `teams` they take turns joining one team and
+    /// the next, so teammates don't start side by side.
+    pub fn new(cols: u16, rows: u16, ids: &[u32], seed: u64, teams: bool) -> Self {
+        let (cols, rows) = (cols.max(MIN_ZONE * 2), rows.max(MIN_ZONE * 2));
+        let players = ids
+            .iter()
+            .enumerate()
+            .map(|(i, &id)| {
+                let (x, y, direction) = start_spot(cols, rows, i, ids.len());
+                Player {
+                    id,
+                    team: teams.then_some(i as u8 % TEAMS),
+                    snake: Snake::new(&cols, &rows, direction, START_LENGTH, Spawn::At(x, y)),
+                    alive: true,
+                    score: 0,
+                }
+            })
+            .collect();
+        let mut royale = Royale {
+            cols,
+            rows,
+            players,
+            food: Vec::new(),
+            zone: Area {
+                left: 0,
+                top: 0,
+                right: cols - 1,
+                bottom: rows - 1,
+            },
+            ticks: 0,
+            rng: GameRng::seed_from_u64(seed),
+        };
+        royale.add_food();
+        royale
+    }
+
+    pub fn player(&self, id: u32) -> Option<&Player> {
+        self.players.iter().find(|p| p.id == id)
+    }
+
+    pub fn turn(&mut self, id: u32, direction: SnakeDirection) {
+        if let Some(player) = self.players.iter_mut().find(|p| p.id == id) {
+            player.snake.turn(direction);
+        }
+    }
+
+    /// Whoever left the server is out of the round, the same as dying.
+    pub fn leave(&mut self, id: u32) {
+        if let Some(i) = self.players.iter().position(|p| p.id == id && p.alive) {
+            self.kill(i);
+        }
+    }
+
+    pub fn alive(&self) -> usize {
+        self.players.iter().filter(|p| p.alive).count()
+    }
+
+    // Snakes or teams, whatever the round is between
+    fn sides(&self, alive_only: bool) -> usize {
+        let mut sides: Vec<(Option<u8>, u32)> = self
+            .players
+            .iter()
+            .filter(|p| p.alive || !alive_only)
+            .map(|p| match p.team {
+                Some(team) => (Some(team), 0),
+                None => (None, p.id),
+            })
+            .collect();
+        sides.sort_unstable();
+        sides.dedup();
+        sides.len()
+    }
+
+    /// Over once at most one side is left, or none with a round of one.
+    pub fn is_over(&self) -> bool {
+        self.sides(true) <= usize::from(self.sides(false) > 1)
+    }
+
+    /// The last one standing, once a round without teams is over. Nobody
+    /// wins when the last two die on the same tick.
+    pub fn winner(&self) -> Option<u32> {
+        match self.is_over() {
+            true => self
+                .players
+                .iter()
+                .find(|p| p.alive && p.team.is_none())
+                .map(|p| p.id),
+            false => None,
+        }
+    }
+
+    /// The last team with a snake left, once a round with teams is over.
+    pub fn winning_team(&self) -> Option<u8> {
+        match self.is_over() {
+            true => self.players.iter().find(|p| p.alive).and_then(|p| p.team),
+            false => None,
+        }
+    }
+
+    /// Everything `team`'s snakes ate this round.
+    pub fn team_score(&self, team: u8) -> u32 {
+        self.players
+            .iter()
+            .filter(|p| p.team == Some(team))
+            .map(|p| p.score)
+            .sum()
+    }
+
+    /// Ticks until the zone closes in next, `None` once it can't anymore.
+    pub fn shrinks_in(&self) -> Option<u64> {
+        let Area {
+            left,
+            top,
+            right,
+            bottom,
+        } = self.zone;
+        if right - left <= MIN_ZONE && bottom - top <= MIN_ZONE {
+            return None;
+        }
+        let since = self.ticks.saturating_sub(GRACE_TICKS);
+        Some(match self.ticks < GRACE_TICKS {
+            true => GRACE_TICKS - self.ticks + SHRINK_EVERY,
+            false => SHRINK_EVERY - since % SHRINK_EVERY,
+        })
+    }
+
+    /// Everyone moves at once, then whoever ran into something dies.
+    pub fn tick(&mut self) {
+        self.ticks += 1;
+        if self.ticks > GRACE_TICKS && (self.ticks - GRACE_TICKS).is_multiple_of(SHRINK_EVERY) {
+            self.shrink();
+        }
+
+        // Where each snake went over an edge, which here is a wall
+        let mut crashed = vec![false; self.players.len()];
+        for (i, player) in self.players.iter_mut().enumerate() {
+            if !player.alive {
+                continue;
+            }
+            let head = player.snake.head();
+            let next = player.snake.next_head(&self.cols, &self.rows);
+            crashed[i] = next.x.abs_diff(head.x) + next.y.abs_diff(head.y) > 1;
+            player.snake.last_moved = player.snake.direction;
+            player.snake.body.insert(0, next);
+            match self.food.iter().position(|&f| f == (next.x, next.y)) {
+                Some(f) => {
+                    self.food.swap_remove(f);
+                    player.score += 1;
+                }
+                None => {
+                    player.snake.body.pop();
+                }
+            }
+        }
+
+        // All checked before anyone is taken off, so two heads that met both die
+        let dead: Vec<bool> = crashed
+            .into_iter()
+            .enumerate()
+            .map(|(i, crashed)| {
+                let player = &self.players[i];
+                let head = player.snake.head();
+                let hit = self.players.iter().enumerate().any(|(j, other)| {
+                    let body = match i == j {
+                        true => &other.snake.body[1..],
+                        // Teammates are nothing to run into
+                        false if other.team.is_some() && other.team == player.team => &[],
+                        false => &other.snake.body[..],
+                    };
+                    other.alive && body.iter().any(|p| p.x == head.x && p.y == head.y)
+                });
+                player.alive && (crashed || hit || !self.zone.contains(head.x, head.y))
+            })
+            .collect();
+        for (i, dead) in dead.into_iter().enumerate() {
+            if dead {
+                self.kill(i);
+            }
+        }
+        self.add_food();
+    }
+
+    fn shrink(&mut self) {
+        let zone = &mut self.zone;
+        if zone.right - zone.left > MIN_ZONE {
+            zone.left += 1;
+            zone.right -= 1;
+        }
+        if zone.bottom - zone.top > MIN_ZONE {
+            zone.top += 1;
+            zone.bottom -= 1;
+        }
+        let zone = self.zone;
+        self.food.retain(|&(x, y)| zone.contains(x, y));
+    }
+
+    fn kill(&mut self, i: usize) {
+        let player = &mut self.players[i];
+        player.alive = false;
+        let body = std::mem::take(&mut player.snake.body);
+        // Not where the head is, that's a wall or another snake
+        for p in body.iter().skip(1).step_by(2) {
+            if self.zone.contains(p.x, p.y) && !self.food.contains(&(p.x, p.y)) {
+                self.food.push((p.x, p.y));
+            }
+        }
+        // Kept for where the view of a dead player stays
+        player.snake.body = body.into_iter().take(1).collect();
+    }
+
+    fn occupied(&self, x: u16, y: u16) -> bool {
+        self.food.contains(&(x, y))
+            || self
+                .players
+                .iter()
+                .any(|p| p.alive && p.snake.occupies(x, y))
+    }
+
+    // Back up to what the players that started need, somewhere in the zone
+    fn add_food(&mut self) {
+        let Area {
+            left,
+            top,
+            right,
+            bottom,
+        } = self.zone;
+        let wanted = self.players.len().max(1) * FOOD_PER_PLAYER;
+        // A few tries each, a crowded zone can go without for a tick
+        for _ in 0..(wanted.saturating_sub(self.food.len()) * 10) {
+            if self.food.len() >= wanted {
+                break;
+            }
+            let x = left + self.rng.below(u32::from(right - left) + 1) as u16;
+            let y = top + self.rng.below(u32::from(bottom - top) + 1) as u16;
+            if !self.occupied(x, y) {
+                self.food.push((x, y));
+            }
+        }
+    }
+
+    /// The part of the board around `id`'s head as one line of JSON, what
+    /// the server sends them every tick. Positions are the board's, `view`
+    /// says which part it is. Someone not in the round (dead, or who came
+    /// in after it started) watches a teammate still alive if there is
+    /// one, the first snake still alive if not.
+    pub fn view_json(&self, id: u32) -> String {
+        let me = self.player(id);
+        let team = me.and_then(|p| p.team);
+        let followed = me
+            .filter(|p| p.alive)
+            .or_else(|| {
+                self.players
+                    .iter()
+                    .find(|p| p.alive && team.is_some() && p.team == team)
+            })
+            .or_else(|| self.players.iter().find(|p| p.alive))
+            .or(me);
+        let center = followed.map_or(
+            SnakeBodyPoint {
+                x: self.cols / 2,
+                y: self.rows / 2,
+                letter: None,
+            },
+            |p| p.snake.head(),
+        );
+        let view = Area {
+            left: center
+                .x
+                .saturating_sub(VIEW_COLS / 2)
+                .min(self.cols.saturating_sub(VIEW_COLS)),
+            top: center
+                .y
+                .saturating_sub(VIEW_ROWS / 2)
+                .min(self.rows.saturating_sub(VIEW_ROWS)),
+            right: 0,
+            bottom: 0,
+        };
+        let view = Area {
+            right: (view.left + VIEW_COLS).min(self.cols) - 1,
+            bottom: (view.top + VIEW_ROWS).min(self.rows) - 1,
+            ..view
+        };
+
+        // Only the ones with something in view, and only that much of them
+        let snakes = json::array(self.players.iter().filter(|p| p.alive).filter_map(|p| {
+            let body: Vec<String> = p
+                .snake
+                .body
+                .iter()
+                .filter(|s| view.contains(s.x, s.y))
+                .map(|s| format!("[{},{}]", s.x, s.y))
+                .collect();
+            (!body.is_empty()).then(|| {
+                format!(
+                    r#"{{"id":{},"team":{},"score":{},"body":{}}}"#,
+                    p.id,
+                    or_null(p.team),
+                    p.score,
+                    json::array(body)
+                )
+            })
+        }));
+        let food = json::array(
+            self.food
+                .iter()
+                .filter(|&&(x, y)| view.contains(x, y))
+                .map(|(x, y)| format!("[{x},{y}]")),
+        );
+        // Empty without teams
+        let teams = json::array(
+            (0..TEAMS)
+                .filter(|&t| self.players.iter().any(|p| p.team == Some(t)))
+                .map(|t| {
+                    let left = self
+                        .players
+                        .iter()
+                        .filter(|p| p.alive && p.team == Some(t))
+                        .count();
+                    format!(
+                        r#"{{"team":{t},"score":{},"left":{left}}}"#,
+                        self.team_score(t)
+                    )
+                }),
+        );
+        format!(
+            r#"{{"tick":{},"cols":{},"rows":{},"you":{},"team":{},"alive":{},"score":{},"view":{},"zone":{},"shrinks_in":{},"snakes":{},"food":{},"players":{},"left":{},"teams":{},"over":{},"winner":{},"winning_team":{}}}"#,
+            self.ticks,
+            self.cols,
+            self.rows,
+            id,
+            or_null(team),
+            me.is_some_and(|p| p.alive),
+            me.map_or(0, |p| p.score),
+            view.json(),
+            self.zone.json(),
+            or_null(self.shrinks_in()),
+            snakes,
+            food,
+            self.players.len(),
+            self.alive(),
+            teams,
+            self.is_over(),
+            or_null(self.winner()),
+            or_null(self.winning_team())
+        )
+    }
+}
+
+fn or_null(value: Option<impl ToString>) -> String {
+    value.map_or("null".to_string(), |v| v.to_string())
+}
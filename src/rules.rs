@@ -0,0 +1,908 @@
+// -- Rules and game modes
+//
+// A `Rule` hooks into the tick to change how the game plays, and a mode is
+// just a set of rules registered on the `Game` when it starts. Every hook
+// has a default that changes nothing, so a rule only implements what it
+// cares about.
+//
+// Modifiers are rules too, picked for a run on top of whatever the mode
+// does (`--modifiers teleport`). They're kept with the score, since a run
+// with them isn't the same game.
+
+use crate::entity::{Behavior, Entity, EntityKind, FADING_STAGES};
+use crate::events::{DeathCause, GameEvent};
+use crate::game::{CLOSE_CALL_BONUS, FASTEST_TIMER, Game, LEVEL_EVERY};
+use crate::objective::{self, ZONE_EVERY, ZONE_POINT_EVERY, ZONE_TICKS};
+use crate::snake::{SnakeBodyPoint, SnakeDirection};
+use std::fmt;
+
+pub trait Rule {
+    fn name(&self) -> &'static str;
+
+    /// One line for the help screen on how it changes the game.
+    fn describe(&self) -> String {
+        String::new()
+    }
+
+    /// Once, when the game is set up, before the first food is placed.
+    fn on_start(&mut self, _game: &mut Game) {}
+
+    /// Before the snake moves.
+    fn on_tick(&mut self, _game: &mut Game) {}
+
+    /// Before `on_tick`, a cause ends the game where the head is. Every
+    /// other death is something the head runs into.
+    fn kills(&mut self, _game: &mut Game) -> Option<DeathCause> {
+        None
+    }
+
+    /// After the snake ate, before the next food is placed.
+    fn on_eat(&mut self, _game: &mut Game) {}
+
+    /// Right after `game.level` went up.
+    fn on_level_up(&mut self, _game: &mut Game) {}
+
+    /// The head is about to run into its body at `at`, returning false lets
+    /// the snake live. It only survives if every rule agrees.
+    fn on_collision(&mut self, _game: &mut Game, _at: SnakeBodyPoint) -> bool {
+        true
+    }
+
+    /// Change where the next food goes, `None` means no food at all.
+    fn modify_spawn(
+        &mut self,
+        _game: &mut Game,
+        position: Option<(u16, u16)>,
+    ) -> Option<(u16, u16)> {
+        position
+    }
+
+    /// Change which way a key turns the snake, before it's queued.
+    fn modify_turn(&mut self, _game: &mut Game, direction: SnakeDirection) -> SnakeDirection {
+        direction
+    }
+
+    /// Change where the head goes this tick, `head` is one step ahead of it
+    /// (before any portal takes it elsewhere).
+    fn modify_head(&mut self, _game: &mut Game, head: SnakeBodyPoint) -> SnakeBodyPoint {
+        head
+    }
+
+    /// Change what the next food is, once `modify_spawn` found it a cell.
+    fn food_kind(&mut self, _game: &mut Game, kind: EntityKind) -> EntityKind {
+        kind
+    }
+
+    /// How far from the head the player can see, `None` is the whole board.
+    fn view_radius(&self) -> Option<u16> {
+        None
+    }
+
+    /// The longest the snake can get, `None` is no limit.
+    fn max_length(&self) -> Option<usize> {
+        None
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Mode {
+    #[default]
+    Classic,
+    Tron,
+    Survival,
+    Arcade,
+    Fog,
+    Rooms,
+    Nibbles,
+    Words,
+    Mirror,
+    Hydra,
+    Surround,
+}
+
+impl Mode {
+    pub fn name(self) -> &'static str {
+        match self {
+            Mode::Classic => "classic",
+            Mode::Tron => "tron",
+            Mode::Survival => "survival",
+            Mode::Arcade => "arcade",
+            Mode::Fog => "fog",
+            Mode::Rooms => "rooms",
+            Mode::Nibbles => "nibbles",
+            Mode::Words => "words",
+            Mode::Mirror => "mirror",
+            Mode::Hydra => "hydra",
+            Mode::Surround => "surround",
+        }
+    }
+
+    /// For challenge codes and binary saves, a new mode takes the next number.
+    pub fn code(self) -> u8 {
+        match self {
+            Mode::Classic => 0,
+            Mode::Tron => 1,
+            Mode::Survival => 2,
+            Mode::Arcade => 3,
+            Mode::Fog => 4,
+            Mode::Rooms => 5,
+            Mode::Nibbles => 6,
+            Mode::Words => 7,
+            Mode::Mirror => 8,
+            Mode::Hydra => 9,
+            Mode::Surround => 10,
+        }
+    }
+
+    pub fn from_code(code: u8) -> Option<Mode> {
+        match code {
+            0 => Some(Mode::Classic),
+            1 => Some(Mode::Tron),
+            2 => Some(Mode::Survival),
+            3 => Some(Mode::Arcade),
+            4 => Some(Mode::Fog),
+            5 => Some(Mode::Rooms),
+            6 => Some(Mode::Nibbles),
+            7 => Some(Mode::Words),
+            8 => Some(Mode::Mirror),
+            9 => Some(Mode::Hydra),
+            10 => Some(Mode::Surround),
+            _ => None,
+        }
+    }
+
+    pub fn rules(self) -> Vec<Box<dyn Rule>> {
+        match self {
+            Mode::Classic => vec![],
+            Mode::Tron => vec![Box::new(Tron)],
+            Mode::Survival => vec![Box::new(Survival { every: 10 })],
+            Mode::Arcade => vec![Box::new(Arcade)],
+            Mode::Fog => vec![Box::new(Fog { radius: 12 })],
+            Mode::Rooms => vec![Box::new(Rooms { count: 3 })],
+            Mode::Nibbles => vec![Box::new(Nibbles { max_length: 15 })],
+            Mode::Words => vec![Box::new(Words)],
+            Mode::Mirror => vec![Box::new(Mirror { delay: 20 })],
+            Mode::Hydra => vec![Box::new(Hydra { every: 5, most: 3 })],
+            Mode::Surround => vec![Box::new(Surround { fade: 30 })],
+        }
+    }
+}
+
+impl std::str::FromStr for Mode {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "classic" => Ok(Mode::Classic),
+            "tron" => Ok(Mode::Tron),
+            "survival" => Ok(Mode::Survival),
+            "arcade" => Ok(Mode::Arcade),
+            "fog" => Ok(Mode::Fog),
+            "rooms" => Ok(Mode::Rooms),
+            "nibbles" => Ok(Mode::Nibbles),
+            "words" => Ok(Mode::Words),
+            "mirror" => Ok(Mode::Mirror),
+            "hydra" => Ok(Mode::Hydra),
+            "surround" => Ok(Mode::Surround),
+            _ => Err(format!("unknown mode `{s}`")),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Modifier {
+    Teleport,
+    Gravity,
+    Hunger,
+    Fog,
+    Inverted,
+    Fleeting,
+    Hills,
+}
+
+impl Modifier {
+    pub const ALL: [Modifier; 7] = [
+        Modifier::Teleport,
+        Modifier::Gravity,
+        Modifier::Hunger,
+        Modifier::Fog,
+        Modifier::Inverted,
+        Modifier::Fleeting,
+        Modifier::Hills,
+    ];
+
+    /// What the weekly mutator picks from. A modifier added later isn't
+    /// in it, so the weeks that already had a pick keep it.
+    pub const WEEKLY: [Modifier; 6] = [
+        Modifier::Teleport,
+        Modifier::Gravity,
+        Modifier::Hunger,
+        Modifier::Fog,
+        Modifier::Inverted,
+        Modifier::Fleeting,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Modifier::Teleport => "teleport",
+            Modifier::Gravity => "gravity",
+            Modifier::Hunger => "hunger",
+            Modifier::Fog => "fog",
+            Modifier::Inverted => "inverted",
+            Modifier::Fleeting => "fleeting",
+            Modifier::Hills => "hills",
+        }
+    }
+
+    pub fn rule(self) -> Box<dyn Rule> {
+        match self {
+            Modifier::Teleport => Box::new(Teleport),
+            Modifier::Gravity => Box::new(Gravity { every: 4 }),
+            Modifier::Hunger => Box::new(Hunger { every: 5 }),
+            Modifier::Fog => Box::new(Fog { radius: 12 }),
+            Modifier::Inverted => Box::new(Inverted),
+            Modifier::Fleeting => Box::new(Fleeting { every: 40 }),
+            Modifier::Hills => Box::new(Hills),
+        }
+    }
+
+    fn bit(self) -> u8 {
+        1 << self as u8
+    }
+}
+
+impl std::str::FromStr for Modifier {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Modifier::ALL
+            .into_iter()
+            .find(|m| m.name() == s)
+            .ok_or(format!("unknown modifier `{s}`"))
+    }
+}
+
+/// The modifiers of a run, a set small enough to copy around with the options.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Modifiers(u8);
+
+impl Modifiers {
+    pub fn with(self, modifier: Modifier) -> Modifiers {
+        Modifiers(self.0 | modifier.bit())
+    }
+
+    pub fn contains(self, modifier: Modifier) -> bool {
+        self.0 & modifier.bit() != 0
+    }
+
+    pub fn is_empty(self) -> bool {
+        self.0 == 0
+    }
+
+    pub fn iter(self) -> impl Iterator<Item = Modifier> {
+        Modifier::ALL.into_iter().filter(move |&m| self.contains(m))
+    }
+
+    pub fn rules(self) -> Vec<Box<dyn Rule>> {
+        self.iter().map(Modifier::rule).collect()
+    }
+
+    /// For challenge codes, one bit per modifier.
+    pub fn bits(self) -> u8 {
+        self.0
+    }
+
+    /// `None` with bits no modifier uses.
+    pub fn from_bits(bits: u8) -> Option<Modifiers> {
+        let all = Modifier::ALL.iter().fold(0, |all, m| all | m.bit());
+        (bits & !all == 0).then_some(Modifiers(bits))
+    }
+}
+
+/// `teleport,other`, or `none`.
+impl fmt::Display for Modifiers {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.is_empty() {
+            return f.write_str("none");
+        }
+        let names: Vec<&str> = self.iter().map(Modifier::name).collect();
+        f.write_str(&names.join(","))
+    }
+}
+
+impl std::str::FromStr for Modifiers {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s == "none" {
+            return Ok(Modifiers::default());
+        }
+        s.split(',')
+            .map(|name| name.trim().parse::<Modifier>())
+            .try_fold(Modifiers::default(), |set, m| Ok(set.with(m?)))
+    }
+}
+
+/// Light cycles: no food, the trail never goes away and every tick alive is a point.
+pub struct Tron;
+
+impl Rule for Tron {
+    fn name(&self) -> &'static str {
+        "tron"
+    }
+
+    fn describe(&self) -> String {
+        "No food, the trail stays forever. Every tick alive is a point.".to_string()
+    }
+
+    fn on_tick(&mut self, game: &mut Game) {
+        game.pending_growth = 1;
+        game.score = game.ticks as u32 + game.close_calls * CLOSE_CALL_BONUS;
+    }
+
+    fn modify_spawn(
+        &mut self,
+        _game: &mut Game,
+        _position: Option<(u16, u16)>,
+    ) -> Option<(u16, u16)> {
+        None
+    }
+}
+
+/// The snake grows on its own every few ticks, food or not.
+pub struct Survival {
+    pub every: u64,
+}
+
+impl Rule for Survival {
+    fn name(&self) -> &'static str {
+        "survival"
+    }
+
+    fn describe(&self) -> String {
+        format!("The snake grows by itself every {} ticks.", self.every)
+    }
+
+    fn on_tick(&mut self, game: &mut Game) {
+        if game.ticks > 0 && game.ticks.is_multiple_of(self.every) {
+            game.pending_growth += 1;
+        }
+    }
+}
+
+/// Every level is faster and puts more walls on the board, every few a boss
+/// shows up instead. Halfway through a level there's a checkpoint.
+pub struct Arcade;
+
+// A boss every this many levels
+pub const BOSS_EVERY: u32 = 3;
+pub const BOSS_PATROL: Behavior = Behavior::Patrol { every: 4, span: 4 };
+// `#` is the boss, digits its weak points
+const BOSS: [&str; 3] = ["1###2", "#####", "  3  "];
+
+impl Rule for Arcade {
+    fn name(&self) -> &'static str {
+        "arcade"
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "Every level is faster and adds walls, every {BOSS_EVERY} levels a boss: eat its weak points in order."
+        )
+    }
+
+    fn on_eat(&mut self, game: &mut Game) {
+        if game.score % LEVEL_EVERY == LEVEL_EVERY / 2
+            && let Some((x, y)) = game.random_free_cell()
+        {
+            game.entities
+                .push(Entity::new(EntityKind::Checkpoint, x, y));
+        }
+    }
+
+    fn on_level_up(&mut self, game: &mut Game) {
+        // Never slower, a game started faster than that stays as it is
+        game.timer = game
+            .timer
+            .saturating_sub(30)
+            .max(FASTEST_TIMER)
+            .min(game.timer);
+        let boss_around = game.entities.iter().any(|e| e.kind == EntityKind::Boss);
+        if game.level.is_multiple_of(BOSS_EVERY) && !boss_around && spawn_boss(game) {
+            return;
+        }
+        for _ in 1..game.level {
+            if let Some((x, y)) = game.random_free_cell() {
+                game.entities.push(Entity::new(EntityKind::Wall, x, y));
+            }
+        }
+    }
+}
+
+/// Put a boss somewhere with room to patrol, false if there's no such place.
+pub fn spawn_boss(game: &mut Game) -> bool {
+    let Behavior::Patrol { span, .. } = BOSS_PATROL else {
+        return false;
+    };
+    let span = span as u16;
+    let (width, height) = (BOSS[0].len() as u16, BOSS.len() as u16);
+    // It may start off going either way
+    let Some((x, y)) = game.random_free_area(width + 2 * span, height) else {
+        return false;
+    };
+    for (dy, row) in BOSS.iter().enumerate() {
+        for (dx, cell) in row.chars().enumerate() {
+            let kind = match cell {
+                '#' => EntityKind::Boss,
+                '1'..='9' => EntityKind::WeakPoint(cell as u8 - b'0'),
+                _ => continue,
+            };
+            let mut entity = Entity::new(kind, x + span + dx as u16, y + dy as u16);
+            entity.behavior = BOSS_PATROL;
+            game.entities.push(entity);
+        }
+    }
+    game.events.push(GameEvent::BossAppeared);
+    true
+}
+
+/// Only what's around the head shows, food further away is an arrow on the edge.
+pub struct Fog {
+    // In columns, rows count double since they're about twice as tall
+    pub radius: u16,
+}
+
+impl Rule for Fog {
+    fn name(&self) -> &'static str {
+        "fog"
+    }
+
+    fn describe(&self) -> String {
+        "Only what's near the head shows, arrows on the edge point to food.".to_string()
+    }
+
+    fn view_radius(&self) -> Option<u16> {
+        Some(self.radius)
+    }
+}
+
+/// Rooms walled in on every side, a door on the right leads to the next
+/// room and one on the left to the one before. The game swaps them in and
+/// out, see `Game::rooms`.
+pub struct Rooms {
+    pub count: usize,
+}
+
+impl Rule for Rooms {
+    fn name(&self) -> &'static str {
+        "rooms"
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "{} rooms joined by doors on the sides, each with its own walls and food.",
+            self.count
+        )
+    }
+
+    fn on_start(&mut self, game: &mut Game) {
+        game.rooms = (0..self.count)
+            .map(|room| room_layout(room, game.cols, game.rows))
+            .collect();
+        game.entities = game.rooms[0].clone();
+    }
+}
+
+/// The walls and doors of a room: a wall all around with a door halfway down
+/// the left and right sides, and something different in the middle of each.
+pub fn room_layout(room: usize, cols: u16, rows: u16) -> Vec<Entity> {
+    // Too small for walls, the doors would be all there is
+    if cols < 7 || rows < 5 {
+        return Vec::new();
+    }
+    let door_y = rows / 2;
+    let mut walls = Vec::new();
+    for x in 0..cols {
+        walls.push((x, 0));
+        walls.push((x, rows - 1));
+    }
+    for y in (1..rows - 1).filter(|&y| y != door_y) {
+        walls.push((0, y));
+        walls.push((cols - 1, y));
+    }
+    // The first room is empty, the snake starts in the middle of it
+    match room % 3 {
+        1 => {
+            for x in cols / 4..cols - cols / 4 {
+                walls.push((x, rows / 4));
+                walls.push((x, rows - 1 - rows / 4));
+            }
+        }
+        2 => {
+            for (x, y) in [(1, 1), (2, 1), (1, 2), (2, 2)] {
+                walls.push((x * cols / 3, y * rows / 3));
+            }
+        }
+        _ => {}
+    }
+
+    let mut entities: Vec<Entity> = walls
+        .into_iter()
+        .map(|(x, y)| Entity::new(EntityKind::Wall, x, y))
+        .collect();
+    entities.push(Entity::new(EntityKind::Door, 0, door_y));
+    entities.push(Entity::new(EntityKind::Door, cols - 1, door_y));
+    entities
+}
+
+/// Like the old QBasic Nibbles: the snake stops growing at `max_length`,
+/// from then on food is worth an extra point and makes the game faster still.
+pub struct Nibbles {
+    pub max_length: usize,
+}
+
+// How much faster each food at full length makes it, and how fast it gets
+const NIBBLES_SPEED_UP: u64 = 10;
+const NIBBLES_FASTEST: u64 = 30;
+
+impl Rule for Nibbles {
+    fn name(&self) -> &'static str {
+        "nibbles"
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "The snake stops growing at {}, then food is worth more and speeds things up.",
+            self.max_length
+        )
+    }
+
+    fn on_eat(&mut self, game: &mut Game) {
+        if game.snake.body.len() < self.max_length {
+            return;
+        }
+        game.score += 1;
+        let timer = game
+            .timer
+            .saturating_sub(NIBBLES_SPEED_UP)
+            .max(NIBBLES_FASTEST);
+        if timer < game.timer {
+            game.timer = timer;
+            game.events.push(GameEvent::SpeedUp { timer });
+        }
+    }
+
+    fn max_length(&self) -> Option<usize> {
+        Some(self.max_length)
+    }
+}
+
+/// Food is letters, and each one eaten rides along at the end of the tail.
+/// When the last ones spell `game.word`, in the order they were eaten, they
+/// come off the snake for `WORD_BONUS` points a letter and another word
+/// comes up. Most food is the letter the word needs next, the rest are
+/// letters it doesn't, and eating one of those starts the word over.
+pub struct Words;
+
+pub const WORD_BONUS: u32 = 3;
+// One in this many letters isn't the one the word needs
+const WRONG_LETTER_EVERY: u32 = 4;
+const WORDS: [&str; 12] = [
+    "SNAKE", "APPLE", "RUST", "TAIL", "CRAB", "BYTE", "GRASS", "ORBIT", "PIXEL", "CARGO", "VENOM",
+    "HISS",
+];
+
+impl Words {
+    fn new_word(game: &mut Game) {
+        game.word = game.rng.pick(&WORDS).to_string();
+    }
+}
+
+impl Rule for Words {
+    fn name(&self) -> &'static str {
+        "words"
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "Food is letters, spell the word with them in order for {WORD_BONUS} points a letter."
+        )
+    }
+
+    fn on_start(&mut self, game: &mut Game) {
+        Words::new_word(game);
+    }
+
+    fn on_eat(&mut self, game: &mut Game) {
+        let spelled = game.spelled();
+        if spelled < game.word.len() {
+            return;
+        }
+        let length = game.snake.body.len() - spelled;
+        game.snake.body.truncate(length);
+        let bonus = WORD_BONUS * spelled as u32;
+        game.score += bonus;
+        game.events.push(GameEvent::WordSpelled { bonus });
+        Words::new_word(game);
+    }
+
+    fn food_kind(&mut self, game: &mut Game, kind: EntityKind) -> EntityKind {
+        let Some(&next) = game.word.as_bytes().get(game.spelled()) else {
+            return kind;
+        };
+        if game.rng.below(WRONG_LETTER_EVERY) > 0 {
+            return EntityKind::Letter(next);
+        }
+        // Anything but the right one: one of the other 25, past it for the
+        // ones after it
+        let wrong = b'A' + game.rng.below(25) as u8;
+        EntityKind::Letter(if wrong >= next { wrong + 1 } else { wrong })
+    }
+}
+
+/// The snake as it was `delay` ticks ago follows it around as a ghost, and
+/// running into the ghost is as bad as into a wall. Going round in a tight
+/// loop or doubling back runs into it. `game.trail` keeps where the head
+/// has been for as long as the ghost needs, the ghost's cells are `Ghost`
+/// entities put back every tick (never over the snake or anything else).
+pub struct Mirror {
+    pub delay: usize,
+}
+
+impl Rule for Mirror {
+    fn name(&self) -> &'static str {
+        "mirror"
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "Your ghost from {} ticks ago follows you, don't run into it.",
+            self.delay
+        )
+    }
+
+    fn on_tick(&mut self, game: &mut Game) {
+        let length = game.snake.body.len();
+        game.trail.push_front(game.snake.head());
+        game.trail.truncate(self.delay + length);
+        game.entities.retain(|e| e.kind != EntityKind::Ghost);
+        let ghost: Vec<SnakeBodyPoint> = game.trail.iter().skip(self.delay).copied().collect();
+        for point in ghost {
+            if game.is_free(point.x, point.y) {
+                game.entities
+                    .push(Entity::new(EntityKind::Ghost, point.x, point.y));
+            }
+        }
+    }
+}
+
+/// Like the old Surround: where the tail leaves a cell it stays a wall for
+/// `fade` ticks, fading out as it goes. `game.trail` keeps where the head
+/// has been, so how far past the snake's length a cell is in it is how long
+/// ago the tail left it. The cells are `Fading` entities put back every tick
+/// (never over the snake or anything else), fresher ones on top.
+pub struct Surround {
+    pub fade: usize,
+}
+
+impl Rule for Surround {
+    fn name(&self) -> &'static str {
+        "surround"
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "Where your tail was stays a wall for {} ticks before it fades.",
+            self.fade
+        )
+    }
+
+    fn on_tick(&mut self, game: &mut Game) {
+        let length = game.snake.body.len();
+        game.trail.push_front(game.snake.head());
+        game.trail.truncate(length + self.fade);
+        game.entities
+            .retain(|e| !matches!(e.kind, EntityKind::Fading(_)));
+        let left: Vec<SnakeBodyPoint> = game.trail.iter().skip(length).copied().collect();
+        for (age, point) in left.into_iter().enumerate() {
+            let stage = (FADING_STAGES - age * FADING_STAGES / self.fade) as u8;
+            if game.is_free(point.x, point.y) {
+                game.entities
+                    .push(Entity::new(EntityKind::Fading(stage), point.x, point.y));
+            }
+        }
+    }
+}
+
+/// Every `every`th food is hydra food: eating it splits the back half of the
+/// snake off into another head (see `Game::heads`), up to `most` more. Every
+/// head takes the same turns, and any of them running into anything, each
+/// other too, ends the game.
+pub struct Hydra {
+    pub every: u32,
+    pub most: usize,
+}
+
+impl Rule for Hydra {
+    fn name(&self) -> &'static str {
+        "hydra"
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "Every {}th food splits your tail off into another head that turns with you.",
+            self.every
+        )
+    }
+
+    fn food_kind(&mut self, game: &mut Game, kind: EntityKind) -> EntityKind {
+        let due = self.every > 0 && (game.score + 1).is_multiple_of(self.every);
+        if kind == EntityKind::Food && due && game.heads.len() < self.most {
+            EntityKind::Split
+        } else {
+            kind
+        }
+    }
+}
+
+/// Eating moves the head to a random free cell, still going the same way.
+pub struct Teleport;
+
+impl Rule for Teleport {
+    fn name(&self) -> &'static str {
+        "teleport"
+    }
+
+    fn describe(&self) -> String {
+        "Eating teleports the head somewhere else on the board.".to_string()
+    }
+
+    fn on_eat(&mut self, game: &mut Game) {
+        if let Some((x, y)) = game.random_free_cell() {
+            game.snake.body[0] = SnakeBodyPoint { x, y, letter: None };
+        }
+    }
+}
+
+/// Every `every` ticks the head lands a cell lower than where it was going,
+/// so the snake keeps sinking unless it's steered back up. Going up there's
+/// no drift, it would only take the head back where it was. Nor is there
+/// when the cell it was going to has anything on it, as the drift would
+/// jump over it: the head moves there as usual, wall or food.
+pub struct Gravity {
+    pub every: u64,
+}
+
+impl Rule for Gravity {
+    fn name(&self) -> &'static str {
+        "gravity"
+    }
+
+    fn describe(&self) -> String {
+        format!("Every {} ticks the snake drifts a cell down.", self.every)
+    }
+
+    fn modify_head(&mut self, game: &mut Game, head: SnakeBodyPoint) -> SnakeBodyPoint {
+        let due = self.every > 0 && (game.ticks + 1).is_multiple_of(self.every);
+        let clear = !game.is_fatal(head.x, head.y) && game.entity_at(head.x, head.y).is_none();
+        if !due || !clear || game.snake.direction == SnakeDirection::Up {
+            return head;
+        }
+        head.step(SnakeDirection::Down, game.cols, game.rows)
+    }
+}
+
+/// Ticks a full stomach lasts.
+pub const HUNGER_FULL: u32 = 100;
+
+/// The snake gets hungry: `game.hunger` runs down a tick at a time and fills
+/// up again with every food. Once it's empty the snake loses the end of its
+/// tail every `every` ticks, and starves when there's only the head left.
+pub struct Hunger {
+    pub every: u64,
+}
+
+impl Hunger {
+    fn due(&self, game: &Game) -> bool {
+        game.hunger == 0 && self.every > 0 && (game.ticks + 1).is_multiple_of(self.every)
+    }
+}
+
+impl Rule for Hunger {
+    fn name(&self) -> &'static str {
+        "hunger"
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "Eat every {HUNGER_FULL} ticks, or lose a segment every {} until you starve.",
+            self.every
+        )
+    }
+
+    fn on_start(&mut self, game: &mut Game) {
+        game.hunger = HUNGER_FULL;
+    }
+
+    fn kills(&mut self, game: &mut Game) -> Option<DeathCause> {
+        (self.due(game) && game.snake.body.len() == 1).then_some(DeathCause::Starved)
+    }
+
+    fn on_tick(&mut self, game: &mut Game) {
+        if game.hunger > 0 {
+            game.hunger -= 1;
+        } else if self.due(game) && game.snake.body.len() > 1 {
+            game.snake.body.pop();
+            game.pending_growth = 0;
+        }
+    }
+
+    fn on_eat(&mut self, game: &mut Game) {
+        game.hunger = HUNGER_FULL;
+    }
+}
+
+/// Every key turns the snake the other way, up goes down and left goes right.
+pub struct Inverted;
+
+impl Rule for Inverted {
+    fn name(&self) -> &'static str {
+        "inverted"
+    }
+
+    fn describe(&self) -> String {
+        "The arrows are the wrong way round.".to_string()
+    }
+
+    fn modify_turn(&mut self, _game: &mut Game, direction: SnakeDirection) -> SnakeDirection {
+        direction.opposite()
+    }
+}
+
+/// Every `every` ticks the food on the board goes off and turns up
+/// somewhere else, eaten or not.
+pub struct Fleeting {
+    pub every: u64,
+}
+
+impl Rule for Fleeting {
+    fn name(&self) -> &'static str {
+        "fleeting"
+    }
+
+    fn describe(&self) -> String {
+        format!("Every {} ticks the food moves somewhere else.", self.every)
+    }
+
+    fn on_tick(&mut self, game: &mut Game) {
+        if self.every == 0 || !(game.ticks + 1).is_multiple_of(self.every) {
+            return;
+        }
+        for i in 0..game.entities.len() {
+            if game.entities[i].kind == EntityKind::Food
+                && let Some((x, y)) = game.random_free_cell()
+            {
+                (game.entities[i].x, game.entities[i].y) = (x, y);
+            }
+        }
+    }
+}
+
+/// Hill zones to score in now and then, see `objective.rs`.
+pub struct Hills;
+
+impl Rule for Hills {
+    fn name(&self) -> &'static str {
+        "hills"
+    }
+
+    fn describe(&self) -> String {
+        format!(
+            "Every {ZONE_EVERY} ticks a hill zone opens for {ZONE_TICKS}, a point every {ZONE_POINT_EVERY} ticks with the head in it."
+        )
+    }
+
+    fn on_tick(&mut self, game: &mut Game) {
+        objective::open_and_close_zones(game);
+    }
+}
@@ -0,0 +1,33 @@
+// -- A tokio runtime for network I/O
+//
+// With the `tokio` feature, network features run as tasks on one runtime in
+// the background instead of a thread each. The game loop stays synchronous
+// and never waits on the runtime: tasks hand what came in over the same
+// channels the threads would, and the loop drains them between ticks, so
+// tick timing doesn't depend on how the network is doing.
+//
+// The runtime starts with the first task and has one worker thread, which
+// is plenty for a chat connection or two.
+
+use std::future::Future;
+use std::sync::LazyLock;
+use tokio::runtime::{Builder, Runtime};
+
+static RUNTIME: LazyLock<Runtime> = LazyLock::new(|| {
+    Builder::new_multi_thread()
+        .worker_threads(1)
+        .thread_name("snake2-io")
+        .enable_io()
+        .build()
+        .expect("can't start the I/O runtime")
+});
+
+/// Run `task` in the background.
+pub fn spawn(task: impl Future<Output = ()> + Send + 'static) {
+    RUNTIME.spawn(task);
+}
+
+/// Wait for `task`, for setting things up before the game starts.
+pub fn block_on<T>(task: impl Future<Output = T>) -> T {
+    RUNTIME.block_on(task)
+}
@@ -0,0 +1,667 @@
+// -- Saved games
+//
+// A save is a small text file, one `key value` pair per line after the
+// version header (see `versioned.rs`), so it's easy to look at when
+// something goes wrong:
+//
+//     snake2-save 12
+//     cols 80
+//     rows 23
+//     mode classic
+//     modifiers none
+//     score 3
+//     level 1
+//     timer 440
+//     speed usual
+//     ticks 120
+//     growth 0
+//     magnet 0
+//     room 0
+//     seed 1234
+//     rng 5678
+//     close_calls 2
+//     chain 0
+//     word -
+//     trail -
+//     heads -
+//     hunger 0
+//     direction right
+//     body 10,5 9,5 8,5:A
+//     entity food 3,4
+//
+// `word` is word mode's (`-` in the others), and so are the letters the
+// segments of the body may carry after a `:`. `trail` is mirror mode's,
+// where the head was, the last tick's first. `heads` is hydra mode's other
+// snakes, each its direction and body, `|` between them:
+// `heads up 4,6 4,7 | left 9,2`. `hunger` is the ticks left before a snake
+// with the hunger modifier starts starving. `speed` is `custom` once `+` or
+// `-` changed the timer, `usual` before.
+//
+// Rules come back from the mode, they don't keep any state of their own.
+// Only the room the snake is in is saved, the others start over from their
+// layout. The random number generator is saved as it is, so the game goes on
+// with the same food it would have had.
+//
+// `save_game_binary` has the same in a much smaller file that isn't meant to
+// be read, see the layout there.
+
+use crate::binary::{Reader, Writer};
+use crate::entity::{Entity, EntityKind};
+use crate::game::{Game, GameOptions, LEVEL_EVERY};
+use crate::rules::{BOSS_PATROL, Mode, Modifiers};
+use crate::snake::{Snake, SnakeBodyPoint, SnakeDirection};
+use crate::versioned::{Format, unchanged};
+
+pub const SAVE: Format = Format {
+    kind: "save",
+    // 1: the header, 2: `level`, 3: `magnet`, 4: `modifiers`, 5: `room`,
+    // 6: `seed` and `rng`, 7: `close_calls`, 8: `chain`, 9: `word`,
+    // 10: `trail`, 11: `heads`, 12: `hunger`, 13: `speed`
+    migrations: &[
+        unchanged,
+        add_level,
+        add_magnet,
+        add_modifiers,
+        add_room,
+        add_rng,
+        add_close_calls,
+        add_chain,
+        add_word,
+        add_trail,
+        add_heads,
+        add_hunger,
+        add_speed,
+    ],
+};
+
+// Of the layout of `save_game_binary`, 2 added `chain`, 3 `word` and
+// letters, 4 `trail`, 5 `heads`, 6 `hunger`, 7 `speed`
+const BINARY_VERSION: u64 = 7;
+
+// Way past any terminal, and it keeps a hand-edited save from asking for
+// billions of cells
+pub const MAX_BOARD: u16 = 1000;
+
+pub fn save_game(game: &Game) -> String {
+    let body: Vec<String> = game
+        .snake
+        .body
+        .iter()
+        .map(|p| match p.letter {
+            Some(letter) => format!("{},{}:{}", p.x, p.y, letter as char),
+            None => format!("{},{}", p.x, p.y),
+        })
+        .collect();
+
+    let mut text = String::new();
+    text.push_str(&format!("cols {}\n", game.cols));
+    text.push_str(&format!("rows {}\n", game.rows));
+    text.push_str(&format!("mode {}\n", game.mode.name()));
+    text.push_str(&format!("modifiers {}\n", game.modifiers));
+    text.push_str(&format!("score {}\n", game.score));
+    text.push_str(&format!("level {}\n", game.level));
+    text.push_str(&format!("timer {}\n", game.timer));
+    let speed = if game.custom_speed { "custom" } else { "usual" };
+    text.push_str(&format!("speed {speed}\n"));
+    text.push_str(&format!("ticks {}\n", game.ticks));
+    text.push_str(&format!("growth {}\n", game.pending_growth));
+    text.push_str(&format!("magnet {}\n", game.magnet_ticks));
+    text.push_str(&format!("room {}\n", game.room));
+    text.push_str(&format!("seed {}\n", game.seed));
+    text.push_str(&format!("rng {}\n", game.rng.state));
+    text.push_str(&format!("close_calls {}\n", game.close_calls));
+    text.push_str(&format!("chain {}\n", game.chain_ticks));
+    let word = if game.word.is_empty() {
+        "-"
+    } else {
+        &game.word
+    };
+    text.push_str(&format!("word {word}\n"));
+    let trail: Vec<String> = game
+        .trail
+        .iter()
+        .map(|p| format!("{},{}", p.x, p.y))
+        .collect();
+    let trail = if trail.is_empty() {
+        "-".to_string()
+    } else {
+        trail.join(" ")
+    };
+    text.push_str(&format!("trail {trail}\n"));
+    let heads: Vec<String> = game
+        .heads
+        .iter()
+        .map(|head| {
+            let body: Vec<String> = head
+                .body
+                .iter()
+                .map(|p| format!("{},{}", p.x, p.y))
+                .collect();
+            format!("{} {}", head.direction.name(), body.join(" "))
+        })
+        .collect();
+    let heads = if heads.is_empty() {
+        "-".to_string()
+    } else {
+        heads.join(" | ")
+    };
+    text.push_str(&format!("heads {heads}\n"));
+    text.push_str(&format!("hunger {}\n", game.hunger));
+    text.push_str(&format!("direction {}\n", game.snake.direction.name()));
+    text.push_str(&format!("body {}\n", body.join(" ")));
+    for entity in &game.entities {
+        text.push_str(&format!(
+            "entity {} {},{}\n",
+            entity.kind.name(),
+            entity.x,
+            entity.y
+        ));
+    }
+    SAVE.write(&text)
+}
+
+pub fn load_game(text: &str) -> Result<Game, String> {
+    let text = SAVE.upgrade(text)?;
+    let mut fields: Vec<(&str, &str)> = Vec::new();
+    for (n, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        let (key, value) = line
+            .split_once(' ')
+            .ok_or(format!("line {}: expected `key value`", n + 1))?;
+        fields.push((key, value.trim()));
+    }
+
+    let field = |key: &str| {
+        fields
+            .iter()
+            .find(|(k, _)| *k == key)
+            .map(|(_, v)| *v)
+            .ok_or(format!("missing `{key}`"))
+    };
+    fn number<T: std::str::FromStr>(key: &str, value: &str) -> Result<T, String> {
+        value.parse().map_err(|_| format!("bad `{key}`: {value}"))
+    }
+
+    let mut entities = Vec::new();
+    for (_, value) in fields.iter().filter(|(k, _)| *k == "entity") {
+        let (kind, point) = value
+            .split_once(' ')
+            .ok_or(format!("bad `entity`: {value}"))?;
+        let kind = EntityKind::from_name(kind).ok_or(format!("unknown entity `{kind}`"))?;
+        let (x, y) = parse_point(point.trim())?;
+        entities.push((kind, x, y));
+    }
+
+    Saved {
+        cols: number("cols", field("cols")?)?,
+        rows: number("rows", field("rows")?)?,
+        mode: field("mode")?.parse()?,
+        modifiers: field("modifiers")?.parse()?,
+        score: number("score", field("score")?)?,
+        level: number("level", field("level")?)?,
+        timer: number("timer", field("timer")?)?,
+        custom_speed: match field("speed")? {
+            "usual" => false,
+            "custom" => true,
+            speed => return Err(format!("bad `speed`: {speed}")),
+        },
+        ticks: number("ticks", field("ticks")?)?,
+        growth: number("growth", field("growth")?)?,
+        magnet: number("magnet", field("magnet")?)?,
+        room: number("room", field("room")?)?,
+        seed: number("seed", field("seed")?)?,
+        rng: number("rng", field("rng")?)?,
+        close_calls: number("close_calls", field("close_calls")?)?,
+        chain: number("chain", field("chain")?)?,
+        word: match field("word")? {
+            "-" => String::new(),
+            word => word.to_string(),
+        },
+        trail: match field("trail")? {
+            "-" => Vec::new(),
+            trail => trail
+                .split_whitespace()
+                .map(parse_point)
+                .collect::<Result<Vec<_>, _>>()?,
+        },
+        heads: match field("heads")? {
+            "-" => Vec::new(),
+            heads => heads
+                .split('|')
+                .map(|head| {
+                    let mut words = head.split_whitespace();
+                    let direction = words.next().unwrap_or("");
+                    let direction = SnakeDirection::from_name(direction)
+                        .ok_or(format!("bad `heads` direction: {direction}"))?;
+                    let body = words.map(parse_point).collect::<Result<Vec<_>, _>>()?;
+                    Ok((direction, body))
+                })
+                .collect::<Result<Vec<_>, String>>()?,
+        },
+        hunger: number("hunger", field("hunger")?)?,
+        direction: SnakeDirection::from_name(field("direction")?)
+            .ok_or(format!("bad `direction`: {}", field("direction")?))?,
+        body: field("body")?
+            .split_whitespace()
+            .map(|segment| match segment.split_once(':') {
+                Some((point, letter)) => match letter.as_bytes() {
+                    &[letter] => Ok((parse_point(point)?, Some(letter))),
+                    _ => Err(format!("bad letter `{letter}`")),
+                },
+                None => Ok((parse_point(segment)?, None)),
+            })
+            .collect::<Result<Vec<_>, String>>()?,
+        entities,
+    }
+    .into_game()
+}
+
+/// The same game as `save_game` in a few bytes, for replays and anything
+/// else that keeps a lot of them. After the header (see `binary.rs`) it's
+/// every field of the text save in the same order, as varints: the mode,
+/// modifiers (their bits) and direction (up, down, left, right) as numbers,
+/// `seed` and `rng` fixed 8 bytes, then the length of the body and `x y` for
+/// each segment from the head, and the number of entities and `kind x y`
+/// for each (`EntityKind::code`). `word` is its length and its bytes, and
+/// each segment has a third number, its letter or 0. `trail` is its length
+/// and `x y` for each point, `heads` how many and for each its direction,
+/// length and points, then `hunger`, then `speed` as 0 or 1. Versions 1
+/// (without `chain`), 2 (without `word` and letters), 3 (without `trail`),
+/// 4 (without `heads`), 5 (without `hunger`) and 6 (without `speed`) still
+/// load.
+pub fn save_game_binary(game: &Game) -> Vec<u8> {
+    let mut out = Writer::new(b's', BINARY_VERSION);
+    for n in [game.cols, game.rows] {
+        out.uint(n.into());
+    }
+    out.uint(game.mode.code().into());
+    out.uint(game.modifiers.bits().into());
+    for n in [game.score, game.level] {
+        out.uint(n.into());
+    }
+    out.uint(game.timer);
+    out.uint(game.ticks);
+    for n in [game.pending_growth, game.magnet_ticks] {
+        out.uint(n.into());
+    }
+    out.uint(game.room as u64);
+    out.fixed(game.seed);
+    out.fixed(game.rng.state);
+    out.uint(game.close_calls.into());
+    out.uint(game.chain_ticks.into());
+    out.bytes(game.word.as_bytes());
+    out.uint(game.trail.len() as u64);
+    for p in &game.trail {
+        out.uint(p.x.into());
+        out.uint(p.y.into());
+    }
+    out.uint(game.heads.len() as u64);
+    for head in &game.heads {
+        out.uint(direction_code(head.direction));
+        out.uint(head.body.len() as u64);
+        for p in &head.body {
+            out.uint(p.x.into());
+            out.uint(p.y.into());
+        }
+    }
+    out.uint(game.hunger.into());
+    out.uint(game.custom_speed.into());
+    out.uint(direction_code(game.snake.direction));
+    out.uint(game.snake.body.len() as u64);
+    for p in &game.snake.body {
+        out.uint(p.x.into());
+        out.uint(p.y.into());
+        out.uint(p.letter.unwrap_or(0).into());
+    }
+    out.uint(game.entities.len() as u64);
+    for e in &game.entities {
+        out.uint(e.kind.code().into());
+        out.uint(e.x.into());
+        out.uint(e.y.into());
+    }
+    out.bytes
+}
+
+pub fn load_game_binary(bytes: &[u8]) -> Result<Game, String> {
+    let (mut input, version) = Reader::open(bytes, b's', "save")?;
+    if version == 0 || version > BINARY_VERSION {
+        return Err(format!(
+            "this binary save is version {version}, this snake2 reads up to version {BINARY_VERSION}"
+        ));
+    }
+    let cols = input.number("cols")?;
+    let rows = input.number("rows")?;
+    let mode = Mode::from_code(input.number("mode")?).ok_or("bad `mode`")?;
+    let modifiers = Modifiers::from_bits(input.number("modifiers")?).ok_or("bad `modifiers`")?;
+    let score = input.number("score")?;
+    let level = input.number("level")?;
+    let timer = input.uint("timer")?;
+    let ticks = input.uint("ticks")?;
+    let growth = input.number("growth")?;
+    let magnet = input.number("magnet")?;
+    let room = input.number("room")?;
+    let seed = input.fixed("seed")?;
+    let rng = input.fixed("rng")?;
+    let close_calls = input.number("close_calls")?;
+    let chain = if version >= 2 {
+        input.number("chain")?
+    } else {
+        0
+    };
+    let word = if version >= 3 {
+        String::from_utf8(input.bytes("word")?.to_vec()).map_err(|_| "bad `word`")?
+    } else {
+        String::new()
+    };
+    // Nothing allocated up front, a bad length runs out of bytes instead of
+    // asking for gigabytes
+    let mut trail = Vec::new();
+    if version >= 4 {
+        for _ in 0..input.uint("trail")? {
+            trail.push((input.number("trail")?, input.number("trail")?));
+        }
+    }
+    let mut heads = Vec::new();
+    if version >= 5 {
+        for _ in 0..input.uint("heads")? {
+            let direction = direction_from_code(input.uint("heads")?).ok_or("bad `heads`")?;
+            let mut body = Vec::new();
+            for _ in 0..input.uint("heads")? {
+                body.push((input.number("heads")?, input.number("heads")?));
+            }
+            heads.push((direction, body));
+        }
+    }
+    let hunger = if version >= 6 {
+        input.number("hunger")?
+    } else {
+        0
+    };
+    let custom_speed = match version {
+        7.. => match input.uint("speed")? {
+            0 => false,
+            1 => true,
+            _ => return Err("bad `speed`".into()),
+        },
+        _ => false,
+    };
+    let direction = direction_from_code(input.uint("direction")?).ok_or("bad `direction`")?;
+
+    let mut body = Vec::new();
+    for _ in 0..input.uint("body")? {
+        let point = (input.number("body")?, input.number("body")?);
+        let letter = match version {
+            1 | 2 => 0,
+            _ => input.number("body")?,
+        };
+        body.push((point, (letter != 0).then_some(letter)));
+    }
+    let mut entities = Vec::new();
+    for _ in 0..input.uint("entities")? {
+        let kind = EntityKind::from_code(input.number("entity")?).ok_or("bad `entity`")?;
+        entities.push((kind, input.number("entity")?, input.number("entity")?));
+    }
+    input.finish()?;
+
+    Saved {
+        cols,
+        rows,
+        mode,
+        modifiers,
+        score,
+        level,
+        timer,
+        custom_speed,
+        ticks,
+        growth,
+        magnet,
+        room,
+        seed,
+        rng,
+        close_calls,
+        chain,
+        word,
+        trail,
+        heads,
+        hunger,
+        direction,
+        body,
+        entities,
+    }
+    .into_game()
+}
+
+/// Up, down, left and right as 0 to 3, the way binary files keep them.
+pub fn direction_code(direction: SnakeDirection) -> u64 {
+    match direction {
+        SnakeDirection::Up => 0,
+        SnakeDirection::Down => 1,
+        SnakeDirection::Left => 2,
+        SnakeDirection::Right => 3,
+    }
+}
+
+pub fn direction_from_code(code: u64) -> Option<SnakeDirection> {
+    match code {
+        0 => Some(SnakeDirection::Up),
+        1 => Some(SnakeDirection::Down),
+        2 => Some(SnakeDirection::Left),
+        3 => Some(SnakeDirection::Right),
+        _ => None,
+    }
+}
+
+// What a save has in it, read from either kind of file but not checked yet
+struct Saved {
+    cols: u16,
+    rows: u16,
+    mode: Mode,
+    modifiers: Modifiers,
+    score: u32,
+    level: u32,
+    timer: u64,
+    custom_speed: bool,
+    ticks: u64,
+    growth: u32,
+    magnet: u32,
+    room: usize,
+    seed: u64,
+    rng: u64,
+    close_calls: u32,
+    chain: u32,
+    word: String,
+    trail: Vec<(u16, u16)>,
+    heads: Vec<(SnakeDirection, Vec<(u16, u16)>)>,
+    hunger: u32,
+    direction: SnakeDirection,
+    body: Vec<((u16, u16), Option<u8>)>,
+    entities: Vec<(EntityKind, u16, u16)>,
+}
+
+impl Saved {
+    fn into_game(self) -> Result<Game, String> {
+        let (cols, rows) = (self.cols, self.rows);
+        if cols < 2 || rows < 2 {
+            return Err(format!("board too small: {cols}x{rows}"));
+        }
+        if cols > MAX_BOARD || rows > MAX_BOARD {
+            return Err(format!("board too big: {cols}x{rows}"));
+        }
+        let on_board = |(x, y): (u16, u16)| {
+            if x >= cols || y >= rows {
+                return Err(format!("point `{x},{y}` is off the board"));
+            }
+            Ok((x, y))
+        };
+        let body = self
+            .body
+            .into_iter()
+            .map(|(p, letter)| on_board(p).map(|(x, y)| SnakeBodyPoint { x, y, letter }))
+            .collect::<Result<Vec<_>, _>>()?;
+        if body.is_empty() {
+            return Err("the snake has no body".into());
+        }
+
+        let options = GameOptions {
+            mode: self.mode,
+            modifiers: self.modifiers,
+            ..Default::default()
+        };
+        let mut game = Game::with_options(cols, rows, self.direction, &options);
+        game.score = self.score;
+        game.level = self.level;
+        game.timer = self.timer;
+        game.custom_speed = self.custom_speed;
+        game.ticks = self.ticks;
+        game.pending_growth = self.growth;
+        game.room = self.room;
+        game.seed = self.seed;
+        game.rng.state = self.rng;
+        game.close_calls = self.close_calls;
+        game.chain_ticks = self.chain;
+        game.hunger = self.hunger;
+        if body
+            .iter()
+            .flat_map(|p| p.letter)
+            .chain(self.word.bytes())
+            .any(|c| !c.is_ascii_uppercase())
+        {
+            return Err("letters have to be A to Z".into());
+        }
+        game.word = self.word;
+        game.trail = self
+            .trail
+            .into_iter()
+            .map(|p| on_board(p).map(|(x, y)| SnakeBodyPoint { x, y, letter: None }))
+            .collect::<Result<_, _>>()?;
+        for (direction, body) in self.heads {
+            let body = body
+                .into_iter()
+                .map(|p| on_board(p).map(|(x, y)| SnakeBodyPoint { x, y, letter: None }))
+                .collect::<Result<Vec<_>, _>>()?;
+            if body.is_empty() {
+                return Err("a head with no body".into());
+            }
+            game.heads.push(Snake {
+                direction,
+                last_moved: direction,
+                body,
+            });
+        }
+        if game.room >= game.rooms.len().max(1) {
+            return Err(format!("no room {} in {}", game.room, game.mode.name()));
+        }
+        game.snake.body = body;
+        game.snake.direction = self.direction;
+        game.snake.last_moved = self.direction;
+        // Already next to the tail doesn't earn the bonus again
+        game.close_call = game.head_next_to_tail();
+
+        game.entities.clear();
+        for (kind, x, y) in self.entities {
+            let (x, y) = on_board((x, y))?;
+            let mut entity = Entity::new(kind, x, y);
+            // Bosses only ever patrol, so that isn't saved
+            if matches!(kind, EntityKind::Boss | EntityKind::WeakPoint(_)) {
+                entity.behavior = BOSS_PATROL;
+            }
+            game.entities.push(entity);
+        }
+        // After the food is back, so it's pulled too
+        game.set_magnet(self.magnet);
+
+        Ok(game)
+    }
+}
+
+// Version 1 had no levels, the score tells which one it would have been
+// (except in tron, where the score counts ticks and nothing levels up)
+fn add_level(body: &str) -> Result<String, String> {
+    let value = |key: &str| {
+        body.lines()
+            .find_map(|line| line.strip_prefix(key)?.strip_prefix(' '))
+            .map(str::trim)
+    };
+    let score: u32 = value("score").and_then(|s| s.parse().ok()).unwrap_or(0);
+    let level = match value("mode") {
+        Some("tron") => 1,
+        _ => score / LEVEL_EVERY + 1,
+    };
+    Ok(append_line(body, &format!("level {level}")))
+}
+
+// Magnets came in version 3, nobody had one on before
+fn add_magnet(body: &str) -> Result<String, String> {
+    Ok(append_line(body, "magnet 0"))
+}
+
+// And modifiers in version 4
+fn add_modifiers(body: &str) -> Result<String, String> {
+    Ok(append_line(body, "modifiers none"))
+}
+
+// Rooms came in version 5, everyone was in the first (and only) one
+fn add_room(body: &str) -> Result<String, String> {
+    Ok(append_line(body, "room 0"))
+}
+
+// Older saves didn't keep the generator, any state does for them
+fn add_rng(body: &str) -> Result<String, String> {
+    Ok(append_line(&append_line(body, "seed 0"), "rng 0"))
+}
+
+// Close call bonuses came in version 7
+fn add_close_calls(body: &str) -> Result<String, String> {
+    Ok(append_line(body, "close_calls 0"))
+}
+
+// Food chains came in version 8
+fn add_chain(body: &str) -> Result<String, String> {
+    Ok(append_line(body, "chain 0"))
+}
+
+// And word mode in version 9
+fn add_word(body: &str) -> Result<String, String> {
+    Ok(append_line(body, "word -"))
+}
+
+// And mirror mode in version 10
+fn add_trail(body: &str) -> Result<String, String> {
+    Ok(append_line(body, "trail -"))
+}
+
+// And hydra mode in version 11
+fn add_heads(body: &str) -> Result<String, String> {
+    Ok(append_line(body, "heads -"))
+}
+
+// The hunger modifier in version 12, nobody was hungry before
+fn add_hunger(body: &str) -> Result<String, String> {
+    Ok(append_line(body, "hunger 0"))
+}
+
+// And `+` and `-` in version 13, every game before went at the usual speed
+fn add_speed(body: &str) -> Result<String, String> {
+    Ok(append_line(body, "speed usual"))
+}
+
+fn append_line(body: &str, line: &str) -> String {
+    let newline = if body.ends_with('\n') || body.is_empty() {
+        ""
+    } else {
+        "\n"
+    };
+    format!("{body}{newline}{line}\n")
+}
+
+fn parse_point(value: &str) -> Result<(u16, u16), String> {
+    let (x, y) = value
+        .split_once(',')
+        .ok_or(format!("bad point `{value}`"))?;
+    let x: u16 = x.parse().map_err(|_| format!("bad point `{value}`"))?;
+    let y: u16 = y.parse().map_err(|_| format!("bad point `{value}`"))?;
+    Ok((x, y))
+}
@@ -0,0 +1,858 @@
+// -- A game server over WebSockets
+//
+// `snake2 server` (`--features server`) lets web pages and remote bots play
+// against the same engine as the terminal. Every connection is a game of its
+// own on its own thread, ticking at the game's speed: each tick goes out as a
+// text message with the board (the same JSON as `--io-protocol`, see
+// `protocol.rs`), and text messages coming in (`up`, `down`, `left`, `right`)
+// turn the snake in time for the next one. The last message has
+// `"over":true`, then the server closes the connection.
+//
+// Next to the board, the first message has the game's challenge code
+// (`"challenge"`, see `challenge.rs`) and every one after it how many turns
+// the server has taken in so far (`"turns"`), so a client can follow along
+// with a game of its own (see `remote.rs`). A text message `ping` and
+// anything after it comes back as `pong` and the same, to time the round
+// trip.
+//
+// `say` and anything after it is chat: it goes out to everyone on the
+// server, whatever game they're in, as `{"chat":{"from":N,"text":...}}`
+// with `N` the number of the connection it came from. The game doesn't
+// wait for it, and it's cut to `CHAT_LENGTH`.
+//
+// The first message also has a `"session"`. A client that lost its
+// connection before the game was over has `RESUME_WITHIN` to connect again
+// with `?session=` and it in the URL, and the game waits for it without
+// ticking. Its first message then has the whole game as it was, a binary
+// save (`"save"`, see `save.rs`) in URL-safe base64 like challenge codes,
+// and the turns taken in so far instead of the challenge.
+//
+// With `--royale` there's one game for everyone instead, see `royale.rs`.
+// Whoever connects waits in the lobby until there are `ServerConfig::royale`
+// players, or at least two and nobody new for `LOBBY_TICKS` ticks, then they
+// all play the round together on one thread, which ticks it and sends each
+// of them their own part of the board. Someone joining halfway watches until
+// the next round, someone leaving for good is out of this one. After the last
+// message of a round (`"over":true`, with the `"winner"`) it's back to the
+// lobby for `LOBBY_TICKS`, everyone still connected plays the next one.
+// `ServerConfig::teams` plays it in teams.
+//
+// Royale players get a `"session"` too, in a message of its own when they
+// connect. One whose connection goes during a round has `RESUME_WITHIN` to
+// come back with it, and the round doesn't wait: their snake goes straight
+// on meanwhile. Back on, they get their view with the next tick like
+// everyone else. Nobody takes over as host if the server goes: it's the
+// only one with the whole game, royale clients only see their part of it,
+// and a client with a game of its own plays on from its copy instead.
+//
+// A plain `GET /metrics` on the same port, no WebSocket, gets how the
+// server is doing in Prometheus' text format, for whoever hosts one: the
+// games being played, how many there have been, their average score once
+// over and how late ticks go out. In a royale every player is a game.
+//
+// Just enough WebSocket (RFC 6455) is written out here, like Discord and
+// Twitch are: the HTTP upgrade, unfragmented text frames, ping and close.
+// Reading a connection blocks, so that happens on a thread of its own which
+// hands what came in over a channel, the same way `twitch.rs` does.
+
+use crate::challenge::{Challenge, encode_base64};
+use crate::discovery::{advertise, host_name};
+use crate::game::{Game, GameOptions, TickOutcome};
+use crate::json;
+use crate::protocol::state_json;
+use crate::royale::Royale;
+use crate::save::{load_game_binary, save_game_binary};
+use crate::snake::SnakeDirection;
+use crate::terminal::base64;
+use std::collections::{BTreeMap, HashMap};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+// From the RFC, appended to the client's key before hashing it
+const GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
+// Nobody steering sends more than this, anything bigger is a broken client
+const MAX_MESSAGE: u64 = 1024;
+
+pub const TEXT: u8 = 0x1;
+pub const CLOSE: u8 = 0x8;
+const PING: u8 = 0x9;
+const PONG: u8 = 0xA;
+
+// A royale round starts this long after the last player came in
+const LOBBY_TICKS: u32 = 50;
+const ROYALE_TICK: Duration = Duration::from_millis(120);
+// A client this slow to take its view is dropped, not waited for
+const WRITE_TIMEOUT: Duration = Duration::from_secs(1);
+// Characters of a chat message, the rest is cut off
+pub const CHAT_LENGTH: usize = 120;
+// How long a game whose client went away waits for it to come back
+pub const RESUME_WITHIN: Duration = Duration::from_secs(30);
+
+#[derive(Clone, Copy, Debug)]
+pub struct ServerConfig {
+    pub port: u16,
+    pub cols: u16,
+    pub rows: u16,
+    pub options: GameOptions,
+    // One game for up to this many players instead of one each
+    pub royale: Option<usize>,
+    // The royale's players in teams, see `royale.rs`
+    pub teams: bool,
+    // Answer `snake2 join` on the LAN, see `discovery.rs`
+    pub advertise: bool,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            port: 9000,
+            cols: 40,
+            rows: 20,
+            options: GameOptions::default(),
+            royale: None,
+            teams: false,
+            advertise: true,
+        }
+    }
+}
+
+/// Accept connections forever, a game for each, or all in the same royale.
+pub fn serve(config: &ServerConfig) -> io::Result<()> {
+    let listener = TcpListener::bind(("0.0.0.0", config.port))?;
+    let shared = Shared::default();
+    if config.advertise {
+        let (config, scores) = (*config, shared.scores.clone());
+        let name = format!("{} {}", host_name(), config.port);
+        let info = move || lan_info(&config, &scores.lock().unwrap());
+        if let Err(e) = advertise(&name, config.port, info) {
+            eprintln!("Can't answer multicast DNS, `snake2 join` won't find this server: {e}");
+        }
+    }
+    if let Some(players) = config.royale {
+        return serve_royale(listener, config, players, &shared);
+    }
+    let mut next_id = 0;
+    for stream in listener.incoming() {
+        let (config, shared) = (*config, shared.clone());
+        let Ok(stream) = stream else {
+            continue;
+        };
+        next_id += 1;
+        let id = next_id;
+        thread::spawn(move || {
+            // A client going away halfway is nothing to stop the server for
+            let _ = play(stream, &config, id, &shared);
+            shared
+                .everyone
+                .lock()
+                .unwrap()
+                .retain(|(other, _)| *other != id);
+            shared.scores.lock().unwrap().remove(&id);
+        });
+    }
+    Ok(())
+}
+
+type Peer = (u32, Sender<Incoming>);
+
+// What the reading thread hands over
+enum Incoming {
+    Turn(SnakeDirection),
+    Ping(Vec<u8>),
+    // A `ping` text message, answered with what goes back
+    Echo(String),
+    // A `say` text message, what was said
+    Say(String),
+    // Somebody's chat, as it goes out
+    Chat(String),
+    Close,
+}
+
+// Games whose client went away, by session: the binary save, the turns
+// taken in and since when
+type Parked = HashMap<String, (Vec<u8>, u64, Instant)>;
+
+// What the connections' threads have in common
+#[derive(Clone, Default)]
+struct Shared {
+    // Every connection with a game of its own, for chat to get to all of them
+    everyone: Arc<Mutex<Vec<Peer>>>,
+    parked: Arc<Mutex<Parked>>,
+    // The score of every game going on, by connection, for the LAN to see
+    scores: Arc<Mutex<BTreeMap<u32, u32>>>,
+    metrics: Arc<Mutex<Metrics>>,
+}
+
+/// What `/metrics` has to say, counted since the server started.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Metrics {
+    pub in_progress: u64,
+    pub games: u64,
+    // Games that are over, and their scores added up
+    pub finished: u64,
+    pub total_score: u64,
+    pub ticks: u64,
+    // How much later than they were due all the ticks went out, added up
+    pub lateness: Duration,
+}
+
+impl Metrics {
+    // A tick due at `due` just went out
+    fn ticked(&mut self, due: Instant) {
+        self.ticks += 1;
+        self.lateness += Instant::now().saturating_duration_since(due);
+    }
+
+    /// In Prometheus' text format.
+    pub fn text(&self) -> String {
+        let average = self.total_score as f64 / self.finished.max(1) as f64;
+        [
+            (
+                "games_in_progress",
+                "gauge",
+                "Games being played right now.",
+                self.in_progress.to_string(),
+            ),
+            (
+                "games_total",
+                "counter",
+                "Games started.",
+                self.games.to_string(),
+            ),
+            (
+                "average_score",
+                "gauge",
+                "Average score of the games that are over.",
+                average.to_string(),
+            ),
+        ]
+        .iter()
+        .map(|(name, kind, help, value)| {
+            format!(
+                "# HELP snake2_{name} {help}\n# TYPE snake2_{name} {kind}\nsnake2_{name} {value}\n"
+            )
+        })
+        .chain([format!(
+            "# HELP snake2_tick_latency_seconds How late ticks went out after they were due.\n\
+             # TYPE snake2_tick_latency_seconds summary\n\
+             snake2_tick_latency_seconds_sum {}\n\
+             snake2_tick_latency_seconds_count {}\n",
+            self.lateness.as_secs_f64(),
+            self.ticks
+        )])
+        .collect()
+    }
+}
+
+// What the server says about itself to `snake2 join`, see `discovery.rs`
+fn lan_info(config: &ServerConfig, scores: &BTreeMap<u32, u32>) -> Vec<String> {
+    let mut info = vec![
+        format!("mode={}", config.options.mode.name()),
+        format!("size={}x{}", config.cols, config.rows),
+        format!("players={}", scores.len()),
+        format!("best={}", scores.values().max().unwrap_or(&0)),
+    ];
+    if let Some(players) = config.royale {
+        info.push(format!("royale={players}"));
+    }
+    if config.teams {
+        info.push("teams=2".to_string());
+    }
+    info
+}
+
+/// A chat message as it goes out to everyone.
+pub fn chat_json(from: u32, text: &str) -> String {
+    format!(
+        r#"{{"chat":{{"from":{from},"text":{}}}}}"#,
+        json::string(text)
+    )
+}
+
+// Whatever was said without what a terminal would do something with
+fn clean_chat(text: &str) -> String {
+    text.trim()
+        .chars()
+        .filter(|c| !c.is_control())
+        .take(CHAT_LENGTH)
+        .collect()
+}
+
+fn broadcast(shared: &Shared, message: &str) {
+    shared
+        .everyone
+        .lock()
+        .unwrap()
+        .retain(|(_, sender)| sender.send(Incoming::Chat(message.to_string())).is_ok());
+}
+
+// Hands what comes in on `reader` to `sender` until the client closes
+fn read_messages<T: Send + 'static>(
+    mut reader: BufReader<TcpStream>,
+    sender: mpsc::Sender<T>,
+    wrap: impl Fn(Incoming) -> T + Send + 'static,
+) {
+    thread::spawn(move || {
+        loop {
+            let message = match read_frame(&mut reader, MAX_MESSAGE) {
+                Ok((TEXT, payload)) => {
+                    let text = String::from_utf8_lossy(&payload);
+                    if let Some(rest) = text.strip_prefix("ping") {
+                        Incoming::Echo(format!("pong{rest}"))
+                    } else if let Some(said) = text.strip_prefix("say ") {
+                        Incoming::Say(clean_chat(said))
+                    } else if let Some(direction) = SnakeDirection::from_name(&text) {
+                        Incoming::Turn(direction)
+                    } else {
+                        continue;
+                    }
+                }
+                Ok((PING, payload)) => Incoming::Ping(payload),
+                Ok((CLOSE, _)) | Err(_) => Incoming::Close,
+                Ok(_) => continue,
+            };
+            let closing = matches!(message, Incoming::Close);
+            // The game is over when nobody listens anymore
+            if sender.send(wrap(message)).is_err() || closing {
+                return;
+            }
+        }
+    });
+}
+
+fn play(mut stream: TcpStream, config: &ServerConfig, id: u32, shared: &Shared) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let Some(path) = handshake(&mut reader, &mut stream, &shared.metrics)? else {
+        return Ok(());
+    };
+
+    let (sender, incoming) = mpsc::channel();
+    shared.everyone.lock().unwrap().push((id, sender.clone()));
+    read_messages(reader, sender, |message| message);
+
+    let resumed = path
+        .split_once("session=")
+        .and_then(|(_, session)| unpark(shared, session.split('&').next()?));
+    let (session, mut game, mut turns, hello) = match resumed {
+        Some((session, save, turns)) => {
+            let game = load_game_binary(&save)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+            let hello = with_field(
+                &state_json(&game, None),
+                "save",
+                &json::string(&encode_base64(&save)),
+            );
+            (session, game, turns, hello)
+        }
+        None => {
+            // Known up front, it goes out in the challenge code
+            let options = GameOptions {
+                seed: Some(config.options.seed.unwrap_or_else(rand::random)),
+                ..config.options
+            };
+            let game =
+                Game::with_options(config.cols, config.rows, SnakeDirection::Right, &options);
+            let challenge = Challenge::new(game.cols, game.rows, game.seed, &options);
+            let hello = with_field(
+                &state_json(&game, None),
+                "challenge",
+                &json::string(&challenge.code()),
+            );
+            let session = encode_base64(&rand::random::<[u8; 12]>());
+            shared.metrics.lock().unwrap().games += 1;
+            (session, game, 0, hello)
+        }
+    };
+    let hello = with_field(&hello, "session", &json::string(&session));
+    let hello = with_field(&hello, "turns", &turns.to_string());
+    shared.metrics.lock().unwrap().in_progress += 1;
+    let played = write_frame(&mut stream, TEXT, hello.as_bytes())
+        .and_then(|()| follow(&mut stream, &incoming, &mut game, &mut turns, (id, shared)));
+    let mut metrics = shared.metrics.lock().unwrap();
+    metrics.in_progress -= 1;
+    if played.as_ref().is_ok_and(|&over| over) {
+        metrics.finished += 1;
+        metrics.total_score += u64::from(game.score);
+        drop(metrics);
+        return write_frame(&mut stream, CLOSE, &[]);
+    }
+    drop(metrics);
+    // Gone halfway through the game, it waits for them to come back
+    let mut parked = shared.parked.lock().unwrap();
+    parked.retain(|_, (_, _, since)| since.elapsed() < RESUME_WITHIN);
+    parked.insert(session, (save_game_binary(&game), turns, Instant::now()));
+    drop(parked);
+    let _ = write_frame(&mut stream, CLOSE, &[]);
+    played.map(|_| ())
+}
+
+// The game ticking and going out to the client, true once it's over and
+// false when the client went away first
+fn follow(
+    stream: &mut TcpStream,
+    incoming: &Receiver<Incoming>,
+    game: &mut Game,
+    turns: &mut u64,
+    (id, shared): (u32, &Shared),
+) -> io::Result<bool> {
+    let mut next_tick = Instant::now() + Duration::from_millis(game.timer);
+    loop {
+        if !wait_for_tick(incoming, game, turns, stream, (id, shared), next_tick)? {
+            return Ok(false);
+        }
+        let outcome = game.tick();
+        shared.scores.lock().unwrap().insert(id, game.score);
+        let state = with_field(
+            &state_json(game, Some(outcome)),
+            "turns",
+            &turns.to_string(),
+        );
+        write_frame(stream, TEXT, state.as_bytes())?;
+        shared.metrics.lock().unwrap().ticked(next_tick);
+        if outcome == TickOutcome::Died {
+            return Ok(true);
+        }
+        next_tick += Duration::from_millis(game.timer);
+    }
+}
+
+// The game left behind under `session`, if it's still there
+fn unpark(shared: &Shared, session: &str) -> Option<(String, Vec<u8>, u64)> {
+    let (save, turns, since) = shared.parked.lock().unwrap().remove(session)?;
+    (since.elapsed() < RESUME_WITHIN).then(|| (session.to_string(), save, turns))
+}
+
+// What the royale's thread hears about, from any of the connections
+enum Hub {
+    // With the session it asked to go back to, if any
+    Joined(u32, TcpStream, Option<String>),
+    From(u32, Incoming),
+}
+
+fn serve_royale(
+    listener: TcpListener,
+    config: &ServerConfig,
+    players: usize,
+    shared: &Shared,
+) -> io::Result<()> {
+    let (sender, hub) = mpsc::channel();
+    let (config, royale_shared) = (*config, shared.clone());
+    thread::spawn(move || run_royale(&hub, &config, players, &royale_shared));
+
+    let mut next_id = 0;
+    for stream in listener.incoming() {
+        let Ok(mut stream) = stream else {
+            continue;
+        };
+        next_id += 1;
+        let (id, sender, metrics) = (next_id, sender.clone(), shared.metrics.clone());
+        // A slow handshake holds up nobody else
+        thread::spawn(move || {
+            let Ok(mut reader) = stream.try_clone().map(BufReader::new) else {
+                return;
+            };
+            let Ok(Some(path)) = handshake(&mut reader, &mut stream, &metrics) else {
+                return;
+            };
+            let session = path
+                .split_once("session=")
+                .and_then(|(_, session)| Some(session.split('&').next()?.to_string()));
+            if stream.set_write_timeout(Some(WRITE_TIMEOUT)).is_err()
+                || sender.send(Hub::Joined(id, stream, session)).is_err()
+            {
+                return;
+            }
+            read_messages(reader, sender, move |message| Hub::From(id, message));
+        });
+    }
+    Ok(())
+}
+
+/// The lobby and the rounds, one after the other, forever.
+fn run_royale(hub: &Receiver<Hub>, config: &ServerConfig, players: usize, shared: &Shared) {
+    let (scores, metrics) = (&shared.scores, &shared.metrics);
+    let mut clients: BTreeMap<u32, TcpStream> = BTreeMap::new();
+    // Every player's session, players in a round who went away and since
+    // when, and the connections players came back on, by player
+    let mut sessions: HashMap<String, u32> = HashMap::new();
+    let mut away: BTreeMap<u32, Instant> = BTreeMap::new();
+    let mut resumed: BTreeMap<u32, u32> = BTreeMap::new();
+    let mut round: Option<Royale> = None;
+    let mut lobby_ticks = LOBBY_TICKS;
+    let mut seed = config.options.seed.unwrap_or_else(rand::random);
+    let mut next_tick = Instant::now() + ROYALE_TICK;
+    loop {
+        // Everything until the tick, the same as `wait_for_tick`
+        loop {
+            let timeout = next_tick.saturating_duration_since(Instant::now());
+            let (id, message) = match hub.recv_timeout(timeout) {
+                Ok(Hub::Joined(connection, stream, session)) => {
+                    let back = session
+                        .and_then(|session| sessions.get(&session).copied())
+                        .filter(|player| away.remove(player).is_some());
+                    if let Some(player) = back {
+                        // Their snake again, it gets its view with the next tick
+                        resumed.insert(player, connection);
+                        clients.insert(player, stream);
+                        continue;
+                    }
+                    let (mut stream, session) =
+                        (stream, encode_base64(&rand::random::<[u8; 12]>()));
+                    let hello = format!(r#"{{"session":{}}}"#, json::string(&session));
+                    if write_frame(&mut stream, TEXT, hello.as_bytes()).is_err() {
+                        continue;
+                    }
+                    sessions.insert(session, connection);
+                    clients.insert(connection, stream);
+                    // A full lobby doesn't wait for anyone else
+                    lobby_ticks = match clients.len() >= players {
+                        true => 0,
+                        false => LOBBY_TICKS,
+                    };
+                    continue;
+                }
+                Ok(Hub::From(connection, message)) => {
+                    let id = resumed
+                        .iter()
+                        .find(|&(_, &on)| on == connection)
+                        .map_or(connection, |(&player, _)| player);
+                    // Still coming in from the connection they came back from
+                    if resumed.get(&id).is_some_and(|&on| on != connection) {
+                        continue;
+                    }
+                    (id, message)
+                }
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return,
+            };
+            let reply = match message {
+                Incoming::Turn(direction) => {
+                    if let Some(ref mut round) = round {
+                        round.turn(id, direction);
+                    }
+                    continue;
+                }
+                Incoming::Ping(payload) => (PONG, payload),
+                Incoming::Echo(text) => (TEXT, text.into_bytes()),
+                Incoming::Say(text) if !text.is_empty() => {
+                    let chat = chat_json(id, &text);
+                    clients.retain(|_, stream| write_frame(stream, TEXT, chat.as_bytes()).is_ok());
+                    continue;
+                }
+                Incoming::Say(_) | Incoming::Chat(_) => continue,
+                Incoming::Close => {
+                    clients.remove(&id);
+                    went_away(id, &round, &mut away);
+                    continue;
+                }
+            };
+            if let Some(stream) = clients.get_mut(&id)
+                && write_frame(stream, reply.0, &reply.1).is_err()
+            {
+                clients.remove(&id);
+                went_away(id, &round, &mut away);
+            }
+        }
+        next_tick += ROYALE_TICK;
+
+        // Not back in time, they're out of the round
+        away.retain(|&id, since| {
+            let waiting = since.elapsed() < RESUME_WITHIN;
+            if !waiting && let Some(ref mut round) = round {
+                round.leave(id);
+            }
+            waiting
+        });
+        sessions.retain(|_, id| clients.contains_key(id) || away.contains_key(id));
+        resumed.retain(|id, _| clients.contains_key(id) || away.contains_key(id));
+
+        let messages: Vec<(u32, String)> = match round {
+            Some(ref mut royale) => {
+                royale.tick();
+                *scores.lock().unwrap() = clients
+                    .keys()
+                    .map(|&id| (id, royale.player(id).map_or(0, |p| p.score)))
+                    .collect();
+                clients
+                    .keys()
+                    .map(|&id| (id, royale.view_json(id)))
+                    .collect()
+            }
+            None => {
+                lobby_ticks = lobby_ticks.saturating_sub(1);
+                let lobby = format!(
+                    r#"{{"lobby":true,"players":{},"max":{},"starts_in":{}}}"#,
+                    clients.len(),
+                    players,
+                    lobby_ticks
+                );
+                clients.keys().map(|&id| (id, lobby.clone())).collect()
+            }
+        };
+        for (id, message) in messages {
+            if let Some(stream) = clients.get_mut(&id)
+                && write_frame(stream, TEXT, message.as_bytes()).is_err()
+            {
+                clients.remove(&id);
+                went_away(id, &round, &mut away);
+            }
+        }
+        if round.is_some() {
+            metrics.lock().unwrap().ticked(next_tick - ROYALE_TICK);
+        }
+
+        if let Some(ref royale) = round
+            && royale.is_over()
+        {
+            let mut metrics = metrics.lock().unwrap();
+            metrics.in_progress = 0;
+            metrics.finished += royale.players.len() as u64;
+            metrics.total_score += royale
+                .players
+                .iter()
+                .map(|p| u64::from(p.score))
+                .sum::<u64>();
+            drop(metrics);
+            round = None;
+            // Nothing to come back to anymore
+            away.clear();
+            lobby_ticks = LOBBY_TICKS;
+        } else if round.is_none() && clients.len() >= 2 && lobby_ticks == 0 {
+            // The first ones in when there are more than fit, the rest wait
+            let ids: Vec<u32> = clients.keys().copied().take(players).collect();
+            round = Some(Royale::new(
+                config.cols,
+                config.rows,
+                &ids,
+                seed,
+                config.teams,
+            ));
+            let mut metrics = metrics.lock().unwrap();
+            metrics.games += ids.len() as u64;
+            metrics.in_progress = ids.len() as u64;
+            seed = seed.wrapping_add(1);
+        }
+    }
+}
+
+// A player whose connection is gone: still alive in the round, their snake
+// goes on without them for `RESUME_WITHIN` in case they come back
+fn went_away(id: u32, round: &Option<Royale>, away: &mut BTreeMap<u32, Instant>) {
+    if let Some(round) = round
+        && round.player(id).is_some_and(|p| p.alive)
+    {
+        away.entry(id).or_insert_with(Instant::now);
+    }
+}
+
+/// Take in what the client sends until `until`, false when it closed.
+fn wait_for_tick(
+    incoming: &Receiver<Incoming>,
+    game: &mut Game,
+    turns: &mut u64,
+    stream: &mut TcpStream,
+    (id, shared): (u32, &Shared),
+    until: Instant,
+) -> io::Result<bool> {
+    loop {
+        let timeout = until.saturating_duration_since(Instant::now());
+        match incoming.recv_timeout(timeout) {
+            Ok(Incoming::Turn(direction)) => {
+                game.turn(direction);
+                *turns += 1;
+            }
+            Ok(Incoming::Ping(payload)) => write_frame(stream, PONG, &payload)?,
+            Ok(Incoming::Echo(text)) => write_frame(stream, TEXT, text.as_bytes())?,
+            Ok(Incoming::Say(text)) if !text.is_empty() => broadcast(shared, &chat_json(id, &text)),
+            Ok(Incoming::Say(_)) => {}
+            Ok(Incoming::Chat(chat)) => write_frame(stream, TEXT, chat.as_bytes())?,
+            Ok(Incoming::Close) | Err(RecvTimeoutError::Disconnected) => return Ok(false),
+            Err(RecvTimeoutError::Timeout) => return Ok(true),
+        }
+    }
+}
+
+/// Read the HTTP upgrade request and agree to it, the path it asked for.
+/// `None` for a `/metrics` request, which has been answered.
+fn handshake(
+    reader: &mut impl BufRead,
+    stream: &mut impl Write,
+    metrics: &Mutex<Metrics>,
+) -> io::Result<Option<String>> {
+    let mut request = String::new();
+    reader.read_line(&mut request)?;
+    let path = request.split_whitespace().nth(1).unwrap_or("/").to_string();
+    let mut key = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':')
+            && name.trim().eq_ignore_ascii_case("sec-websocket-key")
+        {
+            key = Some(value.trim().to_string());
+        }
+    }
+    if key.is_none() && path == "/metrics" {
+        let body = metrics.lock().unwrap().text();
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+            body.len()
+        )?;
+        return Ok(None);
+    }
+    let Some(key) = key else {
+        stream.write_all(b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n")?;
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a WebSocket request",
+        ));
+    };
+    write!(
+        stream,
+        "HTTP/1.1 101 Switching Protocols\r\nUpgrade: websocket\r\nConnection: Upgrade\r\nSec-WebSocket-Accept: {}\r\n\r\n",
+        accept_key(&key)
+    )?;
+    Ok(Some(path))
+}
+
+/// What the server answers to the client's `Sec-WebSocket-Key`.
+pub fn accept_key(key: &str) -> String {
+    base64(&sha1(format!("{key}{GUID}").as_bytes()))
+}
+
+// `json`, an object, with one more field at the end
+fn with_field(json: &str, key: &str, value: &str) -> String {
+    let object = json.strip_suffix('}').unwrap_or(json);
+    format!("{object},{}:{value}}}", json::string(key))
+}
+
+/// One whole frame, the opcode and the unmasked payload, no bigger than `max`.
+pub fn read_frame(reader: &mut impl Read, max: u64) -> io::Result<(u8, Vec<u8>)> {
+    let mut header = [0; 2];
+    reader.read_exact(&mut header)?;
+    let (fin, opcode) = (header[0] & 0x80 != 0, header[0] & 0x0F);
+    let masked = header[1] & 0x80 != 0;
+    let len = match header[1] & 0x7F {
+        126 => {
+            let mut len = [0; 2];
+            reader.read_exact(&mut len)?;
+            u64::from(u16::from_be_bytes(len))
+        }
+        127 => {
+            let mut len = [0; 8];
+            reader.read_exact(&mut len)?;
+            u64::from_be_bytes(len)
+        }
+        len => u64::from(len),
+    };
+    if !fin || len > max {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "fragmented or too big",
+        ));
+    }
+    let mut mask = [0; 4];
+    if masked {
+        reader.read_exact(&mut mask)?;
+    }
+    let mut payload = vec![0; len as usize];
+    reader.read_exact(&mut payload)?;
+    for (i, byte) in payload.iter_mut().enumerate() {
+        *byte ^= mask[i % 4];
+    }
+    Ok((opcode, payload))
+}
+
+fn write_frame(stream: &mut impl Write, opcode: u8, payload: &[u8]) -> io::Result<()> {
+    stream.write_all(&frame(opcode, payload, None))
+}
+
+/// Like the server's, masked the way frames from a client have to be.
+pub fn write_client_frame(stream: &mut impl Write, opcode: u8, payload: &[u8]) -> io::Result<()> {
+    stream.write_all(&frame(opcode, payload, Some(rand::random())))
+}
+
+fn frame(opcode: u8, payload: &[u8], mask: Option<[u8; 4]>) -> Vec<u8> {
+    let mut frame = vec![0x80 | opcode];
+    let masked = if mask.is_some() { 0x80 } else { 0 };
+    match payload.len() {
+        len @ 0..=125 => frame.push(masked | len as u8),
+        len @ 126..=0xFFFF => {
+            frame.push(masked | 126);
+            frame.extend((len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(masked | 127);
+            frame.extend((len as u64).to_be_bytes());
+        }
+    }
+    match mask {
+        Some(mask) => {
+            frame.extend(mask);
+            frame.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+        }
+        None => frame.extend(payload),
+    }
+    frame
+}
+
+fn sha1(data: &[u8]) -> [u8; 20] {
+    let mut h: [u32; 5] = [0x67452301, 0xEFCDAB89, 0x98BADCFE, 0x10325476, 0xC3D2E1F0];
+    let mut message = data.to_vec();
+    message.push(0x80);
+    while message.len() % 64 != 56 {
+        message.push(0);
+    }
+    message.extend((data.len() as u64 * 8).to_be_bytes());
+
+    for block in message.chunks(64) {
+        let mut w = [0u32; 80];
+        for i in 0..16 {
+            w[i] = u32::from_be_bytes([
+                block[4 * i],
+                block[4 * i + 1],
+                block[4 * i + 2],
+                block[4 * i + 3],
+            ]);
+        }
+        for i in 16..80 {
+            w[i] = (w[i - 3] ^ w[i - 8] ^ w[i - 14] ^ w[i - 16]).rotate_left(1);
+        }
+        let [mut a, mut b, mut c, mut d, mut e] = h;
+        for (i, &word) in w.iter().enumerate() {
+            let (f, k) = match i {
+                0..=19 => ((b & c) | (!b & d), 0x5A827999),
+                20..=39 => (b ^ c ^ d, 0x6ED9EBA1),
+                40..=59 => ((b & c) | (b & d) | (c & d), 0x8F1BBCDC),
+                _ => (b ^ c ^ d, 0xCA62C1D6),
+            };
+            let temp = a
+                .rotate_left(5)
+                .wrapping_add(f)
+                .wrapping_add(e)
+                .wrapping_add(k)
+                .wrapping_add(word);
+            (e, d, c, b, a) = (d, c, b.rotate_left(30), a, temp);
+        }
+        for (h, v) in h.iter_mut().zip([a, b, c, d, e]) {
+            *h = h.wrapping_add(v);
+        }
+    }
+
+    let mut out = [0; 20];
+    for (i, word) in h.iter().enumerate() {
+        out[4 * i..4 * i + 4].copy_from_slice(&word.to_be_bytes());
+    }
+    out
+}
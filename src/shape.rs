@@ -0,0 +1,129 @@
+// -- Built-in arenas
+//
+// Boards with walls in a shape, made to fit whatever size the board is
+// instead of drawn by hand: a donut (a block in the middle and a wall all
+// round), a cross (a plus in the middle of an open, wrapping board) and a
+// spiral (corridors winding in from the edge). They come out as `Level`s,
+// so they play, score and get checked just like hand-made ones, and show up
+// first in `snake2 levels` (or straight away with `--arena donut`).
+
+use crate::level::{Cell, Level};
+
+/// Cells between two walls of the spiral.
+pub const CORRIDOR: u16 = 2;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Shape {
+    Donut,
+    Cross,
+    Spiral,
+}
+
+impl Shape {
+    pub const ALL: [Shape; 3] = [Shape::Donut, Shape::Cross, Shape::Spiral];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Shape::Donut => "donut",
+            Shape::Cross => "cross",
+            Shape::Spiral => "spiral",
+        }
+    }
+
+    fn difficulty(self) -> &'static str {
+        match self {
+            Shape::Donut => "easy",
+            Shape::Cross => "medium",
+            Shape::Spiral => "hard",
+        }
+    }
+
+    /// The arena on a `cols` by `rows` board, or what's wrong with it when
+    /// the board is too small for it.
+    pub fn level(self, cols: u16, rows: u16) -> Result<Level, String> {
+        let mut level = Level::new(cols, rows);
+        level.name = self.name().to_string();
+        level.author = "snake2".to_string();
+        level.difficulty = self.difficulty().to_string();
+        let (walls, spawn) = match self {
+            Shape::Donut => {
+                let (x, y) = (cols / 3, rows / 3);
+                let walls = outline(0, 0, cols - 1, rows - 1)
+                    .chain(block(x, y, cols - 1 - x, rows - 1 - y))
+                    .collect();
+                (walls, (cols / 6, rows / 2))
+            }
+            Shape::Cross => {
+                let (x, y) = (cols / 4, rows / 4);
+                let walls = block(x, rows / 2, cols - 1 - x, rows / 2)
+                    .chain(block(cols / 2, y, cols / 2, rows - 1 - y))
+                    .collect();
+                (walls, (x, y))
+            }
+            Shape::Spiral => (spiral(cols, rows), (cols / 2, 1)),
+        };
+        for (x, y) in walls {
+            level.set(x, y, Cell::Wall);
+        }
+        level.set(spawn.0, spawn.1, Cell::Spawn);
+        match level.problems().first() {
+            Some(problem) => Err(format!(
+                "no room for a {} at {cols}x{rows}: {}",
+                self.name(),
+                problem.message
+            )),
+            None => Ok(level),
+        }
+    }
+}
+
+impl std::str::FromStr for Shape {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Shape::ALL
+            .into_iter()
+            .find(|shape| shape.name() == s)
+            .ok_or(format!("unknown arena `{s}`"))
+    }
+}
+
+// Every cell from (x0, y0) to (x1, y1), both included
+fn block(x0: u16, y0: u16, x1: u16, y1: u16) -> impl Iterator<Item = (u16, u16)> {
+    (y0..=y1).flat_map(move |y| (x0..=x1).map(move |x| (x, y)))
+}
+
+// The edge of that block
+fn outline(x0: u16, y0: u16, x1: u16, y1: u16) -> impl Iterator<Item = (u16, u16)> {
+    block(x0, y0, x1, y1).filter(move |&(x, y)| x == x0 || x == x1 || y == y0 || y == y1)
+}
+
+// Walls from the top left corner round the edge and on inwards, right, down,
+// left, up, every turn after the first three `CORRIDOR` cells short of the
+// wall before, until there's no room for another corridor
+fn spiral(cols: u16, rows: u16) -> Vec<(u16, u16)> {
+    let step = i32::from(CORRIDOR) + 1;
+    let (cols, rows) = (i32::from(cols), i32::from(rows));
+    let (mut x, mut y) = (0, 0);
+    let mut walls = vec![(0, 0)];
+    for turn in 0.. {
+        let (dx, dy, length) = match turn % 4 {
+            0 | 2 => {
+                let dx = if turn % 4 == 0 { 1 } else { -1 };
+                (dx, 0, cols - 1 - step * (turn / 2 - 1).max(0))
+            }
+            _ => {
+                let dy = if turn % 4 == 1 { 1 } else { -1 };
+                (0, dy, rows - 1 - step * ((turn - 1) / 2))
+            }
+        };
+        if length < step {
+            break;
+        }
+        for _ in 0..length {
+            (x, y) = (x + dx, y + dy);
+            walls.push((x as u16, y as u16));
+        }
+    }
+    walls
+}
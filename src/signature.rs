@@ -0,0 +1,73 @@
+// -- Signed files
+//
+// High scores and stats end with a line signing everything above it, an
+// HMAC-SHA256 with a key made once per install (`install.key` in the data
+// dir, see `profile.rs`):
+//
+//     snake2-stats 2
+//     games 12
+//     ...
+//     signature 5f1c09...
+//
+// It doesn't stop anyone who reads this, the key is right there, but a
+// score typed into the file by hand no longer passes. Files that don't are
+// still read, only flagged as unverified.
+
+use hmac::{Hmac, KeyInit, Mac};
+use sha2::Sha256;
+
+const PREFIX: &str = "signature ";
+
+/// What was read from a signed file, and whether the signature held.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Signed<T> {
+    pub value: T,
+    pub verified: bool,
+}
+
+fn mac(key: &[u8], text: &str) -> Hmac<Sha256> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any length");
+    mac.update(text.as_bytes());
+    mac
+}
+
+/// `text` with its signature line after it.
+pub fn sign(key: &[u8], text: &str) -> String {
+    let digest = mac(key, text).finalize().into_bytes();
+    format!("{text}{PREFIX}{}\n", to_hex(&digest))
+}
+
+/// `text` without its signature line, verified if it had one that matches.
+pub fn verify<'a>(key: &[u8], text: &'a str) -> Signed<&'a str> {
+    let start = match text.trim_end().rfind('\n') {
+        Some(newline) => newline + 1,
+        None => 0,
+    };
+    let Some(signature) = text[start..].trim_end().strip_prefix(PREFIX) else {
+        return Signed {
+            value: text,
+            verified: false,
+        };
+    };
+    let body = &text[..start];
+    let verified =
+        from_hex(signature).is_some_and(|bytes| mac(key, body).verify_slice(&bytes).is_ok());
+    Signed {
+        value: body,
+        verified,
+    }
+}
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+pub fn from_hex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) || !text.is_ascii() {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&text[i..i + 2], 16).ok())
+        .collect()
+}
@@ -0,0 +1,77 @@
+// -- Batch simulation
+//
+// `snake2 simulate --bot policy.rhai --episodes 100000 --threads 8 --out
+// results.csv` plays a bot through lots of headless games in parallel and
+// writes a CSV line per game, for training and comparing policies without
+// anything drawn. With `--seed` the episodes play the boards that seed and
+// the ones after it give, so a run can be repeated.
+
+use crate::arena::{ArenaConfig, GameResult, play_headless};
+use crate::bot::{Bot, BotError};
+use rayon::prelude::*;
+use std::io::{self, Write};
+
+pub struct SimulateConfig {
+    pub episodes: usize,
+    // 0 is one per core
+    pub threads: usize,
+    pub game: ArenaConfig,
+}
+
+impl Default for SimulateConfig {
+    fn default() -> Self {
+        SimulateConfig {
+            episodes: 1000,
+            threads: 0,
+            game: ArenaConfig::default(),
+        }
+    }
+}
+
+/// Every episode's result, in order.
+pub fn simulate(source: &str, config: &SimulateConfig) -> Result<Vec<GameResult>, BotError> {
+    // Fail early on a script that doesn't even compile
+    Bot::from_source(source, config.game.time_limit)?;
+
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(config.threads)
+        .build()
+        .map_err(|e| BotError::Load(e.to_string()))?;
+    let base_seed = config.game.options.seed;
+    let results = pool.install(|| {
+        (0..config.episodes)
+            .into_par_iter()
+            .map_init(
+                || Bot::from_source(source, config.game.time_limit).expect("bot compiled before"),
+                |bot, episode| {
+                    let mut game = config.game;
+                    game.options.seed = base_seed.map(|seed| seed.wrapping_add(episode as u64));
+                    play_headless(bot, &game)
+                },
+            )
+            .collect()
+    });
+    Ok(results)
+}
+
+pub fn write_csv(results: &[GameResult], mut out: impl Write) -> io::Result<()> {
+    writeln!(
+        out,
+        "episode,seed,score,length,ticks,died,timeouts,crashed,cause"
+    )?;
+    for (episode, r) in results.iter().enumerate() {
+        writeln!(
+            out,
+            "{episode},{},{},{},{},{},{},{},{}",
+            r.seed,
+            r.score,
+            r.length,
+            r.ticks,
+            r.died,
+            r.timeouts,
+            r.crashed,
+            r.cause.map_or("", |c| c.name())
+        )?;
+    }
+    out.flush()
+}
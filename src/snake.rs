@@ -0,0 +1,175 @@
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SnakeDirection {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+impl SnakeDirection {
+    pub fn opposite(self) -> SnakeDirection {
+        match self {
+            SnakeDirection::Up => SnakeDirection::Down,
+            SnakeDirection::Down => SnakeDirection::Up,
+            SnakeDirection::Left => SnakeDirection::Right,
+            SnakeDirection::Right => SnakeDirection::Left,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            SnakeDirection::Up => "up",
+            SnakeDirection::Down => "down",
+            SnakeDirection::Left => "left",
+            SnakeDirection::Right => "right",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<SnakeDirection> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "up" | "u" => Some(SnakeDirection::Up),
+            "down" | "d" => Some(SnakeDirection::Down),
+            "left" | "l" => Some(SnakeDirection::Left),
+            "right" | "r" => Some(SnakeDirection::Right),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SnakeBodyPoint {
+    pub x: u16,
+    pub y: u16,
+    // What the segment carries along, an uppercase ASCII letter in word mode
+    // (see `rules::Words`)
+    pub letter: Option<u8>,
+}
+
+impl SnakeBodyPoint {
+    /// One cell `direction`, wrapping around the edges of the board.
+    pub fn step(self, direction: SnakeDirection, cols: u16, rows: u16) -> SnakeBodyPoint {
+        let mut point = self;
+        match direction {
+            SnakeDirection::Up => {
+                if point.y == 0 {
+                    point.y = rows - 1;
+                } else {
+                    point.y -= 1;
+                }
+            }
+            SnakeDirection::Down => {
+                point.y = (point.y + 1) % rows;
+            }
+            SnakeDirection::Left => {
+                if point.x == 0 {
+                    point.x = cols - 1;
+                } else {
+                    point.x -= 1;
+                }
+            }
+            SnakeDirection::Right => {
+                point.x = (point.x + 1) % cols;
+            }
+        }
+        point
+    }
+}
+
+/// Where the head starts.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Spawn {
+    #[default]
+    Center,
+    Random,
+    At(u16, u16),
+}
+
+impl std::str::FromStr for Spawn {
+    type Err = String;
+
+    /// `center`, `random` or `X,Y`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "center" => Ok(Spawn::Center),
+            "random" => Ok(Spawn::Random),
+            _ => {
+                let (x, y) = s.split_once(',').ok_or(format!("bad spawn `{s}`"))?;
+                let x = x.trim().parse().map_err(|_| format!("bad spawn x `{x}`"))?;
+                let y = y.trim().parse().map_err(|_| format!("bad spawn y `{y}`"))?;
+                Ok(Spawn::At(x, y))
+            }
+        }
+    }
+}
+
+pub struct Snake {
+    pub direction: SnakeDirection,
+    // The direction of the last step actually taken, turns are checked
+    // against this one and not `direction`, which may have changed since
+    pub last_moved: SnakeDirection,
+    pub body: Vec<SnakeBodyPoint>,
+}
+
+impl Snake {
+    /// A snake of `length` segments, the tail laid out behind the head
+    /// (opposite to where it's heading) and wrapping around the edges.
+    pub fn new(
+        cols: &u16,
+        rows: &u16,
+        initial_direction: SnakeDirection,
+        length: u16,
+        spawn: Spawn,
+    ) -> Self {
+        let (x, y) = match spawn {
+            // `Game::with_options` picks random spawns with the game's own
+            // generator, the snake doesn't get to roll dice of its own
+            Spawn::Center | Spawn::Random => (cols / 2, rows / 2),
+            Spawn::At(x, y) => (x.min(cols - 1), y.min(rows - 1)),
+        };
+
+        // As long as the row or column it lies on would bite its own tail
+        let room = match initial_direction {
+            SnakeDirection::Left | SnakeDirection::Right => *cols,
+            SnakeDirection::Up | SnakeDirection::Down => *rows,
+        };
+        let length = length.clamp(1, room.saturating_sub(1).max(1));
+
+        let mut snake = Snake {
+            direction: initial_direction.opposite(),
+            last_moved: initial_direction,
+            body: vec![SnakeBodyPoint { x, y, letter: None }],
+        };
+        for _ in 1..length {
+            let next = snake.next_head(cols, rows);
+            snake.body.insert(0, next);
+        }
+        snake.body.reverse();
+        snake.direction = initial_direction;
+        snake
+    }
+
+    pub fn head(&self) -> SnakeBodyPoint {
+        self.body[0]
+    }
+
+    /// Where the head ends up after one step, wrapping around the edges.
+    pub fn next_head(&self, cols: &u16, rows: &u16) -> SnakeBodyPoint {
+        self.head().step(self.direction, *cols, *rows)
+    }
+
+    pub fn occupies(&self, x: u16, y: u16) -> bool {
+        self.body.iter().any(|segment| segment.x == x && segment.y == y)
+    }
+
+    /// Whether heading `direction` next step wouldn't reverse onto the neck.
+    pub fn can_turn(&self, direction: SnakeDirection) -> bool {
+        direction != self.last_moved.opposite()
+    }
+
+    /// Change direction unless it would reverse the snake onto itself.
+    pub fn turn(&mut self, direction: SnakeDirection) {
+        if self.can_turn(direction) {
+            self.direction = direction;
+        }
+    }
+}
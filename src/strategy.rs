@@ -0,0 +1,308 @@
+// -- Built-in bots
+//
+// Three ways of playing written in Rust, so they need no script and no
+// `scripting` feature: `greedy` heads for the food the short way, only
+// looking one step ahead for what it would run into; `hamiltonian` follows
+// one path through every cell of the board round and round, slow but it
+// never runs into itself; `astar` finds a way to the food with A* and only
+// takes it when the tail can still be reached from the end of it, following
+// its tail around meanwhile otherwise. `snake2 bench-bots` plays them
+// against each other on the same seeds, and `--race` puts the player up
+// against the hamiltonian one.
+
+use crate::game::Game;
+use crate::snake::{SnakeBodyPoint, SnakeDirection};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, VecDeque};
+
+const DIRECTIONS: [SnakeDirection; 4] = [
+    SnakeDirection::Up,
+    SnakeDirection::Down,
+    SnakeDirection::Left,
+    SnakeDirection::Right,
+];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Strategy {
+    Greedy,
+    Hamiltonian,
+    AStar,
+}
+
+impl Strategy {
+    pub const ALL: [Strategy; 3] = [Strategy::Greedy, Strategy::Hamiltonian, Strategy::AStar];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Strategy::Greedy => "greedy",
+            Strategy::Hamiltonian => "hamiltonian",
+            Strategy::AStar => "astar",
+        }
+    }
+
+    /// Where to go next, `None` keeps going the way the snake is (there's
+    /// nowhere safe to go).
+    pub fn next_move(self, game: &Game) -> Option<SnakeDirection> {
+        let board = Board::of(game);
+        match self {
+            Strategy::Greedy => greedy(game, &board),
+            Strategy::Hamiltonian => hamiltonian(game, &board),
+            Strategy::AStar => astar(game, &board),
+        }
+    }
+}
+
+impl std::str::FromStr for Strategy {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Strategy::ALL
+            .into_iter()
+            .find(|strategy| strategy.name() == s)
+            .ok_or(format!("unknown bot `{s}`"))
+    }
+}
+
+// The board as the bots see it, cells by their index row by row
+struct Board {
+    cols: u16,
+    rows: u16,
+    // Walls, bosses and the like, and the other heads in hydra mode
+    walls: Vec<bool>,
+    // The snake from the head back
+    body: Vec<usize>,
+    food: Option<usize>,
+}
+
+impl Board {
+    fn of(game: &Game) -> Board {
+        let cells = usize::from(game.cols) * usize::from(game.rows);
+        let index = |x: u16, y: u16| usize::from(y) * usize::from(game.cols) + usize::from(x);
+        let mut walls = vec![false; cells];
+        for entity in game.entities.iter().filter(|e| e.kind.is_deadly()) {
+            walls[index(entity.x, entity.y)] = true;
+        }
+        for point in game.heads.iter().flat_map(|head| &head.body) {
+            walls[index(point.x, point.y)] = true;
+        }
+        Board {
+            cols: game.cols,
+            rows: game.rows,
+            walls,
+            body: game.snake.body.iter().map(|p| index(p.x, p.y)).collect(),
+            food: game.food_position().map(|(x, y)| index(x, y)),
+        }
+    }
+
+    // One cell `direction` from `cell`, the board wraps around
+    fn step(&self, cell: usize, direction: SnakeDirection) -> usize {
+        let cols = usize::from(self.cols);
+        let point = SnakeBodyPoint {
+            x: (cell % cols) as u16,
+            y: (cell / cols) as u16,
+            letter: None,
+        };
+        let next = point.step(direction, self.cols, self.rows);
+        usize::from(next.y) * cols + usize::from(next.x)
+    }
+
+    // Steps between two cells the short way round, ignoring what's in between
+    fn distance(&self, a: usize, b: usize) -> usize {
+        let cols = usize::from(self.cols);
+        let wrap = |from: usize, to: usize, size: usize| {
+            let d = from.abs_diff(to);
+            d.min(size - d)
+        };
+        wrap(a % cols, b % cols, cols) + wrap(a / cols, b / cols, usize::from(self.rows))
+    }
+
+    // What the snake `body` runs into. Its tail too, it only moves out of
+    // the way after the head has moved in.
+    fn blocked(&self, body: &[usize]) -> Vec<bool> {
+        let mut blocked = self.walls.clone();
+        for &cell in body {
+            blocked[cell] = true;
+        }
+        // The head never was in its own way
+        blocked[body[0]] = false;
+        blocked
+    }
+
+    // The ways the snake can go this tick without running into anything
+    fn safe_moves(&self, game: &Game) -> Vec<(SnakeDirection, usize)> {
+        let blocked = self.blocked(&self.body);
+        DIRECTIONS
+            .into_iter()
+            .filter(|&d| game.snake.can_turn(d))
+            .map(|d| (d, self.step(self.body[0], d)))
+            .filter(|&(_, cell)| !blocked[cell])
+            .collect()
+    }
+
+    // The shortest way from the head to `goal` with A*, as the directions
+    // to take
+    fn path(&self, game: &Game, goal: usize) -> Option<Vec<SnakeDirection>> {
+        let blocked = self.blocked(&self.body);
+        let start = self.body[0];
+        let mut came_from: Vec<Option<(usize, SnakeDirection)>> = vec![None; blocked.len()];
+        let mut cost = vec![usize::MAX; blocked.len()];
+        let mut open = BinaryHeap::new();
+        cost[start] = 0;
+        open.push(Reverse((self.distance(start, goal), start)));
+        while let Some(Reverse((_, cell))) = open.pop() {
+            if cell == goal {
+                let mut path = Vec::new();
+                let mut at = goal;
+                while let Some((from, direction)) = came_from[at] {
+                    path.push(direction);
+                    at = from;
+                }
+                path.reverse();
+                return Some(path);
+            }
+            for direction in DIRECTIONS {
+                // The first step can't turn back onto the neck
+                if cell == start && !game.snake.can_turn(direction) {
+                    continue;
+                }
+                let next = self.step(cell, direction);
+                if blocked[next] || cost[cell] + 1 >= cost[next] {
+                    continue;
+                }
+                cost[next] = cost[cell] + 1;
+                came_from[next] = Some((cell, direction));
+                open.push(Reverse((cost[next] + self.distance(next, goal), next)));
+            }
+        }
+        None
+    }
+
+    // Where the snake would be after going `path`, eating at the end of it
+    fn after(&self, path: &[SnakeDirection]) -> Vec<usize> {
+        let mut body: VecDeque<usize> = self.body.iter().copied().collect();
+        for (i, &direction) in path.iter().enumerate() {
+            body.push_front(self.step(body[0], direction));
+            if i + 1 < path.len() {
+                body.pop_back();
+            }
+        }
+        body.into()
+    }
+
+    // Whether the tail of `body` can be reached from its head, and how many
+    // cells can
+    fn room(&self, body: &[usize]) -> (bool, usize) {
+        let tail = body[body.len() - 1];
+        let mut blocked = self.blocked(body);
+        let mut queue = VecDeque::from([body[0]]);
+        blocked[body[0]] = true;
+        let (mut reached, mut cells) = (body.len() == 1, 0);
+        while let Some(cell) = queue.pop_front() {
+            cells += 1;
+            for direction in DIRECTIONS {
+                let next = self.step(cell, direction);
+                reached |= next == tail;
+                if !blocked[next] {
+                    blocked[next] = true;
+                    queue.push_back(next);
+                }
+            }
+        }
+        (reached, cells)
+    }
+}
+
+fn greedy(game: &Game, board: &Board) -> Option<SnakeDirection> {
+    let moves = board.safe_moves(game);
+    match board.food {
+        Some(food) => moves
+            .into_iter()
+            .min_by_key(|&(_, cell)| board.distance(cell, food))
+            .map(|(direction, _)| direction),
+        None => moves.first().map(|&(direction, _)| direction),
+    }
+}
+
+fn hamiltonian(game: &Game, board: &Board) -> Option<SnakeDirection> {
+    let head = game.snake.head();
+    match cycle_direction(head.x, head.y, game.cols, game.rows) {
+        // Until it's on the cycle going the right way, like at the start
+        Some(direction)
+            if board
+                .safe_moves(game)
+                .iter()
+                .any(|&(safe, _)| safe == direction) =>
+        {
+            Some(direction)
+        }
+        // Off it, the most room with the tail in reach rather than the food:
+        // short cuts to it can shut the snake in before it's on the cycle
+        _ => board
+            .safe_moves(game)
+            .into_iter()
+            .max_by_key(|&(direction, _)| board.room(&board.after(&[direction])))
+            .map(|(direction, _)| direction),
+    }
+}
+
+/// The hamiltonian bot's way round a `cols` by `rows` board, which way it
+/// goes on from every cell, row by row. `None` on a board with no such
+/// cycle, an odd number of both rows and columns.
+pub fn hamiltonian_cycle(cols: u16, rows: u16) -> Option<Vec<SnakeDirection>> {
+    (0..rows)
+        .flat_map(|y| (0..cols).map(move |x| cycle_direction(x, y, cols, rows)))
+        .collect()
+}
+
+// Which way the cycle goes on from (x, y): right along the top row, back and
+// forth along the rows below it but for their first column, and up that
+// column to the start again. It needs an even number of rows, a board with
+// an even number of columns instead is turned on its side, and one with
+// neither has no such cycle.
+fn cycle_direction(x: u16, y: u16, cols: u16, rows: u16) -> Option<SnakeDirection> {
+    use SnakeDirection::*;
+
+    if cols < 2 || rows < 2 {
+        return None;
+    }
+    if !rows.is_multiple_of(2) {
+        if !cols.is_multiple_of(2) {
+            return None;
+        }
+        let sideways = cycle_direction(y, x, rows, cols)?;
+        return Some(match sideways {
+            Up => Left,
+            Down => Right,
+            Left => Up,
+            Right => Down,
+        });
+    }
+    Some(if y == 0 {
+        if x + 1 < cols { Right } else { Down }
+    } else if x == 0 {
+        Up
+    } else if y % 2 == 1 {
+        if x > 1 || y + 1 == rows { Left } else { Down }
+    } else if x + 1 < cols {
+        Right
+    } else {
+        Down
+    })
+}
+
+fn astar(game: &Game, board: &Board) -> Option<SnakeDirection> {
+    if let Some(food) = board.food
+        && let Some(path) = board.path(game, food)
+        && let Some(&first) = path.first()
+        && board.room(&board.after(&path)).0
+    {
+        return Some(first);
+    }
+    // Nowhere safe to eat yet: go where the tail is still in reach, with the
+    // most room around (as if it ate on the way, to be on the safe side)
+    board
+        .safe_moves(game)
+        .into_iter()
+        .max_by_key(|&(direction, _)| board.room(&board.after(&[direction])))
+        .map(|(direction, _)| direction)
+}
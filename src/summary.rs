@@ -0,0 +1,51 @@
+// -- Run summaries
+//
+// `--summary-json PATH` writes what happened in a run as JSON when it's over
+// (`-` prints it on exit instead), for tools that collect runs: the score,
+// how long it took, the seed and options to play it again, how many keys
+// turned the snake and when every food was eaten.
+
+use crate::json;
+
+/// A food eaten, `tick` counts from the start of the run.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Meal {
+    pub tick: u64,
+    pub score: u32,
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct RunSummary {
+    pub score: u32,
+    pub length: usize,
+    pub seconds: u64,
+    pub ticks: u64,
+    pub seed: u64,
+    pub mode: String,
+    pub modifiers: String,
+    // Arrow keys pressed while the snake was moving
+    pub inputs: u64,
+    pub food: Vec<Meal>,
+}
+
+impl RunSummary {
+    pub fn to_json(&self) -> String {
+        let food = json::array(
+            self.food
+                .iter()
+                .map(|m| format!(r#"{{"tick":{},"score":{}}}"#, m.tick, m.score)),
+        );
+        format!(
+            r#"{{"score":{},"length":{},"seconds":{},"ticks":{},"seed":{},"mode":{},"modifiers":{},"inputs":{},"food":{}}}"#,
+            self.score,
+            self.length,
+            self.seconds,
+            self.ticks,
+            self.seed,
+            json::string(&self.mode),
+            json::string(&self.modifiers),
+            self.inputs,
+            food
+        )
+    }
+}
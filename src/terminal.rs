@@ -0,0 +1,337 @@
+use crate::backend::{Backend, Color, Style};
+use crossterm::{
+    ExecutableCommand, QueueableCommand,
+    cursor::{Hide, MoveTo, Show},
+    event::{
+        KeyboardEnhancementFlags, PopKeyboardEnhancementFlags, PushKeyboardEnhancementFlags,
+    },
+    style::{self, Stylize},
+    terminal::{self, Clear, ClearType},
+};
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+// Set when the terminal accepted the kitty keyboard protocol flags
+static KEYBOARD_ENHANCED: AtomicBool = AtomicBool::new(false);
+
+pub struct CrosstermBackend {
+    pub stdout: io::Stdout,
+}
+
+impl CrosstermBackend {
+    pub fn new(stdout: io::Stdout) -> Self {
+        CrosstermBackend { stdout }
+    }
+}
+
+impl Backend for CrosstermBackend {
+    fn clear(&mut self) -> io::Result<()> {
+        self.stdout.execute(Clear(ClearType::All))?;
+        Ok(())
+    }
+
+    fn print(&mut self, x: u16, y: u16, text: &str, text_style: Style) -> io::Result<()> {
+        let mut content = text.stylize();
+        if let Some(color) = text_style.color {
+            content = content.with(match color {
+                Color::Green => style::Color::Green,
+                Color::Red => style::Color::Red,
+                Color::Yellow => style::Color::Yellow,
+                Color::Blue => style::Color::Blue,
+                Color::Magenta => style::Color::Magenta,
+                Color::Cyan => style::Color::Cyan,
+                Color::Rgb(r, g, b) => style::Color::Rgb { r, g, b },
+            });
+        }
+        if text_style.bold {
+            content = content.bold();
+        }
+        if text_style.reverse {
+            content = content.reverse();
+        }
+        if text_style.dim {
+            content = content.dim();
+        }
+        self.stdout
+            .queue(MoveTo(x, y))?
+            .queue(style::PrintStyledContent(content))?;
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.stdout.flush()
+    }
+}
+
+// -- Enable and disable terminal functionalities for the game to work
+
+pub fn enable_game_mode(stdout: &mut io::Stdout) -> io::Result<()> {
+    // Prevents input to be forwaded to the screen but also disables Ctrl+C
+    terminal::enable_raw_mode()?;
+    // Hide the cursor
+    stdout.execute(Hide)?;
+    // Ask for key release events on terminals that speak the kitty keyboard
+    // protocol, the rest keep working with presses only
+    if terminal::supports_keyboard_enhancement().unwrap_or(false) {
+        stdout.execute(PushKeyboardEnhancementFlags(
+            KeyboardEnhancementFlags::DISAMBIGUATE_ESCAPE_CODES
+                | KeyboardEnhancementFlags::REPORT_EVENT_TYPES,
+        ))?;
+        KEYBOARD_ENHANCED.store(true, Ordering::Relaxed);
+    }
+    Ok(())
+}
+
+/// Whether key release events are reported, so keys can be held.
+pub fn keyboard_enhanced() -> bool {
+    KEYBOARD_ENHANCED.load(Ordering::Relaxed)
+}
+
+fn restore_keyboard(stdout: &mut io::Stdout) -> io::Result<()> {
+    if KEYBOARD_ENHANCED.swap(false, Ordering::Relaxed) {
+        stdout.execute(PopKeyboardEnhancementFlags)?;
+    }
+    Ok(())
+}
+
+// Give the terminal back the way the shell expects it
+fn leave_game_mode(stdout: &mut io::Stdout) -> io::Result<()> {
+    restore_keyboard(stdout)?;
+    // Enable normal input again
+    terminal::disable_raw_mode()?;
+    // Show cursor again
+    stdout.execute(Show)?;
+    Ok(())
+}
+
+pub fn disable_game_mode(stdout: &mut io::Stdout) -> io::Result<()> {
+    leave_game_mode(stdout)?;
+    // Clear terminal screen
+    stdout.execute(Clear(ClearType::All))?;
+    println!("\n\n\t\tThe program ended.\n\n");
+    Ok(())
+}
+
+/// Put the terminal back as far as it still can be, when something went
+/// wrong. Nothing is printed, stdout may be gone.
+pub fn restore_terminal(stdout: &mut io::Stdout) {
+    let _ = restore_keyboard(stdout);
+    let _ = terminal::disable_raw_mode();
+    let _ = stdout.execute(Show);
+}
+
+pub fn setup_panic_hook() {
+    std::panic::set_hook(Box::new(|info| {
+        restore_terminal(&mut io::stdout());
+        eprintln!("Panic: {info}");
+    }));
+}
+
+// -- Losing the terminal
+//
+// An SSH connection that drops sends SIGHUP, which would end the process
+// there and then with the game in it. It's only noted instead, the loop
+// checks `hung_up` and handles it like any other write to a terminal that
+// isn't there anymore.
+
+#[cfg(unix)]
+static HUNG_UP: std::sync::LazyLock<std::sync::Arc<AtomicBool>> = std::sync::LazyLock::new(|| {
+    let flag = std::sync::Arc::new(AtomicBool::new(false));
+    let _ = signal_hook::flag::register(signal_hook::consts::SIGHUP, flag.clone());
+    flag
+});
+
+/// Start listening for SIGHUP, call it once before the game loop.
+#[cfg(unix)]
+pub fn watch_hangup() {
+    std::sync::LazyLock::force(&HUNG_UP);
+}
+
+#[cfg(not(unix))]
+pub fn watch_hangup() {}
+
+/// Whether the terminal hung up, as an error to stop the loop with.
+#[cfg(unix)]
+pub fn check_hangup() -> io::Result<()> {
+    if HUNG_UP.load(Ordering::Relaxed) {
+        return Err(io::Error::new(
+            io::ErrorKind::BrokenPipe,
+            "the terminal hung up",
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(not(unix))]
+pub fn check_hangup() -> io::Result<()> {
+    Ok(())
+}
+
+// -- Job control (Ctrl+Z, fg)
+//
+// Raw mode turns Ctrl+Z into a plain key press, so the game has to stop
+// itself: restore the terminal, send itself SIGTSTP and set everything up
+// again once the shell continues it. A SIGCONT after being stopped some
+// other way (`kill -STOP`) is caught too, so the loop can set the terminal
+// back up and redraw.
+
+#[cfg(unix)]
+static RESUMED: std::sync::LazyLock<std::sync::Arc<AtomicBool>> = std::sync::LazyLock::new(|| {
+    let flag = std::sync::Arc::new(AtomicBool::new(false));
+    let _ = signal_hook::flag::register(signal_hook::consts::SIGCONT, flag.clone());
+    flag
+});
+
+/// Start listening for SIGCONT, call it once before the game loop.
+#[cfg(unix)]
+pub fn watch_job_control() {
+    std::sync::LazyLock::force(&RESUMED);
+}
+
+#[cfg(not(unix))]
+pub fn watch_job_control() {}
+
+/// Put the game in the background like Ctrl+Z does in any other program.
+#[cfg(unix)]
+pub fn suspend(stdout: &mut io::Stdout) -> io::Result<()> {
+    stdout.execute(Clear(ClearType::All))?;
+    leave_game_mode(stdout)?;
+    signal_hook::low_level::raise(signal_hook::consts::SIGTSTP)?;
+    // We're back in the foreground here
+    RESUMED.store(false, Ordering::Relaxed);
+    enable_game_mode(stdout)
+}
+
+#[cfg(not(unix))]
+pub fn suspend(_stdout: &mut io::Stdout) -> io::Result<()> {
+    Ok(())
+}
+
+/// Whether the process was continued after a stop since the last call,
+/// raw mode and the hidden cursor are set up again when it was.
+#[cfg(unix)]
+pub fn take_resumed(stdout: &mut io::Stdout) -> io::Result<bool> {
+    if !RESUMED.swap(false, Ordering::Relaxed) {
+        return Ok(false);
+    }
+    restore_keyboard(stdout)?;
+    enable_game_mode(stdout)?;
+    Ok(true)
+}
+
+#[cfg(not(unix))]
+pub fn take_resumed(_stdout: &mut io::Stdout) -> io::Result<bool> {
+    Ok(false)
+}
+
+// -- Terminal multiplexers
+//
+// tmux and screen sit between the game and the terminal. Their messages
+// and prompts (and screen's status line) turn up on the last row, so the
+// game stays off it by default, and escape sequences they don't know
+// themselves are eaten unless they're wrapped up to be passed through.
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Multiplexer {
+    Tmux,
+    Screen,
+}
+
+impl Multiplexer {
+    /// The one the game runs in, going by the environment.
+    pub fn detect() -> Option<Multiplexer> {
+        if std::env::var_os("TMUX").is_some() {
+            return Some(Multiplexer::Tmux);
+        }
+        if std::env::var_os("STY").is_some() {
+            return Some(Multiplexer::Screen);
+        }
+        // Over SSH or sudo those two are gone, TERM usually isn't
+        let term = std::env::var("TERM").unwrap_or_default();
+        if term.starts_with("tmux") {
+            Some(Multiplexer::Tmux)
+        } else if term.starts_with("screen") {
+            Some(Multiplexer::Screen)
+        } else {
+            None
+        }
+    }
+
+    /// `sequence` wrapped to go through to the terminal outside, tmux only
+    /// passes it on with `allow-passthrough` on.
+    pub fn passthrough(self, sequence: &str) -> String {
+        match self {
+            Multiplexer::Tmux => {
+                format!("\x1bPtmux;{}\x1b\\", sequence.replace('\x1b', "\x1b\x1b"))
+            }
+            // screen cuts long ones short, so it goes in pieces
+            Multiplexer::Screen => {
+                let chars: Vec<char> = sequence.chars().collect();
+                chars
+                    .chunks(SCREEN_PASSTHROUGH)
+                    .map(|piece| format!("\x1bP{}\x1b\\", piece.iter().collect::<String>()))
+                    .collect()
+            }
+        }
+    }
+}
+
+// How much screen passes through at a time, well under its limit
+const SCREEN_PASSTHROUGH: usize = 76;
+
+// -- Copying to the clipboard
+//
+// OSC 52 asks the terminal to put some text on the system clipboard, which
+// works over SSH too since it's the terminal doing it. Terminals that don't
+// know it ignore it, and some ask the player first, which is why it can be
+// turned off.
+
+/// Ask the terminal to copy `text` to the clipboard.
+pub fn copy_to_clipboard(stdout: &mut io::Stdout, text: &str) -> io::Result<()> {
+    let osc52 = format!("\x1b]52;c;{}\x07", base64(text.as_bytes()));
+    match Multiplexer::detect() {
+        // Only one of the two, a terminal that asks first would ask twice
+        Some(Multiplexer::Tmux) if !tmux_set_clipboard() => {
+            write!(stdout, "{}", Multiplexer::Tmux.passthrough(&osc52))?
+        }
+        Some(Multiplexer::Screen) => write!(stdout, "{}", Multiplexer::Screen.passthrough(&osc52))?,
+        Some(Multiplexer::Tmux) | None => write!(stdout, "{osc52}")?,
+    }
+    stdout.flush()
+}
+
+// Whether tmux takes OSC 52 itself (`set-clipboard on`), otherwise it only
+// gets through wrapped with `allow-passthrough on`. A tmux that can't be
+// asked, on the other end of SSH, is taken to.
+fn tmux_set_clipboard() -> bool {
+    if std::env::var_os("TMUX").is_none() {
+        return true;
+    }
+    std::process::Command::new("tmux")
+        .args(["show-options", "-gv", "set-clipboard"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .is_none_or(|output| String::from_utf8_lossy(&output.stdout).trim() == "on")
+}
+
+/// Standard base64 with padding, what OSC 52 and the WebSocket handshake
+/// want (challenge codes use their own).
+pub fn base64(bytes: &[u8]) -> String {
+    const ALPHABET: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::new();
+    for chunk in bytes.chunks(3) {
+        let n = chunk
+            .iter()
+            .enumerate()
+            .fold(0u32, |n, (i, &b)| n | u32::from(b) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(ALPHABET[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
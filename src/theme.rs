@@ -0,0 +1,516 @@
+// -- Colors and glyphs
+//
+// Everything the renderer draws asks the theme how it should look. The
+// monochrome theme (NO_COLOR or `--no-color`) only uses bold and reverse,
+// and gives the food a glyph nothing else uses so it can't be mistaken for
+// a piece of the snake.
+//
+// Each level gets its own accent: the status bar takes a color and the food
+// a different glyph, so going up a level shows without reading the score.
+// The snake itself changes as it gets longer: new heads, a brighter body
+// and a crown at 100.
+//
+// With the day/night cycle on (`--day-night`) the colors follow the game
+// clock: bright at the start, dimmer and bluer towards night a couple of
+// minutes in, then back to day.
+//
+// Seasonal themes (`--seasonal`) go on top of everything else, picked by the
+// date: pumpkins for food in October, snow on the board's edge in December.
+//
+// `--grid` puts faint dots on the empty board to judge distances by, see
+// `render::draw_grid`.
+//
+// Glyphs set in the config file (`glyph-wall = █`, see `Glyphs`) go on top
+// of all of it: they stay the same whatever the level, length or date. In
+// emoji mode (`--emoji`) they can be two columns wide, and are drawn in
+// large cells that are too (see `zoom.rs`); without those the theme's own
+// glyphs stand in for them.
+
+use crate::backend::{Color, Style};
+use crate::entity::{EntityKind, FADING_STAGES};
+use crate::snake::SnakeDirection;
+use std::time::Duration;
+use unicode_width::UnicodeWidthStr;
+
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Theme {
+    pub monochrome: bool,
+    // 0 and 1 both mean the first level
+    pub level: u32,
+    // Of the snake being drawn, picks its skin
+    pub length: usize,
+    pub day_night: bool,
+    // Game time, where in the day/night cycle we are
+    pub time: Duration,
+    pub season: Option<Season>,
+    // From the config file, there for the whole run so themes can still be
+    // copied around
+    pub glyphs: Option<&'static Glyphs>,
+    pub grid: Option<Grid>,
+    // Cells two columns wide, emoji glyphs fit in them
+    pub wide_cells: bool,
+}
+
+/// Dots on the empty board, every other cell.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Grid {
+    // Every other cell of every other row, in lines
+    Dots,
+    // Every other cell, shifted by one on every row
+    Checker,
+}
+
+impl Grid {
+    /// Whether the cell at `x`, `y` has a dot.
+    pub fn has_dot(self, x: u16, y: u16) -> bool {
+        match self {
+            Grid::Dots => x.is_multiple_of(2) && y.is_multiple_of(2),
+            Grid::Checker => (x + y).is_multiple_of(2),
+        }
+    }
+}
+
+impl std::str::FromStr for Grid {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dots" => Ok(Grid::Dots),
+            "checker" => Ok(Grid::Checker),
+            _ => Err(format!("unknown grid `{s}`")),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Season {
+    // October
+    Halloween,
+    // December
+    Winter,
+}
+
+impl Season {
+    /// The season of a date, as seconds since the epoch (UTC).
+    pub fn at(unix_secs: u64) -> Option<Season> {
+        match month_of(unix_secs) {
+            10 => Some(Season::Halloween),
+            12 => Some(Season::Winter),
+            _ => None,
+        }
+    }
+}
+
+// 1 to 12, from Howard Hinnant's days-to-civil
+fn month_of(unix_secs: u64) -> u32 {
+    let days = unix_secs / 86_400 + 719_468;
+    let day_of_era = days % 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month = (5 * day_of_year + 2) / 153;
+    if month < 10 {
+        month as u32 + 3
+    } else {
+        month as u32 - 9
+    }
+}
+
+// A whole day and night
+const DAY: Duration = Duration::from_secs(240);
+// The night tint, and how much of it there is at midnight
+const NIGHT: (f32, f32, f32) = (30.0, 40.0, 110.0);
+const NIGHT_MIX: f32 = 0.55;
+
+/// How the snake looks from `from` segments on.
+struct Skin {
+    from: usize,
+    // Up, down, left and right
+    heads: [&'static str; 4],
+    bright: bool,
+}
+
+// Shortest first
+const SKINS: [Skin; 4] = [
+    Skin {
+        from: 0,
+        heads: ["^", "v", "<", ">"],
+        bright: false,
+    },
+    Skin {
+        from: 20,
+        heads: ["▲", "▼", "◀", "▶"],
+        bright: false,
+    },
+    Skin {
+        from: 50,
+        heads: ["▲", "▼", "◀", "▶"],
+        bright: true,
+    },
+    Skin {
+        from: 100,
+        heads: ["♛"; 4],
+        bright: true,
+    },
+];
+
+const LEVEL_ACCENTS: [Color; 5] = [
+    Color::Cyan,
+    Color::Yellow,
+    Color::Blue,
+    Color::Magenta,
+    Color::Green,
+];
+const LEVEL_FOOD: [&str; 5] = ["*", "%", "&", "$", "+"];
+// The heatmap, from hardly ever there to there the most
+const HEAT: [(&str, Color); 4] = [
+    ("░", Color::Blue),
+    ("▒", Color::Cyan),
+    ("▓", Color::Yellow),
+    ("█", Color::Red),
+];
+// Surround mode's fading cells, from about to go to just left
+const FADING: [&str; FADING_STAGES] = ["░", "▒", "▓"];
+const DIGITS: [&str; 10] = ["0", "1", "2", "3", "4", "5", "6", "7", "8", "9"];
+const LETTERS: [&str; 26] = [
+    "A", "B", "C", "D", "E", "F", "G", "H", "I", "J", "K", "L", "M", "N", "O", "P", "Q", "R", "S",
+    "T", "U", "V", "W", "X", "Y", "Z",
+];
+
+impl Theme {
+    /// Follows https://no-color.org: any non-empty `NO_COLOR` turns color off.
+    pub fn from_env(no_color_flag: bool) -> Theme {
+        let no_color_env = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        Theme {
+            monochrome: no_color_flag || no_color_env,
+            level: 1,
+            length: 0,
+            day_night: false,
+            time: Duration::ZERO,
+            season: None,
+            glyphs: None,
+            grid: None,
+            wide_cells: false,
+        }
+    }
+
+    // A glyph from the config file if it fits in a cell as they're drawn
+    fn fitting(&self, glyph: Option<&'static str>) -> Option<&'static str> {
+        glyph.filter(|glyph| self.wide_cells || glyph.width() == 1)
+    }
+
+    /// The same theme with the accents of `level`.
+    pub fn at_level(self, level: u32) -> Theme {
+        Theme { level, ..self }
+    }
+
+    /// The same theme with the skin of a snake `length` long.
+    pub fn at_length(self, length: usize) -> Theme {
+        Theme { length, ..self }
+    }
+
+    /// The same theme at `time` into the game, for the day/night cycle.
+    pub fn at_time(self, time: Duration) -> Theme {
+        Theme { time, ..self }
+    }
+
+    // 0 at noon to 1 at midnight
+    fn darkness(&self) -> f32 {
+        let phase = self.time.as_secs_f32() / DAY.as_secs_f32();
+        (1.0 - (phase * std::f32::consts::TAU).cos()) / 2.0
+    }
+
+    // `color` as the time of day makes it look
+    fn tint(&self, color: Color) -> Color {
+        if !self.day_night {
+            return color;
+        }
+        let (r, g, b) = match color {
+            Color::Green => (80.0, 200.0, 80.0),
+            Color::Red => (220.0, 60.0, 60.0),
+            Color::Yellow => (220.0, 200.0, 60.0),
+            Color::Blue => (80.0, 120.0, 230.0),
+            Color::Magenta => (200.0, 90.0, 200.0),
+            Color::Cyan => (70.0, 200.0, 210.0),
+            Color::Rgb(r, g, b) => (f32::from(r), f32::from(g), f32::from(b)),
+        };
+        let mix = self.darkness() * NIGHT_MIX;
+        let blend = |day: f32, night: f32| (day + (night - day) * mix) as u8;
+        Color::Rgb(blend(r, NIGHT.0), blend(g, NIGHT.1), blend(b, NIGHT.2))
+    }
+
+    fn skin(&self) -> &'static Skin {
+        SKINS
+            .iter()
+            .rev()
+            .find(|skin| self.length >= skin.from)
+            .unwrap_or(&SKINS[0])
+    }
+
+    // `None` on the first level, which keeps the plain look
+    fn level_index(&self) -> Option<usize> {
+        let level = self.level.max(1) as usize;
+        (level > 1).then(|| (level - 2) % LEVEL_ACCENTS.len())
+    }
+
+    fn colored(&self, color: Color, fallback: Style) -> Style {
+        if self.monochrome {
+            fallback
+        } else {
+            Style::fg(self.tint(color))
+        }
+    }
+
+    pub fn snake_head(&self) -> Style {
+        self.colored(Color::Green, Style::plain().bold())
+    }
+
+    pub fn snake_body(&self) -> Style {
+        let style = self.colored(Color::Green, Style::plain());
+        if self.skin().bright {
+            style.bold()
+        } else {
+            style
+        }
+    }
+
+    pub fn food(&self) -> Style {
+        match self.season {
+            Some(Season::Halloween) => self.colored(Color::Yellow, Style::plain().reverse()),
+            _ => self.colored(Color::Red, Style::plain().reverse()),
+        }
+    }
+
+    pub fn text(&self) -> Style {
+        self.colored(Color::Magenta, Style::plain().bold())
+    }
+
+    pub fn status_bar(&self) -> Style {
+        match self.level_index() {
+            Some(i) if !self.monochrome => Style::fg(self.tint(LEVEL_ACCENTS[i])).reverse(),
+            _ => Style::plain().reverse(),
+        }
+    }
+
+    /// The round trip to a game server in the status bar, once it's slow.
+    pub fn lag(&self) -> Style {
+        self.colored(Color::Red, Style::plain().bold()).reverse()
+    }
+
+    /// Where the snake died, in the photo finish.
+    pub fn fatal(&self) -> Style {
+        self.colored(Color::Red, Style::plain().bold()).reverse()
+    }
+
+    pub fn head_glyph(&self, direction: SnakeDirection) -> &'static str {
+        let i = match direction {
+            SnakeDirection::Up => 0,
+            SnakeDirection::Down => 1,
+            SnakeDirection::Left => 2,
+            SnakeDirection::Right => 3,
+        };
+        match self.fitting(self.glyphs.and_then(|g| g.heads[i].as_deref())) {
+            Some(glyph) => glyph,
+            None => self.skin().heads[i],
+        }
+    }
+
+    /// A piece of the snake behind the head.
+    pub fn body_glyph(&self, segment: Segment) -> &'static str {
+        if let Some(glyph) = self.fitting(
+            self.glyphs
+                .and_then(|g| g.body[segment as usize].as_deref()),
+        ) {
+            return glyph;
+        }
+        match segment {
+            Segment::Vertical => "|",
+            Segment::Horizontal => "-",
+            Segment::Corner => "s",
+        }
+    }
+
+    /// Points to food out of sight, and shows turns in the input HUD.
+    pub fn arrow_glyph(&self, direction: SnakeDirection) -> &'static str {
+        match direction {
+            SnakeDirection::Up => "↑",
+            SnakeDirection::Down => "↓",
+            SnakeDirection::Left => "←",
+            SnakeDirection::Right => "→",
+        }
+    }
+
+    pub fn food_glyph(&self) -> &'static str {
+        if self.season == Some(Season::Halloween) {
+            return "Ö";
+        }
+        match self.level_index() {
+            Some(i) => LEVEL_FOOD[i],
+            None if self.monochrome => "@",
+            None => "o",
+        }
+    }
+
+    /// Where a board smaller than the terminal ends.
+    /// The head coming back in on the other side of the board, see
+    /// `render::draw_wrap_ghost`.
+    pub fn wrap_ghost(&self) -> Style {
+        Style::plain()
+    }
+
+    pub fn grid_glyph(&self) -> &'static str {
+        "·"
+    }
+
+    pub fn grid(&self) -> Style {
+        Style::plain().dim()
+    }
+
+    pub fn edge_glyph(&self) -> &'static str {
+        match self.season {
+            Some(Season::Winter) => "*",
+            _ => ":",
+        }
+    }
+
+    pub fn edge(&self) -> Style {
+        match self.season {
+            Some(Season::Winter) => Style::plain().bold(),
+            _ => self.text(),
+        }
+    }
+
+    /// A heatmap cell, `heat` goes from 0 (never there) to 1 (the most).
+    pub fn heat(&self, heat: f32) -> (&'static str, Style) {
+        if heat <= 0.0 {
+            return (" ", Style::plain());
+        }
+        let step = ((heat * HEAT.len() as f32).ceil() as usize).clamp(1, HEAT.len()) - 1;
+        let (glyph, color) = HEAT[step];
+        (glyph, self.colored(color, Style::plain()))
+    }
+
+    /// An uppercase ASCII letter, on food or on the snake in word mode.
+    pub fn letter_glyph(&self, letter: u8) -> &'static str {
+        LETTERS
+            .get(letter.wrapping_sub(b'A') as usize)
+            .copied()
+            .unwrap_or("?")
+    }
+
+    pub fn entity_glyph(&self, kind: EntityKind) -> &'static str {
+        if let Some(glyph) = self.fitting(self.glyphs.and_then(|g| g.entity(kind))) {
+            return glyph;
+        }
+        match kind {
+            EntityKind::Food => self.food_glyph(),
+            EntityKind::Wall => "#",
+            EntityKind::Magnet => "U",
+            EntityKind::Door => "▒",
+            EntityKind::Boss => "X",
+            EntityKind::WeakPoint(n) | EntityKind::Portal(n) | EntityKind::Chain(n) => {
+                DIGITS.get(n as usize).copied().unwrap_or("?")
+            }
+            EntityKind::Checkpoint => "!",
+            EntityKind::Hazard => "^",
+            EntityKind::Letter(c) => self.letter_glyph(c),
+            EntityKind::Ghost => "░",
+            EntityKind::Zone => "+",
+            EntityKind::Split => "Y",
+            EntityKind::Fading(n) => FADING
+                .get((n as usize).saturating_sub(1))
+                .copied()
+                .unwrap_or("░"),
+        }
+    }
+
+    pub fn entity_style(&self, kind: EntityKind) -> Style {
+        match kind {
+            EntityKind::Food => self.food(),
+            EntityKind::Wall => Style::plain(),
+            EntityKind::Magnet => self.colored(Color::Blue, Style::plain().bold()),
+            EntityKind::Door => self.colored(Color::Cyan, Style::plain()),
+            EntityKind::Boss => self.colored(Color::Magenta, Style::plain().bold()),
+            EntityKind::WeakPoint(_) => self.colored(Color::Yellow, Style::plain().reverse()),
+            EntityKind::Checkpoint => self.colored(Color::Green, Style::plain().bold()),
+            EntityKind::Hazard => self.colored(Color::Red, Style::plain()),
+            EntityKind::Portal(_) => self.colored(Color::Cyan, Style::plain().reverse()),
+            EntityKind::Chain(_) => self.colored(Color::Green, Style::plain().reverse()),
+            EntityKind::Letter(_) => self.food(),
+            EntityKind::Ghost => self.colored(Color::Blue, Style::plain()),
+            EntityKind::Zone => self.colored(Color::Yellow, Style::plain()),
+            EntityKind::Split => self.colored(Color::Magenta, Style::plain().bold()),
+            EntityKind::Fading(n) if n as usize >= FADING_STAGES => {
+                self.colored(Color::Green, Style::plain().bold())
+            }
+            EntityKind::Fading(_) => self.colored(Color::Green, Style::plain()),
+        }
+    }
+}
+
+/// Which way a piece of the body goes, from the one ahead of it.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Segment {
+    Vertical,
+    Horizontal,
+    Corner,
+}
+
+/// Glyphs to draw things with instead of the theme's, each one column wide
+/// like a cell of the board, or up to two in emoji mode. Only what's set is
+/// changed.
+#[derive(Clone, Debug, Default)]
+pub struct Glyphs {
+    // Set before the glyphs are, it lets them be two columns wide
+    pub emoji: bool,
+    // Up, down, left and right
+    heads: [Option<String>; 4],
+    // In the order of `Segment`
+    body: [Option<String>; 3],
+    entities: Vec<(EntityKind, String)>,
+}
+
+const HEAD_NAMES: [&str; 4] = ["head-up", "head-down", "head-left", "head-right"];
+const BODY_NAMES: [&str; 3] = ["body-vertical", "body-horizontal", "body-corner"];
+
+impl Glyphs {
+    /// Draw `name` as `glyph`: a head (`head-up`, ...), a piece of the body
+    /// (`body-vertical`, `body-horizontal`, `body-corner`) or an entity by
+    /// its name (`food`, `wall`, `portal1`, ...).
+    pub fn set(&mut self, name: &str, glyph: &str) -> Result<(), String> {
+        let widest = if self.emoji { 2 } else { 1 };
+        if !(1..=widest).contains(&glyph.width()) || glyph.chars().any(char::is_control) {
+            return Err(format!(
+                "the glyph for `{name}` has to be {}, `{glyph}` is {}",
+                match self.emoji {
+                    true => "one or two columns wide in emoji mode",
+                    false => "one column wide (two with --emoji)",
+                },
+                glyph.width()
+            ));
+        }
+        let glyph = glyph.to_string();
+        if let Some(i) = HEAD_NAMES.iter().position(|&n| n == name) {
+            self.heads[i] = Some(glyph);
+        } else if let Some(i) = BODY_NAMES.iter().position(|&n| n == name) {
+            self.body[i] = Some(glyph);
+        } else if let Some(kind) = EntityKind::from_name(name) {
+            self.entities.retain(|&(k, _)| k != kind);
+            self.entities.push((kind, glyph));
+        } else {
+            return Err(format!(
+                "no glyph called `{name}`, there are {}, {} and the entities' (`food`, `wall`, ...)",
+                HEAD_NAMES.join(", "),
+                BODY_NAMES.join(", ")
+            ));
+        }
+        Ok(())
+    }
+
+    fn entity(&self, kind: EntityKind) -> Option<&str> {
+        self.entities
+            .iter()
+            .find(|&&(k, _)| k == kind)
+            .map(|(_, glyph)| glyph.as_str())
+    }
+}
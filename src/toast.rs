@@ -0,0 +1,70 @@
+// -- Toasts
+//
+// Short notices in the top-right corner that go away by themselves: a new
+// high score, a saved game and whatever comes later. They wait in a queue
+// and a few are shown at once, each one's clock starts when it shows up, so
+// a burst of them doesn't expire before anyone sees it.
+
+use crate::backend::Backend;
+use crate::layout::Rect;
+use crate::theme::Theme;
+use std::collections::VecDeque;
+use std::io;
+use std::time::{Duration, Instant};
+
+const SHOWN_AT_ONCE: usize = 3;
+pub const TOAST_TIME: Duration = Duration::from_secs(3);
+
+struct Toast {
+    text: String,
+    // Set once it's on screen
+    until: Option<Instant>,
+}
+
+#[derive(Default)]
+pub struct Toasts {
+    queue: VecDeque<Toast>,
+}
+
+impl Toasts {
+    pub fn new() -> Self {
+        Toasts::default()
+    }
+
+    pub fn push(&mut self, text: impl Into<String>) {
+        self.queue.push_back(Toast {
+            text: text.into(),
+            until: None,
+        });
+    }
+
+    /// Drop the expired ones and start the clock on the ones now showing.
+    pub fn update(&mut self, now: Instant) {
+        self.queue
+            .retain(|t| t.until.is_none_or(|until| now < until));
+        for toast in self.queue.iter_mut().take(SHOWN_AT_ONCE) {
+            toast.until.get_or_insert(now + TOAST_TIME);
+        }
+    }
+
+    /// When the next one goes away, so the loop can wake up for it.
+    pub fn next_expiry(&self) -> Option<Instant> {
+        self.queue.iter().filter_map(|t| t.until).min()
+    }
+
+    /// Stacked in the top-right corner of `area`.
+    pub fn draw(&self, out: &mut impl Backend, area: Rect, theme: &Theme) -> io::Result<()> {
+        let shown = self
+            .queue
+            .iter()
+            .take(SHOWN_AT_ONCE.min(area.height as usize));
+        for (row, toast) in shown.enumerate() {
+            let text = format!(" {} ", toast.text);
+            let width = text.chars().count().min(area.width as usize);
+            let text: String = text.chars().take(width).collect();
+            let x = area.right() - width as u16;
+            out.print(x, area.y + row as u16, &text, theme.status_bar())?;
+        }
+        Ok(())
+    }
+}
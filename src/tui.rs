@@ -0,0 +1,317 @@
+// -- The ratatui frontend
+//
+// The same engine drawn with ratatui (`--features ratatui`, then
+// `snake2 --ui ratatui`): the board is a widget, the status bar, help and
+// game over screen are ratatui blocks and paragraphs, and ratatui's double
+// buffer only sends what changed to the terminal. The board reuses
+// `draw_game` through `BufferBackend`, which writes into a ratatui buffer.
+//
+// It's the lighter cousin of the default frontend in `main.rs`: start,
+// pause, help, Ctrl+Z and game over, but no profiles or challenges.
+
+use crate::backend::{self, Backend};
+use crate::clock::{Stopwatch, format_duration};
+use crate::game::{Game, GameOptions, TickOutcome};
+use crate::help::help_lines;
+use crate::input::{IDLE_WAIT, Input, next_input};
+use crate::render::{draw_game, hunger_gauge, speed_gauge};
+use crate::terminal::{
+    disable_game_mode, enable_game_mode, setup_panic_hook, suspend, take_resumed, watch_job_control,
+};
+use crate::theme::Theme;
+use ratatui::Terminal;
+use ratatui::backend::CrosstermBackend;
+use ratatui::buffer::Buffer;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Clear, Paragraph, Widget};
+use std::io;
+use std::time::{Duration, Instant};
+
+/// Our `Backend` on top of a ratatui buffer, clipped to `area`.
+pub struct BufferBackend<'a> {
+    pub buf: &'a mut Buffer,
+    pub area: Rect,
+}
+
+impl Backend for BufferBackend<'_> {
+    // Every ratatui frame starts out blank
+    fn clear(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn print(&mut self, x: u16, y: u16, text: &str, style: backend::Style) -> io::Result<()> {
+        if x >= self.area.width || y >= self.area.height {
+            return Ok(());
+        }
+        let width = (self.area.width - x) as usize;
+        self.buf.set_stringn(
+            self.area.x + x,
+            self.area.y + y,
+            text,
+            width,
+            to_ratatui(style),
+        );
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn to_ratatui(style: backend::Style) -> Style {
+    let mut out = Style::default();
+    if let Some(color) = style.color {
+        out = out.fg(match color {
+            backend::Color::Green => Color::Green,
+            backend::Color::Red => Color::Red,
+            backend::Color::Yellow => Color::Yellow,
+            backend::Color::Blue => Color::Blue,
+            backend::Color::Magenta => Color::Magenta,
+            backend::Color::Cyan => Color::Cyan,
+            backend::Color::Rgb(r, g, b) => Color::Rgb(r, g, b),
+        });
+    }
+    if style.bold {
+        out = out.add_modifier(Modifier::BOLD);
+    }
+    if style.reverse {
+        out = out.add_modifier(Modifier::REVERSED);
+    }
+    if style.dim {
+        out = out.add_modifier(Modifier::DIM);
+    }
+    out
+}
+
+/// The board, drawn by the same code as the default frontend.
+pub struct Board<'a> {
+    pub game: &'a Game,
+    pub theme: &'a Theme,
+}
+
+impl Widget for Board<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let mut out = BufferBackend { buf, area };
+        // Only a failing terminal makes printing fail, and this isn't one
+        let _ = draw_game(&mut out, self.game, self.theme);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Screen {
+    Start,
+    Playing,
+    Paused,
+    Over,
+}
+
+/// A box in the middle of `area` with `lines` in it, over whatever is there.
+fn popup(buf: &mut Buffer, area: Rect, title: &str, lines: Vec<String>, theme: &Theme) {
+    let width = lines
+        .iter()
+        .map(|l| l.chars().count())
+        .max()
+        .unwrap_or(0)
+        .max(title.chars().count()) as u16
+        + 4;
+    let height = lines.len() as u16 + 2;
+    let [row] = Layout::vertical([Constraint::Length(height)])
+        .flex(ratatui::layout::Flex::Center)
+        .areas(area);
+    let [boxed] = Layout::horizontal([Constraint::Length(width)])
+        .flex(ratatui::layout::Flex::Center)
+        .areas(row);
+
+    Clear.render(boxed, buf);
+    let lines: Vec<Line> = lines.into_iter().map(Line::from).collect();
+    Paragraph::new(lines)
+        .block(
+            Block::bordered()
+                .title(format!(" {title} "))
+                .border_style(to_ratatui(theme.text())),
+        )
+        .render(boxed, buf);
+}
+
+pub fn run(theme: &Theme, options: &GameOptions) -> io::Result<()> {
+    setup_panic_hook();
+    watch_job_control();
+    let mut stdout = io::stdout();
+    enable_game_mode(&mut stdout)?;
+    let mut terminal = Terminal::new(CrosstermBackend::new(stdout))?;
+
+    let mut screen = Screen::Start;
+    let mut game: Option<Game> = None;
+    let mut show_help = false;
+    let mut clock = Stopwatch::new();
+    let mut next_tick = Instant::now();
+    // Nothing happened since the last frame and nothing moves on this screen
+    let mut idle = false;
+
+    loop {
+        if take_resumed(&mut io::stdout())? {
+            idle = false;
+            terminal.clear()?;
+            if screen == Screen::Playing {
+                screen = Screen::Paused;
+                clock.stop();
+            }
+        }
+
+        let now = Instant::now();
+        if screen == Screen::Playing
+            && now >= next_tick
+            && let Some(ref mut g) = game
+        {
+            clock.start();
+            if g.tick() == TickOutcome::Died {
+                clock.stop();
+                screen = Screen::Over;
+            }
+            next_tick = (next_tick + Duration::from_millis(g.timer)).max(now);
+        }
+
+        if !idle {
+            terminal.draw(|frame| {
+                let [board_area, status_area] =
+                    Layout::vertical([Constraint::Min(0), Constraint::Length(1)])
+                        .areas(frame.area());
+                let buf = frame.buffer_mut();
+
+                match game {
+                    Some(ref g) => Board { game: g, theme }.render(board_area, buf),
+                    None => Paragraph::new("Press arrows to move, or q to quit.")
+                        .style(to_ratatui(theme.text()))
+                        .render(board_area, buf),
+                }
+
+                let status = match (&game, screen) {
+                    (Some(g), _) => format!(
+                        " {}  level {}  score {}  length {}  speed {}{}  time {}",
+                        options.mode.name(),
+                        g.level,
+                        g.score,
+                        g.snake.body.len(),
+                        speed_gauge(g),
+                        hunger_gauge(g)
+                            .map(|gauge| format!("  hunger {gauge}"))
+                            .unwrap_or_default(),
+                        format_duration(clock.elapsed())
+                    ),
+                    (None, _) => format!(" {}", options.mode.name()),
+                };
+                let hints = match screen {
+                    Screen::Start => "arrows start  ? help  q quit ",
+                    Screen::Playing => "p pause  ? help  q quit ",
+                    Screen::Paused => "p resume  ? help  q quit ",
+                    Screen::Over => "arrows again  q quit ",
+                };
+                let [left, right] = Layout::horizontal([
+                    Constraint::Min(0),
+                    Constraint::Length(hints.len() as u16),
+                ])
+                .areas(status_area);
+                let bar = to_ratatui(
+                    theme
+                        .at_level(game.as_ref().map_or(1, |g| g.level))
+                        .status_bar(),
+                );
+                Paragraph::new(status).style(bar).render(left, buf);
+                Paragraph::new(hints).style(bar).render(right, buf);
+
+                if screen == Screen::Paused && !show_help {
+                    popup(buf, board_area, "Paused", vec![], theme);
+                }
+                if let (Screen::Over, Some(g)) = (screen, &game) {
+                    let lines = vec![
+                        format!("Score {}, length {}", g.score, g.snake.body.len()),
+                        format!("Time {}", format_duration(clock.elapsed())),
+                    ];
+                    popup(buf, board_area, "Game over", lines, theme);
+                }
+                if show_help {
+                    popup(
+                        buf,
+                        board_area,
+                        "Help",
+                        help_lines(game.as_ref(), options, theme),
+                        theme,
+                    );
+                }
+            })?;
+        }
+
+        let timeout = match screen {
+            Screen::Playing => next_tick.saturating_duration_since(now),
+            _ => IDLE_WAIT,
+        };
+        let input = next_input(timeout)?;
+        idle = input.is_none() && screen != Screen::Playing;
+        match input {
+            Some(Input::Quit) => break,
+            Some(Input::Help) => {
+                if screen == Screen::Playing {
+                    screen = Screen::Paused;
+                    clock.stop();
+                }
+                show_help = !show_help;
+            }
+            Some(Input::Pause) => {
+                show_help = false;
+                screen = match screen {
+                    Screen::Playing => Screen::Paused,
+                    Screen::Paused => {
+                        next_tick = Instant::now();
+                        Screen::Playing
+                    }
+                    other => other,
+                };
+                clock.stop();
+            }
+            Some(Input::Suspend) => {
+                if screen == Screen::Playing {
+                    screen = Screen::Paused;
+                    clock.stop();
+                }
+                suspend(&mut io::stdout())?;
+            }
+            Some(Input::Turn(direction)) => match screen {
+                // After a game over an arrow starts the next one
+                Screen::Start | Screen::Over => {
+                    clock = Stopwatch::new();
+                    let size = terminal.size()?;
+                    // The last row is for the status bar
+                    let rows = size.height.saturating_sub(1).max(2);
+                    game = Some(Game::with_options(size.width, rows, direction, options));
+                    screen = Screen::Playing;
+                    show_help = false;
+                    next_tick = Instant::now();
+                }
+                Screen::Playing => {
+                    if let Some(ref mut g) = game {
+                        g.turn(direction);
+                    }
+                }
+                _ => {}
+            },
+            Some(Input::Resize(..)) => terminal.autoresize()?,
+            Some(Input::Save) | Some(Input::Chat) | Some(Input::Zoom) | Some(Input::Cycle)
+            | Some(Input::Faster) | Some(Input::Slower) | None => {}
+        }
+    }
+
+    drop(terminal);
+    disable_game_mode(&mut io::stdout())?;
+    if let Some(g) = game {
+        println!(
+            "\tScore {}, length {}, time {}.\n",
+            g.score,
+            g.snake.body.len(),
+            format_duration(clock.elapsed())
+        );
+    }
+    Ok(())
+}
@@ -0,0 +1,203 @@
+// -- Twitch chat plays snake
+//
+// Reads a channel's chat over Twitch's IRC gateway, logged in anonymously,
+// and turns messages like `up` or `!left` into votes. The socket lives on
+// its own thread and hands votes over a channel, so the game loop just
+// drains whatever arrived since the last tick. Every few ticks the direction
+// with the most votes goes through `Game::turn` like a key press would, and
+// the tally starts over. Everyone gets one vote per window, a second one
+// from the same user replaces the first.
+//
+// With the `tokio` feature the socket is a task on the shared runtime (see
+// `runtime.rs`) instead, handing votes over the same channel.
+
+use crate::backend::Backend;
+use crate::layout::Rect;
+use crate::snake::SnakeDirection;
+use crate::theme::Theme;
+use std::collections::HashMap;
+use std::io;
+use std::sync::mpsc::{self, Receiver};
+
+const SERVER: &str = "irc.chat.twitch.tv:6667";
+// Anonymous, read-only logins are `justinfan` and any number
+const NICK: &str = "justinfan31337";
+pub const PANEL_WIDTH: u16 = 18;
+
+const DIRECTIONS: [SnakeDirection; 4] = [
+    SnakeDirection::Up,
+    SnakeDirection::Down,
+    SnakeDirection::Left,
+    SnakeDirection::Right,
+];
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Vote {
+    pub user: String,
+    pub direction: SnakeDirection,
+}
+
+/// The votes of the current window.
+#[derive(Clone, Debug, Default)]
+pub struct Tally {
+    votes: HashMap<String, SnakeDirection>,
+}
+
+impl Tally {
+    pub fn add(&mut self, vote: Vote) {
+        self.votes.insert(vote.user, vote.direction);
+    }
+
+    pub fn count(&self, direction: SnakeDirection) -> usize {
+        self.votes.values().filter(|&&d| d == direction).count()
+    }
+
+    /// The direction with the most votes, `None` with no votes or a tie.
+    pub fn winner(&self) -> Option<SnakeDirection> {
+        let mut counts: Vec<(usize, SnakeDirection)> =
+            DIRECTIONS.iter().map(|&d| (self.count(d), d)).collect();
+        counts.sort_by_key(|c| std::cmp::Reverse(c.0));
+        match counts[..] {
+            [(0, _), ..] => None,
+            [(first, _), (second, _), ..] if first == second => None,
+            [(_, direction), ..] => Some(direction),
+            [] => None,
+        }
+    }
+
+    pub fn clear(&mut self) {
+        self.votes.clear();
+    }
+
+    /// The side panel.
+    pub fn draw(
+        &self,
+        out: &mut impl Backend,
+        area: Rect,
+        ticks_left: u64,
+        theme: &Theme,
+    ) -> io::Result<()> {
+        let area = area.inset(1);
+        let mut lines = vec!["Chat votes".to_string(), String::new()];
+        for direction in DIRECTIONS {
+            lines.push(format!(
+                "{:<6}{:>4}",
+                direction.name(),
+                self.count(direction)
+            ));
+        }
+        lines.push(String::new());
+        lines.push(format!("next in {ticks_left}"));
+
+        for (i, line) in lines.iter().take(area.height as usize).enumerate() {
+            let line: String = line.chars().take(area.width as usize).collect();
+            let style = if i == 0 {
+                theme.text().bold()
+            } else {
+                theme.text()
+            };
+            out.print(area.x, area.y + i as u16, &line, style)?;
+        }
+        Ok(())
+    }
+}
+
+pub struct Chat {
+    votes: Receiver<Vote>,
+}
+
+impl Chat {
+    /// Join `channel`'s chat, votes start coming in right away.
+    #[cfg(not(feature = "tokio"))]
+    pub fn connect(channel: &str) -> io::Result<Chat> {
+        use std::io::{BufRead, BufReader, Write};
+        use std::net::TcpStream;
+        use std::thread;
+
+        let channel = channel.trim_start_matches('#').to_lowercase();
+        let mut stream = TcpStream::connect(SERVER)?;
+        write!(stream, "NICK {NICK}\r\nJOIN #{channel}\r\n")?;
+
+        let (sender, votes) = mpsc::channel();
+        let reader = BufReader::new(stream.try_clone()?);
+        thread::spawn(move || {
+            for line in reader.lines() {
+                let Ok(line) = line else {
+                    return;
+                };
+                // Twitch hangs up on clients that don't answer
+                if let Some(server) = line.strip_prefix("PING ") {
+                    if write!(stream, "PONG {server}\r\n").is_err() {
+                        return;
+                    }
+                    continue;
+                }
+                if let Some(vote) = parse_vote(&line)
+                    && sender.send(vote).is_err()
+                {
+                    // The game is over
+                    return;
+                }
+            }
+        });
+        Ok(Chat { votes })
+    }
+
+    /// Join `channel`'s chat, votes start coming in right away.
+    #[cfg(feature = "tokio")]
+    pub fn connect(channel: &str) -> io::Result<Chat> {
+        use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+
+        let channel = channel.trim_start_matches('#').to_lowercase();
+        let stream = crate::runtime::block_on(async {
+            let mut stream = tokio::net::TcpStream::connect(SERVER).await?;
+            let join = format!("NICK {NICK}\r\nJOIN #{channel}\r\n");
+            stream.write_all(join.as_bytes()).await?;
+            io::Result::Ok(stream)
+        })?;
+
+        let (sender, votes) = mpsc::channel();
+        crate::runtime::spawn(async move {
+            let (reader, mut writer) = stream.into_split();
+            let mut lines = tokio::io::BufReader::new(reader).lines();
+            while let Ok(Some(line)) = lines.next_line().await {
+                // Twitch hangs up on clients that don't answer
+                if let Some(server) = line.strip_prefix("PING ") {
+                    let pong = format!("PONG {server}\r\n");
+                    if writer.write_all(pong.as_bytes()).await.is_err() {
+                        return;
+                    }
+                    continue;
+                }
+                if let Some(vote) = parse_vote(&line)
+                    && sender.send(vote).is_err()
+                {
+                    // The game is over
+                    return;
+                }
+            }
+        });
+        Ok(Chat { votes })
+    }
+
+    /// Everything that came in since the last call, without waiting.
+    pub fn drain(&self, tally: &mut Tally) {
+        while let Ok(vote) = self.votes.try_recv() {
+            tally.add(vote);
+        }
+    }
+}
+
+/// A vote out of a raw IRC line, like
+/// `:alice!alice@alice.tmi.twitch.tv PRIVMSG #channel :left`.
+pub fn parse_vote(line: &str) -> Option<Vote> {
+    let rest = line.strip_prefix(':')?;
+    let (user, rest) = rest.split_once('!')?;
+    let (_, message) = rest.split_once(" PRIVMSG ")?;
+    let (_, text) = message.split_once(" :")?;
+    let direction = SnakeDirection::from_name(text.trim().trim_start_matches('!'))?;
+    Some(Vote {
+        user: user.to_string(),
+        direction,
+    })
+}
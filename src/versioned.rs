@@ -0,0 +1,80 @@
+// -- Versions of the files we write
+//
+// Saves, high scores and stats start with a header line naming what the
+// file is and which version of its format it's in:
+//
+//     snake2-save 1
+//
+// Reading goes through `upgrade`, which runs the text through every
+// migration between the file's version and the current one, so the parsers
+// only ever see the current format. Files from before headers existed are
+// version 0. A file from a newer snake2 is an error instead of something
+// to half-read and then overwrite.
+
+/// Turns the body of a file in one version into the next version.
+pub type Migration = fn(&str) -> Result<String, String>;
+
+/// One kind of file, `migrations[n]` goes from version `n` to `n + 1`, so
+/// the current version is the number of migrations.
+pub struct Format {
+    pub kind: &'static str,
+    pub migrations: &'static [Migration],
+}
+
+impl Format {
+    pub fn version(&self) -> u32 {
+        self.migrations.len() as u32
+    }
+
+    /// The header line and `body`, ready to write.
+    pub fn write(&self, body: &str) -> String {
+        format!("snake2-{} {}\n{body}", self.kind, self.version())
+    }
+
+    /// The version of `text`, 0 without a header.
+    pub fn version_of(&self, text: &str) -> Result<u32, String> {
+        Ok(self.split(text)?.0)
+    }
+
+    /// The body of `text` in the current version.
+    pub fn upgrade(&self, text: &str) -> Result<String, String> {
+        let (version, body) = self.split(text)?;
+        if version > self.version() {
+            return Err(format!(
+                "this {} file is version {version}, from a newer snake2 (this one reads up to {})",
+                self.kind,
+                self.version()
+            ));
+        }
+        let mut body = body.to_string();
+        for migrate in &self.migrations[version as usize..] {
+            body = migrate(&body)?;
+        }
+        Ok(body)
+    }
+
+    fn split<'a>(&self, text: &'a str) -> Result<(u32, &'a str), String> {
+        let (first, rest) = text.split_once('\n').unwrap_or((text, ""));
+        let Some(header) = first.trim_end().strip_prefix("snake2-") else {
+            return Ok((0, text));
+        };
+        let (kind, version) = header
+            .split_once(' ')
+            .ok_or(format!("bad header `{}`", first.trim_end()))?;
+        if kind != self.kind {
+            return Err(format!(
+                "expected a {} file, this is a {kind} file",
+                self.kind
+            ));
+        }
+        let version = version
+            .parse()
+            .map_err(|_| format!("bad version `{version}`"))?;
+        Ok((version, rest))
+    }
+}
+
+/// For versions that only added the header.
+pub fn unchanged(body: &str) -> Result<String, String> {
+    Ok(body.to_string())
+}
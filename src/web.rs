@@ -0,0 +1,110 @@
+// -- Browser entry point, see `web/` for the xterm.js side
+//
+// JS owns the timer and the terminal: it forwards key presses to `key`,
+// writes whatever `frame` returns into xterm.js and waits `interval`
+// milliseconds before asking for the next one.
+
+use crate::backend::{AnsiBackend, Backend};
+use crate::game::{Game, TickOutcome};
+use crate::layout::Rect;
+use crate::render::{StatusBar, draw_game};
+use crate::snake::SnakeDirection;
+use crate::theme::Theme;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+pub struct WebGame {
+    cols: u16,
+    rows: u16,
+    game: Option<Game>,
+    over: bool,
+    out: AnsiBackend,
+    theme: Theme,
+}
+
+#[wasm_bindgen]
+impl WebGame {
+    #[wasm_bindgen(constructor)]
+    pub fn new(cols: u16, rows: u16) -> WebGame {
+        WebGame {
+            cols,
+            rows,
+            game: None,
+            over: false,
+            out: AnsiBackend::new(),
+            theme: Theme::default(),
+        }
+    }
+
+    /// Takes `KeyboardEvent.key` values ("ArrowUp", "r", ...).
+    pub fn key(&mut self, key: &str) {
+        let direction = match key {
+            "ArrowUp" => SnakeDirection::Up,
+            "ArrowDown" => SnakeDirection::Down,
+            "ArrowLeft" => SnakeDirection::Left,
+            "ArrowRight" => SnakeDirection::Right,
+            "r" if self.over => {
+                self.game = None;
+                self.over = false;
+                return;
+            }
+            _ => return,
+        };
+
+        match self.game {
+            Some(ref mut g) if !self.over => g.turn(direction),
+            Some(_) => {}
+            None => self.game = Some(Game::new(self.cols, self.rows - 1, direction)),
+        }
+    }
+
+    /// Advance one tick and return the frame as ANSI text for xterm.js.
+    pub fn frame(&mut self) -> String {
+        let _ = self.out.clear();
+
+        match self.game {
+            Some(ref mut g) if !self.over => {
+                if g.tick() == TickOutcome::Died {
+                    self.over = true;
+                }
+                let _ = draw_game(&mut self.out, g, &self.theme);
+            }
+            Some(ref g) => {
+                let _ = draw_game(&mut self.out, g, &self.theme);
+            }
+            None => {
+                let _ = self
+                    .out
+                    .print(0, 0, "Press arrows to move.", self.theme.text());
+            }
+        }
+
+        if let (true, Some(g)) = (self.over, &self.game) {
+            let text = format!("Game Over! Score {}, press r to play again.", g.score);
+            let _ = self.out.print(0, 0, &text, self.theme.text());
+        }
+
+        let status = StatusBar {
+            mode: "classic",
+            hints: match (&self.game, self.over) {
+                (None, _) => "arrows start",
+                (Some(_), false) => "arrows turn",
+                (Some(_), true) => "r restart",
+            },
+            elapsed: None,
+            rival: None,
+        };
+        let status_area = Rect::screen(self.cols, self.rows).split_bottom(1).1;
+        let _ = status.draw(&mut self.out, self.game.as_ref(), status_area, &self.theme);
+
+        self.out.take_frame()
+    }
+
+    /// Milliseconds to wait before the next `frame`.
+    pub fn interval(&self) -> u32 {
+        match self.game {
+            Some(ref g) if !self.over => g.timer as u32,
+            _ => 100,
+        }
+    }
+}
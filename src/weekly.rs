@@ -0,0 +1,88 @@
+// -- The weekly mutator
+//
+// `snake2 --weekly` plays classic with one or two modifiers picked by the
+// week of the year, the same ones for everybody all week, and keeps its
+// runs on a leaderboard of that week's own (see `Profile::record_weekly`).
+// Weeks are ISO 8601 ones: they start on Monday, and the first of a year is
+// the one with its first Thursday in it, so the days around New Year belong
+// to one week and not half of two. The pick is a hash of the week and
+// nothing else, it can't change between two runs or two machines.
+
+use crate::rules::{Modifier, Modifiers};
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Week {
+    pub year: i64,
+    // 1 to 53
+    pub number: u32,
+}
+
+impl Week {
+    /// The week of a date, as seconds since the epoch (UTC).
+    pub fn at(unix_secs: u64) -> Week {
+        let days = (unix_secs / 86_400) as i64;
+        // 1970-01-01 was a Thursday, and a week's Thursday decides its year
+        let monday = days - (days + 3).rem_euclid(7);
+        let thursday = monday + 3;
+        let year = year_of(thursday);
+        Week {
+            year,
+            number: ((thursday - first_of_january(year)) / 7 + 1) as u32,
+        }
+    }
+
+    /// This week's modifiers, one or two of them.
+    pub fn modifiers(self) -> Modifiers {
+        let hash = splitmix(self.year as u64 * 100 + u64::from(self.number));
+        let all = Modifier::WEEKLY.len() as u64;
+        let first = (hash >> 8) % all;
+        let modifiers = Modifiers::default().with(Modifier::WEEKLY[first as usize]);
+        if hash & 1 == 0 {
+            return modifiers;
+        }
+        // Any of the others, counting on from the first
+        let second = (first + 1 + (hash >> 32) % (all - 1)) % all;
+        modifiers.with(Modifier::WEEKLY[second as usize])
+    }
+}
+
+/// `2026-W42`, also the name of its leaderboard.
+impl fmt::Display for Week {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}-W{:02}", self.year, self.number)
+    }
+}
+
+// A well mixed 64 bits out of a few, so weeks next to each other don't
+// get modifiers next to each other
+fn splitmix(n: u64) -> u64 {
+    let mut z = n.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+// From Howard Hinnant's days-to-civil, like `theme::month_of`, for days
+// since the epoch
+fn year_of(days: i64) -> i64 {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    // Its years start in March, January and February are the next one's
+    let march_based = (5 * day_of_year + 2) / 153;
+    era * 400 + year_of_era + i64::from(march_based >= 10)
+}
+
+// And back, the day `year` starts on
+fn first_of_january(year: i64) -> i64 {
+    // January is the 11th month of the year before, counting from March
+    let year = year - 1;
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + 306;
+    era * 146_097 + day_of_era - 719_468
+}
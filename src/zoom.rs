@@ -0,0 +1,107 @@
+// -- Large cells
+//
+// For players who need everything bigger: every cell of the board drawn as
+// a block of 2x1 or 2x2 characters, on a board with half as many cells.
+// `Zoom` is a `Backend` in front of the real one that blows up whatever is
+// drawn through it, so the renderer keeps thinking in cells and only what's
+// wrapped, the board, gets bigger. The status bar, toasts and overlays are
+// drawn straight to the real one and stay as they are.
+//
+// A glyph two columns wide, an emoji (see `theme::Glyphs`), already fills a
+// cell's width and goes in once instead of twice.
+
+use crate::backend::{Backend, Style};
+use crate::layout::Rect;
+use std::io;
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CellSize {
+    // 2x1, twice as wide, about square on most terminals
+    Wide,
+    // 2x2
+    Big,
+}
+
+impl CellSize {
+    pub fn width(self) -> u16 {
+        2
+    }
+
+    pub fn height(self) -> u16 {
+        match self {
+            CellSize::Wide => 1,
+            CellSize::Big => 2,
+        }
+    }
+
+    /// `area` of the screen in cells of this size, whole ones only.
+    pub fn cells_in(self, area: Rect) -> Rect {
+        Rect::new(
+            area.x.div_ceil(self.width()),
+            area.y.div_ceil(self.height()),
+            area.width / self.width(),
+            area.height / self.height(),
+        )
+    }
+
+    /// Whether a `cols` by `rows` board fits in `area` in cells this size.
+    pub fn fits(self, cols: u16, rows: u16, area: Rect) -> bool {
+        let cells = self.cells_in(area);
+        cols <= cells.width && rows <= cells.height
+    }
+}
+
+impl std::str::FromStr for CellSize {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "2x1" => Ok(CellSize::Wide),
+            "2x2" => Ok(CellSize::Big),
+            _ => Err(format!("unknown cell size `{s}`, there's 2x1 and 2x2")),
+        }
+    }
+}
+
+/// Draws to `out` with every character a block of `size`. Text drawn
+/// through it has to be one column a character, like glyphs are, or two
+/// for emoji.
+pub struct Zoom<'a, B: Backend> {
+    pub out: &'a mut B,
+    pub size: CellSize,
+}
+
+impl<B: Backend> Backend for Zoom<'_, B> {
+    fn clear(&mut self) -> io::Result<()> {
+        self.out.clear()
+    }
+
+    fn print(&mut self, x: u16, y: u16, text: &str, style: Style) -> io::Result<()> {
+        let (width, height) = (self.size.width(), self.size.height());
+        // Each character with whatever doesn't take a column of its own
+        // after it, like the variation selector that makes it an emoji
+        let mut glyphs: Vec<String> = Vec::new();
+        for c in text.chars() {
+            match glyphs.last_mut() {
+                Some(glyph) if c.width() == Some(0) => glyph.push(c),
+                _ => glyphs.push(c.to_string()),
+            }
+        }
+        let wide: String = glyphs
+            .iter()
+            .map(|glyph| match glyph.width() {
+                2 => glyph.clone(),
+                _ => glyph.repeat(usize::from(width)),
+            })
+            .collect();
+        for row in 0..height {
+            self.out.print(x * width, y * height + row, &wide, style)?;
+        }
+        Ok(())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+}
@@ -0,0 +1,187 @@
+// -- Same game everywhere
+//
+// Replays, challenge codes and lockstep play all rely on a game playing out
+// bit for bit the same on any machine: the engine is integer math only and
+// its random numbers come from `GameRng`, which owns how it draws them. This
+// plays fixed games in every mode (and a royale) and checks they end exactly
+// where they always have, down to a hash of the whole saved game. A change
+// here is a change to every recorded game, on purpose or not. The weekly
+// mutator's week has to be the same for everybody too.
+
+use proptest::prelude::*;
+use rand::rand_core::SeedableRng;
+use rand::{Rng, RngCore};
+use snake2::game::{Game, GameOptions, TickOutcome};
+use snake2::rng::GameRng;
+use snake2::royale::Royale;
+use snake2::rules::{Mode, Modifier, Modifiers};
+use snake2::save::save_game_binary;
+use snake2::snake::SnakeDirection;
+use snake2::weekly::Week;
+
+const MODES: [Mode; 11] = [
+    Mode::Classic,
+    Mode::Tron,
+    Mode::Survival,
+    Mode::Arcade,
+    Mode::Fog,
+    Mode::Rooms,
+    Mode::Nibbles,
+    Mode::Words,
+    Mode::Mirror,
+    Mode::Hydra,
+    Mode::Surround,
+];
+
+// FNV-1a, written out so the hash can't change under the test either
+fn fnv(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &b| {
+        (hash ^ u64::from(b)).wrapping_mul(0x100_0000_01b3)
+    })
+}
+
+// Straight for the food, the long way round when that's behind
+fn towards(from: (u16, u16), to: (u16, u16)) -> SnakeDirection {
+    match (from.0.cmp(&to.0), from.1.cmp(&to.1)) {
+        (std::cmp::Ordering::Less, _) => SnakeDirection::Right,
+        (std::cmp::Ordering::Greater, _) => SnakeDirection::Left,
+        (_, std::cmp::Ordering::Less) => SnakeDirection::Down,
+        _ => SnakeDirection::Up,
+    }
+}
+
+// A game of `mode` played after the food for up to 2000 ticks: its ticks,
+// score and the hash of its binary save
+fn play(mode: Mode, modifiers: Modifiers) -> (u64, u32, u64) {
+    let options = GameOptions {
+        mode,
+        modifiers,
+        seed: Some(0x5eed),
+        ..Default::default()
+    };
+    let mut game = Game::with_options(32, 16, SnakeDirection::Right, &options);
+    for _ in 0..2000 {
+        let head = game.snake.head();
+        if let Some(food) = game.food_position() {
+            game.turn(towards((head.x, head.y), food));
+        }
+        if game.tick() == TickOutcome::Died {
+            break;
+        }
+    }
+    (game.ticks, game.score, fnv(&save_game_binary(&game)))
+}
+
+#[test]
+fn every_mode_plays_out_the_same() {
+    let played: Vec<_> = MODES
+        .iter()
+        .map(|&mode| (mode.name(), play(mode, Modifiers::default())))
+        .chain([(
+            "teleport+gravity",
+            play(
+                Mode::Classic,
+                Modifiers::default()
+                    .with(Modifier::Teleport)
+                    .with(Modifier::Gravity),
+            ),
+        )])
+        .chain([(
+            "hunger",
+            play(Mode::Classic, Modifiers::default().with(Modifier::Hunger)),
+        )])
+        .chain([(
+            "inverted+fleeting",
+            play(
+                Mode::Classic,
+                Modifiers::default()
+                    .with(Modifier::Inverted)
+                    .with(Modifier::Fleeting),
+            ),
+        )])
+        .chain([(
+            "words+hills",
+            play(Mode::Words, Modifiers::default().with(Modifier::Hills)),
+        )])
+        .collect();
+    assert_eq!(
+        played,
+        [
+            ("classic", (149, 11, 10032538228555584093)),
+            ("tron", (31, 32, 2778160384796688225)),
+            ("survival", (223, 11, 2971705103365958248)),
+            ("arcade", (289, 17, 13485272311181877372)),
+            ("fog", (149, 11, 1592800042861309073)),
+            ("rooms", (26, 0, 10546586676298847328)),
+            ("nibbles", (149, 11, 17610353608965006803)),
+            ("words", (552, 32, 6381802336538467396)),
+            ("mirror", (81, 6, 16178686842771003169)),
+            ("hydra", (68, 5, 9448977010372890371)),
+            ("surround", (101, 6, 17231304729974674956)),
+            ("teleport+gravity", (102, 7, 8693538313045302370)),
+            ("hunger", (149, 11, 9518231802476861743)),
+            ("inverted+fleeting", (444, 13, 9576705100135271001)),
+            ("words+hills", (276, 20, 6331382738844078272)),
+        ]
+    );
+}
+
+#[test]
+fn a_royale_plays_out_the_same() {
+    let ids = [1, 2, 3, 4];
+    let mut royale = Royale::new(60, 30, &ids, 0x5eed, true);
+    let mut views = Vec::new();
+    while !royale.is_over() && royale.ticks < 1000 {
+        for id in ids {
+            let Some(player) = royale.player(id) else {
+                continue;
+            };
+            let head = player.snake.head();
+            if let Some(&food) = royale.food.first() {
+                royale.turn(id, towards((head.x, head.y), food));
+            }
+        }
+        royale.tick();
+        views.extend(ids.iter().flat_map(|&id| royale.view_json(id).into_bytes()));
+    }
+    assert_eq!(
+        (royale.ticks, royale.winning_team(), fnv(&views)),
+        (52, Some(1), 12311021115707435138)
+    );
+}
+
+#[test]
+fn weeks_are_iso_weeks() {
+    // Noon on 2021-01-03, 2021-01-04, 2024-12-30, 2026-10-15 and 2027-01-01
+    let weeks: Vec<_> = [1609675200, 1609761600, 1735560000, 1792065600, 1798804800]
+        .into_iter()
+        .map(|secs| {
+            let week = Week::at(secs);
+            (week.to_string(), week.modifiers().to_string())
+        })
+        .collect();
+    assert_eq!(
+        weeks,
+        [
+            ("2020-W53".to_string(), "gravity,fog".to_string()),
+            ("2021-W01".to_string(), "teleport,fleeting".to_string()),
+            ("2025-W01".to_string(), "teleport".to_string()),
+            ("2026-W42".to_string(), "hunger,fleeting".to_string()),
+            ("2026-W53".to_string(), "hunger,fleeting".to_string()),
+        ]
+    );
+}
+
+proptest! {
+    // Saves and replays from before `GameRng` drew its own numbers have to
+    // get the same ones from it that they got from rand
+    #[test]
+    fn drawing_below_matches_rand(state in any::<u64>(), n in 1..=u32::MAX, small in 1..1000u32) {
+        let mut ours = GameRng::seed_from_u64(state);
+        let mut rands = ours.clone();
+        for n in [n, small] {
+            prop_assert_eq!(ours.below(n), rands.random_range(0..n));
+        }
+        prop_assert_eq!(ours.next_u64(), rands.next_u64());
+    }
+}
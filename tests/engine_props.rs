@@ -0,0 +1,411 @@
+// -- Invariants of the headless engine
+//
+// Random boards, modes and key presses, checking after every tick what must
+// always hold while the snake is alive: the body is one piece with no cell
+// twice, everything stays on the board, it only gets longer by growing and
+// wrapping around an edge is undone by going back. A saved game, loaded,
+// plays on exactly like the one it was saved from, binary saves keep the
+// same as text ones and a replay ends up where the game it recorded did.
+// A hungry snake that doesn't eat only ever gets shorter, down to starving.
+// The hamiltonian bot fills any board it has a cycle for without dying.
+// Gravity never carries the snake over a wall.
+
+use proptest::prelude::*;
+use snake2::bench::play_strategy;
+use snake2::entity::{Entity, EntityKind};
+use snake2::events::{DeathCause, GameEvent};
+use snake2::game::{CLOSE_CALL_BONUS, Game, GameOptions, TickOutcome};
+use snake2::replay::{Recent, Replay};
+use snake2::rules::{HUNGER_FULL, Mode, Modifier, Modifiers};
+use snake2::save::{load_game, load_game_binary, save_game, save_game_binary};
+use snake2::shape::Shape;
+use snake2::snake::{Snake, SnakeBodyPoint, SnakeDirection, Spawn};
+
+const DIRECTIONS: [SnakeDirection; 4] = [
+    SnakeDirection::Up,
+    SnakeDirection::Down,
+    SnakeDirection::Left,
+    SnakeDirection::Right,
+];
+
+fn direction() -> impl Strategy<Value = SnakeDirection> {
+    (0..4usize).prop_map(|i| DIRECTIONS[i])
+}
+
+fn mode() -> impl Strategy<Value = Mode> {
+    prop_oneof![
+        Just(Mode::Classic),
+        Just(Mode::Tron),
+        Just(Mode::Survival),
+        Just(Mode::Arcade),
+        Just(Mode::Fog),
+        Just(Mode::Nibbles),
+        Just(Mode::Mirror),
+        Just(Mode::Hydra),
+        Just(Mode::Surround)
+    ]
+}
+
+fn spawn() -> impl Strategy<Value = Spawn> {
+    prop_oneof![
+        Just(Spawn::Center),
+        Just(Spawn::Random),
+        (0..60u16, 0..40u16).prop_map(|(x, y)| Spawn::At(x, y)),
+    ]
+}
+
+/// One step apart, counting a step over the edge to the other side.
+fn adjacent(a: SnakeBodyPoint, b: SnakeBodyPoint, cols: u16, rows: u16) -> bool {
+    let dx = (a.x + cols - b.x) % cols;
+    let dy = (a.y + rows - b.y) % rows;
+    match (dx, dy) {
+        (0, dy) => dy == 1 || dy == rows - 1,
+        (dx, 0) => dx == 1 || dx == cols - 1,
+        _ => false,
+    }
+}
+
+fn check_body(game: &Game) -> Result<(), TestCaseError> {
+    let body = &game.snake.body;
+    prop_assert!(!body.is_empty());
+    for (i, p) in body.iter().enumerate() {
+        prop_assert!(p.x < game.cols && p.y < game.rows, "{p:?} off the board");
+        prop_assert!(!body[..i].contains(p), "{p:?} is in the body twice");
+    }
+    for pair in body.windows(2) {
+        prop_assert!(
+            adjacent(pair[0], pair[1], game.cols, game.rows),
+            "{:?} and {:?} aren't next to each other",
+            pair[0],
+            pair[1]
+        );
+    }
+    Ok(())
+}
+
+proptest! {
+    #[test]
+    fn the_body_holds_together(
+        cols in 3..60u16,
+        rows in 3..40u16,
+        start_length in 1..12u16,
+        first in direction(),
+        spawn in spawn(),
+        mode in mode(),
+        seed in any::<u64>(),
+        turns in prop::collection::vec(prop::option::of(direction()), 0..300),
+    ) {
+        let options = GameOptions { start_length, spawn, mode, seed: Some(seed), ..Default::default() };
+        let mut game = Game::with_options(cols, rows, first, &options);
+        check_body(&game)?;
+        let start = game.snake.body.len();
+        // Points that didn't come with a segment, food and chain pieces do
+        let mut extra = 0;
+
+        for turn in turns {
+            if let Some(direction) = turn {
+                game.turn(direction);
+            }
+            let before = game.snake.body.len();
+            if game.tick() == TickOutcome::Died {
+                break;
+            }
+            check_body(&game)?;
+            for event in &game.events {
+                if let GameEvent::ChainCompleted { bonus } = event {
+                    extra += bonus;
+                }
+            }
+
+            // At most one segment a tick, and only ever more, but for a
+            // hydra split handing half of it to a new head
+            let after = game.snake.body.len();
+            let split = game.events.iter().any(|e| matches!(e, GameEvent::Split { .. }));
+            prop_assert!(split || after == before || after == before + 1);
+            if mode == Mode::Classic {
+                let bonus = game.close_calls * CLOSE_CALL_BONUS + extra;
+                prop_assert_eq!(after, start + (game.score - bonus) as usize);
+            }
+            for entity in &game.entities {
+                prop_assert!(entity.x < cols && entity.y < rows);
+            }
+        }
+    }
+
+    #[test]
+    fn a_saved_game_plays_on_the_same(
+        cols in 4..16u16,
+        rows in 4..12u16,
+        first in direction(),
+        mode in mode(),
+        seed in any::<u64>(),
+        save_at in 0..100usize,
+        turns in prop::collection::vec(prop::option::of(direction()), 0..200),
+    ) {
+        let options = GameOptions { mode, seed: Some(seed), ..Default::default() };
+        let mut game = Game::with_options(cols, rows, first, &options);
+        let mut loaded: Option<Game> = None;
+
+        for (i, turn) in turns.into_iter().enumerate() {
+            if i == save_at {
+                loaded = Some(load_game(&save_game(&game)).map_err(TestCaseError::fail)?);
+            }
+            if let Some(direction) = turn {
+                game.turn(direction);
+            }
+            let outcome = game.tick();
+            if let Some(ref mut copy) = loaded {
+                if let Some(direction) = turn {
+                    copy.turn(direction);
+                }
+                prop_assert_eq!(copy.tick(), outcome);
+                prop_assert_eq!(&copy.snake.body, &game.snake.body);
+                prop_assert_eq!(&copy.entities, &game.entities);
+                prop_assert_eq!(&copy.rng, &game.rng);
+            }
+            if outcome == TickOutcome::Died {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn binary_saves_keep_what_text_saves_do(
+        cols in 4..200u16,
+        rows in 4..200u16,
+        first in direction(),
+        mode in mode(),
+        seed in any::<u64>(),
+        turns in prop::collection::vec(prop::option::of(direction()), 0..100),
+    ) {
+        let options = GameOptions { mode, seed: Some(seed), ..Default::default() };
+        let mut game = Game::with_options(cols, rows, first, &options);
+        for turn in turns {
+            let bytes = save_game_binary(&game);
+            let loaded = load_game_binary(&bytes).map_err(TestCaseError::fail)?;
+            prop_assert_eq!(save_game(&loaded), save_game(&game));
+            prop_assert!(bytes.len() < save_game(&game).len());
+
+            if let Some(direction) = turn {
+                game.turn(direction);
+            }
+            if game.tick() == TickOutcome::Died {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn a_replay_plays_the_game_it_recorded(
+        cols in 4..16u16,
+        rows in 4..12u16,
+        first in direction(),
+        mode in mode(),
+        seed in any::<u64>(),
+        ticks_before in 0..20usize,
+        turns in prop::collection::vec(prop::option::of(direction()), 0..200),
+    ) {
+        let options = GameOptions { mode, seed: Some(seed), ..Default::default() };
+        let mut game = Game::with_options(cols, rows, first, &options);
+        // Recording can start partway through
+        for _ in 0..ticks_before {
+            if game.tick() == TickOutcome::Died {
+                return Ok(());
+            }
+        }
+        let mut replay = Replay::new(&game);
+        let mut died = false;
+        for turn in turns {
+            if let Some(direction) = turn {
+                replay.turn(&game, direction);
+                game.turn(direction);
+            }
+            if game.tick() == TickOutcome::Died {
+                died = true;
+                break;
+            }
+        }
+        replay.finish(&game, died);
+
+        let read = Replay::from_bytes(&replay.to_bytes()).map_err(TestCaseError::fail)?;
+        prop_assert_eq!(&read, &replay);
+        let noted = Replay::from_notation(&replay.to_notation()).map_err(TestCaseError::fail)?;
+        prop_assert_eq!(&noted, &replay);
+        let played = read.play().map_err(TestCaseError::fail)?;
+        prop_assert_eq!(save_game(&played), save_game(&game));
+    }
+
+    #[test]
+    fn the_end_of_a_run_plays_again(
+        cols in 4..16u16,
+        rows in 4..12u16,
+        first in direction(),
+        mode in mode(),
+        seed in any::<u64>(),
+        window in 1..10u64,
+        turns in prop::collection::vec(prop::option::of(direction()), 0..200),
+    ) {
+        let options = GameOptions { mode, seed: Some(seed), ..Default::default() };
+        let mut game = Game::with_options(cols, rows, first, &options);
+        let mut recent = Recent::new(&game, window);
+        for turn in turns {
+            if let Some(direction) = turn {
+                recent.turn(&game, direction);
+                game.turn(direction);
+            }
+            if game.tick() == TickOutcome::Died {
+                let replay = recent.replay(&game);
+                // Never more than twice the window, however long the run
+                prop_assert!(replay.start_game().map_err(TestCaseError::fail)?.ticks + 2 * window >= game.ticks);
+                let played = replay.play().map_err(TestCaseError::fail)?;
+                prop_assert_eq!(save_game(&played), save_game(&game));
+                return Ok(());
+            }
+            recent.ticked(&game);
+        }
+    }
+
+    #[test]
+    fn a_hungry_snake_shrinks_until_it_eats(
+        cols in 4..40u16,
+        rows in 4..30u16,
+        start_length in 1..12u16,
+        first in direction(),
+        seed in any::<u64>(),
+        turns in prop::collection::vec(prop::option::of(direction()), 0..400),
+    ) {
+        let options = GameOptions {
+            start_length,
+            modifiers: Modifiers::default().with(Modifier::Hunger),
+            seed: Some(seed),
+            ..Default::default()
+        };
+        let mut game = Game::with_options(cols, rows, first, &options);
+        prop_assert_eq!(game.hunger, HUNGER_FULL);
+        for turn in turns {
+            if let Some(direction) = turn {
+                game.turn(direction);
+            }
+            let (hunger, length) = (game.hunger, game.snake.body.len());
+            if game.tick() == TickOutcome::Died {
+                if game.death_cause() == Some(DeathCause::Starved) {
+                    prop_assert_eq!((game.hunger, game.snake.body.len()), (0, 1));
+                }
+                break;
+            }
+            if game.events.iter().any(|e| matches!(e, GameEvent::FoodEaten { .. })) {
+                prop_assert_eq!(game.hunger, HUNGER_FULL);
+            } else if hunger > 0 {
+                prop_assert_eq!(game.hunger, hunger - 1);
+            } else {
+                prop_assert!(game.snake.body.len() <= length);
+            }
+        }
+    }
+
+    #[test]
+    fn gravity_never_jumps_a_wall(
+        cols in 3..40u16,
+        rows in 6..30u16,
+        x in 0..40u16,
+        sideways in any::<bool>(),
+        seed in any::<u64>(),
+    ) {
+        // A wall all the way across the second row from the bottom, the
+        // snake falling onto it from the top or going along just above it
+        let wall = rows - 2;
+        let (first, spawn) = if sideways {
+            (SnakeDirection::Right, Spawn::At(x % cols, wall - 1))
+        } else {
+            (SnakeDirection::Down, Spawn::At(x % cols, 0))
+        };
+        let options = GameOptions {
+            start_length: 1,
+            spawn,
+            modifiers: Modifiers::default().with(Modifier::Gravity),
+            seed: Some(seed),
+            ..Default::default()
+        };
+        let mut game = Game::with_options(cols, rows, first, &options);
+        game.entities.retain(|e| e.y != wall);
+        game.entities.extend((0..cols).map(|x| Entity::new(EntityKind::Wall, x, wall)));
+        for _ in 0..u32::from(cols) * u32::from(rows) * 4 {
+            if game.tick() == TickOutcome::Died {
+                let died = game.events.iter().any(|e| matches!(e, GameEvent::Died { y, .. } if *y == wall));
+                prop_assert!(died, "died off the wall: {:?}", game.events);
+                return Ok(());
+            }
+            prop_assert!(game.snake.body[0].y < wall);
+        }
+        prop_assert!(false, "the snake never reached the wall");
+    }
+
+    #[test]
+    fn wrapping_is_symmetric(
+        cols in 1..200u16,
+        rows in 1..200u16,
+        x in 0..200u16,
+        y in 0..200u16,
+        direction in direction(),
+    ) {
+        let (x, y) = (x % cols, y % rows);
+        let mut snake = Snake::new(&cols, &rows, direction, 1, Spawn::At(x, y));
+        let there = snake.next_head(&cols, &rows);
+        prop_assert!(there.x < cols && there.y < rows);
+
+        snake.body = vec![there];
+        snake.direction = direction.opposite();
+        prop_assert_eq!(snake.next_head(&cols, &rows), SnakeBodyPoint { x, y, letter: None });
+    }
+
+    #[test]
+    fn going_all_the_way_round_comes_back(
+        cols in 1..100u16,
+        rows in 1..100u16,
+        x in 0..100u16,
+        y in 0..100u16,
+        direction in direction(),
+    ) {
+        let (x, y) = (x % cols, y % rows);
+        let mut snake = Snake::new(&cols, &rows, direction, 1, Spawn::At(x, y));
+        let steps = match direction {
+            SnakeDirection::Left | SnakeDirection::Right => cols,
+            SnakeDirection::Up | SnakeDirection::Down => rows,
+        };
+        for _ in 0..steps {
+            snake.body = vec![snake.next_head(&cols, &rows)];
+        }
+        prop_assert_eq!(snake.head(), SnakeBodyPoint { x, y, letter: None });
+    }
+
+    #[test]
+    fn the_hamiltonian_bot_fills_the_board(
+        cols in 2..10u16,
+        half_rows in 1..5u16,
+        sideways in any::<bool>(),
+        seed in any::<u64>(),
+    ) {
+        // An even number of rows, or of columns turned sideways
+        let (cols, rows) = if sideways { (half_rows * 2, cols) } else { (cols, half_rows * 2) };
+        let options = GameOptions { seed: Some(seed), ..GameOptions::default() };
+        // A lap of the board for every cell it grows by is plenty
+        let cells = u64::from(cols) * u64::from(rows);
+        let game = play_strategy(
+            snake2::strategy::Strategy::Hamiltonian,
+            cols,
+            rows,
+            &options,
+            cells * cells,
+        );
+        prop_assert_eq!(game.death_cause(), None);
+        prop_assert!(game.events.contains(&GameEvent::BoardFull), "{} ticks", game.ticks);
+    }
+
+    #[test]
+    fn arenas_fit_any_terminal(cols in 20..250u16, rows in 10..80u16) {
+        for shape in Shape::ALL {
+            let level = shape.level(cols, rows);
+            prop_assert!(level.is_ok(), "{:?}", level.err());
+        }
+    }
+}
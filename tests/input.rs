@@ -0,0 +1,68 @@
+// -- Key presses only
+//
+// Terminals with enhanced keyboard reporting, and Windows, send a release
+// after every press and repeats while a key is held down. Only the press
+// may turn the snake or pause the game, or one key press would count two or
+// three times.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use snake2::input::{Input, map_key};
+use snake2::snake::SnakeDirection;
+
+const KEYS: [KeyCode; 4] = [
+    KeyCode::Left,
+    KeyCode::Char('p'),
+    KeyCode::Char('q'),
+    KeyCode::Char('+'),
+];
+
+fn key(code: KeyCode, kind: KeyEventKind) -> KeyEvent {
+    KeyEvent::new_with_kind(code, KeyModifiers::NONE, kind)
+}
+
+#[test]
+fn presses_map_to_their_input() {
+    assert_eq!(
+        map_key(key(KeyCode::Left, KeyEventKind::Press)),
+        Some(Input::Turn(SnakeDirection::Left))
+    );
+    assert_eq!(
+        map_key(key(KeyCode::Char('p'), KeyEventKind::Press)),
+        Some(Input::Pause)
+    );
+}
+
+#[test]
+fn ctrl_z_suspends() {
+    let ctrl_z = KeyEvent::new(KeyCode::Char('z'), KeyModifiers::CONTROL);
+    assert_eq!(map_key(ctrl_z), Some(Input::Suspend));
+    assert_eq!(
+        map_key(key(KeyCode::Char('z'), KeyEventKind::Press)),
+        Some(Input::Zoom)
+    );
+}
+
+#[test]
+fn ctrl_c_quits() {
+    let ctrl_c = KeyEvent::new(KeyCode::Char('c'), KeyModifiers::CONTROL);
+    assert_eq!(map_key(ctrl_c), Some(Input::Quit));
+    assert_eq!(
+        map_key(key(KeyCode::Char('c'), KeyEventKind::Press)),
+        Some(Input::Cycle)
+    );
+}
+
+#[test]
+fn shift_still_counts() {
+    let plus = KeyEvent::new(KeyCode::Char('+'), KeyModifiers::SHIFT);
+    assert_eq!(map_key(plus), Some(Input::Faster));
+}
+
+#[test]
+fn repeats_and_releases_do_nothing() {
+    for code in KEYS {
+        assert!(map_key(key(code, KeyEventKind::Press)).is_some());
+        assert_eq!(map_key(key(code, KeyEventKind::Repeat)), None);
+        assert_eq!(map_key(key(code, KeyEventKind::Release)), None);
+    }
+}
@@ -0,0 +1,25 @@
+// Glue between xterm.js and the wasm build of the game.
+// Build it first with: wasm-pack build --target web --out-dir web/pkg
+import init, { WebGame } from "./pkg/snake2.js";
+
+await init();
+
+const term = new Terminal({ cols: 60, rows: 24, cursorBlink: false });
+term.open(document.getElementById("terminal"));
+// Hide the cursor, the game draws its own things
+term.write("\x1b[?25l");
+term.focus();
+
+const game = new WebGame(term.cols, term.rows);
+
+term.onKey(({ domEvent }) => {
+  domEvent.preventDefault();
+  game.key(domEvent.key);
+});
+
+function loop() {
+  term.write(game.frame());
+  setTimeout(loop, game.interval());
+}
+
+loop();